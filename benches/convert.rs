@@ -0,0 +1,47 @@
+//! R8 to RGBA8 conversion of a 1024x1024 atlas. Compare with and without `--features simd`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use fontstash::{convert, AlphaMode};
+
+const SIZE: usize = 1024 * 1024;
+
+fn coverage() -> Vec<u8> {
+    (0..SIZE).map(|i| (i * 7 % 256) as u8).collect()
+}
+
+fn expand(c: &mut Criterion) {
+    let pixels = coverage();
+    let mut out = Vec::with_capacity(SIZE * 4);
+
+    let mut group = c.benchmark_group("expand_to_rgba");
+    group.throughput(Throughput::Bytes(SIZE as u64));
+
+    for &(name, mode) in &[
+        ("straight", AlphaMode::Straight),
+        ("premultiplied", AlphaMode::Premultiplied),
+    ] {
+        // the conversion before `fontstash::convert`
+        group.bench_function(format!("{}/flat_map", name), |b| {
+            b.iter(|| {
+                out.clear();
+                out.extend(black_box(&pixels).iter().flat_map(|&a| match mode {
+                    AlphaMode::Straight => [255, 255, 255, a],
+                    AlphaMode::Premultiplied => [a; 4],
+                }));
+            })
+        });
+
+        group.bench_function(format!("{}/convert", name), |b| {
+            b.iter(|| {
+                out.clear();
+                convert::expand_to_rgba(black_box(&pixels), mode, &mut out);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, expand);
+criterion_main!(benches);
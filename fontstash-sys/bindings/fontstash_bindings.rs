@@ -0,0 +1,611 @@
+// Bindings to `fontstash_wrapper.h` used unless the `generate-bindings` feature is enabled.
+//
+// They're target-independent (C types are `core::ffi` aliases). Update them with
+// `fontstash-sys/update_bindings.sh`.
+
+pub const FONS_INVALID: i32 = -1;
+pub type FONSflags = ::core::ffi::c_uint;
+pub const FONSflags_FONS_ZERO_TOPLEFT: FONSflags = 1;
+pub const FONSflags_FONS_ZERO_BOTTOMLEFT: FONSflags = 2;
+pub type FONSalign = ::core::ffi::c_uint;
+pub const FONSalign_FONS_ALIGN_LEFT: FONSalign = 1;
+pub const FONSalign_FONS_ALIGN_CENTER: FONSalign = 2;
+pub const FONSalign_FONS_ALIGN_RIGHT: FONSalign = 4;
+pub const FONSalign_FONS_ALIGN_TOP: FONSalign = 8;
+pub const FONSalign_FONS_ALIGN_MIDDLE: FONSalign = 16;
+pub const FONSalign_FONS_ALIGN_BOTTOM: FONSalign = 32;
+pub const FONSalign_FONS_ALIGN_BASELINE: FONSalign = 64;
+pub type FONSerrorCode = ::core::ffi::c_uint;
+pub const FONSerrorCode_FONS_SCRATCH_FULL: FONSerrorCode = 2;
+pub const FONSerrorCode_FONS_STATES_OVERFLOW: FONSerrorCode = 3;
+pub const FONSerrorCode_FONS_STATES_UNDERFLOW: FONSerrorCode = 4;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FONSparams {
+    pub width: ::core::ffi::c_int,
+    pub height: ::core::ffi::c_int,
+    pub flags: ::core::ffi::c_uchar,
+    pub userPtr: *mut ::core::ffi::c_void,
+    pub renderCreate: Option<
+        unsafe extern "C" fn(
+            uptr: *mut ::core::ffi::c_void,
+            width: ::core::ffi::c_int,
+            height: ::core::ffi::c_int,
+        ) -> ::core::ffi::c_int,
+    >,
+    pub renderResize: Option<
+        unsafe extern "C" fn(
+            uptr: *mut ::core::ffi::c_void,
+            width: ::core::ffi::c_int,
+            height: ::core::ffi::c_int,
+        ) -> ::core::ffi::c_int,
+    >,
+    pub renderExpand:
+        Option<unsafe extern "C" fn(uptr: *mut ::core::ffi::c_void) -> ::core::ffi::c_int>,
+    pub renderUpdate: Option<
+        unsafe extern "C" fn(
+            uptr: *mut ::core::ffi::c_void,
+            rect: *mut ::core::ffi::c_int,
+            data: *const ::core::ffi::c_uchar,
+        ) -> ::core::ffi::c_int,
+    >,
+    pub renderDelete: Option<unsafe extern "C" fn(uptr: *mut ::core::ffi::c_void)>,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSquad {
+    pub x0: f32,
+    pub y0: f32,
+    pub s0: f32,
+    pub t0: f32,
+    pub x1: f32,
+    pub y1: f32,
+    pub s1: f32,
+    pub t1: f32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FONSfont {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FONStextIter {
+    pub x: f32,
+    pub y: f32,
+    pub nextx: f32,
+    pub nexty: f32,
+    pub scale: f32,
+    pub spacing: f32,
+    pub codepoint: ::core::ffi::c_uint,
+    pub isize: ::core::ffi::c_short,
+    pub iblur: ::core::ffi::c_short,
+    pub font: *mut FONSfont,
+    pub prevGlyphIndex: ::core::ffi::c_int,
+    pub str: *const ::core::ffi::c_char,
+    pub next: *const ::core::ffi::c_char,
+    pub end: *const ::core::ffi::c_char,
+    pub utf8state: ::core::ffi::c_uint,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FONScontext {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn fonsCreateInternal(params: *mut FONSparams) -> *mut FONScontext;
+    pub fn fonsDeleteInternal(s: *mut FONScontext);
+    pub fn fonsSetErrorCallback(
+        s: *mut FONScontext,
+        callback: Option<
+            unsafe extern "C" fn(
+                uptr: *mut ::core::ffi::c_void,
+                error: ::core::ffi::c_int,
+                val: ::core::ffi::c_int,
+            ),
+        >,
+        uptr: *mut ::core::ffi::c_void,
+    );
+    pub fn fonsGetAtlasSize(
+        s: *mut FONScontext,
+        width: *mut ::core::ffi::c_int,
+        height: *mut ::core::ffi::c_int,
+    );
+    pub fn fonsExpandAtlas(
+        s: *mut FONScontext,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsResetAtlas(
+        stash: *mut FONScontext,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsAddFontMem(
+        s: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+        data: *mut ::core::ffi::c_uchar,
+        ndata: ::core::ffi::c_int,
+        freeData: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontByName(
+        s: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsAddFallbackFont(
+        stash: *mut FONScontext,
+        base: ::core::ffi::c_int,
+        fallback: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsPushState(s: *mut FONScontext);
+    pub fn fonsPopState(s: *mut FONScontext);
+    pub fn fonsClearState(s: *mut FONScontext);
+    pub fn fonsSetSize(s: *mut FONScontext, size: f32);
+    pub fn fonsSetColor(s: *mut FONScontext, color: ::core::ffi::c_uint);
+    pub fn fonsSetSpacing(s: *mut FONScontext, spacing: f32);
+    pub fn fonsSetBlur(s: *mut FONScontext, blur: f32);
+    pub fn fonsSetAlign(s: *mut FONScontext, align: ::core::ffi::c_int);
+    pub fn fonsSetFont(s: *mut FONScontext, font: ::core::ffi::c_int);
+    pub fn fonsTextBounds(
+        s: *mut FONScontext,
+        x: f32,
+        y: f32,
+        string: *const ::core::ffi::c_char,
+        end: *const ::core::ffi::c_char,
+        bounds: *mut f32,
+    ) -> f32;
+    pub fn fonsLineBounds(s: *mut FONScontext, y: f32, miny: *mut f32, maxy: *mut f32);
+    pub fn fonsVertMetrics(
+        s: *mut FONScontext,
+        ascender: *mut f32,
+        descender: *mut f32,
+        lineh: *mut f32,
+    );
+    pub fn fonsTextIterInit(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+        x: f32,
+        y: f32,
+        str: *const ::core::ffi::c_char,
+        end: *const ::core::ffi::c_char,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextIterNext(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+        quad: *mut FONSquad,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetTextureData(
+        stash: *mut FONScontext,
+        width: *mut ::core::ffi::c_int,
+        height: *mut ::core::ffi::c_int,
+    ) -> *const ::core::ffi::c_uchar;
+    pub fn fonsValidateTexture(
+        s: *mut FONScontext,
+        dirty: *mut ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+// fontstash_ext.h
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSatlasStats {
+    pub usedPixels: ::core::ffi::c_int,
+    pub totalPixels: ::core::ffi::c_int,
+    pub glyphCount: ::core::ffi::c_int,
+    pub skylineNodes: ::core::ffi::c_int,
+}
+extern "C" {
+    pub fn fonsGetAtlasStats(stash: *mut FONScontext, stats: *mut FONSatlasStats);
+}
+extern "C" {
+    pub fn fonsGetFontIndex(stash: *mut FONScontext, font: *mut FONSfont) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsTextIterIsMissing(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+    ) -> ::core::ffi::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSglyphBounds {
+    pub bearingX: f32,
+    pub bearingY: f32,
+    pub width: f32,
+    pub height: f32,
+    pub advance: f32,
+}
+extern "C" {
+    pub fn fonsGetGlyphBounds(
+        stash: *mut FONScontext,
+        codepoint: ::core::ffi::c_uint,
+        bounds: *mut FONSglyphBounds,
+    ) -> ::core::ffi::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSstateInfo {
+    pub font: ::core::ffi::c_int,
+    pub align: ::core::ffi::c_int,
+    pub size: f32,
+    pub color: ::core::ffi::c_uint,
+    pub blur: f32,
+    pub spacing: f32,
+}
+extern "C" {
+    pub fn fonsGetStateInfo(stash: *mut FONScontext, info: *mut FONSstateInfo);
+}
+extern "C" {
+    pub fn fonsGetKerning(
+        stash: *mut FONScontext,
+        left: ::core::ffi::c_uint,
+        right: ::core::ffi::c_uint,
+    ) -> f32;
+}
+extern "C" {
+    pub fn fonsGetMaxStates() -> ::core::ffi::c_int;
+    pub fn fonsGetStateDepth(stash: *mut FONScontext) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsAddFontMemCopy(
+        stash: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+        data: *const ::core::ffi::c_uchar,
+        dataSize: ::core::ffi::c_int,
+        faceIndex: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsAddFontMemAt(
+        stash: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+        data: *mut ::core::ffi::c_uchar,
+        dataSize: ::core::ffi::c_int,
+        freeData: ::core::ffi::c_int,
+        faceIndex: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FONSfontInfo {
+    pub name: *const ::core::ffi::c_char,
+    pub family: *const ::core::ffi::c_char,
+    pub familyLength: ::core::ffi::c_int,
+    pub familyIsUtf16: ::core::ffi::c_int,
+    pub unitsPerEm: ::core::ffi::c_int,
+    pub glyphCount: ::core::ffi::c_int,
+    pub ascent: ::core::ffi::c_int,
+    pub descent: ::core::ffi::c_int,
+    pub lineGap: ::core::ffi::c_int,
+}
+extern "C" {
+    pub fn fonsGetFontInfo(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        info: *mut FONSfontInfo,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsUsesFreetype() -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsHasGlyph(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        codepoint: ::core::ffi::c_uint,
+    ) -> ::core::ffi::c_int;
+}
+pub type FONScolorTable = ::core::ffi::c_uint;
+pub const FONScolorTable_FONS_COLOR_COLR: FONScolorTable = 1;
+pub const FONScolorTable_FONS_COLOR_CBDT: FONScolorTable = 2;
+pub const FONScolorTable_FONS_COLOR_SBIX: FONScolorTable = 4;
+extern "C" {
+    pub fn fonsGetColorTables(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetColorAtlas(
+        stash: *mut FONScontext,
+        enabled: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetColorTextureData(
+        stash: *mut FONScontext,
+        width: *mut ::core::ffi::c_int,
+        height: *mut ::core::ffi::c_int,
+    ) -> *const ::core::ffi::c_uchar;
+    pub fn fonsSetColorGlyphs(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        glyphs: *const ::core::ffi::c_int,
+        nglyphs: ::core::ffi::c_int,
+        layers: *const ::core::ffi::c_int,
+        nlayers: ::core::ffi::c_int,
+        colors: *const ::core::ffi::c_uchar,
+        ncolors: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextIterIsColored(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontData(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        dataSize: *mut ::core::ffi::c_int,
+        faceIndex: *mut ::core::ffi::c_int,
+    ) -> *const ::core::ffi::c_uchar;
+}
+extern "C" {
+    pub fn fonsReloadFontMem(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        data: *const ::core::ffi::c_uchar,
+        dataSize: ::core::ffi::c_int,
+        faceIndex: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSfontAxis {
+    pub tag: ::core::ffi::c_uint,
+    pub minValue: f32,
+    pub defaultValue: f32,
+    pub maxValue: f32,
+    pub value: f32,
+}
+extern "C" {
+    pub fn fonsGetFontAxes(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        axes: *mut FONSfontAxis,
+        maxAxes: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetFontVariation(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        tags: *const ::core::ffi::c_uint,
+        values: *const f32,
+        n: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsGetFontCount(stash: *mut FONScontext) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsTextIterKern(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+        prevGlyphIndex: ::core::ffi::c_int,
+        glyphIndex: ::core::ffi::c_int,
+    ) -> f32;
+}
+extern "C" {
+    pub fn fonsGetRenderCallbacks(stash: *mut FONScontext, params: *mut FONSparams);
+    pub fn fonsSetRenderCallbacks(stash: *mut FONScontext, params: *const FONSparams);
+}
+extern "C" {
+    pub fn fonsGetPixelScale(stash: *mut FONScontext) -> f32;
+    pub fn fonsGetGlyphQuad(
+        stash: *mut FONScontext,
+        glyphIndex: ::core::ffi::c_int,
+        x: f32,
+        y: f32,
+        q: *mut FONSquad,
+    ) -> ::core::ffi::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSdecorationMetrics {
+    pub underlinePosition: ::core::ffi::c_int,
+    pub underlineThickness: ::core::ffi::c_int,
+    pub strikeoutPosition: ::core::ffi::c_int,
+    pub strikeoutThickness: ::core::ffi::c_int,
+}
+extern "C" {
+    pub fn fonsGetDecorationMetrics(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        metrics: *mut FONSdecorationMetrics,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsGetGlyphRects(
+        stash: *mut FONScontext,
+        rects: *mut ::core::ffi::c_int,
+        maxRects: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsTextBoundsNoBitmap(
+        stash: *mut FONScontext,
+        x: f32,
+        y: f32,
+        string: *const ::core::ffi::c_char,
+        end: *const ::core::ffi::c_char,
+        bounds: *mut f32,
+    ) -> f32;
+}
+extern "C" {
+    pub fn fonsAddGlyphBitmap(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        codepoint: ::core::ffi::c_uint,
+        size: f32,
+        data: *const ::core::ffi::c_uchar,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+        stride: ::core::ffi::c_int,
+        xoff: ::core::ffi::c_int,
+        yoff: ::core::ffi::c_int,
+        xadvance: f32,
+    ) -> ::core::ffi::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSdeferredGlyph {
+    pub id: ::core::ffi::c_int,
+    pub font: ::core::ffi::c_int,
+    pub codepoint: ::core::ffi::c_uint,
+    pub size: f32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FONSrasterizer {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSglyphBitmap {
+    pub width: ::core::ffi::c_int,
+    pub height: ::core::ffi::c_int,
+    pub xoff: ::core::ffi::c_int,
+    pub yoff: ::core::ffi::c_int,
+    pub xadvance: f32,
+}
+extern "C" {
+    pub fn fonsSetDeferredGlyphs(
+        stash: *mut FONScontext,
+        enabled: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTakeDeferredGlyphs(
+        stash: *mut FONScontext,
+        glyphs: *mut FONSdeferredGlyph,
+        maxGlyphs: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontRevision(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsAddDeferredGlyph(
+        stash: *mut FONScontext,
+        id: ::core::ffi::c_int,
+        data: *const ::core::ffi::c_uchar,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+        stride: ::core::ffi::c_int,
+        xoff: ::core::ffi::c_int,
+        yoff: ::core::ffi::c_int,
+        xadvance: f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsRasterizeDeferredGlyph(
+        stash: *mut FONScontext,
+        id: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextIterIsDeferred(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsCreateRasterizer(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+    ) -> *mut FONSrasterizer;
+    pub fn fonsDeleteRasterizer(r: *mut FONSrasterizer);
+    pub fn fonsRasterizeGlyph(
+        r: *mut FONSrasterizer,
+        codepoint: ::core::ffi::c_uint,
+        size: f32,
+        bitmap: *mut FONSglyphBitmap,
+    ) -> *const ::core::ffi::c_uchar;
+}
+extern "C" {
+    pub fn fonsClearFonts(stash: *mut FONScontext);
+}
+pub type FONShinting = ::core::ffi::c_uint;
+pub const FONShinting_FONS_HINTING_NONE: FONShinting = 0;
+pub const FONShinting_FONS_HINTING_SLIGHT: FONShinting = 1;
+pub const FONShinting_FONS_HINTING_FULL: FONShinting = 2;
+extern "C" {
+    pub fn fonsSetFreetypeHinting(
+        hinting: ::core::ffi::c_int,
+        autohint: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsGetCapMetrics(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        xHeight: *mut ::core::ffi::c_int,
+        capHeight: *mut ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsReserveAtlasNodes(
+        stash: *mut FONScontext,
+        nnodes: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+pub type FONSpacker = ::core::ffi::c_uint;
+pub const FONSpacker_FONS_PACKER_SKYLINE: FONSpacker = 0;
+pub const FONSpacker_FONS_PACKER_SHELF: FONSpacker = 1;
+pub const FONSpacker_FONS_PACKER_GUILLOTINE: FONSpacker = 2;
+extern "C" {
+    pub fn fonsSetAtlasPacker(
+        stash: *mut FONScontext,
+        packer: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetAtlasPacker(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsSetGlyphPadding(stash: *mut FONScontext, padding: ::core::ffi::c_int);
+    pub fn fonsGetGlyphPadding(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsSetSdf(
+        stash: *mut FONScontext,
+        spread: ::core::ffi::c_int,
+        onedge: ::core::ffi::c_uchar,
+        pixelDistScale: f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetSdf(
+        stash: *mut FONScontext,
+        onedge: *mut ::core::ffi::c_uchar,
+        pixelDistScale: *mut f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsRepackAtlas(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsEvictGlyphs(
+        stash: *mut FONScontext,
+        keep: Option<
+            unsafe extern "C" fn(
+                uptr: *mut ::core::ffi::c_void,
+                font: ::core::ffi::c_int,
+                codepoint: ::core::ffi::c_uint,
+                isize: ::core::ffi::c_short,
+                iblur: ::core::ffi::c_short,
+            ) -> ::core::ffi::c_int,
+        >,
+        uptr: *mut ::core::ffi::c_void,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsSetFontPage(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        page: *mut FONScontext,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontPage(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        pageFont: *mut ::core::ffi::c_int,
+    ) -> *mut FONScontext;
+}
+pub type FONSoutlineVerb = ::core::ffi::c_uint;
+pub const FONSoutlineVerb_FONS_OUTLINE_MOVE: FONSoutlineVerb = 0;
+pub const FONSoutlineVerb_FONS_OUTLINE_LINE: FONSoutlineVerb = 1;
+pub const FONSoutlineVerb_FONS_OUTLINE_QUAD: FONSoutlineVerb = 2;
+pub const FONSoutlineVerb_FONS_OUTLINE_CUBIC: FONSoutlineVerb = 3;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FONSoutlineSegment {
+    pub verb: ::core::ffi::c_int,
+    pub x: f32,
+    pub y: f32,
+    pub cx0: f32,
+    pub cy0: f32,
+    pub cx1: f32,
+    pub cy1: f32,
+}
+extern "C" {
+    pub fn fonsGetGlyphOutline(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        codepoint: ::core::ffi::c_uint,
+        segments: *mut FONSoutlineSegment,
+        maxSegments: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+}
+extern "C" {
+    pub fn fonsGetStateInfoAt(
+        stash: *mut FONScontext,
+        index: ::core::ffi::c_int,
+        info: *mut FONSstateInfo,
+    ) -> ::core::ffi::c_int;
+}
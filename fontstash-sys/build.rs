@@ -1,63 +1,346 @@
 /*!
 Build script of `fontstash-sys`
 
-If the compilation fails, run `cargo clean`.
-
 # What it does
 
 1. Pulls `fontstash` if there's not
-2. Compiles `fontstash` if it's not found in `OUT_DIR`
+2. Compiles `fontstash`. Cargo reruns the build script when the C sources, the headers or the
+configuration below are changed
 4. Links to the output libraries
-5. Makes bindings (FFI) to the C libraries
+5. Makes bindings (FFI) to the C libraries (only with the `generate-bindings` feature)
+
+# Configuration
+
+Compile-time constants of `fontstash` can be overridden with environment variables:
+
+* `FONTSTASH_MAX_STATES`: depth of the state stack (`FONS_MAX_STATES`, defaults to 20)
+* `FONTSTASH_HASH_LUT_SIZE`: buckets of the glyph hash table per font (`FONS_HASH_LUT_SIZE`,
+defaults to 256). It has to be a power of two. Increase it if you render thousands of distinct
+glyphs (e.g. CJK at several sizes)
+* `FONTSTASH_INIT_GLYPHS`: initial capacity of the glyph array per font (`FONS_INIT_GLYPHS`,
+defaults to 256)
+* `FONTSTASH_INIT_ATLAS_NODES`: initial capacity of the skyline nodes of the atlas
+(`FONS_INIT_ATLAS_NODES`, defaults to 256). `StashBuilder::atlas_nodes` reserves more at runtime
+* `FONTSTASH_MAX_FALLBACKS`: fallback fonts per font (`FONS_MAX_FALLBACKS`, defaults to 20)
+
+The `rust-alloc` feature defines `malloc`, `calloc`, `realloc` and `free` as `fons_rs_*`, which
+are implemented in Rust (`heap.rs`) with the allocator given to `set_allocator` (or the global
+allocator). FreeType still allocates with its own allocator.
+
+The `freetype` feature defines `FONS_USE_FREETYPE` and links to FreeType. Include directories
+are got from `pkg-config freetype2` (falls back to `/usr/include/freetype2`).
+
+# System library
+
+Set `FONTSTASH_SYS_USE_SYSTEM=1` to link an existing `fontstash` library found with
+`pkg-config fontstash` instead of compiling the bundled source. The library has to be built from
+`fontstash.c` of this crate (with the same defines), because the bindings are generated from the
+bundled headers including the extensions in `fontstash_ext.h`.
+
+# Dynamic loading
 
-# TODOs
+With the `dlopen` feature, nothing is compiled or linked. The functions are resolved with
+`libloading` from a shared library at runtime (`fontstash_sys::load`), e.g. for plugins that can't
+link C symbols statically. Build the library from `fontstash.c` of this crate.
 
-* TODO: Windows/Linux
+# WebAssembly
+
+`wasm32-unknown-unknown` has no libc. `fontstash` is compiled with clang against the headers in
+`wasm/`, which are implemented in Rust (`wasm.rs`). There's no file system, so add fonts from
+memory.
+
+# Platforms
+
+Tested on Linux, macOS and Windows (MSVC and GNU) in CI. On MSVC, the library is output as
+`fontstash.lib` and `bindgen` needs `LIBCLANG_PATH` unless LLVM is in the `PATH`.
+
+For Android (e.g. `aarch64-linux-android`), set `ANDROID_NDK_HOME` and put the NDK clang in your
+`PATH` (or use `cargo-ndk`). `ANDROID_API_LEVEL` defaults to 21. For iOS (e.g.
+`aarch64-apple-ios`), the SDK is found with `xcrun`. The sysroot is passed to both `cc` and
+bindgen so that host headers aren't picked up.
 */
 
 use ::std::{
-    env,
+    env, fs,
     path::{Path, PathBuf},
     process::Command,
 };
 
 fn main() {
+    // without any `rerun-if-changed`, Cargo would rerun it for any file in the package instead
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    for src in SOURCES {
+        println!("cargo:rerun-if-changed={}", root.join(src).display());
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // the library is loaded at runtime, so there's nothing to compile or link
+    if env::var("CARGO_FEATURE_DLOPEN").is_ok() {
+        if self::is_wasm() {
+            panic!("`dlopen` feature is not supported on `wasm32-unknown-unknown`");
+        }
+        #[cfg(feature = "generate-bindings")]
+        {
+            self::prepare();
+            self::gen_bindings("fontstash_wrapper.h", "fontstash_bindings.rs");
+        }
+        return;
+    }
+
     self::prepare();
-    self::compile("fontstash.c");
+    println!("cargo:rerun-if-env-changed=FONTSTASH_SYS_USE_SYSTEM");
+    if env::var_os("FONTSTASH_SYS_USE_SYSTEM").is_some() {
+        self::link_system("fontstash");
+    } else {
+        self::compile("fontstash.c");
+    }
+    #[cfg(feature = "generate-bindings")]
     self::gen_bindings("fontstash_wrapper.h", "fontstash_bindings.rs");
 }
 
-/// Pulls `fontstash`
+/// Pulls `fontstash` if there's not
+///
+/// The sources are bundled in the published package, so it only runs in a git checkout without
+/// the submodule (e.g. a `git` dependency). Offline and vendored builds don't need git.
 fn prepare() {
     let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    if root.join("fontstash-rs-src/src/fontstash.h").is_file() {
+        return;
+    }
 
     // Note that it FORCES THE SUBMODULE TO CHECKOUT the commit `fontstash-sys` is dependent on
-    Command::new("git")
+    let status = Command::new("git")
         .current_dir(&root)
         .args(&["submodule", "update", "--init", "--recursive"])
         .status()
-        .expect("is git in your PATH?");
+        .expect("`fontstash` sources are missing. Is git in your PATH?");
+
+    if !status.success() {
+        panic!("`fontstash` sources are missing and `git submodule update` failed");
+    }
 }
 
-/// Runs `cc` (only when it's necessary) and links the output libraries
+/// Environment variables overriding compile-time constants of `fontstash`: `(env, define)`
+const DEFINES: &[(&str, &str)] = &[
+    ("FONTSTASH_MAX_STATES", "FONS_MAX_STATES"),
+    ("FONTSTASH_HASH_LUT_SIZE", "FONS_HASH_LUT_SIZE"),
+    ("FONTSTASH_INIT_GLYPHS", "FONS_INIT_GLYPHS"),
+    ("FONTSTASH_INIT_ATLAS_NODES", "FONS_INIT_ATLAS_NODES"),
+    ("FONTSTASH_MAX_FALLBACKS", "FONS_MAX_FALLBACKS"),
+];
+
+/// libc functions renamed with the `rust-alloc` feature: `(name, hook)`
+const ALLOC_HOOKS: &[(&str, &str)] = &[
+    ("malloc", "fons_rs_malloc"),
+    ("calloc", "fons_rs_calloc"),
+    ("realloc", "fons_rs_realloc"),
+    ("free", "fons_rs_free"),
+];
+
+/// C sources and headers of `fontstash`, relative to the manifest directory
+const SOURCES: &[&str] = &[
+    "fontstash.c",
+    "fontstash_ext.h",
+    "fontstash_wrapper.h",
+    "fontstash-rs-src/src/fontstash.h",
+    "wasm",
+];
+
+/// Runs `cc` and links the output libraries
 fn compile(src_path: impl AsRef<Path>) {
     let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
-    let out_lib_path = out_dir.join("libfontstash.a");
-    if !out_lib_path.is_file() {
-        let file = root.join(src_path);
-        cc::Build::new()
-            .file(file)
-            .flag("-w") // suppress errors
-            .compile("libfontstash.a");
+    let mut defines = Vec::new();
+    for (var, define) in DEFINES {
+        println!("cargo:rerun-if-env-changed={}", var);
+        if let Ok(value) = env::var(var) {
+            let n = value
+                .parse::<u32>()
+                .unwrap_or_else(|_| panic!("{} has to be a positive integer", var));
+            // the glyph hash is masked with `FONS_HASH_LUT_SIZE - 1`
+            if *define == "FONS_HASH_LUT_SIZE" && !n.is_power_of_two() {
+                panic!("{} has to be a power of two", var);
+            }
+            defines.push((*define, value));
+        }
     }
 
+    let use_freetype = env::var("CARGO_FEATURE_FREETYPE").is_ok();
+    if use_freetype && self::is_wasm() {
+        panic!("`freetype` feature is not supported on `wasm32-unknown-unknown`");
+    }
+    if use_freetype {
+        defines.push(("FONS_USE_FREETYPE", "1".to_string()));
+    }
+
+    // route the allocations of `fontstash` and `stb_truetype` to `heap.rs`
+    if env::var("CARGO_FEATURE_RUST_ALLOC").is_ok() {
+        for (name, hook) in ALLOC_HOOKS {
+            defines.push((*name, hook.to_string()));
+        }
+    }
+
+    // Cargo reruns the build script only if the sources or the configuration are changed
+    let mut build = cc::Build::new();
+    build.file(root.join(src_path));
+    if self::is_msvc() {
+        // suppress warnings (and the deprecation of `strncpy` and friends)
+        build.flag("/w").define("_CRT_SECURE_NO_WARNINGS", None);
+    } else {
+        build.flag("-w"); // suppress errors
+    }
+    for (define, value) in &defines {
+        build.define(define, value.as_str());
+    }
+    if use_freetype {
+        for dir in self::freetype_include_dirs() {
+            build.include(dir);
+        }
+    }
+    if self::is_wasm() {
+        build.compiler("clang").include(root.join("wasm"));
+    }
+    // `cc` finds the compiler of the NDK or Xcode, but not always the headers
+    if let Some(sysroot) = self::sysroot() {
+        build.flag(&format!("--sysroot={}", sysroot.display()));
+    }
+    build.compile("fontstash");
+
     println!("cargo:rustc-link-search=native={}", out_dir.display());
     println!("cargo:rustc-link-lib=static=fontstash");
+    if use_freetype {
+        println!("cargo:rustc-link-lib=freetype");
+    }
+}
+
+/// Links to a library found with `pkg-config` instead of compiling it
+fn link_system(name: &str) {
+    let output = Command::new("pkg-config")
+        .args(&["--libs", name])
+        .output()
+        .expect("FONTSTASH_SYS_USE_SYSTEM requires pkg-config in your PATH");
+
+    if !output.status.success() {
+        panic!(
+            "pkg-config couldn't find `{}`: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    for flag in String::from_utf8_lossy(&output.stdout).split_whitespace() {
+        if let Some(dir) = flag.strip_prefix("-L") {
+            println!("cargo:rustc-link-search=native={}", dir);
+        } else if let Some(lib) = flag.strip_prefix("-l") {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+    }
 }
 
-/// Generates bindings using a wrapper header file
+/// If we're compiling with MSVC (`cl.exe`)
+fn is_msvc() -> bool {
+    env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("msvc")
+}
+
+/// If we're compiling to `wasm32-unknown-unknown`, which has no libc
+fn is_wasm() -> bool {
+    env::var("TARGET").unwrap() == "wasm32-unknown-unknown"
+}
+
+/// Target triple of clang for the Rust target
+///
+/// They're mostly the same, but Apple targets are `arm64` and Android targets need the API level.
+#[cfg(feature = "generate-bindings")]
+fn clang_target() -> String {
+    let target = env::var("TARGET").unwrap();
+    match target.as_str() {
+        "aarch64-apple-ios" => "arm64-apple-ios".to_string(),
+        "aarch64-apple-ios-sim" => "arm64-apple-ios-simulator".to_string(),
+        "aarch64-apple-darwin" => "arm64-apple-macosx".to_string(),
+        _ if self::is_android() => {
+            // `armv7-linux-androideabi` is `armv7a-linux-androideabi` for clang
+            let target = target.replace("armv7-", "armv7a-");
+            format!("{}{}", target, self::android_api_level())
+        }
+        _ => target,
+    }
+}
+
+/// Sysroot of the target SDK passed to clang for cross compiling, if it's needed
+///
+/// * Android: `$ANDROID_NDK_HOME` (or `$ANDROID_NDK_ROOT`) has to be set
+/// * iOS: got from `xcrun`
+fn sysroot() -> Option<PathBuf> {
+    let target = env::var("TARGET").unwrap();
+
+    if self::is_android() {
+        println!("cargo:rerun-if-env-changed=ANDROID_NDK_HOME");
+        println!("cargo:rerun-if-env-changed=ANDROID_NDK_ROOT");
+        let ndk = env::var_os("ANDROID_NDK_HOME").or_else(|| env::var_os("ANDROID_NDK_ROOT"))?;
+        let prebuilt = PathBuf::from(ndk).join("toolchains/llvm/prebuilt");
+        // there's one directory for the host (e.g. `linux-x86_64`)
+        let host = fs::read_dir(&prebuilt)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .next()?;
+        return Some(host.path().join("sysroot"));
+    }
+
+    if target.ends_with("-apple-ios") || target.ends_with("-apple-ios-sim") {
+        let sdk = if target.starts_with("x86_64") || target.ends_with("-sim") {
+            "iphonesimulator"
+        } else {
+            "iphoneos"
+        };
+        let output = Command::new("xcrun")
+            .args(&["--sdk", sdk, "--show-sdk-path"])
+            .output()
+            .ok()?;
+        if output.status.success() {
+            let path = String::from_utf8_lossy(&output.stdout);
+            return Some(PathBuf::from(path.trim()));
+        }
+    }
+
+    None
+}
+
+fn is_android() -> bool {
+    env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("android")
+}
+
+/// `ANDROID_API_LEVEL` or 21, the minimum 64-bit platforms support
+#[cfg(feature = "generate-bindings")]
+fn android_api_level() -> u32 {
+    println!("cargo:rerun-if-env-changed=ANDROID_API_LEVEL");
+    env::var("ANDROID_API_LEVEL")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(21)
+}
+
+/// Include directories of FreeType
+fn freetype_include_dirs() -> Vec<PathBuf> {
+    let output = Command::new("pkg-config")
+        .args(&["--cflags-only-I", "freetype2"])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .filter_map(|flag| flag.strip_prefix("-I"))
+            .map(PathBuf::from)
+            .collect(),
+        _ => vec![PathBuf::from("/usr/include/freetype2")],
+    }
+}
+
+/// Generates bindings using a wrapper header file into `OUT_DIR`
+///
+/// The build script never writes to the package; `update_bindings.sh` copies the output to the
+/// pregenerated bindings in `bindings/`.
+#[cfg(feature = "generate-bindings")]
 fn gen_bindings(wrapper: impl AsRef<Path>, dst_file_name: impl AsRef<Path>) {
     let wrapper = wrapper.as_ref();
     let dst_file_name = dst_file_name.as_ref();
@@ -66,9 +349,21 @@ fn gen_bindings(wrapper: impl AsRef<Path>, dst_file_name: impl AsRef<Path>) {
     let dst = out_dir.join(&dst_file_name);
 
     println!("cargo:rerun-if-changed={}", wrapper.display());
+    let mut args = vec![format!("--target={}", self::clang_target())];
+    if let Some(sysroot) = self::sysroot() {
+        args.push(format!("--sysroot={}", sysroot.display()));
+    }
+    if self::is_wasm() {
+        let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+        args.push(format!("-I{}", root.join("wasm").display()));
+    }
     let bindings = bindgen::Builder::default()
+        .clang_args(&args)
         .header(format!("{}", wrapper.display()))
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
+        // `no_std` compatible
+        .use_core()
+        .ctypes_prefix("core::ffi")
         .generate()
         .unwrap_or_else(|e| {
             panic!(
@@ -0,0 +1,440 @@
+//! Runtime loading of `fontstash` with the `dlopen` feature
+//!
+//! The functions of the bindings are replaced with ones calling the symbols resolved from a
+//! shared library with [`load`]. The library has to be built from `fontstash.c` of this crate (so
+//! that it exports the extensions in `fontstash_ext.h`). If no library is loaded when a function is
+//! called, [`load_default`] is tried and it panics on failure.
+//!
+//! The declarations mirror the `extern` blocks of `bindings/fontstash_bindings.rs`; update both
+//! when adding a binding.
+
+extern crate std;
+
+use std::{ffi::OsStr, sync::OnceLock};
+
+use libloading::Library;
+
+pub use crate::ffi::*;
+pub use libloading::Error as LoadError;
+
+static API: OnceLock<Api> = OnceLock::new();
+
+/// Loads the library at `path` and resolves the functions
+///
+/// Does nothing if a library is loaded already.
+///
+/// # Safety
+///
+/// The initialization routines of the library are run, and the library has to export the
+/// functions with the signatures of the bindings.
+pub unsafe fn load(path: impl AsRef<OsStr>) -> Result<(), LoadError> {
+    if API.get().is_some() {
+        return Ok(());
+    }
+    let api = Api::resolve(Library::new(path.as_ref())?)?;
+    // another thread might have loaded it in the meantime
+    let _ = API.set(api);
+    Ok(())
+}
+
+/// Loads `fontstash` from the library search path (`libfontstash.so`, `libfontstash.dylib` or
+/// `fontstash.dll`)
+///
+/// # Safety
+///
+/// See [`load`].
+pub unsafe fn load_default() -> Result<(), LoadError> {
+    self::load(libloading::library_filename("fontstash"))
+}
+
+/// If the functions are resolved
+pub fn is_loaded() -> bool {
+    API.get().is_some()
+}
+
+fn api() -> &'static Api {
+    if let Some(api) = API.get() {
+        return api;
+    }
+    if let Err(err) = unsafe { self::load_default() } {
+        panic!(
+            "fontstash-sys: unable to load the fontstash library: {}",
+            err
+        );
+    }
+    API.get().unwrap()
+}
+
+macro_rules! dylib {
+    ($(pub fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;)*) => {
+        /// Resolved functions, which are valid while the library is loaded
+        #[allow(non_snake_case)]
+        struct Api {
+            $($name: unsafe extern "C" fn($($ty),*) $(-> $ret)?,)*
+            _lib: Library,
+        }
+
+        impl Api {
+            unsafe fn resolve(lib: Library) -> Result<Self, LoadError> {
+                Ok(Self {
+                    $($name: *lib.get(concat!(stringify!($name), "\0").as_bytes())?,)*
+                    _lib: lib,
+                })
+            }
+        }
+
+        $(
+            pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+                (self::api().$name)($($arg),*)
+            }
+        )*
+    };
+}
+
+dylib! {
+    pub fn fonsCreateInternal(params: *mut FONSparams) -> *mut FONScontext;
+    pub fn fonsDeleteInternal(s: *mut FONScontext);
+    pub fn fonsSetErrorCallback(
+        s: *mut FONScontext,
+        callback: Option<
+            unsafe extern "C" fn(
+                uptr: *mut ::core::ffi::c_void,
+                error: ::core::ffi::c_int,
+                val: ::core::ffi::c_int,
+            ),
+        >,
+        uptr: *mut ::core::ffi::c_void,
+    );
+    pub fn fonsGetAtlasSize(
+        s: *mut FONScontext,
+        width: *mut ::core::ffi::c_int,
+        height: *mut ::core::ffi::c_int,
+    );
+    pub fn fonsExpandAtlas(
+        s: *mut FONScontext,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsResetAtlas(
+        stash: *mut FONScontext,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsAddFontMem(
+        s: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+        data: *mut ::core::ffi::c_uchar,
+        ndata: ::core::ffi::c_int,
+        freeData: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontByName(
+        s: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsAddFallbackFont(
+        stash: *mut FONScontext,
+        base: ::core::ffi::c_int,
+        fallback: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsPushState(s: *mut FONScontext);
+    pub fn fonsPopState(s: *mut FONScontext);
+    pub fn fonsClearState(s: *mut FONScontext);
+    pub fn fonsSetSize(s: *mut FONScontext, size: f32);
+    pub fn fonsSetColor(s: *mut FONScontext, color: ::core::ffi::c_uint);
+    pub fn fonsSetSpacing(s: *mut FONScontext, spacing: f32);
+    pub fn fonsSetBlur(s: *mut FONScontext, blur: f32);
+    pub fn fonsSetAlign(s: *mut FONScontext, align: ::core::ffi::c_int);
+    pub fn fonsSetFont(s: *mut FONScontext, font: ::core::ffi::c_int);
+    pub fn fonsTextBounds(
+        s: *mut FONScontext,
+        x: f32,
+        y: f32,
+        string: *const ::core::ffi::c_char,
+        end: *const ::core::ffi::c_char,
+        bounds: *mut f32,
+    ) -> f32;
+    pub fn fonsLineBounds(s: *mut FONScontext, y: f32, miny: *mut f32, maxy: *mut f32);
+    pub fn fonsVertMetrics(
+        s: *mut FONScontext,
+        ascender: *mut f32,
+        descender: *mut f32,
+        lineh: *mut f32,
+    );
+    pub fn fonsTextIterInit(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+        x: f32,
+        y: f32,
+        str: *const ::core::ffi::c_char,
+        end: *const ::core::ffi::c_char,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextIterNext(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+        quad: *mut FONSquad,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetTextureData(
+        stash: *mut FONScontext,
+        width: *mut ::core::ffi::c_int,
+        height: *mut ::core::ffi::c_int,
+    ) -> *const ::core::ffi::c_uchar;
+    pub fn fonsValidateTexture(
+        s: *mut FONScontext,
+        dirty: *mut ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetAtlasStats(stash: *mut FONScontext, stats: *mut FONSatlasStats);
+    pub fn fonsGetFontIndex(stash: *mut FONScontext, font: *mut FONSfont) -> ::core::ffi::c_int;
+    pub fn fonsTextIterIsMissing(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetGlyphBounds(
+        stash: *mut FONScontext,
+        codepoint: ::core::ffi::c_uint,
+        bounds: *mut FONSglyphBounds,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetStateInfo(stash: *mut FONScontext, info: *mut FONSstateInfo);
+    pub fn fonsGetKerning(
+        stash: *mut FONScontext,
+        left: ::core::ffi::c_uint,
+        right: ::core::ffi::c_uint,
+    ) -> f32;
+    pub fn fonsGetMaxStates() -> ::core::ffi::c_int;
+    pub fn fonsGetStateDepth(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsAddFontMemCopy(
+        stash: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+        data: *const ::core::ffi::c_uchar,
+        dataSize: ::core::ffi::c_int,
+        faceIndex: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsAddFontMemAt(
+        stash: *mut FONScontext,
+        name: *const ::core::ffi::c_char,
+        data: *mut ::core::ffi::c_uchar,
+        dataSize: ::core::ffi::c_int,
+        freeData: ::core::ffi::c_int,
+        faceIndex: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontInfo(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        info: *mut FONSfontInfo,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsUsesFreetype() -> ::core::ffi::c_int;
+    pub fn fonsHasGlyph(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        codepoint: ::core::ffi::c_uint,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetColorTables(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetColorAtlas(
+        stash: *mut FONScontext,
+        enabled: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetColorTextureData(
+        stash: *mut FONScontext,
+        width: *mut ::core::ffi::c_int,
+        height: *mut ::core::ffi::c_int,
+    ) -> *const ::core::ffi::c_uchar;
+    pub fn fonsSetColorGlyphs(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        glyphs: *const ::core::ffi::c_int,
+        nglyphs: ::core::ffi::c_int,
+        layers: *const ::core::ffi::c_int,
+        nlayers: ::core::ffi::c_int,
+        colors: *const ::core::ffi::c_uchar,
+        ncolors: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextIterIsColored(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontData(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        dataSize: *mut ::core::ffi::c_int,
+        faceIndex: *mut ::core::ffi::c_int,
+    ) -> *const ::core::ffi::c_uchar;
+    pub fn fonsReloadFontMem(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        data: *const ::core::ffi::c_uchar,
+        dataSize: ::core::ffi::c_int,
+        faceIndex: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontAxes(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        axes: *mut FONSfontAxis,
+        maxAxes: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetFontVariation(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        tags: *const ::core::ffi::c_uint,
+        values: *const f32,
+        n: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontCount(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsTextIterKern(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+        prevGlyphIndex: ::core::ffi::c_int,
+        glyphIndex: ::core::ffi::c_int,
+    ) -> f32;
+    pub fn fonsGetRenderCallbacks(stash: *mut FONScontext, params: *mut FONSparams);
+    pub fn fonsSetRenderCallbacks(stash: *mut FONScontext, params: *const FONSparams);
+    pub fn fonsGetPixelScale(stash: *mut FONScontext) -> f32;
+    pub fn fonsGetGlyphQuad(
+        stash: *mut FONScontext,
+        glyphIndex: ::core::ffi::c_int,
+        x: f32,
+        y: f32,
+        q: *mut FONSquad,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetDecorationMetrics(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        metrics: *mut FONSdecorationMetrics,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetGlyphRects(
+        stash: *mut FONScontext,
+        rects: *mut ::core::ffi::c_int,
+        maxRects: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextBoundsNoBitmap(
+        stash: *mut FONScontext,
+        x: f32,
+        y: f32,
+        string: *const ::core::ffi::c_char,
+        end: *const ::core::ffi::c_char,
+        bounds: *mut f32,
+    ) -> f32;
+    pub fn fonsAddGlyphBitmap(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        codepoint: ::core::ffi::c_uint,
+        size: f32,
+        data: *const ::core::ffi::c_uchar,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+        stride: ::core::ffi::c_int,
+        xoff: ::core::ffi::c_int,
+        yoff: ::core::ffi::c_int,
+        xadvance: f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetDeferredGlyphs(
+        stash: *mut FONScontext,
+        enabled: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTakeDeferredGlyphs(
+        stash: *mut FONScontext,
+        glyphs: *mut FONSdeferredGlyph,
+        maxGlyphs: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontRevision(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsAddDeferredGlyph(
+        stash: *mut FONScontext,
+        id: ::core::ffi::c_int,
+        data: *const ::core::ffi::c_uchar,
+        width: ::core::ffi::c_int,
+        height: ::core::ffi::c_int,
+        stride: ::core::ffi::c_int,
+        xoff: ::core::ffi::c_int,
+        yoff: ::core::ffi::c_int,
+        xadvance: f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsRasterizeDeferredGlyph(
+        stash: *mut FONScontext,
+        id: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsTextIterIsDeferred(
+        stash: *mut FONScontext,
+        iter: *mut FONStextIter,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsCreateRasterizer(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+    ) -> *mut FONSrasterizer;
+    pub fn fonsDeleteRasterizer(r: *mut FONSrasterizer);
+    pub fn fonsRasterizeGlyph(
+        r: *mut FONSrasterizer,
+        codepoint: ::core::ffi::c_uint,
+        size: f32,
+        bitmap: *mut FONSglyphBitmap,
+    ) -> *const ::core::ffi::c_uchar;
+    pub fn fonsClearFonts(stash: *mut FONScontext);
+    pub fn fonsSetFreetypeHinting(
+        hinting: ::core::ffi::c_int,
+        autohint: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetCapMetrics(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        xHeight: *mut ::core::ffi::c_int,
+        capHeight: *mut ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsReserveAtlasNodes(
+        stash: *mut FONScontext,
+        nnodes: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetAtlasPacker(
+        stash: *mut FONScontext,
+        packer: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetAtlasPacker(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsSetGlyphPadding(stash: *mut FONScontext, padding: ::core::ffi::c_int);
+    pub fn fonsGetGlyphPadding(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsSetSdf(
+        stash: *mut FONScontext,
+        spread: ::core::ffi::c_int,
+        onedge: ::core::ffi::c_uchar,
+        pixelDistScale: f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetSdf(
+        stash: *mut FONScontext,
+        onedge: *mut ::core::ffi::c_uchar,
+        pixelDistScale: *mut f32,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsRepackAtlas(stash: *mut FONScontext) -> ::core::ffi::c_int;
+    pub fn fonsEvictGlyphs(
+        stash: *mut FONScontext,
+        keep: Option<
+            unsafe extern "C" fn(
+                uptr: *mut ::core::ffi::c_void,
+                font: ::core::ffi::c_int,
+                codepoint: ::core::ffi::c_uint,
+                isize: ::core::ffi::c_short,
+                iblur: ::core::ffi::c_short,
+            ) -> ::core::ffi::c_int,
+        >,
+        uptr: *mut ::core::ffi::c_void,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsSetFontPage(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        page: *mut FONScontext,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetFontPage(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        pageFont: *mut ::core::ffi::c_int,
+    ) -> *mut FONScontext;
+    pub fn fonsGetGlyphOutline(
+        stash: *mut FONScontext,
+        font: ::core::ffi::c_int,
+        codepoint: ::core::ffi::c_uint,
+        segments: *mut FONSoutlineSegment,
+        maxSegments: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+    pub fn fonsGetStateInfoAt(
+        stash: *mut FONScontext,
+        index: ::core::ffi::c_int,
+        info: *mut FONSstateInfo,
+    ) -> ::core::ffi::c_int;
+}
@@ -1,6 +1,2896 @@
 // Source file of `fontstash`
 
+#include <stddef.h>
 #include <stdlib.h>
 #define FONTSTASH_IMPLEMENTATION
 
+#ifdef FONS_USE_FREETYPE
+#include <ft2build.h>
+#include FT_FREETYPE_H
+
+// `fontstash.h` loads glyphs with `FT_LOAD_RENDER | FT_LOAD_FORCE_AUTOHINT`. The FreeType headers
+// are included first so that the flag is replaced with the one set by `fonsSetFreetypeHinting`
+static FT_Int32 fons__extFtHintFlags = FT_LOAD_FORCE_AUTOHINT;
+#undef FT_LOAD_FORCE_AUTOHINT
+#define FT_LOAD_FORCE_AUTOHINT fons__extFtHintFlags
+
+// `fons__tt_buildGlyphBitmap` gets the advance after rendering the glyph. Variable fonts without
+// `HVAR` have no fast advances, and `FT_Get_Advance` would load the glyph again into the slot
+// without the bitmap, so the advance is taken from the rendered glyph instead
+#include FT_ADVANCES_H
+static FT_Error fons__extFtGetAdvance(FT_Face face, FT_UInt glyph, FT_Int32 flags, FT_Fixed* advance)
+{
+	if (FT_Get_Advance(face, glyph, flags | FT_ADVANCE_FLAG_FAST_ONLY, advance) == 0)
+		return 0;
+	if ((flags & FT_LOAD_NO_SCALE) && face->glyph->glyph_index == glyph && face->size->metrics.x_scale != 0) {
+		*advance = FT_DivFix(face->glyph->linearHoriAdvance, face->size->metrics.x_scale);
+		return 0;
+	}
+	return FT_Get_Advance(face, glyph, flags, advance);
+}
+#define FT_Get_Advance fons__extFtGetAdvance
+#endif
+
+// Atlas packers other than the skyline of `fontstash.h` are selected per stash (`FONSpacker`).
+// Calls of the skyline functions on `stash->atlas` are redirected to the dispatchers below, and
+// the skyline functions themselves are renamed to `fons__extSkyline*`. The first token of the
+// first argument tells the calls (`stash`) from the definitions (`FONSatlas`). Any other first
+// token doesn't expand and fails to compile, so a call or a definition changed in `fontstash.h`
+// can't silently bypass the hooks (`fons__extHooks` checks the renamed definitions)
+#define fons__atlasAddRect(a, ...) FONS__EXT_ADD_RECT_##a, __VA_ARGS__)
+#define FONS__EXT_ADD_RECT_FONSatlas fons__extSkylineAddRect(FONSatlas
+#define FONS__EXT_ADD_RECT_stash fons__extAtlasAddRect(stash, stash
+#define fons__atlasReset(a, ...) FONS__EXT_RESET_##a, __VA_ARGS__)
+#define FONS__EXT_RESET_FONSatlas fons__extSkylineReset(FONSatlas
+#define FONS__EXT_RESET_stash fons__extAtlasReset(stash, stash
+#define fons__atlasExpand(a, ...) FONS__EXT_EXPAND_##a, __VA_ARGS__)
+#define FONS__EXT_EXPAND_FONSatlas fons__extSkylineExpand(FONSatlas
+#define FONS__EXT_EXPAND_stash fons__extAtlasExpand(stash, stash
+// The white rectangle of the decorations stays at the origin without the glyph padding
+#define fons__addWhiteRect(a, ...) FONS__EXT_WHITE_RECT_##a, __VA_ARGS__)
+#define FONS__EXT_WHITE_RECT_FONScontext fons__extPlainAddWhiteRect(FONScontext
+#define FONS__EXT_WHITE_RECT_stash fons__extAddWhiteRect(stash
+// Glyphs of an SDF atlas (see `fonsSetSdf`) are "blurred" into distance fields
+#define fons__blur(a, ...) FONS__EXT_BLUR_##a, __VA_ARGS__)
+#define FONS__EXT_BLUR_FONScontext fons__extPlainBlur(FONScontext
+#define FONS__EXT_BLUR_stash fons__extBlur(stash
+// Glyphs of fonts with a dedicated page (see `fonsSetFontPage`) are looked up and rasterized in
+// the atlas of the page
+#define fons__getGlyph(a, ...) FONS__EXT_GET_GLYPH_##a, __VA_ARGS__)
+#define FONS__EXT_GET_GLYPH_FONScontext fons__extPlainGetGlyph(FONScontext
+#define FONS__EXT_GET_GLYPH_stash fons__extGetGlyphPaged(stash
+#define fons__getQuad(a, ...) FONS__EXT_GET_QUAD_##a, __VA_ARGS__)
+#define FONS__EXT_GET_QUAD_FONScontext fons__extPlainGetQuad(FONScontext
+#define FONS__EXT_GET_QUAD_stash fons__extGetQuadPaged(stash
+// wrapped to own the extension data of the stash (`FONSextContext`)
+#define fonsCreateInternal fons__extCreateInternal
+#define fonsDeleteInternal fons__extDeleteInternal
+#define fonsSetErrorCallback fons__extSetErrorCallback
+
+struct FONScontext;
+struct FONSatlas;
+struct FONSfont;
+struct FONSglyph;
+struct FONSquad;
+static int fons__extAtlasAddRect(struct FONScontext* stash, struct FONSatlas* atlas, int rw, int rh, int* rx, int* ry);
+static void fons__extAtlasReset(struct FONScontext* stash, struct FONSatlas* atlas, int w, int h);
+static void fons__extAtlasExpand(struct FONScontext* stash, struct FONSatlas* atlas, int w, int h);
+static void fons__extAddWhiteRect(struct FONScontext* stash, int w, int h);
+static void fons__extBlur(struct FONScontext* stash, unsigned char* dst, int w, int h, int dstStride, int blur);
+static struct FONSglyph* fons__extGetGlyphPaged(struct FONScontext* stash, struct FONSfont* font, unsigned int codepoint, short isize, short iblur);
+static void fons__extGetQuadPaged(struct FONScontext* stash, struct FONSfont* font, int prevGlyphIndex, struct FONSglyph* glyph, float scale, float spacing, float* x, float* y, struct FONSquad* q);
+
 #include "fontstash-rs-src/src/fontstash.h"
+#undef fonsCreateInternal
+#undef fonsDeleteInternal
+#undef fonsSetErrorCallback
+#include "fontstash_ext.h"
+
+#ifdef FONS_USE_FREETYPE
+#include FT_TRUETYPE_TABLES_H
+#include FT_OUTLINE_H
+#include FT_MULTIPLE_MASTERS_H
+#endif
+
+// `_Static_assert` is not in C89 or older MSVC
+#define FONS__EXT_ASSERT(cond, name) typedef char fons__extAssert_##name[(cond) ? 1 : -1]
+
+// the glyph hash is masked with `FONS_HASH_LUT_SIZE - 1`
+FONS__EXT_ASSERT((FONS_HASH_LUT_SIZE & (FONS_HASH_LUT_SIZE - 1)) == 0, lutSizeIsPowerOfTwo);
+FONS__EXT_ASSERT(sizeof(((FONScontext*)0)->states) == sizeof(FONSstate) * FONS_MAX_STATES, maxStates);
+
+// Layouts of the `fontstash.h` internals the extensions were written against. The packers and
+// the repacking move glyphs by their rectangles and grow the skyline nodes, so a field added,
+// moved or widened in `fontstash.h` fails to compile here instead of corrupting the atlas
+#define FONS__EXT_SAME_FIELD(type, layout, field) \
+	FONS__EXT_ASSERT(offsetof(type, field) == offsetof(layout, field) && sizeof(((type*)0)->field) == sizeof(((layout*)0)->field), type##_##field)
+#define FONS__EXT_SAME_SIZE(type, layout) FONS__EXT_ASSERT(sizeof(type) == sizeof(layout), type##_size)
+
+typedef struct {
+	unsigned int codepoint;
+	int index;
+	int next;
+	short size, blur;
+	short x0, y0, x1, y1;
+	short xadv, xoff, yoff;
+} FONSextGlyphLayout;
+FONS__EXT_SAME_SIZE(FONSglyph, FONSextGlyphLayout);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, codepoint);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, next);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, size);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, blur);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, x0);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, y0);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, x1);
+FONS__EXT_SAME_FIELD(FONSglyph, FONSextGlyphLayout, y1);
+
+typedef struct {
+	short x, y, width;
+} FONSextAtlasNodeLayout;
+FONS__EXT_SAME_SIZE(FONSatlasNode, FONSextAtlasNodeLayout);
+
+typedef struct {
+	int width, height;
+	FONSatlasNode* nodes;
+	int nnodes;
+	int cnodes;
+} FONSextAtlasLayout;
+FONS__EXT_SAME_SIZE(FONSatlas, FONSextAtlasLayout);
+FONS__EXT_SAME_FIELD(FONSatlas, FONSextAtlasLayout, nodes);
+FONS__EXT_SAME_FIELD(FONSatlas, FONSextAtlasLayout, nnodes);
+FONS__EXT_SAME_FIELD(FONSatlas, FONSextAtlasLayout, cnodes);
+
+// Paging swaps the atlas fields of the stash (`FONSextAtlasState`) and shares the fallbacks of the
+// font with its copy in the page
+typedef struct {
+	FONSparams params;
+	float itw, ith;
+	unsigned char* texData;
+	int dirtyRect[4];
+	FONSfont** fonts;
+	FONSatlas* atlas;
+} FONSextContextLayout;
+FONS__EXT_SAME_FIELD(FONScontext, FONSextContextLayout, params);
+FONS__EXT_SAME_FIELD(FONScontext, FONSextContextLayout, itw);
+FONS__EXT_SAME_FIELD(FONScontext, FONSextContextLayout, ith);
+FONS__EXT_SAME_FIELD(FONScontext, FONSextContextLayout, texData);
+FONS__EXT_SAME_FIELD(FONScontext, FONSextContextLayout, dirtyRect);
+FONS__EXT_SAME_FIELD(FONScontext, FONSextContextLayout, atlas);
+
+typedef struct {
+	FONSttFontImpl font;
+	char name[64];
+	unsigned char* data;
+	int dataSize;
+	unsigned char freeData;
+	float ascender;
+	float descender;
+	float lineh;
+	FONSglyph* glyphs;
+	int cglyphs;
+	int nglyphs;
+	int lut[FONS_HASH_LUT_SIZE];
+	int fallbacks[FONS_MAX_FALLBACKS];
+	int nfallbacks;
+} FONSextFontLayout;
+FONS__EXT_SAME_SIZE(FONSfont, FONSextFontLayout);
+FONS__EXT_SAME_FIELD(FONSfont, FONSextFontLayout, glyphs);
+FONS__EXT_SAME_FIELD(FONSfont, FONSextFontLayout, nglyphs);
+FONS__EXT_SAME_FIELD(FONSfont, FONSextFontLayout, lut);
+FONS__EXT_SAME_FIELD(FONSfont, FONSextFontLayout, fallbacks);
+FONS__EXT_SAME_FIELD(FONSfont, FONSextFontLayout, nfallbacks);
+
+// The Rust bindings (`bindings/fontstash_bindings.rs`) aren't generated on every build, and the
+// text iterator is copied and edited field by field on the Rust side
+typedef struct {
+	int width, height;
+	unsigned char flags;
+	void* userPtr;
+	int (*renderCreate)(void* uptr, int width, int height);
+	int (*renderResize)(void* uptr, int width, int height);
+	int (*renderExpand)(void* uptr);
+	int (*renderUpdate)(void* uptr, int* rect, const unsigned char* data);
+	void (*renderDelete)(void* uptr);
+} FONSextParamsLayout;
+FONS__EXT_SAME_SIZE(FONSparams, FONSextParamsLayout);
+FONS__EXT_SAME_FIELD(FONSparams, FONSextParamsLayout, userPtr);
+FONS__EXT_SAME_FIELD(FONSparams, FONSextParamsLayout, renderExpand);
+FONS__EXT_SAME_FIELD(FONSparams, FONSextParamsLayout, renderDelete);
+
+typedef struct {
+	float x0, y0, s0, t0;
+	float x1, y1, s1, t1;
+} FONSextQuadLayout;
+FONS__EXT_SAME_SIZE(FONSquad, FONSextQuadLayout);
+
+typedef struct {
+	float x, y, nextx, nexty, scale, spacing;
+	unsigned int codepoint;
+	short isize, iblur;
+	struct FONSfont* font;
+	int prevGlyphIndex;
+	const char* str;
+	const char* next;
+	const char* end;
+	unsigned int utf8state;
+} FONSextTextIterLayout;
+FONS__EXT_SAME_SIZE(FONStextIter, FONSextTextIterLayout);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, nextx);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, nexty);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, codepoint);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, isize);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, font);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, prevGlyphIndex);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, next);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, end);
+FONS__EXT_SAME_FIELD(FONStextIter, FONSextTextIterLayout, utf8state);
+
+// The definitions renamed by the hooks, with the signatures the dispatchers call them with. It
+// fails to compile if `fontstash.h` no longer defines them or changes their signatures
+static const struct {
+	int (*addRect)(FONSatlas* atlas, int rw, int rh, int* rx, int* ry);
+	void (*reset)(FONSatlas* atlas, int w, int h);
+	void (*expand)(FONSatlas* atlas, int w, int h);
+	void (*addWhiteRect)(FONScontext* stash, int w, int h);
+	void (*blur)(FONScontext* stash, unsigned char* dst, int w, int h, int dstStride, int blur);
+	FONSglyph* (*getGlyph)(FONScontext* stash, FONSfont* font, unsigned int codepoint, short isize, short iblur);
+	void (*getQuad)(FONScontext* stash, FONSfont* font, int prevGlyphIndex, FONSglyph* glyph, float scale, float spacing, float* x, float* y, FONSquad* q);
+	FONScontext* (*create)(FONSparams* params);
+	void (*del)(FONScontext* stash);
+	void (*setErrorCallback)(FONScontext* stash, void (*callback)(void* uptr, int error, int val), void* uptr);
+} fons__extHooks = {
+	fons__extSkylineAddRect,
+	fons__extSkylineReset,
+	fons__extSkylineExpand,
+	fons__extPlainAddWhiteRect,
+	fons__extPlainBlur,
+	fons__extPlainGetGlyph,
+	fons__extPlainGetQuad,
+	fons__extCreateInternal,
+	fons__extDeleteInternal,
+	fons__extSetErrorCallback,
+};
+
+typedef struct FONSextRect {
+	int x, y, w, h;
+} FONSextRect;
+
+// Shelf or guillotine packer of an atlas
+typedef struct FONSextPacker {
+	int packer;
+	int width, height;
+	// shelves (`x` is the used width) or free rectangles
+	FONSextRect* rects;
+	int nrects, crects;
+	// bottom of the last shelf
+	int top;
+	// padding of the glyphs packed since the reset, `-1` before the first one and `-2` if they
+	// were packed with different paddings
+	int padding;
+} FONSextPacker;
+
+struct FONSextPage;
+struct FONSextPaging;
+struct FONSextColorFont;
+
+// Glyph queued while the glyphs are deferred (see `fonsSetDeferredGlyphs`). `taken` is set once
+// it's returned by `fonsTakeDeferredGlyphs`
+typedef struct FONSextDeferred {
+	int id;
+	int font;
+	unsigned int codepoint;
+	short isize;
+	int taken;
+} FONSextDeferred;
+
+// Extension data owned by a stash. `fontstash.h` has no user data in the context apart from the
+// renderer's, so it's the user pointer of the error callback (`errorUptr`), which forwards the
+// errors to the callback set with `fonsSetErrorCallback`
+typedef struct FONSextContext {
+	void (*handleError)(void* uptr, int error, int val);
+	void* errorUptr;
+	// `NULL` for the skyline of `fontstash.h`
+	FONSextPacker* packer;
+	// empty pixels around the glyphs added to the atlas, and set while the white rectangle is
+	// added without them
+	int padding;
+	int unpadded;
+	// distance fields instead of coverage if `sdfSpread` is positive
+	int sdfSpread;
+	unsigned char sdfOnEdge;
+	float sdfDistScale;
+	// RGBA pixels of the color glyphs at their rectangles in the atlas (see `fonsSetColorAtlas`),
+	// and the color layers of the fonts
+	int colorAtlas;
+	unsigned char* colorData;
+	struct FONSextColorFont* colorFonts;
+	// glyphs queued for rasterizing elsewhere instead of in `fons__getGlyph`, the last id given
+	// to them and the glyph returned for them meanwhile. `fontRevision` is bumped when the data
+	// of a font is replaced
+	int deferGlyphs;
+	FONSextDeferred* deferred;
+	int ndeferred, cdeferred;
+	int deferredId;
+	int fontRevision;
+	FONSglyph placeholder;
+	// fonts of this stash with a page, and fonts of other stashes with this stash as their page
+	struct FONSextPage* pages;
+	struct FONSextPage* pageRefs;
+	// set while this stash uses the atlas of a page
+	struct FONSextPaging* paging;
+} FONSextContext;
+
+static void fons__extHandleError(void* uptr, int error, int val)
+{
+	FONSextContext* ext = (FONSextContext*)uptr;
+	if (ext->handleError != NULL)
+		ext->handleError(ext->errorUptr, error, val);
+}
+
+// Returns `NULL` for a stash without the extension data (i.e. while it's created)
+static FONSextContext* fons__ext(FONScontext* stash)
+{
+	if (stash == NULL || stash->handleError != fons__extHandleError)
+		return NULL;
+	return (FONSextContext*)stash->errorUptr;
+}
+
+static void fons__extReleasePacker(FONSextContext* ext)
+{
+	if (ext->packer != NULL) {
+		free(ext->packer->rects);
+		free(ext->packer);
+		ext->packer = NULL;
+	}
+}
+
+static int fons__extPushRect(FONSextPacker* p, int x, int y, int w, int h)
+{
+	FONSextRect* r;
+
+	if (w <= 0 || h <= 0)
+		return 1;
+	if (p->nrects + 1 > p->crects) {
+		int crects = p->crects == 0 ? 16 : p->crects * 2;
+		FONSextRect* rects = (FONSextRect*)realloc(p->rects, sizeof(FONSextRect) * crects);
+		if (rects == NULL)
+			return 0;
+		p->rects = rects;
+		p->crects = crects;
+	}
+	r = &p->rects[p->nrects++];
+	r->x = x;
+	r->y = y;
+	r->w = w;
+	r->h = h;
+	return 1;
+}
+
+static void fons__extPackerReset(FONSextPacker* p, int w, int h)
+{
+	p->width = w;
+	p->height = h;
+	p->nrects = 0;
+	p->top = 0;
+	p->padding = -1;
+	if (p->packer == FONS_PACKER_GUILLOTINE)
+		fons__extPushRect(p, 0, 0, w, h);
+}
+
+static void fons__extPackerExpand(FONSextPacker* p, int w, int h)
+{
+	if (p->packer == FONS_PACKER_GUILLOTINE) {
+		fons__extPushRect(p, p->width, 0, w - p->width, h);
+		fons__extPushRect(p, 0, p->height, p->width, h - p->height);
+	}
+	// shelves extend to the new width
+	p->width = w;
+	p->height = h;
+}
+
+// Returns the index of the lowest shelf fitting the rectangle, or `-1`
+static int fons__extFindShelf(FONSextPacker* p, int rw, int rh, int maxh)
+{
+	int i, best = -1;
+
+	for (i = 0; i < p->nrects; i++) {
+		FONSextRect* s = &p->rects[i];
+		if (s->h < rh || s->h > maxh || s->x + rw > p->width)
+			continue;
+		if (best == -1 || s->h < p->rects[best].h)
+			best = i;
+	}
+	return best;
+}
+
+static int fons__extShelfAddRect(FONSextPacker* p, int rw, int rh, int* rx, int* ry)
+{
+	// shelves much taller than the rectangle are used only if a new shelf doesn't fit
+	int i = fons__extFindShelf(p, rw, rh, rh + rh / 2);
+
+	if (i == -1 && rw <= p->width && p->top + rh <= p->height) {
+		if (fons__extPushRect(p, 0, p->top, p->width, rh) == 0)
+			return 0;
+		p->top += rh;
+		i = p->nrects - 1;
+	}
+	if (i == -1)
+		i = fons__extFindShelf(p, rw, rh, p->height);
+	if (i == -1)
+		return 0;
+
+	*rx = p->rects[i].x;
+	*ry = p->rects[i].y;
+	p->rects[i].x += rw;
+	return 1;
+}
+
+static int fons__extGuillotineAddRect(FONSextPacker* p, int rw, int rh, int* rx, int* ry)
+{
+	int i, best = -1;
+	FONSextRect r;
+
+	// best area fit
+	for (i = 0; i < p->nrects; i++) {
+		FONSextRect* f = &p->rects[i];
+		if (f->w < rw || f->h < rh)
+			continue;
+		if (best == -1 || f->w * f->h < p->rects[best].w * p->rects[best].h)
+			best = i;
+	}
+	if (best == -1)
+		return 0;
+
+	r = p->rects[best];
+	p->rects[best] = p->rects[--p->nrects];
+	*rx = r.x;
+	*ry = r.y;
+
+	// split along the shorter leftover axis
+	if (r.w - rw < r.h - rh) {
+		fons__extPushRect(p, r.x + rw, r.y, r.w - rw, rh);
+		fons__extPushRect(p, r.x, r.y + rh, r.w, r.h - rh);
+	} else {
+		fons__extPushRect(p, r.x + rw, r.y, r.w - rw, r.h);
+		fons__extPushRect(p, r.x, r.y + rh, rw, r.h - rh);
+	}
+	return 1;
+}
+
+// Returns a rectangle to the free rectangles, merged with the free ones sharing a whole edge. The
+// region is lost if the allocation failed
+static void fons__extGuillotineFreeRect(FONSextPacker* p, int x, int y, int w, int h)
+{
+	int i, merged = 1;
+
+	while (merged) {
+		merged = 0;
+		for (i = 0; i < p->nrects; i++) {
+			FONSextRect f = p->rects[i];
+			if (f.y == y && f.h == h && (f.x + f.w == x || x + w == f.x)) {
+				x = fons__mini(x, f.x);
+				w += f.w;
+			} else if (f.x == x && f.w == w && (f.y + f.h == y || y + h == f.y)) {
+				y = fons__mini(y, f.y);
+				h += f.h;
+			} else {
+				continue;
+			}
+			p->rects[i] = p->rects[--p->nrects];
+			merged = 1;
+			break;
+		}
+	}
+	fons__extPushRect(p, x, y, w, h);
+}
+
+static FONSextPacker* fons__extFindPacker(FONScontext* stash);
+static FONSextContext* fons__extAtlasOwner(FONScontext* stash);
+
+// The rectangle is inflated by the padding, and the position of the glyph inside is returned
+static int fons__extAtlasAddRect(FONScontext* stash, FONSatlas* atlas, int rw, int rh, int* rx, int* ry)
+{
+	FONSextPacker* p = fons__extFindPacker(stash);
+	FONSextContext* owner = fons__extAtlasOwner(stash);
+	int pad = owner != NULL && !owner->unpadded ? owner->padding : 0;
+	int added;
+
+	rw += pad * 2;
+	rh += pad * 2;
+	if (p == NULL)
+		added = fons__extSkylineAddRect(atlas, rw, rh, rx, ry);
+	else if (p->packer == FONS_PACKER_SHELF)
+		added = fons__extShelfAddRect(p, rw, rh, rx, ry);
+	else
+		added = fons__extGuillotineAddRect(p, rw, rh, rx, ry);
+	if (added) {
+		*rx += pad;
+		*ry += pad;
+		if (p != NULL && (owner == NULL || !owner->unpadded))
+			p->padding = p->padding == -1 || p->padding == pad ? pad : -2;
+	}
+	return added;
+}
+
+// Clears the color pixels for an atlas of `w` x `h`. They're released if the allocation failed
+static void fons__extResetColorAtlas(FONSextContext* ext, int w, int h)
+{
+	unsigned char* data;
+
+	if (ext == NULL || !ext->colorAtlas)
+		return;
+	data = (unsigned char*)realloc(ext->colorData, (size_t)w * h * 4);
+	if (data == NULL) {
+		free(ext->colorData);
+		ext->colorData = NULL;
+		return;
+	}
+	memset(data, 0, (size_t)w * h * 4);
+	ext->colorData = data;
+}
+
+// Keeps the color pixels of the `ow` x `oh` atlas at the top left of the expanded one
+static void fons__extExpandColorAtlas(FONSextContext* ext, int ow, int oh, int w, int h)
+{
+	unsigned char* data;
+	int y;
+
+	if (ext == NULL || ext->colorData == NULL)
+		return;
+	data = (unsigned char*)calloc((size_t)w * h, 4);
+	if (data != NULL) {
+		for (y = 0; y < oh; y++)
+			memcpy(&data[(size_t)y * w * 4], &ext->colorData[(size_t)y * ow * 4], (size_t)ow * 4);
+	}
+	free(ext->colorData);
+	ext->colorData = data;
+}
+
+static void fons__extAtlasReset(FONScontext* stash, FONSatlas* atlas, int w, int h)
+{
+	FONSextPacker* p = fons__extFindPacker(stash);
+
+	// the skyline is kept so that switching back to it works
+	fons__extSkylineReset(atlas, w, h);
+	if (p != NULL)
+		fons__extPackerReset(p, w, h);
+	fons__extResetColorAtlas(fons__extAtlasOwner(stash), w, h);
+}
+
+// The size of the stash is still the previous one
+static void fons__extAtlasExpand(FONScontext* stash, FONSatlas* atlas, int w, int h)
+{
+	FONSextPacker* p = fons__extFindPacker(stash);
+
+	fons__extSkylineExpand(atlas, w, h);
+	if (p != NULL)
+		fons__extPackerExpand(p, w, h);
+	fons__extExpandColorAtlas(fons__extAtlasOwner(stash), stash->params.width, stash->params.height, w, h);
+}
+
+
+// Font of a stash with its glyphs in the atlas of another stash (`page`). The page has a copy of
+// the font, whose glyph cache is used
+typedef struct FONSextPage {
+	FONScontext* stash;
+	FONSfont* font;
+	FONScontext* page;
+	int pageFont;
+	// in the `pages` of `stash`
+	struct FONSextPage* next;
+	// in the `pageRefs` of `page`
+	struct FONSextPage* nextRef;
+} FONSextPage;
+
+static FONSextPage* fons__extFindPage(FONScontext* stash, FONSfont* font)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextPage* e;
+
+	for (e = ext != NULL ? ext->pages : NULL; e != NULL; e = e->next) {
+		if (e->font == font)
+			return e;
+	}
+	return NULL;
+}
+
+static void fons__extUnlinkPage(FONSextPage* e)
+{
+	FONSextPage** link = &fons__ext(e->stash)->pages;
+	while (*link != e)
+		link = &(*link)->next;
+	*link = e->next;
+
+	link = &fons__ext(e->page)->pageRefs;
+	while (*link != e)
+		link = &(*link)->nextRef;
+	*link = e->nextRef;
+
+	free(e);
+}
+
+// Releases the pages of the font, or all the pages from or to the stash if `font` is `NULL`
+static void fons__extReleasePages(FONScontext* stash, FONSfont* font)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextPage* e;
+	FONSextPage* next;
+
+	if (ext == NULL)
+		return;
+	for (e = ext->pages; e != NULL; e = next) {
+		next = e->next;
+		if (font == NULL || e->font == font)
+			fons__extUnlinkPage(e);
+	}
+	if (font == NULL) {
+		while (ext->pageRefs != NULL)
+			fons__extUnlinkPage(ext->pageRefs);
+	}
+}
+
+// Atlas of a stash, which is moved between stashes while using a font with a page
+typedef struct FONSextAtlasState {
+	FONSparams params;
+	float itw, ith;
+	unsigned char* texData;
+	int dirtyRect[4];
+	FONSatlas* atlas;
+} FONSextAtlasState;
+
+static void fons__extSaveAtlas(FONScontext* stash, FONSextAtlasState* state)
+{
+	state->params = stash->params;
+	state->itw = stash->itw;
+	state->ith = stash->ith;
+	state->texData = stash->texData;
+	memcpy(state->dirtyRect, stash->dirtyRect, sizeof(state->dirtyRect));
+	state->atlas = stash->atlas;
+}
+
+static void fons__extLoadAtlas(FONScontext* stash, const FONSextAtlasState* state)
+{
+	stash->params = state->params;
+	stash->itw = state->itw;
+	stash->ith = state->ith;
+	stash->texData = state->texData;
+	memcpy(stash->dirtyRect, state->dirtyRect, sizeof(state->dirtyRect));
+	stash->atlas = state->atlas;
+}
+
+// The stash using the atlas of a page
+typedef struct FONSextPaging {
+	FONScontext* stash;
+	FONScontext* page;
+	int (*renderExpand)(void* uptr);
+	void* userPtr;
+	struct FONSextPaging* prev;
+} FONSextPaging;
+
+// Extension data of the stash owning the atlas in the stash, which is the page while paging
+static FONSextContext* fons__extAtlasOwner(FONScontext* stash)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext != NULL && ext->paging != NULL)
+		ext = fons__ext(ext->paging->page);
+	return ext;
+}
+
+// The packer of the atlas in the stash
+static FONSextPacker* fons__extFindPacker(FONScontext* stash)
+{
+	FONSextContext* ext = fons__extAtlasOwner(stash);
+	return ext != NULL ? ext->packer : NULL;
+}
+
+// The blur of the glyph key, which is the spread in an SDF atlas
+static short fons__extGlyphBlur(FONScontext* stash, short iblur)
+{
+	FONSextContext* owner = fons__extAtlasOwner(stash);
+	return owner != NULL && owner->sdfSpread > 0 ? (short)owner->sdfSpread : iblur;
+}
+
+#define FONS__EXT_EDT_INF 1e20f
+
+// Squared distances `d` to the nearest zeros of `f` (`0` or `FONS__EXT_EDT_INF`) in one row with
+// the lower envelope of parabolas (Felzenszwalb and Huttenlocher). `v` has `n` and `z` `n + 1`
+// elements
+static void fons__extEdt1d(const float* f, int n, float* d, int* v, float* z)
+{
+	int q, k = 0;
+	float s;
+
+	v[0] = 0;
+	z[0] = -FONS__EXT_EDT_INF;
+	z[1] = FONS__EXT_EDT_INF;
+	for (q = 1; q < n; q++) {
+		for (;;) {
+			int r = v[k];
+			s = ((f[q] + (float)(q * q)) - (f[r] + (float)(r * r))) / (float)(2 * q - 2 * r);
+			if (s > z[k] || k == 0)
+				break;
+			k--;
+		}
+		k++;
+		v[k] = q;
+		z[k] = s;
+		z[k + 1] = FONS__EXT_EDT_INF;
+	}
+
+	k = 0;
+	for (q = 0; q < n; q++) {
+		while (z[k + 1] < (float)q)
+			k++;
+		d[q] = (float)((q - v[k]) * (q - v[k])) + f[v[k]];
+	}
+}
+
+// Squared distances of the `w` x `h` grid, transformed in place by columns and then by rows.
+// `tmp` has `3 * max(w, h) + 1` floats and `v` `max(w, h)` ints
+static void fons__extEdt2d(float* grid, int w, int h, float* tmp, int* v)
+{
+	int n = w > h ? w : h, x, y;
+	float* f = tmp;
+	float* d = tmp + n;
+	float* z = tmp + n * 2;
+
+	for (x = 0; x < w; x++) {
+		for (y = 0; y < h; y++)
+			f[y] = grid[x + y * w];
+		fons__extEdt1d(f, h, d, v, z);
+		for (y = 0; y < h; y++)
+			grid[x + y * w] = d[y];
+	}
+	for (y = 0; y < h; y++) {
+		memcpy(f, &grid[y * w], sizeof(float) * w);
+		fons__extEdt1d(f, w, &grid[y * w], v, z);
+	}
+}
+
+// Replaces the coverage of a glyph with its signed distance field. The outline is where the
+// coverage crosses the half, and the distance is positive inside. It's left as coverage if the
+// buffers couldn't be allocated
+static void fons__extSdf(const FONSextContext* owner, unsigned char* dst, int w, int h, int dstStride)
+{
+	int n = w > h ? w : h, x, y;
+	float* toInside = (float*)malloc(sizeof(float) * w * h);
+	float* toOutside = (float*)malloc(sizeof(float) * w * h);
+	float* tmp = (float*)malloc(sizeof(float) * (n * 3 + 1));
+	int* v = (int*)malloc(sizeof(int) * n);
+
+	if (toInside != NULL && toOutside != NULL && tmp != NULL && v != NULL) {
+		for (y = 0; y < h; y++) {
+			for (x = 0; x < w; x++) {
+				int inside = dst[x + y * dstStride] >= 128;
+				toInside[x + y * w] = inside ? 0.0f : FONS__EXT_EDT_INF;
+				toOutside[x + y * w] = inside ? FONS__EXT_EDT_INF : 0.0f;
+			}
+		}
+		fons__extEdt2d(toInside, w, h, tmp, v);
+		fons__extEdt2d(toOutside, w, h, tmp, v);
+
+		for (y = 0; y < h; y++) {
+			for (x = 0; x < w; x++) {
+				int i = x + y * w;
+				// pixel centers are half a pixel off the outline
+				float dist = toInside[i] == 0.0f ? sqrtf(toOutside[i]) - 0.5f : 0.5f - sqrtf(toInside[i]);
+				float val = (float)owner->sdfOnEdge + dist * owner->sdfDistScale;
+				dst[x + y * dstStride] = (unsigned char)(val < 0.0f ? 0.0f : val > 255.0f ? 255.0f : val + 0.5f);
+			}
+		}
+	}
+
+	free(toInside);
+	free(toOutside);
+	free(tmp);
+	free(v);
+}
+
+static void fons__extBlur(FONScontext* stash, unsigned char* dst, int w, int h, int dstStride, int blur)
+{
+	FONSextContext* owner = fons__extAtlasOwner(stash);
+
+	if (owner != NULL && owner->sdfSpread > 0)
+		fons__extSdf(owner, dst, w, h, dstStride);
+	else
+		fons__extPlainBlur(stash, dst, w, h, dstStride, blur);
+}
+
+static void fons__extAddWhiteRect(FONScontext* stash, int w, int h)
+{
+	FONSextContext* ext = fons__extAtlasOwner(stash);
+
+	if (ext != NULL)
+		ext->unpadded = 1;
+	fons__extPlainAddWhiteRect(stash, w, h);
+	if (ext != NULL)
+		ext->unpadded = 0;
+}
+
+// Moves the atlas back to the page while it's expanded, because `renderExpand` expands the page.
+// It's called with the paging as the user pointer
+static int fons__extPagedExpand(void* uptr)
+{
+	FONSextPaging* paging = (FONSextPaging*)uptr;
+	FONSextAtlasState state;
+	int res;
+
+	fons__extSaveAtlas(paging->stash, &state);
+	state.params.renderExpand = paging->renderExpand;
+	state.params.userPtr = paging->userPtr;
+	fons__extLoadAtlas(paging->page, &state);
+
+	res = paging->renderExpand(paging->userPtr);
+
+	fons__extSaveAtlas(paging->page, &state);
+	state.params.renderExpand = fons__extPagedExpand;
+	state.params.userPtr = paging;
+	fons__extLoadAtlas(paging->stash, &state);
+	return res;
+}
+
+// Moves the atlas of the page into the stash
+static void fons__extEnterPage(FONScontext* stash, FONSextPage* e, FONSextPaging* paging, FONSextAtlasState* saved)
+{
+	FONSextAtlasState state;
+	FONSfont* pageFont = e->page->fonts[e->pageFont];
+
+	FONSextContext* ext = fons__ext(stash);
+
+	fons__extSaveAtlas(stash, saved);
+	fons__extSaveAtlas(e->page, &state);
+	paging->stash = stash;
+	paging->page = e->page;
+	paging->renderExpand = state.params.renderExpand;
+	paging->userPtr = state.params.userPtr;
+	paging->prev = ext->paging;
+	ext->paging = paging;
+	if (state.params.renderExpand != NULL) {
+		state.params.renderExpand = fons__extPagedExpand;
+		state.params.userPtr = paging;
+	}
+	fons__extLoadAtlas(stash, &state);
+
+	// fallbacks are looked up in the stash
+	pageFont->nfallbacks = e->font->nfallbacks;
+	memcpy(pageFont->fallbacks, e->font->fallbacks, sizeof(pageFont->fallbacks));
+}
+
+static void fons__extLeavePage(FONScontext* stash, FONSextPage* e, FONSextPaging* paging, const FONSextAtlasState* saved)
+{
+	FONSextAtlasState state;
+
+	fons__extSaveAtlas(stash, &state);
+	state.params.renderExpand = paging->renderExpand;
+	state.params.userPtr = paging->userPtr;
+	fons__extLoadAtlas(e->page, &state);
+	fons__extLoadAtlas(stash, saved);
+	fons__ext(stash)->paging = paging->prev;
+	e->page->fonts[e->pageFont]->nfallbacks = 0;
+}
+
+static int fons__extGetColorGlyph(FONScontext* stash, FONSfont* font, unsigned int codepoint, short isize, short iblur, FONSglyph** glyph);
+static int fons__extDeferGlyph(FONScontext* stash, FONSfont* font, unsigned int codepoint, short isize, short iblur, FONSglyph** glyph);
+
+static FONSglyph* fons__extGetGlyphPaged(FONScontext* stash, FONSfont* font, unsigned int codepoint, short isize, short iblur)
+{
+	FONSextPage* e = fons__extFindPage(stash, font);
+	FONSextPaging paging;
+	FONSextAtlasState saved;
+	FONSglyph* glyph;
+
+	if (e == NULL && fons__extGetColorGlyph(stash, font, codepoint, isize, iblur, &glyph))
+		return glyph;
+	if (e == NULL && fons__extDeferGlyph(stash, font, codepoint, isize, iblur, &glyph))
+		return glyph;
+	if (e == NULL)
+		return fons__extPlainGetGlyph(stash, font, codepoint, isize, fons__extGlyphBlur(stash, iblur));
+
+	fons__extEnterPage(stash, e, &paging, &saved);
+	glyph = fons__extPlainGetGlyph(stash, e->page->fonts[e->pageFont], codepoint, isize, fons__extGlyphBlur(stash, iblur));
+	fons__extLeavePage(stash, e, &paging, &saved);
+	return glyph;
+}
+
+// Texture coordinates of the glyphs in a page are normalized with the size of the page
+static void fons__extGetQuadPaged(FONScontext* stash, FONSfont* font, int prevGlyphIndex, FONSglyph* glyph, float scale, float spacing, float* x, float* y, FONSquad* q)
+{
+	FONSextPage* e = fons__extFindPage(stash, font);
+	float itw = stash->itw, ith = stash->ith;
+
+	if (e != NULL) {
+		stash->itw = e->page->itw;
+		stash->ith = e->page->ith;
+	}
+	fons__extPlainGetQuad(stash, font, prevGlyphIndex, glyph, scale, spacing, x, y, q);
+	stash->itw = itw;
+	stash->ith = ith;
+}
+
+// Color layers of a font (see `fonsSetColorGlyphs`)
+typedef struct FONSextColorFont {
+	FONSfont* font;
+	// base glyph, first layer and number of layers, sorted by the base glyph
+	int* glyphs;
+	int nglyphs;
+	// glyph and color (`-1` for white)
+	int* layers;
+	int nlayers;
+	// RGBA
+	unsigned char* colors;
+	struct FONSextColorFont* next;
+} FONSextColorFont;
+
+// Releases the color layers of `font`, or of all the fonts if it's `NULL`
+static void fons__extReleaseColorFonts(FONSextContext* ext, FONSfont* font)
+{
+	FONSextColorFont** link = &ext->colorFonts;
+
+	while (*link != NULL) {
+		FONSextColorFont* cf = *link;
+		if (font != NULL && cf->font != font) {
+			link = &cf->next;
+			continue;
+		}
+		*link = cf->next;
+		free(cf->glyphs);
+		free(cf->layers);
+		free(cf->colors);
+		free(cf);
+	}
+}
+
+// Drops the deferred glyphs of the font, or of all the fonts if it's `-1`, when the data of the
+// fonts is replaced
+static void fons__extDropDeferred(FONSextContext* ext, int font)
+{
+	int i, n = 0;
+
+	for (i = 0; i < ext->ndeferred; i++) {
+		if (font != -1 && ext->deferred[i].font != font)
+			ext->deferred[n++] = ext->deferred[i];
+	}
+	ext->ndeferred = n;
+	ext->fontRevision++;
+}
+
+FONScontext* fonsCreateInternal(FONSparams* params)
+{
+	FONScontext* stash = fons__extHooks.create(params);
+	FONSextContext* ext;
+
+	if (stash == NULL)
+		return NULL;
+	ext = (FONSextContext*)calloc(1, sizeof(FONSextContext));
+	if (ext == NULL) {
+		fons__extDeleteInternal(stash);
+		return NULL;
+	}
+	ext->handleError = stash->handleError;
+	ext->errorUptr = stash->errorUptr;
+	stash->handleError = fons__extHandleError;
+	stash->errorUptr = ext;
+	return stash;
+}
+
+void fonsDeleteInternal(FONScontext* stash)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext != NULL) {
+		fons__extReleasePacker(ext);
+		fons__extReleasePages(stash, NULL);
+		fons__extReleaseColorFonts(ext, NULL);
+		free(ext->colorData);
+		free(ext->deferred);
+		stash->handleError = ext->handleError;
+		stash->errorUptr = ext->errorUptr;
+		free(ext);
+	}
+	fons__extDeleteInternal(stash);
+}
+
+void fonsSetErrorCallback(FONScontext* stash, void (*callback)(void* uptr, int error, int val), void* uptr)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext == NULL) {
+		fons__extSetErrorCallback(stash, callback, uptr);
+		return;
+	}
+	ext->handleError = callback;
+	ext->errorUptr = uptr;
+}
+
+// Returns the glyph index of the codepoint in the font or its fallbacks, or `0` if it's missing
+static int fons__extFindGlyph(FONScontext* stash, FONSfont* font, unsigned int codepoint, FONSfont** renderFont)
+{
+	int i, g;
+
+	*renderFont = font;
+	g = fons__tt_getGlyphIndex(&font->font, codepoint);
+	if (g != 0)
+		return g;
+
+	for (i = 0; i < font->nfallbacks; i++) {
+		FONSfont* fallback = stash->fonts[font->fallbacks[i]];
+		g = fons__tt_getGlyphIndex(&fallback->font, codepoint);
+		if (g != 0) {
+			*renderFont = fallback;
+			return g;
+		}
+	}
+	return 0;
+}
+
+// Color glyphs are keyed with this blur, so coverage glyphs cached before loading the layers (or
+// after disabling the color atlas) are not mixed up with them
+#define FONS__EXT_COLOR_BLUR -1
+
+// The layers of the glyph of the codepoint if it's drawn in color: `[layer index, count]` in the
+// returned font. Glyphs of fonts with a page and blurred, SDF or missing glyphs are not in color
+static const int* fons__extFindColorLayers(FONScontext* stash, FONSfont* font, unsigned int codepoint, short iblur, int* g, FONSfont** renderFont, const FONSextColorFont** colorFont)
+{
+	FONSextContext* ext = fons__ext(stash);
+	const FONSextColorFont* cf;
+	int lo = 0, hi;
+
+	if (ext == NULL || ext->colorData == NULL || ext->colorFonts == NULL || ext->sdfSpread > 0 || iblur != 0)
+		return NULL;
+	if (fons__extFindPage(stash, font) != NULL)
+		return NULL;
+	*g = fons__extFindGlyph(stash, font, codepoint, renderFont);
+	if (*g == 0)
+		return NULL;
+
+	for (cf = ext->colorFonts; cf != NULL && cf->font != *renderFont; cf = cf->next)
+		;
+	if (cf == NULL)
+		return NULL;
+	*colorFont = cf;
+
+	hi = cf->nglyphs;
+	while (lo < hi) {
+		int mid = (lo + hi) / 2;
+		if (cf->glyphs[mid * 3] < *g)
+			lo = mid + 1;
+		else
+			hi = mid;
+	}
+	if (lo == cf->nglyphs || cf->glyphs[lo * 3] != *g)
+		return NULL;
+	return &cf->glyphs[lo * 3 + 1];
+}
+
+// Composites the coverage of a layer in `color` over the RGBA pixels (straight alpha)
+static void fons__extCompositeLayer(unsigned char* dst, int dstStride, const unsigned char* mask, int w, int h, const unsigned char* color)
+{
+	int x, y, c;
+
+	for (y = 0; y < h; y++) {
+		for (x = 0; x < w; x++) {
+			unsigned char* d = &dst[x * 4 + y * dstStride];
+			int sa = mask[x + y * w] * color[3] / 255;
+			int da = d[3];
+			int oa = sa + da * (255 - sa) / 255;
+			if (oa == 0)
+				continue;
+			for (c = 0; c < 3; c++)
+				d[c] = (unsigned char)((color[c] * sa + d[c] * da * (255 - sa) / 255) / oa);
+			d[3] = (unsigned char)oa;
+		}
+	}
+}
+
+// Rasterizes the layers of a color glyph into the color pixels, and their union into the atlas.
+// Returns `0` if the glyph is not drawn in color, otherwise `1` with the glyph (`NULL` if the
+// atlas is full)
+static int fons__extGetColorGlyph(FONScontext* stash, FONSfont* font, unsigned int codepoint, short isize, short iblur, FONSglyph** out)
+{
+	static const unsigned char white[4] = {255, 255, 255, 255};
+	FONSextContext* ext = fons__ext(stash);
+	const FONSextColorFont* cf = NULL;
+	FONSfont* renderFont;
+	const int* layers;
+	int i, g, advance = 0, lsb, x0, y0, x1, y1, ux0 = 0, uy0 = 0, ux1 = 0, uy1 = 0, gw, gh, gx, gy, y, added;
+	const int pad = 2;
+	float size = isize / 10.0f;
+	float scale;
+	unsigned int h;
+	FONSglyph* glyph;
+
+	layers = fons__extFindColorLayers(stash, font, codepoint, iblur, &g, &renderFont, &cf);
+	if (layers == NULL)
+		return 0;
+	*out = NULL;
+	if (isize < 2)
+		return 1;
+
+	h = fons__hashint(codepoint) & (FONS_HASH_LUT_SIZE - 1);
+	for (i = font->lut[h]; i != -1; i = font->glyphs[i].next) {
+		if (font->glyphs[i].codepoint == codepoint && font->glyphs[i].size == isize && font->glyphs[i].blur == FONS__EXT_COLOR_BLUR) {
+			*out = &font->glyphs[i];
+			return 1;
+		}
+	}
+
+	// the box is the union of the layers
+	scale = fons__tt_getPixelHeightScale(&renderFont->font, size);
+	for (i = 0; i < layers[1]; i++) {
+		int lg = cf->layers[(layers[0] + i) * 2];
+		if (!fons__tt_buildGlyphBitmap(&renderFont->font, lg, size, scale, &advance, &lsb, &x0, &y0, &x1, &y1) || x0 >= x1 || y0 >= y1)
+			continue;
+		if (ux0 >= ux1 || uy0 >= uy1) {
+			ux0 = x0, uy0 = y0, ux1 = x1, uy1 = y1;
+		} else {
+			ux0 = fons__mini(ux0, x0), uy0 = fons__mini(uy0, y0);
+			ux1 = fons__maxi(ux1, x1), uy1 = fons__maxi(uy1, y1);
+		}
+	}
+	// the advance of the base glyph
+	fons__tt_buildGlyphBitmap(&renderFont->font, g, size, scale, &advance, &lsb, &x0, &y0, &x1, &y1);
+	gw = ux1 - ux0 + pad * 2;
+	gh = uy1 - uy0 + pad * 2;
+
+	added = fons__atlasAddRect(stash->atlas, gw, gh, &gx, &gy);
+	if (added == 0 && stash->params.renderExpand != NULL && stash->params.renderExpand(stash->params.userPtr))
+		added = fons__atlasAddRect(stash->atlas, gw, gh, &gx, &gy);
+	// `renderExpand` may have failed to reallocate the color pixels
+	if (added == 0 || ext->colorData == NULL)
+		return 1;
+
+	glyph = fons__allocGlyph(font);
+	if (glyph == NULL)
+		return 1;
+	glyph->codepoint = codepoint;
+	glyph->size = isize;
+	glyph->blur = FONS__EXT_COLOR_BLUR;
+	glyph->index = g;
+	glyph->x0 = (short)gx;
+	glyph->y0 = (short)gy;
+	glyph->x1 = (short)(glyph->x0 + gw);
+	glyph->y1 = (short)(glyph->y0 + gh);
+	glyph->xadv = (short)(scale * advance * 10.0f);
+	glyph->xoff = (short)(ux0 - pad);
+	glyph->yoff = (short)(uy0 - pad);
+	glyph->next = font->lut[h];
+	font->lut[h] = font->nglyphs - 1;
+
+	for (y = 0; y < gh; y++)
+		memset(&ext->colorData[((size_t)gx + (size_t)(gy + y) * stash->params.width) * 4], 0, (size_t)gw * 4);
+	for (i = 0; i < layers[1]; i++) {
+		int lg = cf->layers[(layers[0] + i) * 2];
+		int color = cf->layers[(layers[0] + i) * 2 + 1];
+		unsigned char* mask;
+		size_t dx, dy;
+		if (!fons__tt_buildGlyphBitmap(&renderFont->font, lg, size, scale, &advance, &lsb, &x0, &y0, &x1, &y1) || x0 >= x1 || y0 >= y1)
+			continue;
+		mask = (unsigned char*)calloc((size_t)(x1 - x0) * (y1 - y0), 1);
+		if (mask == NULL)
+			continue;
+		stash->nscratch = 0;
+		fons__tt_renderGlyphBitmap(&renderFont->font, mask, x1 - x0, y1 - y0, x1 - x0, scale, scale, lg);
+		dx = (size_t)(gx + pad + x0 - ux0);
+		dy = (size_t)(gy + pad + y0 - uy0);
+		fons__extCompositeLayer(&ext->colorData[(dx + dy * stash->params.width) * 4], stash->params.width * 4, mask, x1 - x0, y1 - y0, color < 0 ? white : &cf->colors[color * 4]);
+		free(mask);
+	}
+	// coverage for renderers without the color pixels
+	for (y = 0; y < gh; y++) {
+		for (i = 0; i < gw; i++) {
+			size_t at = (size_t)(gx + i) + (size_t)(gy + y) * stash->params.width;
+			stash->texData[at] = ext->colorData[at * 4 + 3];
+		}
+	}
+
+	stash->dirtyRect[0] = fons__mini(stash->dirtyRect[0], glyph->x0);
+	stash->dirtyRect[1] = fons__mini(stash->dirtyRect[1], glyph->y0);
+	stash->dirtyRect[2] = fons__maxi(stash->dirtyRect[2], glyph->x1);
+	stash->dirtyRect[3] = fons__maxi(stash->dirtyRect[3], glyph->y1);
+
+	*out = glyph;
+	return 1;
+}
+
+int fonsSetColorAtlas(FONScontext* stash, int enabled)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext == NULL)
+		return 0;
+	if (!enabled) {
+		free(ext->colorData);
+		ext->colorData = NULL;
+		ext->colorAtlas = 0;
+		return 1;
+	}
+	ext->colorAtlas = 1;
+	fons__extResetColorAtlas(ext, stash->params.width, stash->params.height);
+	return ext->colorData != NULL;
+}
+
+const unsigned char* fonsGetColorTextureData(FONScontext* stash, int* width, int* height)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (width != NULL)
+		*width = stash->params.width;
+	if (height != NULL)
+		*height = stash->params.height;
+	return ext != NULL ? ext->colorData : NULL;
+}
+
+int fonsSetColorGlyphs(FONScontext* stash, int font, const int* glyphs, int nglyphs, const int* layers, int nlayers, const unsigned char* colors, int ncolors)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextColorFont* cf;
+	int i;
+
+	if (ext == NULL || font < 0 || font >= stash->nfonts || nglyphs < 0 || nlayers < 0 || ncolors < 0)
+		return 0;
+	for (i = 0; i < nglyphs; i++) {
+		const int* r = &glyphs[i * 3];
+		if ((i > 0 && r[0] <= glyphs[(i - 1) * 3]) || r[1] < 0 || r[2] < 0 || r[1] > nlayers - r[2])
+			return 0;
+	}
+	for (i = 0; i < nlayers; i++) {
+		if (layers[i * 2 + 1] < -1 || layers[i * 2 + 1] >= ncolors)
+			return 0;
+	}
+
+	fons__extReleaseColorFonts(ext, stash->fonts[font]);
+	if (nglyphs == 0)
+		return 1;
+
+	cf = (FONSextColorFont*)calloc(1, sizeof(FONSextColorFont));
+	if (cf == NULL)
+		return 0;
+	cf->glyphs = (int*)malloc(sizeof(int) * 3 * nglyphs);
+	cf->layers = (int*)malloc(sizeof(int) * 2 * (nlayers > 0 ? nlayers : 1));
+	cf->colors = (unsigned char*)malloc(4 * (ncolors > 0 ? ncolors : 1));
+	if (cf->glyphs == NULL || cf->layers == NULL || cf->colors == NULL) {
+		free(cf->glyphs);
+		free(cf->layers);
+		free(cf->colors);
+		free(cf);
+		return 0;
+	}
+	memcpy(cf->glyphs, glyphs, sizeof(int) * 3 * nglyphs);
+	if (nlayers > 0)
+		memcpy(cf->layers, layers, sizeof(int) * 2 * nlayers);
+	if (ncolors > 0)
+		memcpy(cf->colors, colors, 4 * ncolors);
+	cf->font = stash->fonts[font];
+	cf->nglyphs = nglyphs;
+	cf->nlayers = nlayers;
+	cf->next = ext->colorFonts;
+	ext->colorFonts = cf;
+	return 1;
+}
+
+int fonsTextIterIsColored(FONScontext* stash, FONStextIter* iter)
+{
+	const FONSextColorFont* cf;
+	FONSfont* renderFont;
+	int g;
+	return fons__extFindColorLayers(stash, iter->font, iter->codepoint, iter->iblur, &g, &renderFont, &cf) != NULL;
+}
+
+static int fons__extFaceIndex(FONSfont* font);
+
+const unsigned char* fonsGetFontData(FONScontext* stash, int font, int* dataSize, int* faceIndex)
+{
+	FONSfont* f;
+
+	if (font < 0 || font >= stash->nfonts)
+		return NULL;
+	f = stash->fonts[font];
+	if (dataSize != NULL)
+		*dataSize = f->dataSize;
+	if (faceIndex != NULL)
+		*faceIndex = fons__extFaceIndex(f);
+	return f->data;
+}
+
+void fonsGetAtlasStats(FONScontext* stash, FONSatlasStats* stats)
+{
+	int i, j;
+
+	stats->usedPixels = 0;
+	stats->totalPixels = stash->params.width * stash->params.height;
+	stats->glyphCount = 0;
+	stats->skylineNodes = stash->atlas->nnodes;
+	{
+		FONSextPacker* p = fons__extFindPacker(stash);
+		if (p != NULL)
+			stats->skylineNodes = p->nrects;
+	}
+
+	for (i = 0; i < stash->nfonts; i++) {
+		FONSfont* font = stash->fonts[i];
+		for (j = 0; j < font->nglyphs; j++) {
+			FONSglyph* glyph = &font->glyphs[j];
+			stats->usedPixels += (glyph->x1 - glyph->x0) * (glyph->y1 - glyph->y0);
+		}
+		stats->glyphCount += font->nglyphs;
+	}
+}
+
+int fonsGetFontIndex(FONScontext* stash, FONSfont* font)
+{
+	int i;
+	for (i = 0; i < stash->nfonts; i++) {
+		if (stash->fonts[i] == font)
+			return i;
+	}
+	return FONS_INVALID;
+}
+
+int fonsTextIterIsMissing(FONScontext* stash, FONStextIter* iter)
+{
+	FONSfont* renderFont;
+	return fons__extFindGlyph(stash, iter->font, iter->codepoint, &renderFont) == 0;
+}
+
+int fonsGetGlyphBounds(FONScontext* stash, unsigned int codepoint, FONSglyphBounds* bounds)
+{
+	FONSstate* state = fons__getState(stash);
+	FONSfont* font;
+	int g, advance, lsb, x0, y0, x1, y1;
+	float scale;
+
+	if (state->font < 0 || state->font >= stash->nfonts)
+		return 0;
+	g = fons__extFindGlyph(stash, stash->fonts[state->font], codepoint, &font);
+	if (g == 0)
+		return 0;
+
+	scale = fons__tt_getPixelHeightScale(&font->font, state->size);
+	fons__tt_buildGlyphBitmap(&font->font, g, state->size, scale, &advance, &lsb, &x0, &y0, &x1, &y1);
+
+	bounds->bearingX = (float)x0;
+	bounds->bearingY = (float)y0;
+	bounds->width = (float)(x1 - x0);
+	bounds->height = (float)(y1 - y0);
+	bounds->advance = scale * advance;
+	return 1;
+}
+
+void fonsGetStateInfo(FONScontext* stash, FONSstateInfo* info)
+{
+	FONSstate* state = fons__getState(stash);
+	info->font = state->font;
+	info->align = state->align;
+	info->size = state->size;
+	info->color = state->color;
+	info->blur = state->blur;
+	info->spacing = state->spacing;
+}
+
+int fonsGetStateInfoAt(FONScontext* stash, int index, FONSstateInfo* info)
+{
+	FONSstate* state;
+
+	if (index < 0 || index >= stash->nstates)
+		return 0;
+	state = &stash->states[index];
+	info->font = state->font;
+	info->align = state->align;
+	info->size = state->size;
+	info->color = state->color;
+	info->blur = state->blur;
+	info->spacing = state->spacing;
+	return 1;
+}
+
+float fonsGetKerning(FONScontext* stash, unsigned int left, unsigned int right)
+{
+	FONSstate* state = fons__getState(stash);
+	FONSfont* font;
+	int g1, g2;
+
+	if (state->font < 0 || state->font >= stash->nfonts)
+		return 0.0f;
+	font = stash->fonts[state->font];
+
+	g1 = fons__tt_getGlyphIndex(&font->font, left);
+	g2 = fons__tt_getGlyphIndex(&font->font, right);
+	if (g1 == 0 || g2 == 0)
+		return 0.0f;
+
+	return fons__tt_getGlyphKernAdvance(&font->font, g1, g2) * fons__tt_getPixelHeightScale(&font->font, state->size);
+}
+
+int fonsGetMaxStates(void)
+{
+	return FONS_MAX_STATES;
+}
+
+int fonsGetStateDepth(FONScontext* stash)
+{
+	return stash->nstates;
+}
+
+// Initializes `impl` with the face at the index of the font collection. Returns `0` on failure
+static int fons__extInitFace(FONScontext* stash, FONSttFontImpl* impl, unsigned char* data, int dataSize, int faceIndex)
+{
+#ifdef FONS_USE_FREETYPE
+	FONS_NOTUSED(stash);
+	return FT_New_Memory_Face(ftLibrary, (const FT_Byte*)data, dataSize, faceIndex, &impl->font) == 0;
+#else
+	int offset = stbtt_GetFontOffsetForIndex(data, faceIndex);
+	FONS_NOTUSED(dataSize);
+	impl->font.userdata = stash;
+	return offset >= 0 && stbtt_InitFont(&impl->font, data, offset);
+#endif
+}
+
+static void fons__extDoneFace(FONSttFontImpl* impl)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Done_Face(impl->font);
+#else
+	FONS_NOTUSED(impl);
+#endif
+}
+
+// Updates the normalized vertical metrics in the same way as `fonsAddFontMem`
+static void fons__extUpdateMetrics(FONSfont* font)
+{
+	int ascent, descent, lineGap, fh;
+
+	fons__tt_getFontVMetrics(&font->font, &ascent, &descent, &lineGap);
+	ascent += lineGap;
+	fh = ascent - descent;
+	font->ascender = (float)ascent / (float)fh;
+	font->descender = (float)descent / (float)fh;
+	font->lineh = font->ascender - font->descender;
+}
+
+int fonsAddFontMemAt(FONScontext* stash, const char* name, unsigned char* data, int dataSize, int freeData, int faceIndex)
+{
+	int idx;
+	FONSfont* font;
+	FONSttFontImpl impl;
+
+	idx = fonsAddFontMem(stash, name, data, dataSize, freeData);
+	if (idx == FONS_INVALID || faceIndex == 0)
+		return idx;
+
+	// `fonsAddFontMem` always loads the first face, so re-initialize the font with the face
+	font = stash->fonts[idx];
+	if (!fons__extInitFace(stash, &impl, data, dataSize, faceIndex)) {
+		fons__freeFont(font);
+		stash->nfonts--;
+		return FONS_INVALID;
+	}
+	fons__extDoneFace(&font->font);
+	font->font = impl;
+	fons__extUpdateMetrics(font);
+
+	return idx;
+}
+
+int fonsAddFontMemCopy(FONScontext* stash, const char* name, const unsigned char* data, int dataSize, int faceIndex)
+{
+	unsigned char* copy = (unsigned char*)malloc(dataSize);
+	if (copy == NULL)
+		return FONS_INVALID;
+	memcpy(copy, data, dataSize);
+	// the copy is owned and freed by the stash
+	return fonsAddFontMemAt(stash, name, copy, dataSize, 1, faceIndex);
+}
+
+int fonsGetFontInfo(FONScontext* stash, int font, FONSfontInfo* info)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Face face;
+#else
+	stbtt_fontinfo* tt;
+	int len = 0;
+#endif
+
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+
+	info->name = stash->fonts[font]->name;
+
+#ifdef FONS_USE_FREETYPE
+	face = stash->fonts[font]->font.font;
+	info->family = face->family_name;
+	info->familyLength = face->family_name == NULL ? 0 : (int)strlen(face->family_name);
+	info->familyIsUtf16 = 0;
+	info->unitsPerEm = face->units_per_EM;
+	info->glyphCount = (int)face->num_glyphs;
+	info->ascent = face->ascender;
+	info->descent = face->descender;
+	info->lineGap = face->height - (face->ascender - face->descender);
+#else
+	tt = &stash->fonts[font]->font.font;
+
+	// family name (name ID 1): Windows Unicode BMP (English), then Mac Roman
+	info->family = stbtt_GetFontNameString(tt, &len, STBTT_PLATFORM_ID_MICROSOFT,
+		STBTT_MS_EID_UNICODE_BMP, STBTT_MS_LANG_ENGLISH, 1);
+	info->familyIsUtf16 = 1;
+	if (info->family == NULL) {
+		info->family = stbtt_GetFontNameString(tt, &len, STBTT_PLATFORM_ID_MAC,
+			STBTT_MAC_EID_ROMAN, STBTT_MAC_LANG_ENGLISH, 1);
+		info->familyIsUtf16 = 0;
+	}
+	info->familyLength = info->family == NULL ? 0 : len;
+
+	info->unitsPerEm = ttUSHORT(tt->data + tt->head + 18);
+	info->glyphCount = tt->numGlyphs;
+	stbtt_GetFontVMetrics(tt, &info->ascent, &info->descent, &info->lineGap);
+#endif
+
+	return 1;
+}
+
+int fonsUsesFreetype(void)
+{
+#ifdef FONS_USE_FREETYPE
+	return 1;
+#else
+	return 0;
+#endif
+}
+
+int fonsHasGlyph(FONScontext* stash, int font, unsigned int codepoint)
+{
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+	return fons__tt_getGlyphIndex(&stash->fonts[font]->font, codepoint) != 0;
+}
+
+// Returns `1` if the font has the SFNT table
+static int fons__extHasTable(FONSfont* font, const char* tag)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_ULong len = 0;
+	FT_ULong ftTag = FT_MAKE_TAG(tag[0], tag[1], tag[2], tag[3]);
+	return FT_Load_Sfnt_Table(font->font.font, ftTag, 0, NULL, &len) == 0;
+#else
+	return stbtt__find_table(font->font.font.data, font->font.font.fontstart, tag) != 0;
+#endif
+}
+
+int fonsGetColorTables(FONScontext* stash, int font)
+{
+	FONSfont* f;
+	int tables = 0;
+
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+	f = stash->fonts[font];
+
+	if (fons__extHasTable(f, "COLR"))
+		tables |= FONS_COLOR_COLR;
+	if (fons__extHasTable(f, "CBDT"))
+		tables |= FONS_COLOR_CBDT;
+	if (fons__extHasTable(f, "sbix"))
+		tables |= FONS_COLOR_SBIX;
+	return tables;
+}
+
+// Invalidates the cached glyphs of a font whose outlines changed. Their pixels are left in the
+// atlas until it's reset
+static void fons__extInvalidateFont(FONScontext* stash, int font)
+{
+	FONSfont* f = stash->fonts[font];
+	int i;
+
+	// the glyph indices of the layers are of the old outlines, and so are the deferred glyphs
+	if (fons__ext(stash) != NULL) {
+		fons__extReleaseColorFonts(fons__ext(stash), f);
+		fons__extDropDeferred(fons__ext(stash), font);
+	}
+
+	f->nglyphs = 0;
+	for (i = 0; i < FONS_HASH_LUT_SIZE; i++)
+		f->lut[i] = -1;
+}
+
+int fonsReloadFontMem(FONScontext* stash, int font, const unsigned char* data, int dataSize, int faceIndex)
+{
+	FONSfont* f;
+	FONSttFontImpl impl;
+	unsigned char* copy;
+
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+	f = stash->fonts[font];
+
+	copy = (unsigned char*)malloc(dataSize);
+	if (copy == NULL)
+		return 0;
+	memcpy(copy, data, dataSize);
+
+	// keep the old font on failure
+	if (!fons__extInitFace(stash, &impl, copy, dataSize, faceIndex)) {
+		free(copy);
+		return 0;
+	}
+
+	fons__extDoneFace(&f->font);
+	if (f->freeData && f->data)
+		free(f->data);
+
+	f->font = impl;
+	f->data = copy;
+	f->dataSize = dataSize;
+	f->freeData = 1;
+	fons__extUpdateMetrics(f);
+	fons__extInvalidateFont(stash, font);
+
+	return 1;
+}
+
+int fonsGetFontAxes(FONScontext* stash, int font, FONSfontAxis* axes, int maxAxes)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Face face;
+	FT_MM_Var* mm;
+	FT_Fixed* coords;
+	int i, n;
+
+	if (font < 0 || font >= stash->nfonts)
+		return -1;
+	face = stash->fonts[font]->font.font;
+	if (!FT_HAS_MULTIPLE_MASTERS(face) || FT_Get_MM_Var(face, &mm) != 0)
+		return 0;
+
+	n = (int)mm->num_axis;
+	coords = (FT_Fixed*)malloc(sizeof(FT_Fixed) * (n > 0 ? n : 1));
+	if (coords == NULL || FT_Get_Var_Design_Coordinates(face, (FT_UInt)n, coords) != 0) {
+		// the default instance
+		for (i = 0; coords != NULL && i < n; i++)
+			coords[i] = mm->axis[i].def;
+	}
+	for (i = 0; i < n && i < maxAxes; i++) {
+		axes[i].tag = (unsigned int)mm->axis[i].tag;
+		axes[i].minValue = mm->axis[i].minimum / 65536.0f;
+		axes[i].defaultValue = mm->axis[i].def / 65536.0f;
+		axes[i].maxValue = mm->axis[i].maximum / 65536.0f;
+		axes[i].value = (coords != NULL ? coords[i] : mm->axis[i].def) / 65536.0f;
+	}
+
+	free(coords);
+	FT_Done_MM_Var(ftLibrary, mm);
+	return n;
+#else
+	FONS_NOTUSED(axes);
+	FONS_NOTUSED(maxAxes);
+	return font < 0 || font >= stash->nfonts ? -1 : 0;
+#endif
+}
+
+int fonsSetFontVariation(FONScontext* stash, int font, const unsigned int* tags, const float* values, int n)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Face face;
+	FT_MM_Var* mm;
+	FT_Fixed* coords;
+	int i, j, res = 1;
+
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+	face = stash->fonts[font]->font.font;
+	if (!FT_HAS_MULTIPLE_MASTERS(face) || FT_Get_MM_Var(face, &mm) != 0)
+		return -1;
+
+	coords = (FT_Fixed*)malloc(sizeof(FT_Fixed) * (mm->num_axis > 0 ? mm->num_axis : 1));
+	if (coords == NULL) {
+		FT_Done_MM_Var(ftLibrary, mm);
+		return 0;
+	}
+	if (FT_Get_Var_Design_Coordinates(face, mm->num_axis, coords) != 0) {
+		for (j = 0; j < (int)mm->num_axis; j++)
+			coords[j] = mm->axis[j].def;
+	}
+
+	for (i = 0; i < n && res == 1; i++) {
+		for (j = 0; j < (int)mm->num_axis; j++) {
+			if (mm->axis[j].tag == tags[i])
+				break;
+		}
+		if (j == (int)mm->num_axis) {
+			res = -1;
+		} else {
+			FT_Fixed v = (FT_Fixed)(values[i] * 65536.0f);
+			if (v < mm->axis[j].minimum)
+				v = mm->axis[j].minimum;
+			if (v > mm->axis[j].maximum)
+				v = mm->axis[j].maximum;
+			coords[j] = v;
+		}
+	}
+	if (res == 1 && FT_Set_Var_Design_Coordinates(face, mm->num_axis, coords) != 0)
+		res = 0;
+
+	free(coords);
+	FT_Done_MM_Var(ftLibrary, mm);
+	if (res == 1) {
+		fons__extUpdateMetrics(stash->fonts[font]);
+		fons__extInvalidateFont(stash, font);
+	}
+	return res;
+#else
+	FONS_NOTUSED(stash);
+	FONS_NOTUSED(font);
+	FONS_NOTUSED(tags);
+	FONS_NOTUSED(values);
+	FONS_NOTUSED(n);
+	return 0;
+#endif
+}
+
+int fonsGetFontCount(FONScontext* stash)
+{
+	return stash->nfonts;
+}
+
+float fonsTextIterKern(FONScontext* stash, FONStextIter* iter, int prevGlyphIndex, int glyphIndex)
+{
+	FONS_NOTUSED(stash);
+	return fons__tt_getGlyphKernAdvance(&iter->font->font, prevGlyphIndex, glyphIndex) * iter->scale;
+}
+
+void fonsGetRenderCallbacks(FONScontext* stash, FONSparams* params)
+{
+	params->userPtr = stash->params.userPtr;
+	params->renderCreate = stash->params.renderCreate;
+	params->renderResize = stash->params.renderResize;
+	params->renderExpand = stash->params.renderExpand;
+	params->renderUpdate = stash->params.renderUpdate;
+	params->renderDelete = stash->params.renderDelete;
+}
+
+void fonsSetRenderCallbacks(FONScontext* stash, const FONSparams* params)
+{
+	stash->params.userPtr = params->userPtr;
+	stash->params.renderCreate = params->renderCreate;
+	stash->params.renderResize = params->renderResize;
+	stash->params.renderExpand = params->renderExpand;
+	stash->params.renderUpdate = params->renderUpdate;
+	stash->params.renderDelete = params->renderDelete;
+}
+
+// Glyphs added by index are keyed with this bit set, which no Unicode codepoint has
+#define FONS_EXT_GLYPH_INDEX_KEY 0x80000000u
+
+// `fons__getGlyph` keyed with the glyph index (no fallback lookup)
+static FONSglyph* fons__extGetGlyphByIndex(FONScontext* stash, FONSfont* font, int g, short isize, short iblur)
+{
+	int i, advance, lsb, x0, y0, x1, y1, gw, gh, gx, gy, x, y, pad, added;
+	unsigned int key = FONS_EXT_GLYPH_INDEX_KEY | (unsigned int)g;
+	unsigned int h;
+	float size = isize / 10.0f;
+	float scale;
+	FONSglyph* glyph;
+	unsigned char* dst;
+
+	iblur = fons__extGlyphBlur(stash, iblur);
+	if (isize < 2)
+		return NULL;
+	if (iblur > 20)
+		iblur = 20;
+	pad = iblur + 2;
+
+	stash->nscratch = 0;
+
+	h = fons__hashint(key) & (FONS_HASH_LUT_SIZE - 1);
+	i = font->lut[h];
+	while (i != -1) {
+		if (font->glyphs[i].codepoint == key && font->glyphs[i].size == isize && font->glyphs[i].blur == iblur)
+			return &font->glyphs[i];
+		i = font->glyphs[i].next;
+	}
+
+	scale = fons__tt_getPixelHeightScale(&font->font, size);
+	fons__tt_buildGlyphBitmap(&font->font, g, size, scale, &advance, &lsb, &x0, &y0, &x1, &y1);
+	gw = x1 - x0 + pad * 2;
+	gh = y1 - y0 + pad * 2;
+
+	added = fons__atlasAddRect(stash->atlas, gw, gh, &gx, &gy);
+	if (added == 0 && stash->params.renderExpand != NULL && stash->params.renderExpand(stash->params.userPtr))
+		added = fons__atlasAddRect(stash->atlas, gw, gh, &gx, &gy);
+	if (added == 0)
+		return NULL;
+
+	glyph = fons__allocGlyph(font);
+	glyph->codepoint = key;
+	glyph->size = isize;
+	glyph->blur = iblur;
+	glyph->index = g;
+	glyph->x0 = (short)gx;
+	glyph->y0 = (short)gy;
+	glyph->x1 = (short)(glyph->x0 + gw);
+	glyph->y1 = (short)(glyph->y0 + gh);
+	glyph->xadv = (short)(scale * advance * 10.0f);
+	glyph->xoff = (short)(x0 - pad);
+	glyph->yoff = (short)(y0 - pad);
+	glyph->next = font->lut[h];
+	font->lut[h] = font->nglyphs - 1;
+
+	dst = &stash->texData[(glyph->x0 + pad) + (glyph->y0 + pad) * stash->params.width];
+	fons__tt_renderGlyphBitmap(&font->font, dst, gw - pad * 2, gh - pad * 2, stash->params.width, scale, scale, g);
+
+	// one pixel empty border
+	dst = &stash->texData[glyph->x0 + glyph->y0 * stash->params.width];
+	for (y = 0; y < gh; y++) {
+		dst[y * stash->params.width] = 0;
+		dst[gw - 1 + y * stash->params.width] = 0;
+	}
+	for (x = 0; x < gw; x++) {
+		dst[x] = 0;
+		dst[x + (gh - 1) * stash->params.width] = 0;
+	}
+
+	if (iblur > 0) {
+		stash->nscratch = 0;
+		fons__blur(stash, dst, gw, gh, stash->params.width, iblur);
+	}
+
+	stash->dirtyRect[0] = fons__mini(stash->dirtyRect[0], glyph->x0);
+	stash->dirtyRect[1] = fons__mini(stash->dirtyRect[1], glyph->y0);
+	stash->dirtyRect[2] = fons__maxi(stash->dirtyRect[2], glyph->x1);
+	stash->dirtyRect[3] = fons__maxi(stash->dirtyRect[3], glyph->y1);
+
+	return glyph;
+}
+
+static FONSglyph* fons__extGetGlyphByIndexPaged(FONScontext* stash, FONSfont* font, int g, short isize, short iblur)
+{
+	FONSextPage* e = fons__extFindPage(stash, font);
+	FONSextPaging paging;
+	FONSextAtlasState saved;
+	FONSglyph* glyph;
+
+	if (e == NULL)
+		return fons__extGetGlyphByIndex(stash, font, g, isize, iblur);
+
+	fons__extEnterPage(stash, e, &paging, &saved);
+	glyph = fons__extGetGlyphByIndex(stash, e->page->fonts[e->pageFont], g, isize, iblur);
+	fons__extLeavePage(stash, e, &paging, &saved);
+	return glyph;
+}
+
+float fonsGetPixelScale(FONScontext* stash)
+{
+	FONSstate* state = fons__getState(stash);
+
+	if (state->font < 0 || state->font >= stash->nfonts)
+		return 0.0f;
+	return fons__tt_getPixelHeightScale(&stash->fonts[state->font]->font, state->size);
+}
+
+int fonsGetGlyphQuad(FONScontext* stash, int glyphIndex, float x, float y, FONSquad* q)
+{
+	FONSstate* state = fons__getState(stash);
+	FONSfont* font;
+	FONSglyph* glyph;
+	short isize = (short)(state->size * 10.0f);
+	short iblur = (short)state->blur;
+	float scale;
+
+	if (state->font < 0 || state->font >= stash->nfonts)
+		return 0;
+	font = stash->fonts[state->font];
+
+	glyph = fons__extGetGlyphByIndexPaged(stash, font, glyphIndex, isize, iblur);
+	if (glyph == NULL)
+		return 0;
+
+	scale = fons__tt_getPixelHeightScale(&font->font, (float)isize / 10.0f);
+	y += fons__getVertAlign(stash, font, state->align, isize);
+	fons__getQuad(stash, font, -1, glyph, scale, state->spacing, &x, &y, q);
+	return 1;
+}
+
+int fonsGetDecorationMetrics(FONScontext* stash, int font, FONSdecorationMetrics* metrics)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Face face;
+	TT_OS2* os2;
+#else
+	stbtt_fontinfo* tt;
+	stbtt_uint32 post, os2;
+#endif
+
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+
+	memset(metrics, 0, sizeof(*metrics));
+
+#ifdef FONS_USE_FREETYPE
+	face = stash->fonts[font]->font.font;
+	metrics->underlinePosition = face->underline_position;
+	metrics->underlineThickness = face->underline_thickness;
+	os2 = (TT_OS2*)FT_Get_Sfnt_Table(face, FT_SFNT_OS2);
+	if (os2 != NULL && os2->version != 0xFFFF) {
+		metrics->strikeoutPosition = os2->yStrikeoutPosition;
+		metrics->strikeoutThickness = os2->yStrikeoutSize;
+	}
+#else
+	tt = &stash->fonts[font]->font.font;
+	post = stbtt__find_table(tt->data, tt->fontstart, "post");
+	if (post != 0) {
+		metrics->underlinePosition = ttSHORT(tt->data + post + 8);
+		metrics->underlineThickness = ttSHORT(tt->data + post + 10);
+	}
+	os2 = stbtt__find_table(tt->data, tt->fontstart, "OS/2");
+	if (os2 != 0) {
+		metrics->strikeoutThickness = ttSHORT(tt->data + os2 + 26);
+		metrics->strikeoutPosition = ttSHORT(tt->data + os2 + 28);
+	}
+#endif
+
+	return 1;
+}
+
+typedef struct FONSextOutline {
+	FONSoutlineSegment* segments;
+	int maxSegments;
+	int nsegments;
+} FONSextOutline;
+
+static void fons__extPushSegment(FONSextOutline* o, int verb, float x, float y, float cx0, float cy0, float cx1, float cy1)
+{
+	FONSoutlineSegment* s;
+
+	if (o->nsegments < o->maxSegments) {
+		s = &o->segments[o->nsegments];
+		s->verb = verb;
+		s->x = x;
+		s->y = y;
+		s->cx0 = cx0;
+		s->cy0 = cy0;
+		s->cx1 = cx1;
+		s->cy1 = cy1;
+	}
+	o->nsegments++;
+}
+
+#ifdef FONS_USE_FREETYPE
+static int fons__extFtMoveTo(const FT_Vector* to, void* user)
+{
+	fons__extPushSegment((FONSextOutline*)user, FONS_OUTLINE_MOVE, (float)to->x, (float)to->y, 0, 0, 0, 0);
+	return 0;
+}
+
+static int fons__extFtLineTo(const FT_Vector* to, void* user)
+{
+	fons__extPushSegment((FONSextOutline*)user, FONS_OUTLINE_LINE, (float)to->x, (float)to->y, 0, 0, 0, 0);
+	return 0;
+}
+
+static int fons__extFtConicTo(const FT_Vector* c, const FT_Vector* to, void* user)
+{
+	fons__extPushSegment((FONSextOutline*)user, FONS_OUTLINE_QUAD, (float)to->x, (float)to->y, (float)c->x, (float)c->y, 0, 0);
+	return 0;
+}
+
+static int fons__extFtCubicTo(const FT_Vector* c0, const FT_Vector* c1, const FT_Vector* to, void* user)
+{
+	fons__extPushSegment((FONSextOutline*)user, FONS_OUTLINE_CUBIC, (float)to->x, (float)to->y, (float)c0->x, (float)c0->y, (float)c1->x, (float)c1->y);
+	return 0;
+}
+#endif
+
+int fonsGetGlyphOutline(FONScontext* stash, int font, unsigned int codepoint, FONSoutlineSegment* segments, int maxSegments)
+{
+	FONSextOutline o;
+	FONSfont* f;
+	int g;
+#ifdef FONS_USE_FREETYPE
+	FT_Face face;
+	FT_Outline_Funcs funcs;
+#else
+	stbtt_vertex* v = NULL;
+	int i, nv;
+#endif
+
+	if (font < 0 || font >= stash->nfonts)
+		return -1;
+	f = stash->fonts[font];
+	o.segments = segments;
+	o.maxSegments = segments == NULL ? 0 : maxSegments;
+	o.nsegments = 0;
+
+	g = fons__tt_getGlyphIndex(&f->font, codepoint);
+	if (g == 0)
+		return 0;
+
+#ifdef FONS_USE_FREETYPE
+	face = f->font.font;
+	if (FT_Load_Glyph(face, (FT_UInt)g, FT_LOAD_NO_SCALE | FT_LOAD_NO_BITMAP) != 0 || face->glyph->format != FT_GLYPH_FORMAT_OUTLINE)
+		return 0;
+	memset(&funcs, 0, sizeof(funcs));
+	funcs.move_to = fons__extFtMoveTo;
+	funcs.line_to = fons__extFtLineTo;
+	funcs.conic_to = fons__extFtConicTo;
+	funcs.cubic_to = fons__extFtCubicTo;
+	if (FT_Outline_Decompose(&face->glyph->outline, &funcs, &o) != 0)
+		return 0;
+#else
+	// the vertices are allocated from the scratch buffer like rasterization
+	stash->nscratch = 0;
+	nv = stbtt_GetGlyphShape(&f->font.font, g, &v);
+	for (i = 0; i < nv; i++) {
+		stbtt_vertex* p = &v[i];
+		switch (p->type) {
+		case STBTT_vmove:
+			fons__extPushSegment(&o, FONS_OUTLINE_MOVE, p->x, p->y, 0, 0, 0, 0);
+			break;
+		case STBTT_vline:
+			fons__extPushSegment(&o, FONS_OUTLINE_LINE, p->x, p->y, 0, 0, 0, 0);
+			break;
+		case STBTT_vcurve:
+			fons__extPushSegment(&o, FONS_OUTLINE_QUAD, p->x, p->y, p->cx, p->cy, 0, 0);
+			break;
+		case STBTT_vcubic:
+			fons__extPushSegment(&o, FONS_OUTLINE_CUBIC, p->x, p->y, p->cx, p->cy, p->cx1, p->cy1);
+			break;
+		}
+	}
+	if (v != NULL)
+		stbtt_FreeShape(&f->font.font, v);
+	stash->nscratch = 0;
+#endif
+
+	return o.nsegments;
+}
+
+int fonsGetGlyphRects(FONScontext* stash, int* rects, int maxRects)
+{
+	int i, j, n = 0;
+
+	for (i = 0; i < stash->nfonts; i++) {
+		FONSfont* font = stash->fonts[i];
+		for (j = 0; j < font->nglyphs; j++, n++) {
+			FONSglyph* glyph = &font->glyphs[j];
+			if (n >= maxRects)
+				continue;
+			rects[n * 4 + 0] = glyph->x0;
+			rects[n * 4 + 1] = glyph->y0;
+			rects[n * 4 + 2] = glyph->x1;
+			rects[n * 4 + 3] = glyph->y1;
+		}
+	}
+
+	return n;
+}
+
+float fonsTextBoundsNoBitmap(FONScontext* stash, float x, float y, const char* str, const char* end, float* bounds)
+{
+	FONSstate* state = fons__getState(stash);
+	unsigned int codepoint;
+	unsigned int utf8state = 0;
+	FONSfont* font;
+	FONSfont* renderFont;
+	int g, prevGlyphIndex = -1;
+	int advance, lsb, x0, y0, x1, y1;
+	short isize = (short)(state->size * 10.0f);
+	float scale, renderScale, kern;
+	float minx, miny, maxx, maxy, startx, width;
+
+	if (state->font < 0 || state->font >= stash->nfonts)
+		return 0;
+	font = stash->fonts[state->font];
+	if (font->data == NULL)
+		return 0;
+
+	scale = fons__tt_getPixelHeightScale(&font->font, (float)isize / 10.0f);
+
+	// align vertically in the same way as `fonsTextBounds`
+	y += fons__getVertAlign(stash, font, state->align, isize);
+
+	minx = maxx = x;
+	miny = maxy = y;
+	startx = x;
+
+	if (end == NULL)
+		end = str + strlen(str);
+
+	for (; str != end; ++str) {
+		if (fons__decutf8(&utf8state, &codepoint, *(const unsigned char*)str))
+			continue;
+
+		// missing glyphs are measured as `.notdef` of the font, as they're rendered
+		g = fons__extFindGlyph(stash, font, codepoint, &renderFont);
+		renderScale = fons__tt_getPixelHeightScale(&renderFont->font, (float)isize / 10.0f);
+		fons__tt_buildGlyphBitmap(&renderFont->font, g, (float)isize / 10.0f, renderScale, &advance, &lsb, &x0, &y0, &x1, &y1);
+
+		// kerning and spacing as `fons__getQuad`
+		if (prevGlyphIndex != -1) {
+			kern = fons__tt_getGlyphKernAdvance(&font->font, prevGlyphIndex, g) * scale;
+			x += (int)(kern + state->spacing + 0.5f);
+		}
+
+		if (x + x0 < minx) minx = x + x0;
+		if (x + x1 > maxx) maxx = x + x1;
+		if (y + y0 < miny) miny = y + y0;
+		if (y + y1 > maxy) maxy = y + y1;
+
+		x += (int)(renderScale * advance + 0.5f);
+		prevGlyphIndex = g;
+	}
+
+	width = x - startx;
+
+	if (state->align & FONS_ALIGN_RIGHT) {
+		minx -= width;
+		maxx -= width;
+	} else if (state->align & FONS_ALIGN_CENTER) {
+		minx -= width * 0.5f;
+		maxx -= width * 0.5f;
+	}
+
+	if (bounds) {
+		bounds[0] = minx;
+		bounds[1] = miny;
+		bounds[2] = maxx;
+		bounds[3] = maxy;
+	}
+
+	return width;
+}
+
+int fonsAddGlyphBitmap(FONScontext* stash, int font, unsigned int codepoint, float size, const unsigned char* data, int width, int height, int stride, int xoff, int yoff, float xadvance)
+{
+	int i, x, y, gx, gy, gw, gh, added;
+	short isize = (short)(size * 10.0f);
+	unsigned int h;
+	FONSfont* f;
+	FONSglyph* glyph;
+	unsigned char* dst;
+
+	if (font < 0 || font >= stash->nfonts || width < 0 || height < 0 || isize < 2)
+		return 0;
+	f = stash->fonts[font];
+	{
+		FONSextPage* e = fons__extFindPage(stash, f);
+		if (e != NULL)
+			return fonsAddGlyphBitmap(e->page, e->pageFont, codepoint, size, data, width, height, stride, xoff, yoff, xadvance);
+	}
+	// coverage can't be looked up with the spread as the blur
+	if (fons__ext(stash) != NULL && fons__ext(stash)->sdfSpread > 0)
+		return 0;
+
+	h = fons__hashint(codepoint) & (FONS_HASH_LUT_SIZE - 1);
+	i = f->lut[h];
+	while (i != -1) {
+		if (f->glyphs[i].codepoint == codepoint && f->glyphs[i].size == isize && f->glyphs[i].blur == 0)
+			return 0;
+		i = f->glyphs[i].next;
+	}
+
+	// one pixel empty border as rasterized glyphs
+	gw = width + 2;
+	gh = height + 2;
+
+	added = fons__atlasAddRect(stash->atlas, gw, gh, &gx, &gy);
+	if (added == 0 && stash->params.renderExpand != NULL && stash->params.renderExpand(stash->params.userPtr))
+		added = fons__atlasAddRect(stash->atlas, gw, gh, &gx, &gy);
+	if (added == 0)
+		return -1;
+
+	glyph = fons__allocGlyph(f);
+	if (glyph == NULL)
+		return 0;
+	glyph->codepoint = codepoint;
+	glyph->size = isize;
+	glyph->blur = 0;
+	// kerning is read from the font
+	glyph->index = fons__tt_getGlyphIndex(&f->font, codepoint);
+	glyph->x0 = (short)gx;
+	glyph->y0 = (short)gy;
+	glyph->x1 = (short)(glyph->x0 + gw);
+	glyph->y1 = (short)(glyph->y0 + gh);
+	glyph->xadv = (short)(xadvance * 10.0f);
+	glyph->xoff = (short)(xoff - 1);
+	glyph->yoff = (short)(yoff - 1);
+	glyph->next = f->lut[h];
+	f->lut[h] = f->nglyphs - 1;
+
+	dst = &stash->texData[glyph->x0 + glyph->y0 * stash->params.width];
+	for (y = 0; y < gh; y++) {
+		for (x = 0; x < gw; x++) {
+			int inside = x > 0 && y > 0 && x <= width && y <= height;
+			dst[x + y * stash->params.width] = inside ? data[(x - 1) + (y - 1) * stride] : 0;
+		}
+	}
+
+	stash->dirtyRect[0] = fons__mini(stash->dirtyRect[0], glyph->x0);
+	stash->dirtyRect[1] = fons__mini(stash->dirtyRect[1], glyph->y0);
+	stash->dirtyRect[2] = fons__maxi(stash->dirtyRect[2], glyph->x1);
+	stash->dirtyRect[3] = fons__maxi(stash->dirtyRect[3], glyph->y1);
+
+	return 1;
+}
+
+// Advance of the glyph in font units, read without rasterizing the glyph
+static int fons__extGlyphAdvance(FONSttFontImpl* impl, int g)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Fixed advance;
+	return FT_Get_Advance(impl->font, g, FT_LOAD_NO_SCALE, &advance) == 0 ? (int)advance : 0;
+#else
+	int advance, lsb;
+	stbtt_GetGlyphHMetrics(&impl->font, g, &advance, &lsb);
+	return advance;
+#endif
+}
+
+static FONSextDeferred* fons__extFindDeferred(FONSextContext* ext, int font, unsigned int codepoint, short isize)
+{
+	int i;
+
+	for (i = 0; i < ext->ndeferred; i++) {
+		FONSextDeferred* d = &ext->deferred[i];
+		if (d->font == font && d->codepoint == codepoint && d->isize == isize)
+			return d;
+	}
+	return NULL;
+}
+
+// Queues the glyph instead of rasterizing it while the glyphs are deferred and returns the
+// placeholder, which is an empty quad advancing the pen as the glyph. Only glyphs of the font
+// itself without blur are deferred; glyphs of the fallbacks are rasterized as usual
+static int fons__extDeferGlyph(FONScontext* stash, FONSfont* font, unsigned int codepoint, short isize, short iblur, FONSglyph** out)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextDeferred* d;
+	FONSglyph* p;
+	unsigned int h;
+	int i, g, fi;
+	float scale;
+
+	if (ext == NULL || !ext->deferGlyphs || isize < 2 || fons__extGlyphBlur(stash, iblur) != 0)
+		return 0;
+	h = fons__hashint(codepoint) & (FONS_HASH_LUT_SIZE - 1);
+	for (i = font->lut[h]; i != -1; i = font->glyphs[i].next) {
+		if (font->glyphs[i].codepoint == codepoint && font->glyphs[i].size == isize && font->glyphs[i].blur == 0)
+			return 0;
+	}
+	g = fons__tt_getGlyphIndex(&font->font, codepoint);
+	fi = fonsGetFontIndex(stash, font);
+	if (g == 0 || fi == FONS_INVALID)
+		return 0;
+
+	if (fons__extFindDeferred(ext, fi, codepoint, isize) == NULL) {
+		if (ext->ndeferred == ext->cdeferred) {
+			int c = ext->cdeferred == 0 ? 64 : ext->cdeferred * 2;
+			d = (FONSextDeferred*)realloc(ext->deferred, sizeof(FONSextDeferred) * c);
+			// rasterized now instead
+			if (d == NULL)
+				return 0;
+			ext->deferred = d;
+			ext->cdeferred = c;
+		}
+		d = &ext->deferred[ext->ndeferred++];
+		d->id = ++ext->deferredId;
+		d->font = fi;
+		d->codepoint = codepoint;
+		d->isize = isize;
+		d->taken = 0;
+	}
+
+	// `fons__getQuad` makes a quad of the rectangle without its border
+	scale = fons__tt_getPixelHeightScale(&font->font, isize / 10.0f);
+	p = &ext->placeholder;
+	memset(p, 0, sizeof(*p));
+	p->codepoint = codepoint;
+	p->size = isize;
+	p->index = g;
+	p->x1 = 2;
+	p->y1 = 2;
+	p->xadv = (short)(scale * fons__extGlyphAdvance(&font->font, g) * 10.0f);
+	p->next = -1;
+	*out = p;
+	return 1;
+}
+
+static int fons__extRemoveDeferred(FONSextContext* ext, int id, FONSextDeferred* out)
+{
+	int i;
+
+	if (ext == NULL)
+		return 0;
+	for (i = 0; i < ext->ndeferred; i++) {
+		if (ext->deferred[i].id == id) {
+			*out = ext->deferred[i];
+			ext->deferred[i] = ext->deferred[--ext->ndeferred];
+			return 1;
+		}
+	}
+	return 0;
+}
+
+int fonsSetDeferredGlyphs(FONScontext* stash, int enabled)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext == NULL)
+		return 0;
+	ext->deferGlyphs = enabled != 0;
+	// the glyphs being rasterized are rejected by `fonsAddDeferredGlyph`
+	if (!enabled)
+		ext->ndeferred = 0;
+	return 1;
+}
+
+int fonsTakeDeferredGlyphs(FONScontext* stash, FONSdeferredGlyph* glyphs, int maxGlyphs)
+{
+	FONSextContext* ext = fons__ext(stash);
+	int i, n = 0;
+
+	if (ext == NULL)
+		return 0;
+	for (i = 0; i < ext->ndeferred && n < maxGlyphs; i++) {
+		FONSextDeferred* d = &ext->deferred[i];
+		if (d->taken)
+			continue;
+		d->taken = 1;
+		glyphs[n].id = d->id;
+		glyphs[n].font = d->font;
+		glyphs[n].codepoint = d->codepoint;
+		glyphs[n].size = d->isize / 10.0f;
+		n++;
+	}
+	return n;
+}
+
+int fonsGetFontRevision(FONScontext* stash)
+{
+	FONSextContext* ext = fons__ext(stash);
+	return ext != NULL ? ext->fontRevision : 0;
+}
+
+int fonsAddDeferredGlyph(FONScontext* stash, int id, const unsigned char* data, int width, int height, int stride, int xoff, int yoff, float xadvance)
+{
+	FONSextDeferred d;
+
+	if (!fons__extRemoveDeferred(fons__ext(stash), id, &d))
+		return 0;
+	return fonsAddGlyphBitmap(stash, d.font, d.codepoint, d.isize / 10.0f, data, width, height, stride, xoff, yoff, xadvance);
+}
+
+int fonsRasterizeDeferredGlyph(FONScontext* stash, int id)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextDeferred d;
+	FONSglyph* glyph;
+	int defer;
+
+	if (!fons__extRemoveDeferred(ext, id, &d))
+		return 0;
+	defer = ext->deferGlyphs;
+	ext->deferGlyphs = 0;
+	glyph = fons__extGetGlyphPaged(stash, stash->fonts[d.font], d.codepoint, d.isize, 0);
+	ext->deferGlyphs = defer;
+	return glyph != NULL ? 1 : -1;
+}
+
+int fonsTextIterIsDeferred(FONScontext* stash, FONStextIter* iter)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext == NULL || !ext->deferGlyphs || fons__extFindPage(stash, iter->font) != NULL || fons__extGlyphBlur(stash, iter->iblur) != 0 || fonsTextIterIsColored(stash, iter))
+		return 0;
+	return fons__extFindDeferred(ext, fonsGetFontIndex(stash, iter->font), iter->codepoint, iter->isize) != NULL;
+}
+
+struct FONSrasterizer {
+	FONSttFontImpl font;
+	unsigned char* data;
+#ifdef FONS_USE_FREETYPE
+	FT_Library library;
+#else
+	// only the scratch buffer is used, by the allocations of `stb_truetype`
+	FONScontext* scratch;
+#endif
+	// of the last glyph
+	unsigned char* pixels;
+	int npixels;
+};
+
+FONSrasterizer* fonsCreateRasterizer(FONScontext* stash, int font)
+{
+	FONSrasterizer* r;
+	FONSfont* f;
+
+	if (font < 0 || font >= stash->nfonts || stash->fonts[font]->data == NULL)
+		return NULL;
+	f = stash->fonts[font];
+	r = (FONSrasterizer*)calloc(1, sizeof(FONSrasterizer));
+	if (r == NULL)
+		return NULL;
+	r->data = (unsigned char*)malloc(f->dataSize);
+	if (r->data == NULL)
+		goto error;
+	memcpy(r->data, f->data, f->dataSize);
+
+#ifdef FONS_USE_FREETYPE
+	// FreeType libraries can be used on different threads, but not one library
+	if (FT_Init_FreeType(&r->library) != 0) {
+		r->library = NULL;
+		goto error;
+	}
+	if (FT_New_Memory_Face(r->library, (const FT_Byte*)r->data, f->dataSize, fons__extFaceIndex(f), &r->font.font) != 0) {
+		r->font.font = NULL;
+		goto error;
+	}
+	// with the coordinates of the variation axes
+	if (FT_HAS_MULTIPLE_MASTERS(f->font.font)) {
+		FT_Fixed coords[16];
+		FT_UInt n = 16;
+		FT_MM_Var* mm;
+		if (FT_Get_MM_Var(f->font.font, &mm) == 0) {
+			n = mm->num_axis < n ? mm->num_axis : n;
+			FT_Done_MM_Var(ftLibrary, mm);
+			if (FT_Get_Var_Design_Coordinates(f->font.font, n, coords) == 0)
+				FT_Set_Var_Design_Coordinates(r->font.font, n, coords);
+		}
+	}
+#else
+	r->scratch = (FONScontext*)calloc(1, sizeof(FONScontext));
+	if (r->scratch == NULL)
+		goto error;
+	r->scratch->scratch = (unsigned char*)malloc(FONS_SCRATCH_BUF_SIZE);
+	if (r->scratch->scratch == NULL)
+		goto error;
+	if (!fons__extInitFace(r->scratch, &r->font, r->data, f->dataSize, fons__extFaceIndex(f)))
+		goto error;
+#endif
+	return r;
+
+error:
+	fonsDeleteRasterizer(r);
+	return NULL;
+}
+
+void fonsDeleteRasterizer(FONSrasterizer* r)
+{
+	if (r == NULL)
+		return;
+#ifdef FONS_USE_FREETYPE
+	if (r->font.font != NULL)
+		FT_Done_Face(r->font.font);
+	if (r->library != NULL)
+		FT_Done_FreeType(r->library);
+#else
+	if (r->scratch != NULL)
+		free(r->scratch->scratch);
+	free(r->scratch);
+#endif
+	free(r->pixels);
+	free(r->data);
+	free(r);
+}
+
+const unsigned char* fonsRasterizeGlyph(FONSrasterizer* r, unsigned int codepoint, float size, FONSglyphBitmap* bitmap)
+{
+	int g, advance, lsb, x0, y0, x1, y1, w, h;
+	short isize = (short)(size * 10.0f);
+	float scale;
+
+	if (isize < 2)
+		return NULL;
+	// quantized as the sizes of the glyph keys
+	size = isize / 10.0f;
+	g = fons__tt_getGlyphIndex(&r->font, codepoint);
+	if (g == 0)
+		return NULL;
+#ifndef FONS_USE_FREETYPE
+	r->scratch->nscratch = 0;
+#endif
+	scale = fons__tt_getPixelHeightScale(&r->font, size);
+	if (!fons__tt_buildGlyphBitmap(&r->font, g, size, scale, &advance, &lsb, &x0, &y0, &x1, &y1))
+		return NULL;
+
+	// the empty border of the glyphs rasterized by `fons__getGlyph`, which `fonsAddGlyphBitmap`
+	// doesn't add
+	w = x1 - x0 + 2;
+	h = y1 - y0 + 2;
+	if (w * h > r->npixels) {
+		unsigned char* pixels = (unsigned char*)realloc(r->pixels, (size_t)w * h);
+		if (pixels == NULL)
+			return NULL;
+		r->pixels = pixels;
+		r->npixels = w * h;
+	}
+	memset(r->pixels, 0, (size_t)w * h);
+	fons__tt_renderGlyphBitmap(&r->font, &r->pixels[1 + w], w - 2, h - 2, w, scale, scale, g);
+
+	bitmap->width = w;
+	bitmap->height = h;
+	bitmap->xoff = x0 - 1;
+	bitmap->yoff = y0 - 1;
+	bitmap->xadvance = scale * advance;
+	return r->pixels;
+}
+
+void fonsClearFonts(FONScontext* stash)
+{
+	int i;
+
+	fons__extReleasePages(stash, NULL);
+	if (fons__ext(stash) != NULL) {
+		fons__extReleaseColorFonts(fons__ext(stash), NULL);
+		fons__extDropDeferred(fons__ext(stash), -1);
+	}
+	for (i = 0; i < stash->nfonts; i++) {
+		fons__extDoneFace(&stash->fonts[i]->font);
+		fons__freeFont(stash->fonts[i]);
+		stash->fonts[i] = NULL;
+	}
+	stash->nfonts = 0;
+}
+
+int fonsSetFreetypeHinting(int hinting, int autohint)
+{
+#ifdef FONS_USE_FREETYPE
+	FT_Int32 flags;
+	switch (hinting) {
+	case FONS_HINTING_NONE:
+		flags = FT_LOAD_NO_HINTING;
+		break;
+	case FONS_HINTING_SLIGHT:
+		flags = FT_LOAD_TARGET_LIGHT;
+		break;
+	default:
+		flags = FT_LOAD_TARGET_NORMAL;
+		break;
+	}
+	if (hinting != FONS_HINTING_NONE)
+		flags |= autohint ? (1L << 5) : FT_LOAD_NO_AUTOHINT; // `FT_LOAD_FORCE_AUTOHINT`
+	fons__extFtHintFlags = flags;
+	return 1;
+#else
+	(void)hinting;
+	(void)autohint;
+	return 0;
+#endif
+}
+
+int fonsGetCapMetrics(FONScontext* stash, int font, int* xHeight, int* capHeight)
+{
+#ifdef FONS_USE_FREETYPE
+	TT_OS2* os2;
+#else
+	stbtt_fontinfo* tt;
+	stbtt_uint32 os2;
+#endif
+
+	*xHeight = 0;
+	*capHeight = 0;
+	if (font < 0 || font >= stash->nfonts)
+		return 0;
+
+#ifdef FONS_USE_FREETYPE
+	os2 = (TT_OS2*)FT_Get_Sfnt_Table(stash->fonts[font]->font.font, FT_SFNT_OS2);
+	if (os2 == NULL || os2->version < 2 || os2->version == 0xFFFF)
+		return 0;
+	*xHeight = os2->sxHeight;
+	*capHeight = os2->sCapHeight;
+#else
+	tt = &stash->fonts[font]->font.font;
+	os2 = stbtt__find_table(tt->data, tt->fontstart, "OS/2");
+	if (os2 == 0 || ttUSHORT(tt->data + os2) < 2)
+		return 0;
+	*xHeight = ttSHORT(tt->data + os2 + 86);
+	*capHeight = ttSHORT(tt->data + os2 + 88);
+#endif
+
+	return 1;
+}
+
+int fonsReserveAtlasNodes(FONScontext* stash, int nnodes)
+{
+	FONSatlas* atlas = stash->atlas;
+	FONSatlasNode* nodes;
+
+	if (nnodes <= atlas->cnodes)
+		return 1;
+	nodes = (FONSatlasNode*)realloc(atlas->nodes, sizeof(FONSatlasNode) * nnodes);
+	if (nodes == NULL)
+		return 0;
+	atlas->nodes = nodes;
+	atlas->cnodes = nnodes;
+
+	return 1;
+}
+
+int fonsSetAtlasPacker(FONScontext* stash, int packer)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextPacker* p;
+
+	if (ext == NULL)
+		return 0;
+	if (packer == FONS_PACKER_SKYLINE) {
+		fons__extReleasePacker(ext);
+		return 1;
+	}
+	if (packer != FONS_PACKER_SHELF && packer != FONS_PACKER_GUILLOTINE)
+		return 0;
+
+	p = ext->packer;
+	if (p == NULL) {
+		p = (FONSextPacker*)calloc(1, sizeof(FONSextPacker));
+		if (p == NULL)
+			return 0;
+		ext->packer = p;
+	}
+	p->packer = packer;
+	fons__extPackerReset(p, stash->params.width, stash->params.height);
+
+	return 1;
+}
+
+int fonsGetAtlasPacker(FONScontext* stash)
+{
+	FONSextPacker* p = fons__extFindPacker(stash);
+	return p != NULL ? p->packer : FONS_PACKER_SKYLINE;
+}
+
+void fonsSetGlyphPadding(FONScontext* stash, int padding)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext != NULL)
+		ext->padding = fons__maxi(padding, 0);
+}
+
+int fonsGetGlyphPadding(FONScontext* stash)
+{
+	FONSextContext* ext = fons__ext(stash);
+	return ext != NULL ? ext->padding : 0;
+}
+
+int fonsSetSdf(FONScontext* stash, int spread, unsigned char onedge, float pixelDistScale)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext == NULL || spread < 0 || spread > 20 || !(pixelDistScale > 0.0f))
+		return 0;
+	ext->sdfSpread = spread;
+	ext->sdfOnEdge = onedge;
+	ext->sdfDistScale = pixelDistScale;
+	return 1;
+}
+
+int fonsGetSdf(FONScontext* stash, unsigned char* onedge, float* pixelDistScale)
+{
+	FONSextContext* ext = fons__ext(stash);
+
+	if (ext == NULL || ext->sdfSpread == 0)
+		return 0;
+	if (onedge != NULL)
+		*onedge = ext->sdfOnEdge;
+	if (pixelDistScale != NULL)
+		*pixelDistScale = ext->sdfDistScale;
+	return ext->sdfSpread;
+}
+
+// Sorts glyphs by decreasing height, then by decreasing width
+static int fons__extCompareGlyphSize(const void* a, const void* b)
+{
+	const FONSglyph* ga = *(const FONSglyph* const*)a;
+	const FONSglyph* gb = *(const FONSglyph* const*)b;
+	int ha = ga->y1 - ga->y0, hb = gb->y1 - gb->y0;
+
+	if (ha != hb)
+		return hb - ha;
+	return (gb->x1 - gb->x0) - (ga->x1 - ga->x0);
+}
+
+int fonsRepackAtlas(FONScontext* stash)
+{
+	int i, j, y, n = 0, w = stash->params.width, h = stash->params.height;
+	FONSglyph** glyphs;
+	int* pos;
+	unsigned char* data;
+	FONSextContext* ext = fons__ext(stash);
+	unsigned char* colorData = NULL;
+
+	for (i = 0; i < stash->nfonts; i++)
+		n += stash->fonts[i]->nglyphs;
+
+	glyphs = (FONSglyph**)malloc(sizeof(FONSglyph*) * (n > 0 ? n : 1));
+	pos = (int*)malloc(sizeof(int) * 2 * (n > 0 ? n : 1));
+	data = (unsigned char*)malloc(w * h);
+	if (glyphs == NULL || pos == NULL || data == NULL)
+		goto error;
+
+	n = 0;
+	for (i = 0; i < stash->nfonts; i++) {
+		FONSfont* font = stash->fonts[i];
+		for (j = 0; j < font->nglyphs; j++)
+			glyphs[n++] = &font->glyphs[j];
+	}
+	qsort(glyphs, n, sizeof(FONSglyph*), fons__extCompareGlyphSize);
+
+	if (stash->params.renderResize != NULL && stash->params.renderResize(stash->params.userPtr, w, h) == 0)
+		goto error;
+
+	// the reset clears the color pixels, which are moved below
+	if (ext != NULL) {
+		colorData = ext->colorData;
+		ext->colorData = NULL;
+	}
+	fons__extAtlasReset(stash, stash->atlas, w, h);
+	for (i = 0; i < n; i++) {
+		int gw = glyphs[i]->x1 - glyphs[i]->x0, gh = glyphs[i]->y1 - glyphs[i]->y0;
+		if (fons__extAtlasAddRect(stash, stash->atlas, gw, gh, &pos[i * 2], &pos[i * 2 + 1]) == 0)
+			break;
+	}
+
+	if (i < n) {
+		// the glyphs didn't fit in the packing order
+		free(glyphs);
+		free(pos);
+		free(data);
+		free(colorData);
+		fonsResetAtlas(stash, w, h);
+		return -1;
+	}
+
+	memset(data, 0, w * h);
+	for (i = 0; i < n; i++) {
+		FONSglyph* glyph = glyphs[i];
+		int gw = glyph->x1 - glyph->x0, gh = glyph->y1 - glyph->y0;
+		for (y = 0; y < gh; y++) {
+			memcpy(&data[pos[i * 2] + (pos[i * 2 + 1] + y) * w], &stash->texData[glyph->x0 + (glyph->y0 + y) * w], gw);
+			if (colorData != NULL && ext->colorData != NULL)
+				memcpy(&ext->colorData[((size_t)pos[i * 2] + (size_t)(pos[i * 2 + 1] + y) * w) * 4], &colorData[((size_t)glyph->x0 + (size_t)(glyph->y0 + y) * w) * 4], (size_t)gw * 4);
+		}
+		glyph->x0 = (short)pos[i * 2];
+		glyph->y0 = (short)pos[i * 2 + 1];
+		glyph->x1 = (short)(glyph->x0 + gw);
+		glyph->y1 = (short)(glyph->y0 + gh);
+	}
+
+	free(stash->texData);
+	stash->texData = data;
+	free(colorData);
+	stash->dirtyRect[0] = 0;
+	stash->dirtyRect[1] = 0;
+	stash->dirtyRect[2] = w;
+	stash->dirtyRect[3] = h;
+
+	free(glyphs);
+	free(pos);
+	return 1;
+
+error:
+	free(glyphs);
+	free(pos);
+	free(data);
+	return 0;
+}
+
+// Removes the glyph from the cache of the font, moving the last glyph into its slot
+static void fons__extRemoveGlyph(FONSfont* font, int i)
+{
+	int last = font->nglyphs - 1;
+	int* link = &font->lut[fons__hashint(font->glyphs[i].codepoint) & (FONS_HASH_LUT_SIZE - 1)];
+
+	while (*link != i)
+		link = &font->glyphs[*link].next;
+	*link = font->glyphs[i].next;
+
+	if (i != last) {
+		link = &font->lut[fons__hashint(font->glyphs[last].codepoint) & (FONS_HASH_LUT_SIZE - 1)];
+		while (*link != last)
+			link = &font->glyphs[*link].next;
+		*link = i;
+		font->glyphs[i] = font->glyphs[last];
+	}
+	font->nglyphs--;
+}
+
+int fonsEvictGlyphs(FONScontext* stash, int (*keep)(void* uptr, int font, unsigned int codepoint, short isize, short iblur), void* uptr)
+{
+	FONSextContext* ext = fons__ext(stash);
+	FONSextPacker* p = ext != NULL ? ext->packer : NULL;
+	int i, j, y, pad, n = 0, w = stash->params.width;
+
+	if (p == NULL || p->packer != FONS_PACKER_GUILLOTINE)
+		return -1;
+	// the padding can't be told apart from the neighbours if it varies, so it stays used
+	pad = p->padding > 0 ? p->padding : 0;
+
+	for (i = 0; i < stash->nfonts; i++) {
+		FONSfont* font = stash->fonts[i];
+		// backwards, so that the glyphs moved by the removal are the visited ones
+		for (j = font->nglyphs - 1; j >= 0; j--) {
+			FONSglyph g = font->glyphs[j];
+			int x0 = g.x0 - pad, y0 = g.y0 - pad, x1 = g.x1 + pad, y1 = g.y1 + pad;
+			if (keep(uptr, i, g.codepoint, g.size, g.blur == FONS__EXT_COLOR_BLUR ? 0 : g.blur))
+				continue;
+
+			fons__extRemoveGlyph(font, j);
+			fons__extGuillotineFreeRect(p, x0, y0, x1 - x0, y1 - y0);
+			// the padding of `fontstash.h` is not cleared when rasterizing
+			for (y = y0; y < y1; y++) {
+				memset(&stash->texData[x0 + y * w], 0, x1 - x0);
+				if (ext->colorData != NULL)
+					memset(&ext->colorData[((size_t)x0 + (size_t)y * w) * 4], 0, (size_t)(x1 - x0) * 4);
+			}
+			stash->dirtyRect[0] = fons__mini(stash->dirtyRect[0], x0);
+			stash->dirtyRect[1] = fons__mini(stash->dirtyRect[1], y0);
+			stash->dirtyRect[2] = fons__maxi(stash->dirtyRect[2], x1);
+			stash->dirtyRect[3] = fons__maxi(stash->dirtyRect[3], y1);
+			n++;
+		}
+	}
+	return n;
+}
+
+// Index of the face of the font in its font collection
+static int fons__extFaceIndex(FONSfont* font)
+{
+#ifdef FONS_USE_FREETYPE
+	return (int)font->font.font->face_index;
+#else
+	int i, offset;
+
+	for (i = 0; (offset = stbtt_GetFontOffsetForIndex(font->data, i)) >= 0; i++) {
+		if (offset == font->font.font.fontstart)
+			return i;
+	}
+	return 0;
+#endif
+}
+
+int fonsSetFontPage(FONScontext* stash, int font, FONScontext* page)
+{
+	FONSfont* f;
+	FONSextPage* e;
+	int pageFont;
+
+	if (font < 0 || font >= stash->nfonts || page == stash || fons__ext(stash) == NULL)
+		return 0;
+	if (page != NULL && fons__ext(page) == NULL)
+		return 0;
+	f = stash->fonts[font];
+
+	fons__extReleasePages(stash, f);
+	if (page == NULL)
+		return 1;
+
+	pageFont = fonsAddFontMemCopy(page, f->name, f->data, f->dataSize, fons__extFaceIndex(f));
+	if (pageFont == FONS_INVALID)
+		return 0;
+	e = (FONSextPage*)calloc(1, sizeof(FONSextPage));
+	if (e == NULL)
+		return 0;
+	e->stash = stash;
+	e->font = f;
+	e->page = page;
+	e->pageFont = pageFont;
+	e->next = fons__ext(stash)->pages;
+	fons__ext(stash)->pages = e;
+	e->nextRef = fons__ext(page)->pageRefs;
+	fons__ext(page)->pageRefs = e;
+
+	return 1;
+}
+
+FONScontext* fonsGetFontPage(FONScontext* stash, int font, int* pageFont)
+{
+	FONSextPage* e;
+
+	if (font < 0 || font >= stash->nfonts)
+		return NULL;
+	e = fons__extFindPage(stash, stash->fonts[font]);
+	if (e == NULL)
+		return NULL;
+	if (pageFont != NULL)
+		*pageFont = e->pageFont;
+	return e->page;
+}
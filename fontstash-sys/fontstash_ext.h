@@ -0,0 +1,355 @@
+// Extensions to `fontstash` used by `fontstash-rs`
+//
+// They're implemented in `fontstash.c`, where the internals of `fontstash` are visible. Include
+// this after `fontstash.h`.
+
+#ifndef FONTSTASH_EXT_H
+#define FONTSTASH_EXT_H
+
+struct FONSatlasStats {
+	int usedPixels;
+	int totalPixels;
+	int glyphCount;
+	int skylineNodes;
+};
+typedef struct FONSatlasStats FONSatlasStats;
+
+void fonsGetAtlasStats(FONScontext* stash, FONSatlasStats* stats);
+
+// Returns the index of the font or `FONS_INVALID`
+int fonsGetFontIndex(FONScontext* stash, struct FONSfont* font);
+
+// Returns `1` if neither the font of the iterator nor its fallbacks has the current codepoint
+int fonsTextIterIsMissing(FONScontext* stash, FONStextIter* iter);
+
+struct FONSglyphBounds {
+	float bearingX;
+	float bearingY;
+	float width;
+	float height;
+	float advance;
+};
+typedef struct FONSglyphBounds FONSglyphBounds;
+
+// Measures a glyph of the current font (or its fallbacks) and size without rasterizing it.
+// Returns `0` if the glyph is missing.
+int fonsGetGlyphBounds(FONScontext* stash, unsigned int codepoint, FONSglyphBounds* bounds);
+
+struct FONSstateInfo {
+	int font;
+	int align;
+	float size;
+	unsigned int color;
+	float blur;
+	float spacing;
+};
+typedef struct FONSstateInfo FONSstateInfo;
+
+// Reads the current state
+void fonsGetStateInfo(FONScontext* stash, FONSstateInfo* info);
+
+// Returns the kerning adjustment between two codepoints in pixels for the current font and size.
+// Returns `0` if either of them is missing in the font.
+float fonsGetKerning(FONScontext* stash, unsigned int left, unsigned int right);
+
+// Returns `FONS_MAX_STATES`
+int fonsGetMaxStates(void);
+
+// Returns the number of states in the state stack (`1` if no state is pushed)
+int fonsGetStateDepth(FONScontext* stash);
+
+// Adds the face at `faceIndex` of a font collection (`.ttc`). `0` for plain font files
+int fonsAddFontMemAt(FONScontext* stash, const char* name, unsigned char* data, int dataSize, int freeData, int faceIndex);
+
+// Adds a font with a copy of the data, which is freed by the stash
+int fonsAddFontMemCopy(FONScontext* stash, const char* name, const unsigned char* data, int dataSize, int faceIndex);
+
+struct FONSfontInfo {
+	const char* name; // name given on creation
+	const char* family; // family name in the `name` table, not null-terminated (or `NULL`)
+	int familyLength; // in bytes
+	int familyIsUtf16; // UTF-16BE (Windows platform) or Mac Roman (ASCII with FreeType)
+	int unitsPerEm;
+	int glyphCount;
+	int ascent; // in font units
+	int descent; // in font units
+	int lineGap; // in font units
+};
+typedef struct FONSfontInfo FONSfontInfo;
+
+// Reads metadata of a font. Returns `0` if the font index is invalid
+int fonsGetFontInfo(FONScontext* stash, int font, FONSfontInfo* info);
+
+// Returns `1` if fontstash is built with `FONS_USE_FREETYPE`
+int fonsUsesFreetype(void);
+
+// Returns `1` if the font itself (not its fallbacks) has a glyph for the codepoint
+int fonsHasGlyph(FONScontext* stash, int font, unsigned int codepoint);
+
+enum FONScolorTable {
+	FONS_COLOR_COLR = 1 << 0, // layered vector glyphs
+	FONS_COLOR_CBDT = 1 << 1, // embedded bitmaps (Google)
+	FONS_COLOR_SBIX = 1 << 2, // embedded bitmaps (Apple)
+};
+
+// Returns the color glyph tables (`FONScolorTable`) the font has. Only the layers of `COLR` are
+// rasterized in color (see `fonsSetColorGlyphs`)
+int fonsGetColorTables(FONScontext* stash, int font);
+
+// Keeps RGBA pixels of the color glyphs at their rectangles in the atlas, which get the coverage
+// of the glyphs. Reset the atlas after enabling it. Returns `0` if the pixels couldn't be
+// allocated
+int fonsSetColorAtlas(FONScontext* stash, int enabled);
+
+// The RGBA pixels (straight alpha) of the color glyphs, or `NULL` without the color atlas. They're
+// dirty in the same rectangle as the atlas (`fonsValidateTexture`)
+const unsigned char* fonsGetColorTextureData(FONScontext* stash, int* width, int* height);
+
+// Rasterizes the glyphs of `font` in color with the color atlas, from the layers (glyph and color)
+// of a decoded `COLR` table. `glyphs` has `nglyphs` records of the base glyph, its first layer
+// and the number of layers, sorted by the base glyph. `layers` has `nlayers` records of the
+// glyph and the index in `colors` (`-1` for white), and `colors` has `ncolors` RGBA colors. The
+// data is copied, and `nglyphs` of `0` removes the layers. Glyphs in the glyph caches or with
+// blur are not in color, neither in an SDF atlas or a page (`fonsSetFontPage`). Reloading the
+// font removes the layers. Returns `0` for invalid records or if the allocation failed
+int fonsSetColorGlyphs(FONScontext* stash, int font, const int* glyphs, int nglyphs, const int* layers, int nlayers, const unsigned char* colors, int ncolors);
+
+// Returns `1` if the glyph of the last `fonsTextIterNext` is in color, in which case the quad
+// samples the color pixels
+int fonsTextIterIsColored(FONScontext* stash, FONStextIter* iter);
+
+// The data of the font and the index of the face in it, or `NULL` if the font index is invalid
+const unsigned char* fonsGetFontData(FONScontext* stash, int font, int* dataSize, int* faceIndex);
+
+// Replaces the data of a font with a copy of `data` and invalidates its glyphs. The font index is
+// preserved. Returns `0` (and keeps the old data) if the data can't be loaded
+int fonsReloadFontMem(FONScontext* stash, int font, const unsigned char* data, int dataSize, int faceIndex);
+
+// Variation axis of a variable font, in design coordinates
+struct FONSfontAxis {
+	unsigned int tag; // e.g. `'wght'`, big-endian bytes of the tag
+	float minValue, defaultValue, maxValue;
+	float value; // the current coordinate
+};
+typedef struct FONSfontAxis FONSfontAxis;
+
+// Writes up to `maxAxes` variation axes of the font. Returns the number of the axes (`0` for a
+// static font or without `FONS_USE_FREETYPE`), or `-1` if the font index is invalid
+int fonsGetFontAxes(FONScontext* stash, int font, FONSfontAxis* axes, int maxAxes);
+
+// Sets the design coordinates of the axes `tags` of a variable font (clamped to their ranges; the
+// other axes are kept) and invalidates its glyphs. Reloading the font resets the coordinates.
+// Returns `0` without `FONS_USE_FREETYPE` or for an invalid font index, and `-1` (and keeps the
+// coordinates) if the font doesn't have one of the axes
+int fonsSetFontVariation(FONScontext* stash, int font, const unsigned int* tags, const float* values, int n);
+
+// Returns the number of fonts in the stash
+int fonsGetFontCount(FONScontext* stash);
+
+// Returns the kerning between two glyph indices in pixels, in the same way as `fons__getQuad`
+float fonsTextIterKern(FONScontext* stash, FONStextIter* iter, int prevGlyphIndex, int glyphIndex);
+
+// Reads the user pointer and the callbacks (other fields are left as they are)
+void fonsGetRenderCallbacks(FONScontext* stash, FONSparams* params);
+
+// Replaces the user pointer and the callbacks (other fields are ignored)
+void fonsSetRenderCallbacks(FONScontext* stash, const FONSparams* params);
+
+// Returns the scale from font units to pixels for the current font and size (`0` without font)
+float fonsGetPixelScale(FONScontext* stash);
+
+// Rasterizes a glyph of the current font by its glyph index (e.g. from a shaping engine) and
+// writes its quad with the pen at `(x, y)`. The current size, blur and vertical alignment are
+// applied. Returns `0` if there's no font or the atlas is full
+int fonsGetGlyphQuad(FONScontext* stash, int glyphIndex, float x, float y, FONSquad* q);
+
+struct FONSdecorationMetrics {
+	int underlinePosition; // top of the underline, y-up from the baseline (`post` table)
+	int underlineThickness;
+	int strikeoutPosition; // top of the strikeout, y-up from the baseline (`OS/2` table)
+	int strikeoutThickness;
+};
+typedef struct FONSdecorationMetrics FONSdecorationMetrics;
+
+// Reads the underline and strikeout metrics of a font in font units. They're `0` if the font
+// doesn't have the table. Returns `0` if the font index is invalid
+int fonsGetDecorationMetrics(FONScontext* stash, int font, FONSdecorationMetrics* metrics);
+
+// Writes the atlas rectangles `[x0, y0, x1, y1]` of up to `maxRects` cached glyphs (of all fonts)
+// into `rects`. Returns the number of cached glyphs, which can be greater than `maxRects`
+int fonsGetGlyphRects(FONScontext* stash, int* rects, int maxRects);
+
+// `fonsTextBounds` that reads glyph metrics from the font instead of rasterizing the glyphs, so
+// the atlas is not touched. Bounds don't include the padding of blurred glyphs
+float fonsTextBoundsNoBitmap(FONScontext* stash, float x, float y, const char* str, const char* end, float* bounds);
+
+// Inserts a pre-rasterized glyph of `font` at `size` (without blur) into the atlas. `xoff` and
+// `yoff` are the offset of the bitmap from the pen at the baseline. Text drawn with the font at
+// the size uses the bitmap instead of rasterizing the glyph. Returns `1` on success, `0` if the
+// font index is invalid, the glyph is cached already or the atlas is an SDF atlas (`fonsSetSdf`)
+// and `-1` if the atlas is full
+int fonsAddGlyphBitmap(FONScontext* stash, int font, unsigned int codepoint, float size, const unsigned char* data, int width, int height, int stride, int xoff, int yoff, float xadvance);
+
+// Queues the glyphs missing in the glyph cache instead of rasterizing them, e.g. for rasterizing
+// them on another thread with `fonsRasterizeGlyph`. Meanwhile text has empty quads advancing the
+// pen as the glyphs. Only glyphs of the font itself (not of the fallbacks) without blur are
+// queued, neither in an SDF atlas, in color or in a page (`fonsSetFontPage`). Disabling it drops
+// the queue. Returns `0` for a stash being created
+int fonsSetDeferredGlyphs(FONScontext* stash, int enabled);
+
+struct FONSdeferredGlyph {
+	int id;
+	int font;
+	unsigned int codepoint;
+	float size;
+};
+typedef struct FONSdeferredGlyph FONSdeferredGlyph;
+
+// Writes up to `maxGlyphs` glyphs queued since the last call. They stay in the queue until they're
+// added with `fonsAddDeferredGlyph` or `fonsRasterizeDeferredGlyph`. Reloading a font (or setting
+// its variation) drops its glyphs, and clearing the fonts drops all of them. Returns the number of
+// the written glyphs
+int fonsTakeDeferredGlyphs(FONScontext* stash, FONSdeferredGlyph* glyphs, int maxGlyphs);
+
+// Incremented when the outlines of the fonts are replaced (`fonsReloadFontMem`,
+// `fonsSetFontVariation`, `fonsClearFonts`), so rasterizers of the fonts have to be created again
+int fonsGetFontRevision(FONScontext* stash);
+
+// `fonsAddGlyphBitmap` for a queued glyph. Returns `0` if the glyph is not in the queue anymore
+int fonsAddDeferredGlyph(FONScontext* stash, int id, const unsigned char* data, int width, int height, int stride, int xoff, int yoff, float xadvance);
+
+// Removes a queued glyph and rasterizes it now, e.g. if it couldn't be rasterized elsewhere.
+// Returns `1` on success, `0` if the glyph is not in the queue anymore and `-1` if the atlas is
+// full
+int fonsRasterizeDeferredGlyph(FONScontext* stash, int id);
+
+// Returns `1` if the quad of the last `fonsTextIterNext` is the placeholder of a queued glyph
+int fonsTextIterIsDeferred(FONScontext* stash, FONStextIter* iter);
+
+// Rasterizer of the glyphs of a font with its own copy of the font data, which can be used on
+// another thread than the stash (one thread at a time)
+typedef struct FONSrasterizer FONSrasterizer;
+
+// Returns `NULL` if the font index is invalid or the allocation failed
+FONSrasterizer* fonsCreateRasterizer(FONScontext* stash, int font);
+
+void fonsDeleteRasterizer(FONSrasterizer* r);
+
+struct FONSglyphBitmap {
+	int width;
+	int height;
+	int xoff;
+	int yoff;
+	float xadvance;
+};
+typedef struct FONSglyphBitmap FONSglyphBitmap;
+
+// Rasterizes the glyph (of the font itself, not of its fallbacks) at `size` in the same way as the
+// stash, with its empty border. `fonsAddGlyphBitmap` of the bitmap makes the same quads as the
+// glyphs rasterized by the stash. Returns the pixels (`width` in stride), valid until the next
+// call, or `NULL` if the glyph is missing or the allocation failed
+const unsigned char* fonsRasterizeGlyph(FONSrasterizer* r, unsigned int codepoint, float size, FONSglyphBitmap* bitmap);
+
+// Removes all the fonts. Font indices of the states become invalid, and the pixels of the glyphs
+// are left in the atlas until it's reset
+void fonsClearFonts(FONScontext* stash);
+
+enum FONShinting {
+	FONS_HINTING_NONE = 0,
+	FONS_HINTING_SLIGHT = 1, // vertical hinting only
+	FONS_HINTING_FULL = 2,
+};
+
+// Sets how FreeType hints glyphs (`FONShinting`) in all the stashes. `autohint` forces the
+// FreeType autohinter over the hinting instructions of the fonts. Cached glyphs are not
+// re-rasterized. Returns `0` without FreeType
+int fonsSetFreetypeHinting(int hinting, int autohint);
+
+// Reads `sxHeight` and `sCapHeight` of the `OS/2` table (version 2 or later) in font units.
+// Returns `0` if the font index is invalid or the font doesn't have them
+int fonsGetCapMetrics(FONScontext* stash, int font, int* xHeight, int* capHeight);
+
+// Grows the skyline node array of the atlas to `nnodes` (`FONS_INIT_ATLAS_NODES` on creation).
+// The array still doubles when it's full. Returns `0` if the allocation failed
+int fonsReserveAtlasNodes(FONScontext* stash, int nnodes);
+
+enum FONSpacker {
+	FONS_PACKER_SKYLINE = 0, // packer of `fontstash.h`
+	FONS_PACKER_SHELF = 1, // rows of similar heights
+	FONS_PACKER_GUILLOTINE = 2, // free rectangles split on each insertion
+};
+
+// Selects the atlas packer (`FONSpacker`) of the stash. The new packer doesn't know the glyphs
+// in the atlas, so call it on an empty atlas or reset the atlas afterwards. The packer is owned
+// by the stash. Returns `0` for an invalid packer or if the allocation failed
+int fonsSetAtlasPacker(FONScontext* stash, int packer);
+
+int fonsGetAtlasPacker(FONScontext* stash);
+
+// Empty pixels kept around each glyph added to the atlas from now on (`0` by default), e.g. for
+// sampling with mipmaps or linear filtering at fractional scales. It's in addition to the padding
+// of `fontstash.h` (`blur + 2`). Reset or repack the atlas to apply it to the cached glyphs. With
+// a dedicated page (`fonsSetFontPage`), the padding of the page is used. Negative values are `0`
+void fonsSetGlyphPadding(FONScontext* stash, int padding);
+
+int fonsGetGlyphPadding(FONScontext* stash);
+
+// Stores the glyphs added to the atlas from now on as signed distance fields instead of coverage.
+// Texels are `onedge` on the outline and change by `pixelDistScale` per pixel, increasing inside.
+// `spread` (up to `20`) is the blur of the glyph keys and the distance covered around the
+// glyphs, so the blur of the states is ignored. `0` switches back to coverage. Reset the atlas
+// after switching. Glyph bitmaps can't be added to an SDF atlas (`fonsAddGlyphBitmap` returns
+// `0`). Returns `0` for invalid parameters
+int fonsSetSdf(FONScontext* stash, int spread, unsigned char onedge, float pixelDistScale);
+
+// Returns the spread, or `0` for a coverage atlas
+int fonsGetSdf(FONScontext* stash, unsigned char* onedge, float* pixelDistScale);
+
+// Packs the glyphs in the atlas again from the tallest one and calls `renderResize` with the
+// same size. The whole atlas is marked dirty. Returns `0` if `renderResize` failed (nothing is
+// changed) and `-1` if the glyphs didn't fit, in which case the atlas is reset
+int fonsRepackAtlas(FONScontext* stash);
+
+// Removes the cached glyphs `keep` returns `0` for and frees their regions of the atlas, which
+// are cleared and marked dirty. `iblur` is `0` for color glyphs. Only the guillotine packer can
+// reuse the regions, so it returns `-1` without evicting for the other packers. Returns the
+// number of evicted glyphs
+int fonsEvictGlyphs(FONScontext* stash, int (*keep)(void* uptr, int font, unsigned int codepoint, short isize, short iblur), void* uptr);
+
+// Moves the glyphs of the font into the atlas of `page`, another stash, e.g. for an icon font
+// with large glyphs. Quads of the font sample the texture of the page. The page gets a copy of
+// the font (with no fallbacks), so reset or repack the pages separately. Pass `NULL` to use the
+// atlas of the stash again. Deleting either stash releases the page. Returns `0` if the font
+// index is invalid, `page` is the stash itself or the allocation failed
+int fonsSetFontPage(FONScontext* stash, int font, FONScontext* page);
+
+// Returns the page of the font set with `fonsSetFontPage` and the index of the copied font in it,
+// or `NULL` if the glyphs of the font are in the atlas of the stash
+FONScontext* fonsGetFontPage(FONScontext* stash, int font, int* pageFont);
+
+enum FONSoutlineVerb {
+	FONS_OUTLINE_MOVE = 0, // starts a contour at `(x, y)`
+	FONS_OUTLINE_LINE = 1,
+	FONS_OUTLINE_QUAD = 2, // with the control point `(cx0, cy0)`
+	FONS_OUTLINE_CUBIC = 3, // with the control points `(cx0, cy0)` and `(cx1, cy1)`
+};
+
+struct FONSoutlineSegment {
+	int verb; // `FONSoutlineVerb`
+	float x, y; // end point
+	float cx0, cy0;
+	float cx1, cy1;
+};
+typedef struct FONSoutlineSegment FONSoutlineSegment;
+
+// Writes up to `maxSegments` segments of the outline of the glyph of the font (fallbacks are not
+// considered) in font units, y-up from the baseline. Contours are closed by their last segment.
+// Returns the number of segments, which can be greater than `maxSegments`, or `-1` if the font
+// index is invalid. Missing glyphs and glyphs without outlines (e.g. bitmaps) have no segment
+int fonsGetGlyphOutline(FONScontext* stash, int font, unsigned int codepoint, FONSoutlineSegment* segments, int maxSegments);
+
+// Reads the state at `index` of the state stack, where `0` is the bottom and
+// `fonsGetStateDepth - 1` is the current state. Returns `0` if the index is out of the stack
+int fonsGetStateInfoAt(FONScontext* stash, int index, FONSstateInfo* info);
+
+#endif // FONTSTASH_EXT_H
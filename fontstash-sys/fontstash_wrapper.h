@@ -6,3 +6,4 @@
 // #define FONTSTASH_IMPLEMENTATION
 
 #include "fontstash-rs-src/src/fontstash.h"
+#include "fontstash_ext.h"
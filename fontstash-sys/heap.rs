@@ -0,0 +1,123 @@
+//! Allocator of `fontstash` on `wasm32-unknown-unknown` and with the `rust-alloc` feature
+//!
+//! Allocations go to the allocator installed with [`set_allocator`], or the global allocator of
+//! Rust. With `rust-alloc`, `malloc` and friends are renamed to `fons_rs_*` at build time (see
+//! `build.rs`), which are defined here.
+
+use core::ffi::c_void;
+
+use alloc::alloc::{self as heap, GlobalAlloc, Layout};
+
+/// `malloc` has to be aligned for any type
+const ALIGN: usize = 16;
+
+static mut ALLOCATOR: Option<&'static (dyn GlobalAlloc + Sync)> = None;
+
+/// Routes the allocations of `fontstash` to `allocator` instead of the global allocator
+///
+/// # Safety
+///
+/// Call it before creating any `FONScontext`, at most once. Memory allocated by `fontstash` has
+/// to be freed by the same allocator, so it can't be changed while any context is alive.
+pub unsafe fn set_allocator(allocator: &'static (dyn GlobalAlloc + Sync)) {
+    ALLOCATOR = Some(allocator);
+}
+
+fn allocator() -> Option<&'static (dyn GlobalAlloc + Sync)> {
+    unsafe { core::ptr::addr_of!(ALLOCATOR).read() }
+}
+
+/// Allocations are prefixed with its size (padded to [`ALIGN`]) so that `free` can get the layout
+fn layout(size: usize) -> Option<Layout> {
+    Layout::from_size_align(size.checked_add(ALIGN)?, ALIGN).ok()
+}
+
+unsafe fn alloc_with(size: usize, zeroed: bool) -> *mut c_void {
+    let layout = match self::layout(size) {
+        Some(layout) => layout,
+        None => return core::ptr::null_mut(),
+    };
+    let base = match (self::allocator(), zeroed) {
+        (Some(a), false) => a.alloc(layout),
+        (Some(a), true) => a.alloc_zeroed(layout),
+        (None, false) => heap::alloc(layout),
+        (None, true) => heap::alloc_zeroed(layout),
+    };
+    if base.is_null() {
+        return core::ptr::null_mut();
+    }
+    (base as *mut usize).write(size);
+    base.add(ALIGN) as *mut c_void
+}
+
+pub unsafe fn malloc(size: usize) -> *mut c_void {
+    self::alloc_with(size, false)
+}
+
+pub unsafe fn calloc(count: usize, size: usize) -> *mut c_void {
+    match count.checked_mul(size) {
+        Some(size) => self::alloc_with(size, true),
+        None => core::ptr::null_mut(),
+    }
+}
+
+pub unsafe fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    if ptr.is_null() {
+        return self::malloc(size);
+    }
+    let base = (ptr as *mut u8).sub(ALIGN);
+    let old_size = (base as *const usize).read();
+    let old_layout = self::layout(old_size).unwrap();
+    let new_layout = match self::layout(size) {
+        Some(layout) => layout,
+        None => return core::ptr::null_mut(),
+    };
+    let base = match self::allocator() {
+        Some(a) => a.realloc(base, old_layout, new_layout.size()),
+        None => heap::realloc(base, old_layout, new_layout.size()),
+    };
+    if base.is_null() {
+        return core::ptr::null_mut();
+    }
+    (base as *mut usize).write(size);
+    base.add(ALIGN) as *mut c_void
+}
+
+pub unsafe fn free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let base = (ptr as *mut u8).sub(ALIGN);
+    let size = (base as *const usize).read();
+    let layout = self::layout(size).unwrap();
+    match self::allocator() {
+        Some(a) => a.dealloc(base, layout),
+        None => heap::dealloc(base, layout),
+    }
+}
+
+// `malloc` and friends renamed by `build.rs`
+
+#[cfg(feature = "rust-alloc")]
+#[no_mangle]
+pub unsafe extern "C" fn fons_rs_malloc(size: usize) -> *mut c_void {
+    self::malloc(size)
+}
+
+#[cfg(feature = "rust-alloc")]
+#[no_mangle]
+pub unsafe extern "C" fn fons_rs_calloc(count: usize, size: usize) -> *mut c_void {
+    self::calloc(count, size)
+}
+
+#[cfg(feature = "rust-alloc")]
+#[no_mangle]
+pub unsafe extern "C" fn fons_rs_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    self::realloc(ptr, size)
+}
+
+#[cfg(feature = "rust-alloc")]
+#[no_mangle]
+pub unsafe extern "C" fn fons_rs_free(ptr: *mut c_void) {
+    self::free(ptr)
+}
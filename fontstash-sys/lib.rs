@@ -4,5 +4,32 @@
 
 // Supress all errors
 #![allow(warnings)]
+#![no_std]
 
-include!(concat!(env!("OUT_DIR"), "/fontstash_bindings.rs"));
+mod ffi {
+    #[cfg(feature = "generate-bindings")]
+    include!(concat!(env!("OUT_DIR"), "/fontstash_bindings.rs"));
+
+    #[cfg(not(feature = "generate-bindings"))]
+    include!("bindings/fontstash_bindings.rs");
+}
+
+#[cfg(not(feature = "dlopen"))]
+pub use ffi::*;
+
+// the functions are shadowed by the ones resolved at runtime
+#[cfg(feature = "dlopen")]
+mod dlopen;
+#[cfg(feature = "dlopen")]
+pub use dlopen::*;
+
+#[cfg(any(feature = "rust-alloc", all(target_arch = "wasm32", target_os = "unknown")))]
+extern crate alloc;
+
+#[cfg(any(feature = "rust-alloc", all(target_arch = "wasm32", target_os = "unknown")))]
+mod heap;
+#[cfg(any(feature = "rust-alloc", all(target_arch = "wasm32", target_os = "unknown")))]
+pub use heap::set_allocator;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod wasm;
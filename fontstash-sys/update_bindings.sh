@@ -0,0 +1,27 @@
+#!/bin/sh
+# Regenerates the pregenerated bindings (`bindings/fontstash_bindings.rs`) with bindgen
+#
+# The build script generates them into `OUT_DIR` with the `generate-bindings` feature (requires
+# libclang), and they're copied here with the header comment. Update `dlopen.rs` along with them.
+
+set -eu
+cd "$(dirname "$0")"
+
+dst=bindings/fontstash_bindings.rs
+out_dir=$(cargo build --features generate-bindings --message-format=json |
+    grep '"reason":"build-script-executed"' |
+    grep '"package_id":"[^"]*fontstash-sys' |
+    sed 's/.*"out_dir":"\([^"]*\)".*/\1/' |
+    tail -n 1)
+
+if [ ! -f "$out_dir/fontstash_bindings.rs" ]; then
+    echo "bindgen output is not found in \`$out_dir\`" >&2
+    exit 1
+fi
+
+{
+    sed -n '1,/^$/p' "$dst"
+    cat "$out_dir/fontstash_bindings.rs"
+} >"$dst.tmp"
+mv "$dst.tmp" "$dst"
+echo "updated $dst"
@@ -0,0 +1,153 @@
+//! libc functions used by `fontstash` on `wasm32-unknown-unknown`, which has no libc
+//!
+//! Declared in `wasm/*.h`. `memset`, `memcpy`, `memmove` and `memcmp` come from
+//! `compiler_builtins`. Allocations go through [`heap`].
+
+use core::ffi::{c_char, c_double, c_float, c_int, c_long, c_void};
+
+use crate::heap;
+
+#[no_mangle]
+pub unsafe extern "C" fn malloc(size: usize) -> *mut c_void {
+    heap::malloc(size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn calloc(count: usize, size: usize) -> *mut c_void {
+    heap::calloc(count, size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    heap::realloc(ptr, size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn free(ptr: *mut c_void) {
+    heap::free(ptr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn strlen(s: *const c_char) -> usize {
+    let mut n = 0;
+    while *s.add(n) != 0 {
+        n += 1;
+    }
+    n
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn strcmp(a: *const c_char, b: *const c_char) -> c_int {
+    let mut i = 0;
+    loop {
+        let (x, y) = (*a.add(i) as u8, *b.add(i) as u8);
+        if x != y || x == 0 {
+            return x as c_int - y as c_int;
+        }
+        i += 1;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn strncpy(dst: *mut c_char, src: *const c_char, n: usize) -> *mut c_char {
+    let mut i = 0;
+    while i < n && *src.add(i) != 0 {
+        *dst.add(i) = *src.add(i);
+        i += 1;
+    }
+    // pads the rest with NUL like libc
+    while i < n {
+        *dst.add(i) = 0;
+        i += 1;
+    }
+    dst
+}
+
+// math (`stb_truetype` and the blur of `fontstash`)
+
+#[no_mangle]
+pub extern "C" fn floor(x: c_double) -> c_double {
+    libm::floor(x)
+}
+
+#[no_mangle]
+pub extern "C" fn ceil(x: c_double) -> c_double {
+    libm::ceil(x)
+}
+
+#[no_mangle]
+pub extern "C" fn sqrt(x: c_double) -> c_double {
+    libm::sqrt(x)
+}
+
+#[no_mangle]
+pub extern "C" fn pow(x: c_double, y: c_double) -> c_double {
+    libm::pow(x, y)
+}
+
+#[no_mangle]
+pub extern "C" fn fmod(x: c_double, y: c_double) -> c_double {
+    libm::fmod(x, y)
+}
+
+#[no_mangle]
+pub extern "C" fn cos(x: c_double) -> c_double {
+    libm::cos(x)
+}
+
+#[no_mangle]
+pub extern "C" fn acos(x: c_double) -> c_double {
+    libm::acos(x)
+}
+
+#[no_mangle]
+pub extern "C" fn fabs(x: c_double) -> c_double {
+    libm::fabs(x)
+}
+
+#[no_mangle]
+pub extern "C" fn floorf(x: c_float) -> c_float {
+    libm::floorf(x)
+}
+
+#[no_mangle]
+pub extern "C" fn ceilf(x: c_float) -> c_float {
+    libm::ceilf(x)
+}
+
+#[no_mangle]
+pub extern "C" fn sqrtf(x: c_float) -> c_float {
+    libm::sqrtf(x)
+}
+
+#[no_mangle]
+pub extern "C" fn expf(x: c_float) -> c_float {
+    libm::expf(x)
+}
+
+// stdio: there's no file system, so `fonsAddFont` always fails
+
+#[no_mangle]
+pub extern "C" fn fopen(path: *const c_char, mode: *const c_char) -> *mut c_void {
+    core::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn fclose(fp: *mut c_void) -> c_int {
+    -1
+}
+
+#[no_mangle]
+pub extern "C" fn fseek(fp: *mut c_void, offset: c_long, whence: c_int) -> c_int {
+    -1
+}
+
+#[no_mangle]
+pub extern "C" fn ftell(fp: *mut c_void) -> c_long {
+    -1
+}
+
+#[no_mangle]
+pub extern "C" fn fread(dst: *mut c_void, size: usize, count: usize, fp: *mut c_void) -> usize {
+    0
+}
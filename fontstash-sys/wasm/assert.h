@@ -0,0 +1,5 @@
+// `assert` is disabled on `wasm32-unknown-unknown`
+
+#pragma once
+
+#define assert(x) ((void)0)
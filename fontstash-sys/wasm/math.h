@@ -0,0 +1,16 @@
+// Subset of libc used by `fontstash` on `wasm32-unknown-unknown` (implemented in `wasm.rs`)
+
+#pragma once
+
+double floor(double x);
+double ceil(double x);
+double sqrt(double x);
+double pow(double x, double y);
+double fmod(double x, double y);
+double cos(double x);
+double acos(double x);
+double fabs(double x);
+float floorf(float x);
+float ceilf(float x);
+float sqrtf(float x);
+float expf(float x);
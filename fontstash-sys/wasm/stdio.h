@@ -0,0 +1,19 @@
+// Subset of libc used by `fontstash` on `wasm32-unknown-unknown` (implemented in `wasm.rs`)
+//
+// There's no file system: `fopen` always fails, so load fonts from memory.
+
+#pragma once
+
+#include <stddef.h>
+
+#define SEEK_SET 0
+#define SEEK_CUR 1
+#define SEEK_END 2
+
+typedef struct FILE FILE;
+
+FILE* fopen(const char* path, const char* mode);
+int fclose(FILE* fp);
+int fseek(FILE* fp, long offset, int whence);
+long ftell(FILE* fp);
+size_t fread(void* dst, size_t size, size_t count, FILE* fp);
@@ -0,0 +1,10 @@
+// Subset of libc used by `fontstash` on `wasm32-unknown-unknown` (implemented in `wasm.rs`)
+
+#pragma once
+
+#include <stddef.h>
+
+void* malloc(size_t size);
+void* calloc(size_t count, size_t size);
+void* realloc(void* ptr, size_t size);
+void free(void* ptr);
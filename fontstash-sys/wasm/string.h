@@ -0,0 +1,13 @@
+// Subset of libc used by `fontstash` on `wasm32-unknown-unknown` (implemented in `wasm.rs`)
+
+#pragma once
+
+#include <stddef.h>
+
+void* memset(void* dst, int c, size_t n);
+void* memcpy(void* dst, const void* src, size_t n);
+void* memmove(void* dst, const void* src, size_t n);
+int memcmp(const void* a, const void* b, size_t n);
+size_t strlen(const char* s);
+int strcmp(const char* a, const char* b);
+char* strncpy(char* dst, const char* src, size_t n);
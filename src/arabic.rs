@@ -0,0 +1,209 @@
+/*!
+Contextual forms of Arabic letters (`arabic-shaping` feature)
+
+FontStash maps each codepoint to one glyph, so Arabic text renders with isolated letterforms.
+Before iteration, letters are replaced with their initial, medial, final or isolated forms in the
+Arabic Presentation Forms-B block, and LAM + ALEF with the lam-alef ligatures. The font has to have
+glyphs for the presentation forms (most Arabic fonts do).
+
+Text has to be in the logical order. [`FontStash::bidi_glyphs_into`] shapes each run before
+reordering it. This is not a replacement of a shaping engine (see `shape`); marks are not
+positioned and letters outside the basic Arabic block are not joined.
+*/
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use crate::FontStash;
+
+/// `(first form, number of forms)` of U+0621 to U+064A. The forms are isolated, final, initial
+/// and medial in this order
+const FORMS: [(u16, u8); 42] = [
+    (0xFE80, 1), // HAMZA
+    (0xFE81, 2), // ALEF WITH MADDA ABOVE
+    (0xFE83, 2), // ALEF WITH HAMZA ABOVE
+    (0xFE85, 2), // WAW WITH HAMZA ABOVE
+    (0xFE87, 2), // ALEF WITH HAMZA BELOW
+    (0xFE89, 4), // YEH WITH HAMZA ABOVE
+    (0xFE8D, 2), // ALEF
+    (0xFE8F, 4), // BEH
+    (0xFE93, 2), // TEH MARBUTA
+    (0xFE95, 4), // TEH
+    (0xFE99, 4), // THEH
+    (0xFE9D, 4), // JEEM
+    (0xFEA1, 4), // HAH
+    (0xFEA5, 4), // KHAH
+    (0xFEA9, 2), // DAL
+    (0xFEAB, 2), // THAL
+    (0xFEAD, 2), // REH
+    (0xFEAF, 2), // ZAIN
+    (0xFEB1, 4), // SEEN
+    (0xFEB5, 4), // SHEEN
+    (0xFEB9, 4), // SAD
+    (0xFEBD, 4), // DAD
+    (0xFEC1, 4), // TAH
+    (0xFEC5, 4), // ZAH
+    (0xFEC9, 4), // AIN
+    (0xFECD, 4), // GHAIN
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),      // TATWEEL (join causing)
+    (0xFED1, 4), // FEH
+    (0xFED5, 4), // QAF
+    (0xFED9, 4), // KAF
+    (0xFEDD, 4), // LAM
+    (0xFEE1, 4), // MEEM
+    (0xFEE5, 4), // NOON
+    (0xFEE9, 4), // HEH
+    (0xFEED, 2), // WAW
+    (0xFEEF, 2), // ALEF MAKSURA
+    (0xFEF1, 4), // YEH
+];
+
+const LAM: char = '\u{0644}';
+const TATWEEL: char = '\u{0640}';
+
+/// How a character joins to its neighbors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Joining {
+    /// Doesn't join
+    None,
+    /// Joins to the previous character only
+    Right,
+    /// Joins to both sides
+    Dual,
+    /// Makes the neighbors join without changing its own shape
+    Causing,
+    /// Skipped when looking for the neighbors (marks)
+    Transparent,
+}
+
+fn joining(c: char) -> Joining {
+    match c {
+        TATWEEL => Joining::Causing,
+        '\u{064B}'..='\u{065F}' | '\u{0670}' => Joining::Transparent,
+        '\u{0621}'..='\u{064A}' => match self::FORMS[c as usize - 0x0621].1 {
+            4 => Joining::Dual,
+            2 => Joining::Right,
+            _ => Joining::None,
+        },
+        _ => Joining::None,
+    }
+}
+
+/// Lam-alef ligature (isolated form) of LAM followed by `alef`
+fn lam_alef(alef: char) -> Option<u32> {
+    Some(match alef {
+        '\u{0622}' => 0xFEF5,
+        '\u{0623}' => 0xFEF7,
+        '\u{0625}' => 0xFEF9,
+        '\u{0627}' => 0xFEFB,
+        _ => return None,
+    })
+}
+
+/// If `c` has contextual forms
+pub(crate) fn is_shaped(c: char) -> bool {
+    matches!(self::joining(c), Joining::Right | Joining::Dual)
+}
+
+/// Characters of `text` with contextual forms, with the byte offsets of the source characters
+///
+/// A lam-alef ligature has the offset of the LAM.
+pub(crate) fn shaped_chars(text: &str) -> Vec<(usize, char)> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let joins_next = |c: char| matches!(self::joining(c), Joining::Dual | Joining::Causing);
+    let joins_prev = |c: char| {
+        matches!(
+            self::joining(c),
+            Joining::Right | Joining::Dual | Joining::Causing
+        )
+    };
+
+    let mut out = Vec::with_capacity(chars.len());
+    // if the last non-transparent character joins to the next one
+    let mut prev_joins = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        let kind = self::joining(c);
+        if kind == Joining::Transparent {
+            out.push((offset, c));
+            i += 1;
+            continue;
+        }
+
+        let ligature = match chars.get(i + 1) {
+            Some(&(_, alef)) if c == LAM => self::lam_alef(alef),
+            _ => None,
+        };
+        if let Some(isolated) = ligature {
+            // lam-alef joins to the previous character only
+            let form = isolated + prev_joins as u32;
+            out.push((offset, core::char::from_u32(form).unwrap()));
+            prev_joins = false;
+            i += 2;
+            continue;
+        }
+
+        let next = chars[i + 1..]
+            .iter()
+            .map(|&(_, c)| c)
+            .find(|&c| self::joining(c) != Joining::Transparent);
+        let next_joins = joins_next(c) && next.is_some_and(joins_prev);
+        let prev = prev_joins && joins_prev(c);
+
+        let shaped = match kind {
+            Joining::Right | Joining::Dual => {
+                // right-joining letters never join to the next one, so they don't need the
+                // initial nor the medial form
+                let form = match (prev, next_joins) {
+                    (false, false) => 0,
+                    (true, false) => 1,
+                    (false, true) => 2,
+                    (true, true) => 3,
+                };
+                let first = self::FORMS[c as usize - 0x0621].0 as u32;
+                core::char::from_u32(first + form).unwrap()
+            }
+            _ => c,
+        };
+
+        out.push((offset, shaped));
+        prev_joins = joins_next(c);
+        i += 1;
+    }
+
+    out
+}
+
+/// Replaces Arabic letters in `text` with their contextual forms. Borrowed if there's no letter
+/// to replace
+pub fn shape(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(self::is_shaped) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    out.extend(self::shaped_chars(text).into_iter().map(|(_, c)| c));
+    Cow::Owned(out)
+}
+
+/// Arabic shaping
+impl FontStash {
+    /// Replaces Arabic letters with their contextual forms before iteration and measurement (on
+    /// by default)
+    ///
+    /// Byte offsets of [`GlyphQuad`](crate::GlyphQuad) are still into the given text, where a
+    /// lam-alef ligature has the offset of the LAM. Turn it off if the text is shaped before it
+    /// reaches the stash.
+    pub fn set_arabic_shaping(&self, shape: bool) {
+        self.update_options(|opts| opts.arabic_shaping = shape);
+    }
+
+    pub fn shapes_arabic(&self) -> bool {
+        self.options().arabic_shaping
+    }
+}
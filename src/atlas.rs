@@ -0,0 +1,627 @@
+/*!
+Atlas size, occupancy and pixels
+*/
+
+use core::ffi::c_int;
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    convert, resume_callback_panic, sys, AtlasEvent, FonsError, FontIx, FontStash, Quad, Result,
+};
+
+/// Atlas
+impl FontStash {
+    pub fn atlas_size(&self) -> [u32; 2] {
+        let [mut x, mut y] = [0, 0];
+        unsafe {
+            sys::fonsGetAtlasSize(self.raw(), &mut x, &mut y);
+        }
+        [x as u32, y as u32]
+    }
+
+    /// Occupancy of the atlas. Use it to decide whether to expand or reset the atlas
+    pub fn atlas_stats(&self) -> AtlasStats {
+        let mut stats: sys::FONSatlasStats = unsafe { core::mem::zeroed() };
+        unsafe {
+            sys::fonsGetAtlasStats(self.raw(), &mut stats);
+        }
+        AtlasStats {
+            used_pixels: stats.usedPixels as u32,
+            total_pixels: stats.totalPixels as u32,
+            glyph_count: stats.glyphCount as u32,
+            skyline_nodes: stats.skylineNodes as u32,
+        }
+    }
+
+    /// If atlas sizes are rounded up to powers of two (see [`crate::StashBuilder::power_of_two`])
+    pub fn is_power_of_two_atlas(&self) -> bool {
+        self.fons.power_of_two
+    }
+
+    /// `[w, h]` rounded up to powers of two if [`FontStash::is_power_of_two_atlas`]
+    pub fn atlas_dimensions(&self, w: u32, h: u32) -> [u32; 2] {
+        if self.fons.power_of_two {
+            [w.next_power_of_two(), h.next_power_of_two()]
+        } else {
+            [w, h]
+        }
+    }
+
+    /// Creates fontstash atlas size copying the previous data
+    ///
+    /// The size is rounded with [`FontStash::atlas_dimensions`].
+    pub fn expand_atlas(&self, w: u32, h: u32) -> Result<()> {
+        let [w, h] = self.atlas_dimensions(w, h);
+        trace_span!("fontstash::expand_atlas", w, h);
+        let res = unsafe { sys::fonsExpandAtlas(self.raw(), w as i32, h as i32) };
+        resume_callback_panic();
+        if res != 0 {
+            log_debug!("fontstash: expanded the atlas to {}x{}", w, h);
+            self.bump_stats(|s| s.atlas_expansions += 1);
+            let [width, height] = [w, h];
+            self.fire_atlas_event(AtlasEvent::Resized { width, height });
+            self.fire_atlas_event(AtlasEvent::Expanded { width, height });
+            Ok(())
+        } else {
+            log_warn!(
+                "fontstash: renderResize failed expanding the atlas to {}x{}",
+                w,
+                h
+            );
+            Err(FonsError::RenderResizeError())
+        }
+    }
+
+    /// Creates new fontstash atlas with size without copying the previous data
+    ///
+    /// The size is rounded with [`FontStash::atlas_dimensions`].
+    pub fn reset_atlas(&self, w: u32, h: u32) -> Result<()> {
+        let [w, h] = self.atlas_dimensions(w, h);
+        trace_span!("fontstash::reset_atlas", w, h);
+        log_debug!("fontstash: resetting the atlas to {}x{}", w, h);
+        let res = unsafe { sys::fonsResetAtlas(self.raw(), w as i32, h as i32) };
+        resume_callback_panic();
+        self.bump_stats(|s| s.atlas_resets += 1);
+        self.clear_quad_cache();
+        self.invalidate_measure_cache();
+        if res == 1 {
+            let [width, height] = [w, h];
+            self.fire_atlas_event(AtlasEvent::Resized { width, height });
+            self.fire_atlas_event(AtlasEvent::Reset { width, height });
+            Ok(())
+        } else {
+            log_warn!(
+                "fontstash: renderResize failed resetting the atlas to {}x{}",
+                w,
+                h
+            );
+            Err(FonsError::RenderResizeError())
+        }
+    }
+
+    pub fn atlas_packer(&self) -> AtlasPacker {
+        match unsafe { sys::fonsGetAtlasPacker(self.raw()) } as sys::FONSpacker {
+            sys::FONSpacker_FONS_PACKER_SHELF => AtlasPacker::Shelf,
+            sys::FONSpacker_FONS_PACKER_GUILLOTINE => AtlasPacker::Guillotine,
+            _ => AtlasPacker::Skyline,
+        }
+    }
+
+    /// Switches the packer and resets the atlas, because the new packer doesn't know the glyphs
+    /// packed so far. Prefer [`crate::StashBuilder::packer`]
+    ///
+    /// Returns [`FonsError::FailedToCreate`] if the packer couldn't be allocated.
+    pub fn set_atlas_packer(&self, packer: AtlasPacker) -> Result<()> {
+        if unsafe { sys::fonsSetAtlasPacker(self.raw(), packer.raw() as c_int) } == 0 {
+            log_warn!("fontstash: failed to set the atlas packer {:?}", packer);
+            return Err(FonsError::FailedToCreate());
+        }
+        let [w, h] = self.atlas_size();
+        self.reset_atlas(w, h)
+    }
+
+    /// Empty pixels around each glyph in the atlas (see [`FontStash::set_glyph_padding`])
+    pub fn glyph_padding(&self) -> u32 {
+        unsafe { sys::fonsGetGlyphPadding(self.raw()) as u32 }
+    }
+
+    /// Keeps `px` empty pixels around the glyphs added to the atlas from now on, e.g. so that
+    /// mipmaps or linear filtering at fractional scales don't bleed the neighbours. Prefer
+    /// [`crate::StashBuilder::glyph_padding`]
+    ///
+    /// Cached glyphs keep their padding until the atlas is repacked or reset. Glyphs of a font
+    /// dedicated to a page get the padding of the page (see [`FontStash::dedicate_font`]).
+    /// Returns [`FonsError::InvalidParams`] if the padding on both sides doesn't fit in the atlas.
+    pub fn set_glyph_padding(&self, px: u32) -> Result<()> {
+        let [w, h] = self.atlas_size();
+        if px as u64 * 2 >= w.min(h) as u64 {
+            return Err(FonsError::InvalidParams(
+                "glyph padding doesn't fit in the atlas",
+            ));
+        }
+        unsafe { sys::fonsSetGlyphPadding(self.raw(), px as c_int) };
+        Ok(())
+    }
+
+    /// Packs the glyphs in the atlas again from the tallest one, which defragments the atlas
+    /// filled in the order of use
+    ///
+    /// The texture is recreated with `renderResize` (same size) and the whole atlas is uploaded
+    /// on the next `renderUpdate`. Returns `Ok(false)` if the glyphs didn't fit in the new order,
+    /// in which case the atlas is reset as with [`FontStash::reset_atlas`].
+    pub fn repack(&self) -> Result<bool> {
+        let [width, height] = self.atlas_size();
+        trace_span!("fontstash::repack", width, height);
+        let res = unsafe { sys::fonsRepackAtlas(self.raw()) };
+        resume_callback_panic();
+        // glyph positions are changed
+        self.clear_quad_cache();
+
+        match res {
+            1 => {
+                log_debug!("fontstash: repacked the atlas");
+                self.fire_atlas_event(AtlasEvent::Resized { width, height });
+                self.fire_atlas_event(AtlasEvent::Repacked { width, height });
+                Ok(true)
+            }
+            -1 => {
+                log_warn!("fontstash: glyphs didn't fit while repacking; the atlas is reset");
+                self.bump_stats(|s| s.atlas_resets += 1);
+                self.invalidate_measure_cache();
+                self.fire_atlas_event(AtlasEvent::Resized { width, height });
+                self.fire_atlas_event(AtlasEvent::Reset { width, height });
+                Ok(false)
+            }
+            _ => {
+                log_warn!("fontstash: renderResize failed repacking the atlas");
+                Err(FonsError::RenderResizeError())
+            }
+        }
+    }
+
+    /// Rasterizes `chars` into the atlas ahead of time, e.g. while showing a loading screen
+    ///
+    /// Glyphs are rasterized with the current blur. The state is restored after prewarming.
+    /// Returns [`FonsError::StaleFontHandle`] for a stale font and [`FonsError::AtlasFull`] if a
+    /// glyph didn't fit in the atlas even after `renderResize`/`renderExpand`.
+    pub fn prewarm(
+        &self,
+        font: FontIx,
+        size: f32,
+        chars: impl Iterator<Item = char>,
+    ) -> Result<()> {
+        trace_span!("fontstash::prewarm", size);
+        self.check_font(font)?;
+        let text = chars.collect::<String>();
+
+        self.scoped_state(|| {
+            self.set_font(font);
+            self.set_size(size);
+            let mut iter = self.text_iter(&text)?;
+            while let Some(glyph) = iter.next_glyph() {
+                if iter.is_atlas_full {
+                    return Err(self.atlas_full_error(glyph.codepoint));
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Atlas
+impl FontStash {
+    /// Quad drawing the whole atlas into `dest` (`[left_x, top_y, right_x, bottom_y]`), e.g. for
+    /// a debug overlay. Draw it with the texture of the atlas
+    pub fn debug_atlas_quad(&self, dest: [f32; 4]) -> Quad {
+        Quad::new(dest, [0.0, 0.0, 1.0, 1.0])
+    }
+
+    /// Rectangles of the cached glyphs in the atlas, in pixels. Map them with
+    /// [`FontStash::debug_atlas_rects`] to draw their outlines over [`FontStash::debug_atlas_quad`]
+    pub fn atlas_glyph_rects(&self) -> Vec<[u32; 4]> {
+        let n = unsafe { sys::fonsGetGlyphRects(self.raw(), core::ptr::null_mut(), 0) };
+        let mut rects = alloc::vec![[0 as c_int; 4]; n as usize];
+        unsafe {
+            sys::fonsGetGlyphRects(self.raw(), rects.as_mut_ptr() as *mut c_int, n);
+        }
+        rects
+            .into_iter()
+            .map(|r| [r[0] as u32, r[1] as u32, r[2] as u32, r[3] as u32])
+            .collect()
+    }
+
+    /// [`FontStash::atlas_glyph_rects`] mapped into `dest` of [`FontStash::debug_atlas_quad`]
+    pub fn debug_atlas_rects(&self, dest: [f32; 4]) -> Vec<[f32; 4]> {
+        let [w, h] = self.atlas_size();
+        let sx = (dest[2] - dest[0]) / w.max(1) as f32;
+        let sy = (dest[3] - dest[1]) / h.max(1) as f32;
+        self.atlas_glyph_rects()
+            .into_iter()
+            .map(|r| {
+                [
+                    dest[0] + r[0] as f32 * sx,
+                    dest[1] + r[1] as f32 * sy,
+                    dest[0] + r[2] as f32 * sx,
+                    dest[1] + r[3] as f32 * sy,
+                ]
+            })
+            .collect()
+    }
+
+    /// [`FonsError::AtlasFull`] for a glyph of the current state. The padding is `blur + 2` pixels
+    /// (the spread instead of the blur in an SDF atlas) plus [`FontStash::glyph_padding`]
+    fn atlas_full_error(&self, c: char) -> FonsError {
+        let blur = self.sdf_params().map_or(self.blur(), |p| p.spread as f32);
+        let pad = 2.0 * (blur + 2.0 + self.glyph_padding() as f32);
+        let needed = self
+            .glyph_bounds(c)
+            .map_or(0.0, |b| (b.size[0] + pad) * (b.size[1] + pad));
+
+        let stats = self.atlas_stats();
+        log_debug!("fontstash: atlas full ({:?}, {:?})", c, stats);
+        FonsError::AtlasFull {
+            needed: needed as u32,
+            available: stats.total_pixels.saturating_sub(stats.used_pixels),
+        }
+    }
+}
+
+/// Snapshot of atlas occupancy returned by [`FontStash::atlas_stats`]
+///
+/// * `used_pixels`: sum of the areas of cached glyphs
+/// * `total_pixels`: `width * height` of the atlas
+/// * `glyph_count`: number of cached glyphs (of all fonts)
+/// * `skyline_nodes`: number of nodes in the skyline packer (shelves or free rectangles with the
+///   other [`AtlasPacker`]s). More nodes means more fragmentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasStats {
+    pub used_pixels: u32,
+    pub total_pixels: u32,
+    pub glyph_count: u32,
+    pub skyline_nodes: u32,
+}
+
+impl AtlasStats {
+    /// `used_pixels / total_pixels`
+    pub fn occupancy(&self) -> f32 {
+        if self.total_pixels == 0 {
+            0.0
+        } else {
+            self.used_pixels as f32 / self.total_pixels as f32
+        }
+    }
+}
+
+/// Rectangle packing algorithm of the atlas, selected with [`crate::StashBuilder::packer`]
+///
+/// The skyline packer fragments with mixed glyph sizes, e.g. small UI text and large headings in
+/// one atlas. [`FontStash::repack`] defragments the atlas with any packer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AtlasPacker {
+    /// Packer of `fontstash.h`, which places glyphs on the lowest skyline
+    #[default]
+    Skyline,
+    /// Rows of similar heights. Fast and good for glyphs of a few sizes
+    Shelf,
+    /// Free rectangles split on each insertion. Tight for mixed sizes, slower with many glyphs
+    Guillotine,
+}
+
+impl AtlasPacker {
+    pub(crate) fn raw(self) -> sys::FONSpacker {
+        match self {
+            Self::Skyline => sys::FONSpacker_FONS_PACKER_SKYLINE,
+            Self::Shelf => sys::FONSpacker_FONS_PACKER_SHELF,
+            Self::Guillotine => sys::FONSpacker_FONS_PACKER_GUILLOTINE,
+        }
+    }
+}
+
+/// How coverage is converted into RGBA pixels by [`FontStash::with_pixels_rgba`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// `[255, 255, 255, coverage]`, blended with `src_alpha, one_minus_src_alpha`
+    #[default]
+    Straight,
+    /// `[coverage; 4]`, blended with `one, one_minus_src_alpha`. It doesn't get dark fringes when
+    /// the texture is filtered
+    Premultiplied,
+}
+
+impl AlphaMode {
+    pub(crate) fn rgba(self, coverage: u8) -> [u8; 4] {
+        match self {
+            Self::Straight => [255, 255, 255, coverage],
+            Self::Premultiplied => [coverage; 4],
+        }
+    }
+}
+
+/// Borrowed pixels of the atlas returned by [`FontStash::texture_data`]
+///
+/// Pixel is in one byte (8 bits alpha channel only).
+#[derive(Debug, Clone, Copy)]
+pub struct TextureView<'a> {
+    pub pixels: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Texture
+impl FontStash {
+    /// Runs `f` with the pixels of the atlas and its `width`, `height`
+    ///
+    /// Returns [`FonsError::NoTextureData`] if `fonsGetTextureData` returned null.
+    ///
+    /// NOTE: Pixel is in one byte (8 bits alpha channel only)
+    pub fn with_pixels<R>(&self, f: impl FnOnce(&[u8], u32, u32) -> R) -> Result<R> {
+        let (mut w, mut h) = (0, 0);
+        let ptr = unsafe { sys::fonsGetTextureData(self.raw(), &mut w, &mut h) };
+        if ptr.is_null() {
+            log_warn!("fontstash: no texture data");
+            return Err(FonsError::NoTextureData());
+        }
+
+        let pixels = unsafe { core::slice::from_raw_parts(ptr, (w * h) as usize) };
+        Ok(f(pixels, w as u32, h as u32))
+    }
+
+    /// Runs `f` with the atlas expanded to RGBA8 (white RGB and the coverage in alpha), and its
+    /// `width`, `height`. Use it for texture APIs that reject one-channel uploads
+    pub fn with_pixels_rgba<R>(
+        &self,
+        mode: AlphaMode,
+        f: impl FnOnce(&[u8], u32, u32) -> R,
+    ) -> Result<R> {
+        let mut rgba = Vec::new();
+        let [w, h] = self.pixels_rgba_into(mode, &mut rgba)?;
+        Ok(f(&rgba, w, h))
+    }
+
+    /// Clears `out` and fills it with the atlas expanded to RGBA8 (see
+    /// [`FontStash::with_pixels_rgba`]). Returns `[width, height]`. Reuse `out` to avoid
+    /// allocations
+    pub fn pixels_rgba_into(&self, mode: AlphaMode, out: &mut Vec<u8>) -> Result<[u32; 2]> {
+        let lut = self.coverage_lut();
+        self.with_pixels(|pixels, w, h| {
+            out.clear();
+            match &lut {
+                Some(lut) => convert::expand_to_rgba_adjusted(pixels, mode, lut, out),
+                None => convert::expand_to_rgba(pixels, mode, out),
+            }
+            [w, h]
+        })
+    }
+
+    /// Copies the rectangle `[x0, y0, x1, y1]` of the atlas into `out`, row by row without padding
+    ///
+    /// Use it with [`FontStash::take_dirty_rect`] to update a CPU-side mirror of the atlas.
+    pub fn copy_texture_region(&self, rect: [u32; 4], out: &mut [u8]) -> Result<()> {
+        let lut = self.coverage_lut();
+        self.copy_region_impl(rect, 1, out, |src, dst| {
+            dst.copy_from_slice(src);
+            if let Some(lut) = &lut {
+                lut.apply_in_place(dst);
+            }
+        })
+    }
+
+    /// [`FontStash::copy_texture_region`] expanded to RGBA8 (see [`FontStash::with_pixels_rgba`]).
+    /// `out` needs four bytes per pixel
+    pub fn copy_texture_region_rgba(
+        &self,
+        rect: [u32; 4],
+        mode: AlphaMode,
+        out: &mut [u8],
+    ) -> Result<()> {
+        let lut = self.coverage_lut();
+        self.copy_region_impl(rect, 4, out, |src, dst| {
+            convert::expand_into_adjusted(src, mode, lut.as_ref(), dst)
+        })
+    }
+
+    /// Copies rows of the region with `copy_row(src, dst)`, where `dst` has `bpp` bytes per pixel
+    fn copy_region_impl(
+        &self,
+        rect: [u32; 4],
+        bpp: usize,
+        out: &mut [u8],
+        mut copy_row: impl FnMut(&[u8], &mut [u8]),
+    ) -> Result<()> {
+        self.with_pixels(|pixels, w, h| {
+            let [x0, y0, x1, y1] = rect;
+            if !(x0 <= x1 && y0 <= y1 && x1 <= w && y1 <= h) {
+                return Err(FonsError::InvalidRegion(rect));
+            }
+
+            let row_len = (x1 - x0) as usize;
+            let needed = row_len * (y1 - y0) as usize * bpp;
+            if out.len() < needed {
+                return Err(FonsError::BufferTooSmall {
+                    needed,
+                    available: out.len(),
+                });
+            }
+
+            if row_len == 0 {
+                return Ok(());
+            }
+            for (y, dst) in (y0..y1).zip(out.chunks_exact_mut(row_len * bpp)) {
+                let start = (y * w + x0) as usize;
+                copy_row(&pixels[start..start + row_len], dst);
+            }
+            Ok(())
+        })?
+    }
+
+    /// Borrows the pixels of the atlas without a closure, e.g. for upload APIs taking a slice.
+    /// Prefer [`FontStash::with_pixels`], which is safe
+    ///
+    /// # Safety
+    ///
+    /// The pixels are owned by the `FONScontext` shared with the clones of the stash. While the
+    /// view is alive, nothing may write or reallocate them: no glyph may be rasterized (text
+    /// iteration, measurement, [`FontStash::prewarm`], ...) and the atlas may not be expanded,
+    /// reset or repacked, through this stash, its clones or the callbacks of the renderer.
+    pub unsafe fn texture_data(&self) -> Option<TextureView<'_>> {
+        let (mut w, mut h) = (0, 0);
+        let ptr = unsafe { sys::fonsGetTextureData(self.raw(), &mut w, &mut h) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        Some(TextureView {
+            pixels: unsafe { core::slice::from_raw_parts(ptr, (w * h) as usize) },
+            width: w as u32,
+            height: h as u32,
+        })
+    }
+
+    /// Copies the atlas into a grayscale image, e.g. to inspect or post-process it
+    #[cfg(feature = "image")]
+    pub fn atlas_image(&self) -> Result<image::GrayImage> {
+        self.with_pixels(|pixels, w, h| image::GrayImage::from_raw(w, h, pixels.to_vec()))?
+            .ok_or_else(|| {
+                FonsError::FailedToSaveAtlas(
+                    "texture data is smaller than the atlas size".to_string(),
+                )
+            })
+    }
+
+    /// Writes the atlas into a grayscale PNG file. Useful to see how glyphs are packed
+    #[cfg(feature = "image")]
+    pub fn dump_atlas_png(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.atlas_image()?
+            .save_with_format(path.as_ref(), image::ImageFormat::Png)
+            .map_err(|e| FonsError::FailedToSaveAtlas(e.to_string()))
+    }
+
+    // FIXME: what's this
+    // pub fn dirty(&self) -> (bool, i32) {
+    //     let mut dirty_flags = 0;
+    //     let x = unsafe { sys::fonsValidateTexture(self.raw(), &mut dirty_flags) };
+    //     (x == 1, dirty_flags)
+    // }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_font, AtlasPacker, FonsError, FontStash};
+
+    /// Pixels of each cached glyph
+    fn glyph_pixels(stash: &FontStash) -> Vec<Vec<u8>> {
+        let rects = stash.atlas_glyph_rects();
+        stash
+            .with_pixels(|pixels, w, _h| {
+                let rows = |r: [u32; 4]| (r[1]..r[3]).map(move |y| (y * w + r[0], y * w + r[2]));
+                rects
+                    .iter()
+                    .map(|&r| {
+                        let rows = rows(r).map(|(a, b)| &pixels[a as usize..b as usize]);
+                        rows.flatten().copied().collect()
+                    })
+                    .collect()
+            })
+            .unwrap()
+    }
+
+    fn assert_disjoint(rects: &[[u32; 4]], [w, h]: [u32; 2]) {
+        for (i, a) in rects.iter().enumerate() {
+            assert!(
+                a[0] < a[2] && a[1] < a[3] && a[2] <= w && a[3] <= h,
+                "{:?}",
+                a
+            );
+            for b in &rects[i + 1..] {
+                let overlaps = a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3];
+                assert!(!overlaps, "{:?} and {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn packers_and_repack() {
+        let packers = [
+            AtlasPacker::Skyline,
+            AtlasPacker::Shelf,
+            AtlasPacker::Guillotine,
+        ];
+        for packer in packers {
+            let stash = FontStash::builder(256, 256).packer(packer).build().unwrap();
+            assert_eq!(stash.atlas_packer(), packer);
+            let font = stash.add_font_mem_copy("mono", &test_font::data()).unwrap();
+            // mixed sizes in the order of use
+            for size in [12.0, 40.0, 20.0, 64.0, 16.0] {
+                stash.prewarm(font, size, "abcd".chars()).unwrap();
+            }
+            let rects = stash.atlas_glyph_rects();
+            assert_eq!(rects.len(), 20);
+            self::assert_disjoint(&rects, stash.atlas_size());
+
+            // the glyphs are moved with their pixels
+            let pixels = self::glyph_pixels(&stash);
+            assert!(stash.repack().unwrap(), "{:?}", packer);
+            let repacked = stash.atlas_glyph_rects();
+            assert_eq!(repacked.len(), 20);
+            self::assert_disjoint(&repacked, stash.atlas_size());
+            assert_eq!(self::glyph_pixels(&stash), pixels, "{:?}", packer);
+            assert_eq!(stash.atlas_packer(), packer);
+        }
+
+        // switching the packer resets the atlas
+        let (stash, font) = test_font::stash();
+        stash.prewarm(font, 20.0, "ab".chars()).unwrap();
+        stash.set_atlas_packer(AtlasPacker::Shelf).unwrap();
+        assert_eq!(stash.atlas_packer(), AtlasPacker::Shelf);
+        assert!(stash.atlas_glyph_rects().is_empty());
+    }
+
+    #[test]
+    fn prewarm_restores_state() {
+        let (stash, font) = test_font::stash();
+        stash.set_size(12.0);
+        stash.prewarm(font, 20.0, "abc".chars()).unwrap();
+        assert_eq!(stash.atlas_stats().glyph_count, 3);
+        assert_eq!(stash.size(), 12.0);
+
+        // the prewarmed glyphs are not rasterized again
+        stash.set_font(font);
+        stash.set_size(20.0);
+        assert_eq!(stash.text_iter("cab").unwrap().count(), 3);
+        assert_eq!(stash.atlas_stats().glyph_count, 3);
+    }
+
+    #[test]
+    fn prewarm_errors() {
+        let stash = FontStash::builder(32, 32).build().unwrap();
+        let font = stash.add_font_mem_copy("mono", &test_font::data()).unwrap();
+        assert!(matches!(
+            stash.prewarm(font, 100.0, "a".chars()),
+            Err(FonsError::AtlasFull { .. })
+        ));
+
+        stash.clear_fonts().unwrap();
+        assert!(matches!(
+            stash.prewarm(font, 20.0, "a".chars()),
+            Err(FonsError::StaleFontHandle(f)) if f == font
+        ));
+    }
+
+    #[test]
+    fn texture_data_views_pixels() {
+        let (stash, font) = test_font::stash();
+        stash.prewarm(font, 20.0, "ab".chars()).unwrap();
+
+        let expected = stash
+            .with_pixels(|pixels, w, h| (pixels.to_vec(), w, h))
+            .unwrap();
+        // SAFETY: nothing is rasterized while the view is alive
+        let view = unsafe { stash.texture_data() }.unwrap();
+        assert_eq!(
+            (view.pixels, view.width, view.height),
+            (&expected.0[..], 512, 512)
+        );
+        assert!(view.pixels.iter().any(|&p| p != 0));
+    }
+}
@@ -0,0 +1,307 @@
+/*!
+Quads grouped by the texture they sample, for minimizing texture binds
+
+There's one atlas texture per [`FontStash`], so a page is a stash. Draw texts from several stashes
+(e.g. a UI font and a CJK font at different atlas sizes) and bind each texture once:
+
+```text
+let mut batches = QuadBatches::default();
+batches.push(&ui, [10.0, 10.0], "Score")?;
+batches.push(&cjk, [10.0, 40.0], "スコア")?;
+batches.push(&ui, [10.0, 70.0], "Time")?;
+
+for (page, quads) in batches.iter() {
+    // bind the texture of `page` and draw `quads`
+}
+```
+
+A font can also have a stash of its own as the page (see [`FontStash::dedicate_font`]), e.g. an
+icon font with huge glyphs that would otherwise fragment the atlas of the UI font and evict its
+glyphs. Its quads are grouped by [`GlyphQuad::page`]:
+
+```text
+let icons = FontStash::builder(1024, 1024).renderer(&mut *icon_renderer).build()?;
+stash.dedicate_font(icon_font, &icons)?;
+```
+
+Many labels with a few styles are laid out with [`FontStash::batch`], which applies each style once:
+
+```text
+stash.batch(&[([10.0, 10.0], "Score", &title), ([10.0, 40.0], "120", &body)], &mut batches)?;
+```
+*/
+
+use core::ffi::c_int;
+
+use alloc::vec::Vec;
+
+use crate::{
+    sys,
+    vertex::{self, QuadVertex},
+    FonsError, FontIx, FontStash, GlyphQuad, Quad, Result, TextStyle,
+};
+
+/// Texture the quads sample. It's the identity of a [`FontStash`] (see [`FontStash::page_id`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PageId(usize);
+
+/// Atlas pages
+impl FontStash {
+    /// Texture of the quads emitted by this stash. Clones of a stash share the page
+    pub fn page_id(&self) -> PageId {
+        PageId(self.raw() as usize)
+    }
+
+    /// Rasterizes the glyphs of `font` into the atlas of `page` instead of this stash
+    ///
+    /// The page gets a copy of the font, so upload, reset and repack its atlas separately; it's
+    /// kept alive while the font is dedicated to it. Glyphs of the fallbacks are also added to the
+    /// page when the font falls back. Glyphs already in the atlas of this stash are left until
+    /// it's reset. Returns [`FonsError::InvalidParams`] if `page` is this stash (or its clone) and
+    /// [`FonsError::FailedToAllocFont`] if the font couldn't be copied.
+    pub fn dedicate_font(&self, font: FontIx, page: &FontStash) -> Result<()> {
+        self.check_font(font)?;
+        if page.page_id() == self.page_id() {
+            return Err(FonsError::InvalidParams(
+                "a stash can't be a page of itself",
+            ));
+        }
+
+        if unsafe { sys::fonsSetFontPage(self.raw(), font.raw() as c_int, page.raw()) } == 0 {
+            self.remove_font_page(font);
+            return Err(FonsError::FailedToAllocFont());
+        }
+        // cached quads sample the previous page
+        self.clear_quad_cache();
+
+        let mut pages = self.fons.pages.borrow_mut();
+        match pages.iter_mut().find(|(f, _)| *f == font) {
+            Some((_, p)) => *p = page.clone(),
+            None => pages.push((font, page.clone())),
+        }
+        Ok(())
+    }
+
+    /// Rasterizes the glyphs of `font` into the atlas of this stash again and releases the page
+    pub fn undedicate_font(&self, font: FontIx) -> Result<()> {
+        self.check_font(font)?;
+        unsafe {
+            sys::fonsSetFontPage(self.raw(), font.raw() as c_int, core::ptr::null_mut());
+        }
+        self.clear_quad_cache();
+        self.remove_font_page(font);
+        Ok(())
+    }
+
+    /// The stash `font` is dedicated to, if any (see [`FontStash::dedicate_font`])
+    pub fn font_page(&self, font: FontIx) -> Option<FontStash> {
+        let pages = self.fons.pages.borrow();
+        pages
+            .iter()
+            .find(|(f, _)| *f == font)
+            .map(|(_, page)| page.clone())
+    }
+
+    fn remove_font_page(&self, font: FontIx) {
+        // dropped outside of the borrow
+        let page = {
+            let mut pages = self.fons.pages.borrow_mut();
+            let ix = pages.iter().position(|(f, _)| *f == font);
+            ix.map(|ix| pages.swap_remove(ix))
+        };
+        drop(page);
+    }
+}
+
+/// Quads grouped by [`PageId`] in the order the pages first appear
+#[derive(Debug, Clone, Default)]
+pub struct QuadBatches {
+    batches: Vec<(PageId, Vec<Quad>)>,
+}
+
+impl QuadBatches {
+    /// Clears the quads keeping the allocations
+    pub fn clear(&mut self) {
+        for (_, quads) in &mut self.batches {
+            quads.clear();
+        }
+    }
+
+    /// Lays out one line of text with [`FontStash::text_iter_at`] and appends the quads to the
+    /// batch of its page, which is the dedicated one if the current font has it
+    pub fn push(&mut self, stash: &FontStash, pos: impl Into<[f32; 2]>, text: &str) -> Result<()> {
+        let pos = pos.into();
+        let iter = stash.text_iter_at(pos, text)?;
+        let page = iter.page_id();
+        self.batch_mut(page).extend(iter.filter(|q| !q.is_empty()));
+        Ok(())
+    }
+
+    /// Appends the glyph quads to the batches of their pages
+    pub fn push_glyphs(&mut self, glyphs: impl IntoIterator<Item = GlyphQuad>) {
+        for glyph in glyphs {
+            if !glyph.quad.is_empty() {
+                self.batch_mut(glyph.page).push(glyph.quad);
+            }
+        }
+    }
+
+    /// Quads of the page, which are appended by the caller
+    pub fn batch_mut(&mut self, page: PageId) -> &mut Vec<Quad> {
+        let ix = match self.batches.iter().position(|(p, _)| *p == page) {
+            Some(ix) => ix,
+            None => {
+                self.batches.push((page, Vec::new()));
+                self.batches.len() - 1
+            }
+        };
+        &mut self.batches[ix].1
+    }
+
+    /// Non-empty batches
+    pub fn iter(&self) -> impl Iterator<Item = (PageId, &[Quad])> + '_ {
+        self.batches
+            .iter()
+            .filter(|(_, quads)| !quads.is_empty())
+            .map(|(page, quads)| (*page, quads.as_slice()))
+    }
+}
+
+/// Text drawn with [`FontStash::batch`]: `(pos, text, style)`
+pub type StyledText<'a> = ([f32; 2], &'a str, &'a TextStyle);
+
+/// Batching
+impl FontStash {
+    /// Lays out one line of each text with its style and appends the quads to `out`
+    ///
+    /// Texts are grouped by style (in the order the styles first appear), so each style is
+    /// applied once and only the states that differ from the previous style are set (see
+    /// [`FontStash::set_state`]). Quads of a style come after the ones of the previous styles
+    /// even if the texts are interleaved, so overlapping texts of different styles may be drawn
+    /// in another order. The state is restored after the batch.
+    pub fn batch(&self, texts: &[StyledText], out: &mut QuadBatches) -> Result<()> {
+        self.for_each_by_style(texts, |pos, text, _| out.push(self, pos, text))
+    }
+
+    /// [`FontStash::batch`] into one vertex buffer with the colors of the styles (see
+    /// [`vertex::write_vertices`])
+    ///
+    /// All the quads sample the atlas of this stash, so it returns [`FonsError::InvalidParams`] if
+    /// a font of the styles is dedicated to another page (see [`FontStash::dedicate_font`]). The
+    /// vertices written before the error are left in `out`.
+    pub fn batch_vertices<V: QuadVertex>(
+        &self,
+        texts: &[StyledText],
+        out: &mut Vec<V>,
+    ) -> Result<()> {
+        let page = self.page_id();
+        self.for_each_by_style(texts, |pos, text, style| {
+            let iter = self.text_iter_at(pos, text)?;
+            if iter.page_id() != page {
+                return Err(FonsError::InvalidParams(
+                    "a font of the batch is dedicated to another page",
+                ));
+            }
+            vertex::write_vertices(iter.filter(|q| !q.is_empty()), style.color, out);
+            Ok(())
+        })
+    }
+
+    /// Calls `f` with the texts grouped by style, setting the style before each group
+    fn for_each_by_style(
+        &self,
+        texts: &[StyledText],
+        mut f: impl FnMut([f32; 2], &str, &TextStyle) -> Result<()>,
+    ) -> Result<()> {
+        // `(group, text index)`. Groups are made by comparison since styles have floats
+        let mut styles: Vec<&TextStyle> = Vec::new();
+        let mut order = Vec::with_capacity(texts.len());
+        for (i, &(_, _, style)) in texts.iter().enumerate() {
+            let group = match styles.iter().position(|s| *s == style) {
+                Some(group) => group,
+                None => {
+                    styles.push(style);
+                    styles.len() - 1
+                }
+            };
+            order.push((group, i));
+        }
+        order.sort_unstable();
+
+        self.scoped_state(|| {
+            let mut current = None;
+            for (group, i) in order {
+                let (pos, text, style) = texts[i];
+                if current != Some(group) {
+                    self.set_state(style);
+                    current = Some(group);
+                }
+                f(pos, text, style)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Lays out `(stash, pos, text)`s into `batches` (cleared first) and returns the quads grouped by
+/// page
+pub fn batched_quads<'b, 'a: 'b>(
+    texts: impl IntoIterator<Item = (&'a FontStash, [f32; 2], &'a str)>,
+    batches: &'b mut QuadBatches,
+) -> Result<impl Iterator<Item = (PageId, &'b [Quad])> + 'b> {
+    batches.clear();
+    for (stash, pos, text) in texts {
+        batches.push(stash, pos, text)?;
+    }
+    Ok(batches.iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_font, vertex::Vertex};
+
+    #[test]
+    fn dedicated_font() {
+        let (stash, font) = test_font::stash();
+        let page = FontStash::builder(256, 256).build().unwrap();
+        assert!(matches!(
+            stash.dedicate_font(font, &stash.clone()),
+            Err(FonsError::InvalidParams(_))
+        ));
+
+        stash.dedicate_font(font, &page).unwrap();
+        assert_eq!(stash.font_page(font).unwrap().page_id(), page.page_id());
+        stash.set_font(font);
+        stash.set_size(20.0);
+
+        // the glyphs are rasterized into the page and the quads sample it
+        let glyphs = stash.glyph_iter("abc").unwrap().collect::<Vec<_>>();
+        assert!(glyphs.iter().all(|g| g.page == page.page_id()));
+        assert_eq!(page.atlas_stats().glyph_count, 3);
+        assert_eq!(stash.atlas_stats().glyph_count, 0);
+
+        let mut batches = QuadBatches::default();
+        batches.push(&stash, [0.0, 0.0], "ab").unwrap();
+        let pages = batches
+            .iter()
+            .map(|(p, q)| (p, q.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(pages, [(page.page_id(), 2)]);
+
+        let mut vertices = Vec::<Vertex>::new();
+        let style = TextStyle::new(font, 20.0);
+        assert!(matches!(
+            stash.batch_vertices(&[([0.0, 0.0], "ab", &style)], &mut vertices),
+            Err(FonsError::InvalidParams(_))
+        ));
+
+        // back to the atlas of the stash
+        stash.undedicate_font(font).unwrap();
+        assert!(stash.font_page(font).is_none());
+        let iter = stash.text_iter("ab").unwrap();
+        assert_eq!(iter.page_id(), stash.page_id());
+        assert_eq!(iter.count(), 2);
+        assert_eq!(stash.atlas_stats().glyph_count, 2);
+    }
+}
@@ -0,0 +1,146 @@
+/*!
+Bidirectional text (`unicode-bidi` feature)
+
+Text is reordered into the visual order with the Unicode Bidirectional Algorithm, and then it's
+passed to FontStash. Each paragraph (split with newlines) is a line. Mirrored characters such as
+parentheses are not replaced.
+
+With the `arabic-shaping` feature, [`FontStash::bidi_glyphs_into`] and
+[`FontStash::text_bounds_bidi`] shape right-to-left runs before reversing them (see
+[`FontStash::set_arabic_shaping`]).
+*/
+
+use core::ops::Range;
+
+use unicode_bidi::BidiInfo;
+
+use crate::{FontStash, GlyphQuad, Result};
+
+/// Line of text reordered into the visual order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisualLine {
+    /// Characters in the visual order
+    pub text: String,
+    /// Runs in the visual order: `(logical byte range, is_rtl)`
+    pub runs: Vec<(Range<usize>, bool)>,
+    /// Logical byte offsets of the characters, indexed by byte offsets in `text`
+    logical: Vec<usize>,
+}
+
+impl VisualLine {
+    fn push(&mut self, c: char, logical: usize) {
+        self.text.push(c);
+        self.logical
+            .extend(core::iter::repeat_n(logical, c.len_utf8()));
+    }
+
+    /// Maps a byte offset in [`VisualLine::text`] to the byte offset in the source text
+    pub fn logical_offset(&self, visual: usize) -> usize {
+        self.logical.get(visual).copied().unwrap_or_else(|| {
+            // end of the line
+            self.runs.iter().map(|(r, _)| r.end).max().unwrap_or(0)
+        })
+    }
+}
+
+/// Reorders each paragraph of `text` into the visual order
+pub fn visual_lines(text: &str) -> Vec<VisualLine> {
+    self::visual_lines_impl(text, false)
+}
+
+/// Visual lines with right-to-left runs shaped if `shapes_arabic`
+#[cfg_attr(not(feature = "arabic-shaping"), allow(unused_variables))]
+fn visual_lines_impl(text: &str, shapes_arabic: bool) -> Vec<VisualLine> {
+    let info = BidiInfo::new(text, None);
+
+    info.paragraphs
+        .iter()
+        .map(|para| {
+            let line = para.range.start..para.range.start + {
+                let s = &text[para.range.clone()];
+                s.trim_end_matches(&['\n', '\r'][..]).len()
+            };
+            let (levels, runs) = info.visual_runs(para, line.clone());
+
+            let mut visual = VisualLine {
+                text: String::with_capacity(line.len()),
+                runs: Vec::with_capacity(runs.len()),
+                logical: Vec::with_capacity(line.len()),
+            };
+
+            for run in runs {
+                let is_rtl = levels[run.start].is_rtl();
+                visual.runs.push((run.clone(), is_rtl));
+
+                let s = &text[run.clone()];
+                if is_rtl {
+                    #[cfg(feature = "arabic-shaping")]
+                    if shapes_arabic {
+                        for (i, c) in crate::arabic::shaped_chars(s).into_iter().rev() {
+                            visual.push(c, run.start + i);
+                        }
+                        continue;
+                    }
+                    for (i, c) in s.char_indices().rev() {
+                        visual.push(c, run.start + i);
+                    }
+                } else {
+                    for (i, c) in s.char_indices() {
+                        visual.push(c, run.start + i);
+                    }
+                }
+            }
+
+            visual
+        })
+        .collect()
+}
+
+/// Bidirectional text
+impl FontStash {
+    /// Clears `out` and fills it with the glyphs of `text` in the visual order
+    ///
+    /// Lines are placed as [`FontStash::multiline_iter`] does. `byte_offset`s of the glyphs are
+    /// offsets in the logical (source) text.
+    pub fn bidi_glyphs_into(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        out: &mut Vec<GlyphQuad>,
+    ) -> Result<()> {
+        let pos = pos.into();
+        out.clear();
+        let line_height = self.vert_metrics().line_height;
+
+        for (i, line) in self.visual_lines(text).iter().enumerate() {
+            let y = pos[1] + line_height * i as f32;
+            for mut glyph in self.glyph_iter_at([pos[0], y], &line.text)? {
+                glyph.byte_offset = line.logical_offset(glyph.byte_offset);
+                out.push(glyph);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `[left_x, top_y, right_x, bottom_y]` of bidirectional text, measured in the visual
+    /// order
+    pub fn text_bounds_bidi(&self, pos: impl Into<[f32; 2]>, text: &str) -> [f32; 4] {
+        let pos = pos.into();
+        let visual = self
+            .visual_lines(text)
+            .into_iter()
+            .map(|line| line.text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.text_bounds(pos, &visual)
+    }
+
+    fn visual_lines(&self, text: &str) -> Vec<VisualLine> {
+        #[cfg(feature = "arabic-shaping")]
+        let shapes_arabic = self.shapes_arabic();
+        #[cfg(not(feature = "arabic-shaping"))]
+        let shapes_arabic = false;
+        self::visual_lines_impl(text, shapes_arabic)
+    }
+}
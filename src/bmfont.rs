@@ -0,0 +1,353 @@
+/*!
+AngelCode BMFont (`.fnt`, text format) export and import of the atlas
+
+Bake glyphs offline for engines that only read BMFont:
+
+```text
+let mut fnt = std::fs::File::create("ui.fnt")?;
+stash.export_bmfont(font, 32.0, ' '..='~', "ui.png", &mut fnt)?;
+stash.dump_atlas_png("ui.png")?; // with the `image` feature
+```
+
+The descriptor refers to the whole atlas as one page, so save the atlas after exporting.
+
+Conversely, hand-tuned bitmap fonts can be mixed with dynamic text. [`FontStash::import_bmfont`]
+inserts the glyphs into the atlas and they're drawn with the same iterators:
+
+```text
+let fnt = BmFont::parse(&std::fs::read_to_string("pixel.fnt")?)?;
+let page = image::open("pixel_0.png")?.to_luma_alpha8(); // take the alpha channel
+let alpha = page.pixels().map(|p| p[1]).collect::<Vec<_>>();
+stash.import_bmfont(font, &fnt, &[&alpha])?;
+```
+*/
+
+use core::ffi::c_int;
+use std::io::Write;
+
+use crate::{
+    resume_callback_panic, sys, Align, AtlasEvent, FonsError, FontIx, FontStash, Result, TextStyle,
+};
+
+/// One character of the descriptor in atlas pixels
+struct Char {
+    id: u32,
+    rect: [i32; 4],
+    offset: [i32; 2],
+    advance: i32,
+}
+
+/// BMFont
+impl FontStash {
+    /// Rasterizes `charset` with `font` at `size` and writes a BMFont descriptor of them to
+    /// `writer`, referring to the atlas as `page_file`
+    ///
+    /// Metrics are in atlas pixels, i.e., multiplied by [`FontStash::dpi_scale`]. Glyphs are
+    /// rasterized with the current blur. Characters missing in the font are skipped. Kerning pairs
+    /// are not exported. The state is restored after exporting.
+    pub fn export_bmfont(
+        &self,
+        font: FontIx,
+        size: f32,
+        charset: impl IntoIterator<Item = char>,
+        page_file: &str,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        let charset = charset.into_iter().collect::<Vec<_>>();
+        // rasterize all the glyphs first, so that texture coordinates are not changed by
+        // expanding the atlas
+        self.prewarm(font, size, charset.iter().copied())?;
+
+        let style = TextStyle {
+            blur: self.blur(),
+            align: Align::LEFT | Align::TOP,
+            ..TextStyle::new(font, size)
+        };
+        let name = self.font_info(font).map(|info| info.name);
+
+        let (chars, metrics) = self.scoped_state(|| {
+            self.apply_style(&style);
+            Ok((self.bmfont_chars(&charset), self.vert_metrics()))
+        })?;
+
+        let dpi = self.dpi_scale();
+        let [w, h] = self.atlas_size();
+        let px = |v: f32| (v * dpi).round() as i32;
+
+        writeln!(
+            writer,
+            "info face=\"{}\" size={} bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing=0,0",
+            name.as_deref().unwrap_or(""),
+            px(size),
+        )?;
+        writeln!(
+            writer,
+            "common lineHeight={} base={} scaleW={} scaleH={} pages=1 packed=0",
+            px(metrics.line_height),
+            px(metrics.ascender),
+            w,
+            h,
+        )?;
+        writeln!(writer, "page id=0 file=\"{}\"", page_file)?;
+        writeln!(writer, "chars count={}", chars.len())?;
+        for c in &chars {
+            writeln!(
+                writer,
+                "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page=0 chnl=15",
+                c.id,
+                c.rect[0],
+                c.rect[1],
+                c.rect[2] - c.rect[0],
+                c.rect[3] - c.rect[1],
+                c.offset[0],
+                c.offset[1],
+                c.advance,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn bmfont_chars(&self, charset: &[char]) -> Vec<Char> {
+        let dpi = self.dpi_scale();
+        let [w, h] = self.atlas_size();
+        let [w, h] = [w as f32, h as f32];
+
+        let mut chars = Vec::with_capacity(charset.len());
+        for &c in charset {
+            let mut buf = [0; 4];
+            let glyph = match self.glyph_iter(c.encode_utf8(&mut buf)) {
+                Ok(mut iter) => iter.next(),
+                Err(_) => None,
+            };
+            let glyph = match glyph {
+                Some(glyph) if !glyph.is_missing => glyph,
+                _ => continue,
+            };
+
+            let [x0, y0, _, _] = glyph.quad.position_rect();
+            let [s0, t0, s1, t1] = glyph.quad.uv_rect();
+            chars.push(Char {
+                id: c as u32,
+                rect: [
+                    (s0 * w).round() as i32,
+                    (t0 * h).round() as i32,
+                    (s1 * w).round() as i32,
+                    (t1 * h).round() as i32,
+                ],
+                // the line starts at the origin with `Align::TOP`
+                offset: [(x0 * dpi).round() as i32, (y0 * dpi).round() as i32],
+                advance: (glyph.x_advance * dpi).round() as i32,
+            });
+        }
+        chars
+    }
+}
+
+/// Descriptor of a BMFont (text format) parsed with [`BmFont::parse`]
+///
+/// * `size`: font size in pixels (`info size`, made positive)
+/// * `base`: distance from the top of the line to the baseline
+/// * `scale`: `[width, height]` of the pages
+/// * `pages`: file names of the pages
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BmFont {
+    pub size: f32,
+    pub line_height: f32,
+    pub base: f32,
+    pub scale: [u32; 2],
+    pub pages: Vec<String>,
+    pub chars: Vec<BmChar>,
+}
+
+/// Character of a [`BmFont`]
+///
+/// * `rect`: `[x, y, width, height]` in the page
+/// * `offset`: offset of the bitmap from the pen at the top of the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BmChar {
+    pub id: u32,
+    pub rect: [u32; 4],
+    pub offset: [i32; 2],
+    pub advance: i32,
+    pub page: u32,
+}
+
+impl BmFont {
+    /// Parses a descriptor in the text format. Fails with [`FonsError::InvalidBmFont`]
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut fnt = Self::default();
+
+        for (i, line) in src.lines().enumerate() {
+            let line_no = i + 1;
+            let mut tokens = self::tokens(line);
+            let tag = match tokens.next() {
+                Some((tag, None)) => tag,
+                Some(_) => return Err(self::error(line_no, "expected a tag")),
+                None => continue,
+            };
+
+            let mut c = BmChar::default();
+            let mut page_id = None;
+            for (key, value) in tokens {
+                let value = value.ok_or_else(|| self::error(line_no, "expected `key=value`"))?;
+                let int = || -> Result<i32> {
+                    value
+                        .parse::<i32>()
+                        .map_err(|_| self::error(line_no, "expected an integer"))
+                };
+                match (tag, key) {
+                    ("info", "size") => fnt.size = int()?.abs() as f32,
+                    ("common", "lineHeight") => fnt.line_height = int()? as f32,
+                    ("common", "base") => fnt.base = int()? as f32,
+                    ("common", "scaleW") => fnt.scale[0] = int()? as u32,
+                    ("common", "scaleH") => fnt.scale[1] = int()? as u32,
+                    ("page", "id") => page_id = Some(int()? as usize),
+                    ("page", "file") => {
+                        let id = page_id.unwrap_or(fnt.pages.len());
+                        if fnt.pages.len() <= id {
+                            fnt.pages.resize(id + 1, String::new());
+                        }
+                        fnt.pages[id] = value.to_string();
+                    }
+                    ("char", "id") => c.id = int()? as u32,
+                    ("char", "x") => c.rect[0] = int()? as u32,
+                    ("char", "y") => c.rect[1] = int()? as u32,
+                    ("char", "width") => c.rect[2] = int()? as u32,
+                    ("char", "height") => c.rect[3] = int()? as u32,
+                    ("char", "xoffset") => c.offset[0] = int()?,
+                    ("char", "yoffset") => c.offset[1] = int()?,
+                    ("char", "xadvance") => c.advance = int()?,
+                    ("char", "page") => c.page = int()? as u32,
+                    // other keys and tags (e.g. `kerning`) are ignored
+                    _ => {}
+                }
+            }
+
+            if tag == "char" {
+                fnt.chars.push(c);
+            }
+        }
+
+        if fnt.size == 0.0 {
+            return Err(self::error(0, "no `info size`"));
+        }
+        Ok(fnt)
+    }
+}
+
+fn error(line: usize, reason: &'static str) -> FonsError {
+    FonsError::InvalidBmFont { line, reason }
+}
+
+/// `key=value` pairs of a line (and the tag as a key without value). Values can be quoted
+fn tokens(line: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    let mut rest = line.trim_start();
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let key_end = rest.find(|c: char| c == '=' || c.is_whitespace());
+        let (key, after) = match key_end {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+
+        let (value, after) = match after.strip_prefix('=') {
+            Some(v) if v.starts_with('"') => match v[1..].find('"') {
+                Some(end) => (Some(&v[1..1 + end]), &v[end + 2..]),
+                None => (Some(&v[1..]), ""),
+            },
+            Some(v) => {
+                let end = v.find(char::is_whitespace).unwrap_or(v.len());
+                (Some(&v[..end]), &v[end..])
+            }
+            None => (None, after),
+        };
+
+        rest = after.trim_start();
+        Some((key, value))
+    })
+}
+
+/// BMFont import
+impl FontStash {
+    /// Inserts the glyphs of a BMFont into the atlas as glyphs of `font`, so that text drawn with
+    /// `font` at `fnt.size` uses the bitmaps. Returns the number of the inserted glyphs
+    ///
+    /// `pages` are one-channel coverage of each page (`fnt.scale` in size), e.g. the alpha
+    /// channel of the page images. `font` still provides vertical metrics, kerning and the glyphs
+    /// of other sizes and characters. The size is in atlas pixels, i.e., the text is drawn with
+    /// `fnt.size / dpi_scale()` in logical pixels. Glyphs cached already are not replaced, so
+    /// import before drawing. Resetting the atlas drops the glyphs. Returns
+    /// [`FonsError::InvalidParams`] for an SDF atlas (see [`crate::sdf`]).
+    pub fn import_bmfont(&self, font: FontIx, fnt: &BmFont, pages: &[&[u8]]) -> Result<usize> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+        if self.sdf_params().is_some() {
+            return Err(FonsError::InvalidParams(
+                "bitmaps can't be imported into an SDF atlas",
+            ));
+        }
+
+        let [w, h] = fnt.scale;
+        for page in pages {
+            let needed = (w * h) as usize;
+            if page.len() < needed {
+                return Err(FonsError::BufferTooSmall {
+                    needed,
+                    available: page.len(),
+                });
+            }
+        }
+
+        let mut n = 0;
+        for c in &fnt.chars {
+            let [x, y, cw, ch] = c.rect;
+            let page = match pages.get(c.page as usize) {
+                Some(page) => page,
+                None => continue,
+            };
+            if x + cw > w || y + ch > h {
+                return Err(FonsError::InvalidRegion([x, y, x + cw, y + ch]));
+            }
+
+            let res = unsafe {
+                sys::fonsAddGlyphBitmap(
+                    self.raw(),
+                    font.0 as c_int,
+                    c.id,
+                    fnt.size,
+                    page.as_ptr().add((x + y * w) as usize),
+                    cw as c_int,
+                    ch as c_int,
+                    w as c_int,
+                    c.offset[0],
+                    // from the top of the line to from the baseline
+                    c.offset[1] - fnt.base as i32,
+                    c.advance as f32,
+                )
+            };
+            resume_callback_panic();
+
+            match res {
+                1 => n += 1,
+                -1 => {
+                    self.fire_atlas_event(AtlasEvent::Full);
+                    let stats = self.atlas_stats();
+                    return Err(FonsError::AtlasFull {
+                        needed: (cw + 2) * (ch + 2),
+                        available: stats.total_pixels.saturating_sub(stats.used_pixels),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        self.invalidate_measure_cache();
+        Ok(n)
+    }
+}
@@ -0,0 +1,377 @@
+/*!
+[`StashBuilder`] covering all the fields of `FONSparams`
+
+Compile-time constants of `fontstash` (e.g. `FONS_MAX_STATES`) are set with the environment
+variables of `fontstash-sys` (see its build script). The builder can only check them, e.g. with
+[`StashBuilder::require_states`].
+*/
+
+use core::{
+    ffi::{c_int, c_uchar, c_void},
+    ptr::NonNull,
+};
+
+use crate::{
+    render::resume_callback_panic, sdf::SdfParams, sys, AtlasPacker, ErrorCallback, Flags,
+    FonsContextDrop, FonsError, FontStash, Renderer, Result,
+};
+
+/// Builder of [`FontStash`] with full control of `FONSparams`
+///
+/// ```text
+/// let stash = StashBuilder::new(512, 512)
+///     .renderer(&mut *renderer)
+///     .atlas_nodes(1024)
+///     .require_states(8)
+///     .build()?;
+/// ```
+///
+/// Without a renderer or callbacks, the atlas lives only in the `FONScontext` (read it with
+/// [`FontStash::texture_data`] or aggregate updates with [`FontStash::set_update_aggregation`]).
+#[derive(Debug, Clone, Copy)]
+pub struct StashBuilder {
+    params: sys::FONSparams,
+    atlas_nodes: Option<u32>,
+    min_states: usize,
+    error_callback: Option<(ErrorCallback, *mut c_void)>,
+    power_of_two: bool,
+    packer: AtlasPacker,
+    glyph_padding: u32,
+    sdf: Option<SdfParams>,
+}
+
+impl StashBuilder {
+    /// Atlas of `w` x `h` with [`Flags::TopLeft`] and no callbacks
+    pub fn new(w: u32, h: u32) -> Self {
+        Self {
+            params: sys::FONSparams {
+                width: w as c_int,
+                height: h as c_int,
+                flags: Flags::TopLeft as u8,
+                userPtr: core::ptr::null_mut(),
+                renderCreate: None,
+                renderResize: None,
+                renderExpand: None,
+                renderUpdate: None,
+                renderDelete: None,
+            },
+            atlas_nodes: None,
+            min_states: 0,
+            error_callback: None,
+            power_of_two: false,
+            packer: AtlasPacker::Skyline,
+            glyph_padding: 0,
+            sdf: None,
+        }
+    }
+
+    /// The parameters passed to `fonsCreateInternal`
+    pub fn params(&self) -> &sys::FONSparams {
+        &self.params
+    }
+
+    /// Initial size of the atlas
+    pub fn size(mut self, w: u32, h: u32) -> Self {
+        self.params.width = w as c_int;
+        self.params.height = h as c_int;
+        self
+    }
+
+    /// Origin of the glyph coordinates. Rust-side layout (e.g. [`crate::layout`]) assumes
+    /// [`Flags::TopLeft`]
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.params.flags = flags as u8;
+        self
+    }
+
+    /// Sets the user pointer and the callbacks of [`Renderer`], same as [`FontStash::init`]
+    ///
+    /// The `renderer` has to have consistant memory position while the stash is alive. Maybe put
+    /// it in a `Box`. `renderDelete` is kept.
+    pub fn renderer<R: Renderer>(mut self, renderer: *mut R) -> Self {
+        self.params.userPtr = renderer as *mut c_void;
+        self.params.renderCreate = Some(R::create);
+        self.params.renderResize = Some(R::resize);
+        self.params.renderExpand = Some(R::expand);
+        self.params.renderUpdate = Some(R::update);
+        self
+    }
+
+    /// Pointer passed to the callbacks
+    ///
+    /// # Safety
+    ///
+    /// It has to be valid for the callbacks while the stash (and its clones) are alive.
+    pub unsafe fn user_ptr(mut self, uptr: *mut c_void) -> Self {
+        self.params.userPtr = uptr;
+        self
+    }
+
+    /// `renderCreate`, called with the atlas size on creation. Return `0` to fail the creation
+    ///
+    /// # Safety
+    ///
+    /// The callback has to be sound for the user pointer. Panicking in it is undefined behavior
+    /// (see [`crate::catch_callback_panic`]).
+    pub unsafe fn render_create(
+        mut self,
+        f: Option<unsafe extern "C" fn(*mut c_void, c_int, c_int) -> c_int>,
+    ) -> Self {
+        self.params.renderCreate = f;
+        self
+    }
+
+    /// `renderResize`, called when the atlas is expanded or reset
+    ///
+    /// # Safety
+    ///
+    /// Same as [`StashBuilder::render_create`].
+    pub unsafe fn render_resize(
+        mut self,
+        f: Option<unsafe extern "C" fn(*mut c_void, c_int, c_int) -> c_int>,
+    ) -> Self {
+        self.params.renderResize = f;
+        self
+    }
+
+    /// `renderExpand`, called when the atlas is full. Return non-zero after expanding it
+    ///
+    /// # Safety
+    ///
+    /// Same as [`StashBuilder::render_create`].
+    pub unsafe fn render_expand(
+        mut self,
+        f: Option<unsafe extern "C" fn(*mut c_void) -> c_int>,
+    ) -> Self {
+        self.params.renderExpand = f;
+        self
+    }
+
+    /// `renderUpdate`, called with the dirty rectangle `[x0, y0, x1, y1]` and the atlas data
+    ///
+    /// # Safety
+    ///
+    /// Same as [`StashBuilder::render_create`].
+    pub unsafe fn render_update(
+        mut self,
+        f: Option<unsafe extern "C" fn(*mut c_void, *mut c_int, *const c_uchar) -> c_int>,
+    ) -> Self {
+        self.params.renderUpdate = f;
+        self
+    }
+
+    /// `renderDelete`, called when the `FONScontext` is deleted
+    ///
+    /// # Safety
+    ///
+    /// Same as [`StashBuilder::render_create`].
+    pub unsafe fn render_delete(mut self, f: Option<unsafe extern "C" fn(*mut c_void)>) -> Self {
+        self.params.renderDelete = f;
+        self
+    }
+
+    /// Error callback set with `fonsSetErrorCallback` after creation (see
+    /// [`crate::set_error_callback`])
+    ///
+    /// # Safety
+    ///
+    /// `uptr` has to be valid for the callback while the stash (and its clones) are alive.
+    pub unsafe fn error_callback(mut self, callback: ErrorCallback, uptr: *mut c_void) -> Self {
+        self.error_callback = Some((callback, uptr));
+        self
+    }
+
+    /// Reserves skyline nodes of the atlas, which are `FONS_INIT_ATLAS_NODES` by default
+    ///
+    /// Fragmented atlases (many glyph sizes in a large atlas) need more nodes. They're grown on
+    /// demand anyways, so it only avoids reallocations.
+    pub fn atlas_nodes(mut self, n: u32) -> Self {
+        self.atlas_nodes = Some(n);
+        self
+    }
+
+    /// Rounds the atlas size up to powers of two on creation, [`FontStash::expand_atlas`] and
+    /// [`FontStash::reset_atlas`], for GPUs requiring POT textures (e.g. some GL ES 2 devices)
+    ///
+    /// `renderExpand` callbacks calling `fonsExpandAtlas` directly have to round the size
+    /// themselves.
+    pub fn power_of_two(mut self, enabled: bool) -> Self {
+        self.power_of_two = enabled;
+        self
+    }
+
+    /// Rectangle packing algorithm of the atlas
+    pub fn packer(mut self, packer: AtlasPacker) -> Self {
+        self.packer = packer;
+        self
+    }
+
+    /// Empty pixels around each glyph in the atlas, e.g. so that mipmaps or linear filtering at
+    /// fractional scales don't bleed the neighbours (see [`FontStash::set_glyph_padding`])
+    pub fn glyph_padding(mut self, px: u32) -> Self {
+        self.glyph_padding = px;
+        self
+    }
+
+    /// Stores the glyphs as signed distance fields instead of coverage (see [`crate::sdf`])
+    pub fn sdf(mut self, params: SdfParams) -> Self {
+        self.sdf = Some(params);
+        self
+    }
+
+    /// Fails [`StashBuilder::build`] if `FONS_MAX_STATES` is less than `n`
+    pub fn require_states(mut self, n: usize) -> Self {
+        self.min_states = n;
+        self
+    }
+
+    /// Checks the combination of the parameters
+    ///
+    /// Returns [`FonsError::InvalidParams`] if:
+    ///
+    /// * the atlas is empty or has more pixels than `c_int` can count (after rounding with
+    ///   [`StashBuilder::power_of_two`])
+    /// * the number of atlas nodes is zero or too large for `c_int`
+    /// * `renderExpand` is given without `renderResize`, which is called to resize the texture
+    /// * the [`StashBuilder::glyph_padding`] on both sides doesn't fit in the atlas
+    /// * the [`StashBuilder::sdf`] parameters are invalid (see [`SdfParams::validate`])
+    /// * `FONS_MAX_STATES` is less than [`StashBuilder::require_states`]
+    pub fn validate(&self) -> Result<()> {
+        let [w, h] = self.atlas_size()?;
+
+        match self.atlas_nodes {
+            Some(0) => return Err(FonsError::InvalidParams("atlas nodes have to be positive")),
+            Some(n) if n > c_int::MAX as u32 => {
+                return Err(FonsError::InvalidParams("atlas nodes are too many"));
+            }
+            _ => {}
+        }
+
+        if self.params.renderExpand.is_some() && self.params.renderResize.is_none() {
+            return Err(FonsError::InvalidParams(
+                "`renderExpand` requires `renderResize`",
+            ));
+        }
+
+        if self.glyph_padding as i64 * 2 >= w.min(h) as i64 {
+            return Err(FonsError::InvalidParams(
+                "glyph padding doesn't fit in the atlas",
+            ));
+        }
+
+        if let Some(sdf) = &self.sdf {
+            sdf.validate()?;
+        }
+
+        if self.min_states > FontStash::max_states() {
+            return Err(FonsError::InvalidParams(
+                "`FONS_MAX_STATES` is less than the required states",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Size of the atlas to create, rounded up to powers of two if it's enabled
+    fn atlas_size(&self) -> Result<[c_int; 2]> {
+        let [w, h] = [self.params.width, self.params.height];
+        if w <= 0 || h <= 0 {
+            return Err(FonsError::InvalidParams("atlas size has to be positive"));
+        }
+
+        let [w, h] = if self.power_of_two {
+            [
+                (w as u32).next_power_of_two() as i64,
+                (h as u32).next_power_of_two() as i64,
+            ]
+        } else {
+            [w as i64, h as i64]
+        };
+        if w * h > c_int::MAX as i64 {
+            return Err(FonsError::InvalidParams("atlas size is too large"));
+        }
+
+        Ok([w as c_int, h as c_int])
+    }
+
+    /// Validates the parameters and creates the `FONScontext`
+    ///
+    /// Returns [`FonsError::InvalidParams`] (see [`StashBuilder::validate`]) or
+    /// [`FonsError::FailedToCreate`] if `fonsCreateInternal` failed (including `renderCreate`
+    /// returning `0`) or the atlas nodes or the packer couldn't be allocated.
+    pub fn build(self) -> Result<FontStash> {
+        self.validate()?;
+
+        #[cfg(feature = "dlopen")]
+        unsafe { sys::load_default() }.map_err(|err| FonsError::FailedToLoadLibrary(err.into()))?;
+
+        let [width, height] = self.atlas_size()?;
+        let mut params = sys::FONSparams {
+            width,
+            height,
+            ..self.params
+        };
+        let raw = unsafe { sys::fonsCreateInternal(&mut params) };
+        resume_callback_panic();
+        let raw = match NonNull::new(raw) {
+            Some(raw) => raw,
+            None => {
+                log_warn!(
+                    "fontstash: fonsCreateInternal failed ({}x{})",
+                    params.width,
+                    params.height
+                );
+                return Err(FonsError::FailedToCreate());
+            }
+        };
+        // deleted on failure
+        let mut fons = FonsContextDrop::new(raw);
+        fons.power_of_two = self.power_of_two;
+
+        if let Some(n) = self.atlas_nodes {
+            if unsafe { sys::fonsReserveAtlasNodes(raw.as_ptr(), n as c_int) } == 0 {
+                log_warn!("fontstash: failed to reserve {} atlas nodes", n);
+                return Err(FonsError::FailedToCreate());
+            }
+        }
+
+        if self.packer != AtlasPacker::Skyline
+            && unsafe { sys::fonsSetAtlasPacker(raw.as_ptr(), self.packer.raw() as c_int) } == 0
+        {
+            log_warn!(
+                "fontstash: failed to set the atlas packer {:?}",
+                self.packer
+            );
+            return Err(FonsError::FailedToCreate());
+        }
+
+        if self.glyph_padding != 0 {
+            unsafe { sys::fonsSetGlyphPadding(raw.as_ptr(), self.glyph_padding as c_int) };
+        }
+
+        if let Some(sdf) = self.sdf {
+            unsafe {
+                sys::fonsSetSdf(
+                    raw.as_ptr(),
+                    sdf.spread as c_int,
+                    sdf.on_edge,
+                    sdf.pixel_dist_scale,
+                );
+            }
+        }
+
+        if let Some((callback, uptr)) = self.error_callback {
+            crate::set_error_callback(raw.as_ptr(), callback, uptr);
+        }
+
+        Ok(FontStash::from_fons(alloc::rc::Rc::new(fons)))
+    }
+}
+
+/// Builder
+impl FontStash {
+    /// [`StashBuilder`] of a `w` x `h` atlas
+    pub fn builder(w: u32, h: u32) -> StashBuilder {
+        StashBuilder::new(w, h)
+    }
+}
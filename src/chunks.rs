@@ -0,0 +1,179 @@
+/*!
+Text iteration over pieces of text that are not one contiguous `&str`, such as the chunks of a
+rope or a `Vec<char>`
+
+The `FONStextIter` state (pen, kerning and the Rust-side options) continues across the pieces, so
+the glyphs are placed as if the text were concatenated. Horizontal alignment is done by iterating
+the pieces twice. NFC normalization and Arabic shaping are applied to each piece.
+*/
+
+use alloc::{borrow::Cow, string::String};
+
+use crate::{Align, FonsTextIter, FontStash, GlyphQuad, Result};
+
+/// Piece of text given to [`ChunkedGlyphIter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextPiece<'a> {
+    Str(&'a str),
+    Char(char),
+}
+
+impl<'a> TextPiece<'a> {
+    /// Length used for byte offsets: UTF-8 bytes of a `Str`, and `1` for a `Char`
+    fn source_len(&self) -> usize {
+        match self {
+            Self::Str(s) => s.len(),
+            Self::Char(_) => 1,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for TextPiece<'a> {
+    fn from(s: &'a str) -> Self {
+        Self::Str(s)
+    }
+}
+
+impl<'a> From<char> for TextPiece<'a> {
+    fn from(c: char) -> Self {
+        Self::Char(c)
+    }
+}
+
+impl<'a> From<&'a char> for TextPiece<'a> {
+    fn from(c: &'a char) -> Self {
+        Self::Char(*c)
+    }
+}
+
+/// Iterator of [`GlyphQuad`]s over pieces of text
+///
+/// `byte_offset` is the offset of the piece (sum of the lengths of the previous pieces, where a
+/// `char` counts as `1`) plus the byte offset in the piece. Decoration lines and the second quads
+/// of faux bold are not yielded, as [`crate::GlyphIter`] doesn't.
+pub struct ChunkedGlyphIter<'a, I> {
+    inner: FonsTextIter<'a>,
+    pieces: I,
+    /// Offset of the current piece
+    base: usize,
+    /// Length of the current piece
+    len: usize,
+}
+
+impl<'a, I> ChunkedGlyphIter<'a, I>
+where
+    I: Iterator + Clone,
+    I::Item: Into<TextPiece<'a>>,
+{
+    fn new(stash: &'a FontStash, pos: [f32; 2], pieces: I) -> Result<Self> {
+        let align = stash.align();
+        if !align.intersects(Align::CENTER | Align::RIGHT) {
+            return Self::init(stash, pos, pieces);
+        }
+
+        // FontStash can only align contiguous text
+        let advance = stash.with_left_align(|| -> Result<f32> {
+            let mut iter = Self::init(stash, [0.0, 0.0], pieces.clone())?;
+            while iter.next().is_some() {}
+            Ok(iter.inner.next_x())
+        })?;
+
+        let x = if align.contains(Align::RIGHT) {
+            pos[0] - advance
+        } else {
+            pos[0] - advance / 2.0
+        };
+
+        stash.with_left_align(|| Self::init(stash, [x, pos[1]], pieces))
+    }
+
+    fn init(stash: &'a FontStash, pos: [f32; 2], pieces: I) -> Result<Self> {
+        let inner = FonsTextIter::init(stash, pos, "", stash.options())?;
+        Ok(Self {
+            inner,
+            pieces,
+            base: 0,
+            len: 0,
+        })
+    }
+
+    /// Moves on to the next piece. Returns `false` at the end
+    fn next_piece(&mut self) -> bool {
+        let piece = match self.pieces.next() {
+            Some(piece) => piece.into(),
+            None => return false,
+        };
+
+        self.base += self.len;
+        self.len = piece.source_len();
+
+        let text = match piece {
+            TextPiece::Str(s) => Cow::Borrowed(s),
+            TextPiece::Char(c) => {
+                // reuse the buffer of the last character
+                let mut buf = match core::mem::replace(&mut self.inner.text, Cow::Borrowed("")) {
+                    Cow::Owned(buf) => buf,
+                    Cow::Borrowed(_) => String::new(),
+                };
+                buf.clear();
+                buf.push(c);
+                Cow::Owned(buf)
+            }
+        };
+        self.inner.continue_with(text);
+        true
+    }
+}
+
+impl<'a, I> Iterator for ChunkedGlyphIter<'a, I>
+where
+    I: Iterator + Clone,
+    I::Item: Into<TextPiece<'a>>,
+{
+    type Item = GlyphQuad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(mut glyph) = self.inner.next_glyph() {
+                glyph.byte_offset += self.base;
+                return Some(glyph);
+            }
+            if !self.next_piece() {
+                return None;
+            }
+        }
+    }
+}
+
+/// Chunked text
+impl FontStash {
+    /// Iterates glyphs of the characters without encoding them into a `String` first, e.g. for
+    /// editors storing text as `Vec<char>`. `byte_offset` of the glyphs is the index of the
+    /// character
+    pub fn text_iter_chars<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        chars: &'a [char],
+    ) -> Result<ChunkedGlyphIter<'a, core::slice::Iter<'a, char>>> {
+        let pos = pos.into();
+        ChunkedGlyphIter::new(self, pos, chars.iter())
+    }
+
+    /// Iterates glyphs of text split into chunks, e.g. `ropey::Rope::chunks`, without
+    /// concatenating them. Kerning and advances continue across the chunk boundaries
+    ///
+    /// The chunks are iterated twice for centered or right-aligned text. `byte_offset` of the
+    /// glyphs is into the concatenated text.
+    pub fn text_iter_chunks<'a, I>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        chunks: I,
+    ) -> Result<ChunkedGlyphIter<'a, I::IntoIter>>
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: Clone,
+    {
+        let pos = pos.into();
+        ChunkedGlyphIter::new(self, pos, chunks.into_iter())
+    }
+}
@@ -0,0 +1,241 @@
+/*!
+Color glyphs of `COLR`/`CPAL` fonts (e.g. emoji), with the `color-glyphs` feature
+
+The layers of the `COLR` table are decoded here and rasterized into an RGBA page by FontStash:
+
+```text
+stash.enable_color_glyphs(true)?;
+let emoji = stash.add_font_mem_copy("emoji", &data)?;
+stash.load_color_glyphs(emoji, 0)?;
+
+for glyph in stash.text_iter("🙂")?.glyphs() {
+    // sample the color page without tinting it with the text color
+    let texture = if glyph.is_colored { &color_texture } else { &atlas_texture };
+}
+// upload the dirty rectangle of both
+stash.with_color_pixels(|rgba, w, h| { /* .. */ });
+```
+
+The color page has the size of the atlas and the color glyphs at the same rectangles, so the
+quads sample either of them. The atlas gets the coverage of the color glyphs for renderers using
+only the atlas. Layers with the text color are white. Only the layers of `COLR` version 0 are
+decoded; gradients of version 1 and bitmap tables (`CBDT`, `sbix`) are not supported (see
+[`FontStash::color_tables`]). [`FontStash::load_color_glyphs`] returns
+[`FonsError::UnsupportedColorTables`] for a font with only bitmap tables, whose glyphs would be
+drawn from their outlines (or empty) otherwise.
+
+Glyphs with blur, in an SDF atlas ([`crate::sdf`]) or of a dedicated font
+([`FontStash::dedicate_font`]) are rasterized in coverage only.
+*/
+
+use core::ffi::c_int;
+
+use alloc::vec::Vec;
+
+use crate::{sfnt, sys, ColorTables, FonsError, FontIx, FontStash, Result};
+
+/// Color layers of a font decoded from `COLR` (version 0) and a palette of `CPAL`
+///
+/// * `glyphs`: `[base glyph, first layer, number of layers]` sorted by the base glyph
+/// * `layers`: `(glyph, color)`, where the color is an index into `colors` or `None` for the text
+///   color, from the bottom layer
+/// * `colors`: RGBA (straight alpha)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ColorGlyphs {
+    pub glyphs: Vec<[u16; 3]>,
+    pub layers: Vec<(u16, Option<u16>)>,
+    pub colors: Vec<[u8; 4]>,
+}
+
+impl ColorGlyphs {
+    /// Decodes the face of `data` with the palette. Returns `None` if the face has no `COLR` or
+    /// `CPAL` table, if they're malformed or if the palette is out of range
+    pub fn parse(data: &[u8], face_index: u32, palette: u16) -> Option<Self> {
+        let colr = sfnt::find_table(data, face_index, b"COLR")?;
+        let cpal = sfnt::find_table(data, face_index, b"CPAL")?;
+        let colors = self::parse_cpal(cpal, palette)?;
+
+        if sfnt::read_u16(colr, 0)? > 1 {
+            return None;
+        }
+        let n_glyphs = sfnt::read_u16(colr, 2)? as usize;
+        let glyphs_offset = sfnt::read_u32(colr, 4)? as usize;
+        let layers_offset = sfnt::read_u32(colr, 8)? as usize;
+        let n_layers = sfnt::read_u16(colr, 12)? as usize;
+
+        let mut glyphs = Vec::with_capacity(n_glyphs);
+        for i in 0..n_glyphs {
+            let record = glyphs_offset + 6 * i;
+            let glyph = [
+                sfnt::read_u16(colr, record)?,
+                sfnt::read_u16(colr, record + 2)?,
+                sfnt::read_u16(colr, record + 4)?,
+            ];
+            if glyph[1] as usize + glyph[2] as usize > n_layers {
+                return None;
+            }
+            glyphs.push(glyph);
+        }
+        // they're sorted in valid fonts
+        glyphs.sort_unstable_by_key(|g| g[0]);
+        glyphs.dedup_by_key(|g| g[0]);
+
+        let mut layers = Vec::with_capacity(n_layers);
+        for i in 0..n_layers {
+            let record = layers_offset + 4 * i;
+            let glyph = sfnt::read_u16(colr, record)?;
+            let color = match sfnt::read_u16(colr, record + 2)? {
+                0xffff => None,
+                ix if (ix as usize) < colors.len() => Some(ix),
+                _ => return None,
+            };
+            layers.push((glyph, color));
+        }
+
+        Some(Self {
+            glyphs,
+            layers,
+            colors,
+        })
+    }
+}
+
+/// Colors of the palette in RGBA
+fn parse_cpal(cpal: &[u8], palette: u16) -> Option<Vec<[u8; 4]>> {
+    let n_entries = sfnt::read_u16(cpal, 2)? as usize;
+    let n_palettes = sfnt::read_u16(cpal, 4)?;
+    let n_records = sfnt::read_u16(cpal, 6)? as usize;
+    let records_offset = sfnt::read_u32(cpal, 8)? as usize;
+    if palette >= n_palettes {
+        return None;
+    }
+
+    let first = sfnt::read_u16(cpal, 12 + 2 * palette as usize)? as usize;
+    if first + n_entries > n_records {
+        return None;
+    }
+    let records = cpal.get(records_offset + 4 * first..records_offset + 4 * (first + n_entries))?;
+    // BGRA
+    Some(
+        records
+            .chunks_exact(4)
+            .map(|c| [c[2], c[1], c[0], c[3]])
+            .collect(),
+    )
+}
+
+/// Color glyphs
+impl FontStash {
+    /// Keeps the RGBA page of the color glyphs (or releases it) and resets the atlas
+    ///
+    /// Returns [`FonsError::FailedToCreate`] if the page couldn't be allocated.
+    pub fn enable_color_glyphs(&self, enabled: bool) -> Result<()> {
+        if unsafe { sys::fonsSetColorAtlas(self.raw(), enabled as c_int) } == 0 {
+            log_warn!("fontstash: failed to allocate the color page");
+            return Err(FonsError::FailedToCreate());
+        }
+        let [w, h] = self.atlas_size();
+        self.reset_atlas(w, h)
+    }
+
+    /// Decodes the color layers of `font` with the palette and rasterizes its glyphs in color
+    /// from now on. Returns the number of the color glyphs
+    ///
+    /// Glyphs cached already are rasterized again in color. The layers are kept until the font is
+    /// reloaded. Returns [`FonsError::UnsupportedColorTables`] if the font has color glyphs only
+    /// in `CBDT` or `sbix`, [`FonsError::InvalidFontData`] if the font has no valid `COLR`/`CPAL`
+    /// tables or the palette, and [`FonsError::FailedToAllocFont`] if the layers couldn't be
+    /// copied.
+    pub fn load_color_glyphs(&self, font: FontIx, palette: u16) -> Result<usize> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+
+        let tables = self.color_tables(font);
+        if !tables.contains(ColorTables::COLR) && !tables.is_empty() {
+            log_warn!("fontstash: color tables {:?} are not rasterized", tables);
+            return Err(FonsError::UnsupportedColorTables(tables));
+        }
+
+        let (mut size, mut face) = (0, 0);
+        let ptr =
+            unsafe { sys::fonsGetFontData(self.raw(), font.0 as c_int, &mut size, &mut face) };
+        if ptr.is_null() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+        let data = unsafe { core::slice::from_raw_parts(ptr, size as usize) };
+        let colors =
+            ColorGlyphs::parse(data, face as u32, palette).ok_or(FonsError::InvalidFontData())?;
+
+        let glyphs = colors
+            .glyphs
+            .iter()
+            .flat_map(|g| g.iter().map(|&x| x as c_int))
+            .collect::<Vec<_>>();
+        let mut layers = Vec::with_capacity(colors.layers.len() * 2);
+        for &(g, c) in &colors.layers {
+            layers.extend_from_slice(&[g as c_int, c.map_or(-1, |c| c as c_int)]);
+        }
+        let res = unsafe {
+            sys::fonsSetColorGlyphs(
+                self.raw(),
+                font.0 as c_int,
+                glyphs.as_ptr(),
+                colors.glyphs.len() as c_int,
+                layers.as_ptr(),
+                colors.layers.len() as c_int,
+                colors.colors.as_ptr() as *const u8,
+                colors.colors.len() as c_int,
+            )
+        };
+        if res == 0 {
+            return Err(FonsError::FailedToAllocFont());
+        }
+        // cached quads sample the coverage glyphs
+        self.clear_quad_cache();
+        Ok(colors.glyphs.len())
+    }
+
+    /// Runs `f` with the RGBA pixels (straight alpha) of the color page and its `width`, `height`.
+    /// Returns `None` if the page is not enabled
+    ///
+    /// They're dirty in the same rectangle as the atlas, so upload them together.
+    pub fn with_color_pixels<R>(&self, f: impl FnOnce(&[u8], u32, u32) -> R) -> Option<R> {
+        let (mut w, mut h) = (0, 0);
+        let ptr = unsafe { sys::fonsGetColorTextureData(self.raw(), &mut w, &mut h) };
+        if ptr.is_null() {
+            return None;
+        }
+        let pixels = unsafe { core::slice::from_raw_parts(ptr, (w * h * 4) as usize) };
+        Some(f(pixels, w as u32, h as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font;
+
+    #[test]
+    fn bitmap_tables_unsupported() {
+        let stash = FontStash::builder(256, 256).build().unwrap();
+        stash.enable_color_glyphs(true).unwrap();
+        // CBDT version 3.0 without bitmaps
+        let data = test_font::data_with(alloc::vec![(b"CBDT", alloc::vec![0, 3, 0, 0])]);
+        let font = stash.add_font_mem_copy("bitmaps", &data).unwrap();
+        assert_eq!(stash.color_tables(font), ColorTables::CBDT);
+        assert!(matches!(
+            stash.load_color_glyphs(font, 0),
+            Err(FonsError::UnsupportedColorTables(ColorTables::CBDT))
+        ));
+
+        // no color table at all
+        let font = stash.add_font_mem_copy("mono", &test_font::data()).unwrap();
+        assert!(stash.color_tables(font).is_empty());
+        assert!(matches!(
+            stash.load_color_glyphs(font, 0),
+            Err(FonsError::InvalidFontData())
+        ));
+    }
+}
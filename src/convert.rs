@@ -0,0 +1,189 @@
+/*!
+Conversion of atlas pixels (one byte of coverage per pixel) into RGBA8
+
+With the `simd` feature, 16 pixels are converted at a time with SSE2 on `x86_64`. Other targets
+use the scalar loop, which the compiler can still vectorize. Coverage can be adjusted with a
+[`CoverageLut`] before it's expanded.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{AlphaMode, FontStash};
+
+/// Appends `coverage` expanded to RGBA8 to `out`
+pub fn expand_to_rgba(coverage: &[u8], mode: AlphaMode, out: &mut Vec<u8>) {
+    let start = out.len();
+    out.resize(start + coverage.len() * 4, 0);
+    self::expand_into(coverage, mode, &mut out[start..]);
+}
+
+/// Writes `coverage` expanded to RGBA8 to `out`, which is four times as long as `coverage`
+pub(crate) fn expand_into(coverage: &[u8], mode: AlphaMode, out: &mut [u8]) {
+    debug_assert_eq!(coverage.len() * 4, out.len());
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    let n = unsafe { self::expand_sse2(coverage, mode, out) };
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    let n = 0;
+
+    self::expand_scalar(&coverage[n..], mode, &mut out[n * 4..]);
+}
+
+fn expand_scalar(coverage: &[u8], mode: AlphaMode, out: &mut [u8]) {
+    for (px, &a) in out.chunks_exact_mut(4).zip(coverage) {
+        px.copy_from_slice(&mode.rgba(a));
+    }
+}
+
+/// Converts the pixels in chunks of 16 and returns the number of the converted pixels
+///
+/// # Safety
+///
+/// `out` has to be four times as long as `coverage`. SSE2 is always available on `x86_64`.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+unsafe fn expand_sse2(coverage: &[u8], mode: AlphaMode, out: &mut [u8]) -> usize {
+    use core::arch::x86_64::*;
+
+    let zero = _mm_setzero_si128();
+    let white = _mm_set1_epi32(0x00ff_ffff);
+    let chunks = coverage.chunks_exact(16);
+    let n = coverage.len() - chunks.remainder().len();
+
+    for (i, chunk) in chunks.enumerate() {
+        let a = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+        // coverage in the high byte of each 16-bit lane, and then of each 32-bit lane
+        let [lo, hi] = [_mm_unpacklo_epi8(zero, a), _mm_unpackhi_epi8(zero, a)];
+        let pixels = [
+            _mm_unpacklo_epi16(zero, lo),
+            _mm_unpackhi_epi16(zero, lo),
+            _mm_unpacklo_epi16(zero, hi),
+            _mm_unpackhi_epi16(zero, hi),
+        ];
+
+        for (j, alpha) in pixels.iter().enumerate() {
+            let rgba = match mode {
+                AlphaMode::Straight => _mm_or_si128(*alpha, white),
+                AlphaMode::Premultiplied => {
+                    let x = _mm_or_si128(*alpha, _mm_srli_epi32(*alpha, 8));
+                    _mm_or_si128(x, _mm_srli_epi32(x, 16))
+                }
+            };
+            let dst = out.as_mut_ptr().add((i * 16 + j * 4) * 4);
+            _mm_storeu_si128(dst as *mut __m128i, rgba);
+        }
+    }
+
+    n
+}
+
+/// Table applied to coverage before expansion, e.g. for gamma correction of small text
+///
+/// Set it with [`FontStash::set_coverage_lut`]. The SIMD path is not used while it's applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageLut {
+    pub table: [u8; 256],
+}
+
+impl Default for CoverageLut {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl CoverageLut {
+    pub const IDENTITY: Self = Self {
+        table: {
+            let mut table = [0; 256];
+            let mut i = 0;
+            while i < 256 {
+                table[i] = i as u8;
+                i += 1;
+            }
+            table
+        },
+    };
+
+    /// `coverage ^ (1 / gamma)` and then `x + contrast * x * (1 - x)`, which boosts partial
+    /// coverage keeping `0` and `255`
+    ///
+    /// `gamma > 1` thickens the text (for dark text on light backgrounds) and `gamma < 1` thins
+    /// it. Around `1.4` and a contrast of `0.2` works for small dark text.
+    #[cfg(feature = "std")]
+    pub fn gamma(gamma: f32, contrast: f32) -> Self {
+        let mut table = [0; 256];
+        for (i, x) in table.iter_mut().enumerate() {
+            let c = (i as f32 / 255.0).powf(1.0 / gamma.max(f32::EPSILON));
+            let c = c + contrast * c * (1.0 - c);
+            *x = (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        }
+        Self { table }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    pub fn apply(&self, coverage: u8) -> u8 {
+        self.table[coverage as usize]
+    }
+
+    pub fn apply_in_place(&self, coverage: &mut [u8]) {
+        for c in coverage {
+            *c = self.apply(*c);
+        }
+    }
+}
+
+/// [`expand_to_rgba`] with `lut` applied to the coverage
+pub fn expand_to_rgba_adjusted(
+    coverage: &[u8],
+    mode: AlphaMode,
+    lut: &CoverageLut,
+    out: &mut Vec<u8>,
+) {
+    let start = out.len();
+    out.resize(start + coverage.len() * 4, 0);
+    self::expand_into_adjusted(coverage, mode, Some(lut), &mut out[start..]);
+}
+
+/// [`expand_into`] with `lut` applied to the coverage, if any
+pub(crate) fn expand_into_adjusted(
+    coverage: &[u8],
+    mode: AlphaMode,
+    lut: Option<&CoverageLut>,
+    out: &mut [u8],
+) {
+    let lut = match lut {
+        Some(lut) => lut,
+        None => return self::expand_into(coverage, mode, out),
+    };
+
+    debug_assert_eq!(coverage.len() * 4, out.len());
+    for (px, &a) in out.chunks_exact_mut(4).zip(coverage) {
+        px.copy_from_slice(&mode.rgba(lut.apply(a)));
+    }
+}
+
+/// Coverage adjustment
+impl FontStash {
+    /// Sets the table applied to coverage by the Rust-side copies of the atlas:
+    /// [`FontStash::pixels_rgba_into`], [`FontStash::copy_texture_region`] and
+    /// [`FontStash::copy_texture_region_rgba`]
+    ///
+    /// Raw pixels ([`FontStash::with_pixels`] and the data given to `renderUpdate`) are never
+    /// changed. Apply [`FontStash::coverage_lut`] when uploading them, or sample it in the shader
+    /// as a 256x1 texture.
+    pub fn set_coverage_lut(&self, lut: Option<CoverageLut>) {
+        *self.fons.coverage_lut.borrow_mut() = lut.filter(|lut| !lut.is_identity());
+    }
+
+    pub fn coverage_lut(&self) -> Option<CoverageLut> {
+        self.fons.coverage_lut.borrow().clone()
+    }
+
+    /// [`FontStash::set_coverage_lut`] with [`CoverageLut::gamma`]
+    #[cfg(feature = "std")]
+    pub fn set_coverage_gamma(&self, gamma: f32, contrast: f32) {
+        self.set_coverage_lut(Some(CoverageLut::gamma(gamma, contrast)));
+    }
+}
@@ -0,0 +1,121 @@
+/*!
+Aggregation of dirty regions, enabled with [`FontStash::set_update_aggregation`]
+
+While it's enabled, the user pointer of `FONScontext` is replaced with [`Aggregator`] and the
+callbacks are forwarded to the original [`crate::Renderer`], except `renderUpdate`. Updated
+regions are merged into one rectangle until [`FontStash::take_dirty_rect`] is called.
+*/
+
+use core::{
+    cell::Cell,
+    ffi::{c_int, c_uchar, c_void},
+};
+
+use alloc::boxed::Box;
+
+use crate::{sys, FontStash};
+
+/// User pointer and callbacks replaced while aggregating updates
+#[derive(Debug)]
+pub(crate) struct Aggregator {
+    params: sys::FONSparams,
+    /// `[x0, y0, x1, y1]`
+    dirty: Cell<Option<[c_int; 4]>>,
+}
+
+unsafe fn aggregator<'a>(uptr: *mut c_void) -> &'a Aggregator {
+    &*(uptr as *const Aggregator)
+}
+
+unsafe extern "C" fn create(uptr: *mut c_void, width: c_int, height: c_int) -> c_int {
+    let a = aggregator(uptr);
+    a.params
+        .renderCreate
+        .map_or(1, |f| f(a.params.userPtr, width, height))
+}
+
+unsafe extern "C" fn resize(uptr: *mut c_void, width: c_int, height: c_int) -> c_int {
+    let a = aggregator(uptr);
+    a.params
+        .renderResize
+        .map_or(1, |f| f(a.params.userPtr, width, height))
+}
+
+unsafe extern "C" fn expand(uptr: *mut c_void) -> c_int {
+    let a = aggregator(uptr);
+    a.params.renderExpand.map_or(0, |f| f(a.params.userPtr))
+}
+
+unsafe extern "C" fn update(uptr: *mut c_void, rect: *mut c_int, _data: *const c_uchar) -> c_int {
+    let a = aggregator(uptr);
+    let rect = core::slice::from_raw_parts(rect, 4);
+    let merged = match a.dirty.get() {
+        Some(d) => [
+            d[0].min(rect[0]),
+            d[1].min(rect[1]),
+            d[2].max(rect[2]),
+            d[3].max(rect[3]),
+        ],
+        None => [rect[0], rect[1], rect[2], rect[3]],
+    };
+    a.dirty.set(Some(merged));
+    1
+}
+
+unsafe extern "C" fn delete(uptr: *mut c_void) {
+    let a = aggregator(uptr);
+    if let Some(f) = a.params.renderDelete {
+        f(a.params.userPtr);
+    }
+}
+
+/// Dirty region aggregation
+impl FontStash {
+    /// Coalesces texture updates. While it's enabled, [`crate::Renderer::update`] is not called;
+    /// upload [`FontStash::take_dirty_rect`] once per frame instead
+    pub fn set_update_aggregation(&self, enabled: bool) {
+        let mut slot = self.fons.aggregator.borrow_mut();
+        if enabled == slot.is_some() {
+            return;
+        }
+
+        unsafe {
+            if enabled {
+                let mut params: sys::FONSparams = core::mem::zeroed();
+                sys::fonsGetRenderCallbacks(self.raw(), &mut params);
+
+                let a = Box::new(Aggregator {
+                    params,
+                    dirty: Cell::new(None),
+                });
+
+                let mut replaced = params;
+                replaced.userPtr = &*a as *const Aggregator as *mut c_void;
+                replaced.renderCreate = Some(create);
+                replaced.renderResize = Some(resize);
+                replaced.renderExpand = Some(expand);
+                replaced.renderUpdate = Some(update);
+                replaced.renderDelete = Some(delete);
+                sys::fonsSetRenderCallbacks(self.raw(), &replaced);
+
+                *slot = Some(a);
+            } else if let Some(a) = slot.take() {
+                sys::fonsSetRenderCallbacks(self.raw(), &a.params);
+            }
+        }
+    }
+
+    /// `[x0, y0, x1, y1]` of the atlas region updated since the last call, if any
+    ///
+    /// Always `None` unless [`FontStash::set_update_aggregation`] is enabled.
+    pub fn take_dirty_rect(&self) -> Option<[u32; 4]> {
+        let slot = self.fons.aggregator.borrow();
+        let d = slot.as_ref()?.dirty.take()?;
+        // counted here because `renderUpdate` is not called while aggregating
+        self.bump_stats(|s| {
+            s.texture_updates += 1;
+            s.bytes_uploaded += crate::stats::area(d);
+        });
+        Some([d[0] as u32, d[1] as u32, d[2] as u32, d[3] as u32])
+    }
+}
@@ -0,0 +1,136 @@
+/*!
+Text effects drawn in two passes: a back pass (outline or shadow) and then the fill pass
+
+Effects don't need extra channels in the atlas; the back pass is made of the same glyphs placed
+differently.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{vertex, Color, FontStash, Quad, Result};
+
+/// Quads of text drawn in two passes. Draw `back` first
+#[derive(Debug, Clone, Default)]
+pub struct LayeredQuads {
+    pub back: Vec<Quad>,
+    pub back_color: Color,
+    pub front: Vec<Quad>,
+    pub front_color: Color,
+}
+
+impl LayeredQuads {
+    pub fn clear(&mut self) {
+        self.back.clear();
+        self.front.clear();
+    }
+
+    /// Appends the vertices of the back pass and then the front pass to `out` (see
+    /// [`vertex::write_vertices`])
+    pub fn write_vertices<V: vertex::QuadVertex>(&self, out: &mut Vec<V>) {
+        vertex::write_vertices(self.back.iter().copied(), self.back_color, out);
+        vertex::write_vertices(self.front.iter().copied(), self.front_color, out);
+    }
+}
+
+/// `cos(pi / 4)`
+const DIAGONAL: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+const DIRECTIONS: [[f32; 2]; 8] = [
+    [1.0, 0.0],
+    [DIAGONAL, DIAGONAL],
+    [0.0, 1.0],
+    [-DIAGONAL, DIAGONAL],
+    [-1.0, 0.0],
+    [-DIAGONAL, -DIAGONAL],
+    [0.0, -1.0],
+    [DIAGONAL, -DIAGONAL],
+];
+
+/// Offsets of the outline passes: rings of 8 directions, one ring per pixel of the width
+fn ring_offsets(width: f32) -> impl Iterator<Item = [f32; 2]> {
+    let n_rings = (width.max(0.0) as u32 + 1).max(1);
+    (1..=n_rings).flat_map(move |i| {
+        let r = width * i as f32 / n_rings as f32;
+        DIRECTIONS.iter().map(move |d| [d[0] * r, d[1] * r])
+    })
+}
+
+/// Effects
+impl FontStash {
+    /// Lays out one line of text with an outline of `width` pixels in `outline_color`
+    ///
+    /// Clears `out` and fills it. The outline pass is the fill quads repeated at offsets around
+    /// the text, so it's made of the current state as is. The front color is the current color.
+    pub fn outline_quads_into(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        width: f32,
+        outline_color: impl Into<Color>,
+        out: &mut LayeredQuads,
+    ) -> Result<()> {
+        let pos = pos.into();
+        out.clear();
+        out.front.extend(self.text_iter_at(pos, text)?);
+        out.front_color = self.color();
+        out.back_color = outline_color.into();
+
+        let glyphs = out.front.iter().filter(|q| !q.is_empty());
+        for [dx, dy] in self::ring_offsets(width) {
+            out.back.extend(glyphs.clone().map(|q| q.translate(dx, dy)));
+        }
+
+        Ok(())
+    }
+
+    /// Lays out one line of text with a drop shadow moved by `offset`
+    ///
+    /// Clears `out` and fills it. The shadow pass is rasterized with the current blur for soft
+    /// shadows, and the fill pass without blur. The front color is the current color.
+    pub fn shadowed_quads_into(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        offset: impl Into<[f32; 2]>,
+        shadow_color: impl Into<Color>,
+        out: &mut LayeredQuads,
+    ) -> Result<()> {
+        let [pos, offset] = [pos.into(), offset.into()];
+        out.clear();
+        out.back_color = shadow_color.into();
+        out.front_color = self.color();
+
+        let blur = self.blur();
+        let res = self.shadowed_quads_impl(pos, text, offset, out);
+        self.set_blur(blur);
+
+        res
+    }
+
+    fn shadowed_quads_impl(
+        &self,
+        pos: [f32; 2],
+        text: &str,
+        offset: [f32; 2],
+        out: &mut LayeredQuads,
+    ) -> Result<()> {
+        let shadow_pos = [pos[0] + offset[0], pos[1] + offset[1]];
+        out.back.extend(self.text_iter_at(shadow_pos, text)?);
+        self.set_blur(0.0);
+        out.front.extend(self.text_iter_at(pos, text)?);
+        Ok(())
+    }
+
+    /// [`FontStash::shadowed_quads_into`] returning new quads
+    pub fn shadowed_quads(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        offset: impl Into<[f32; 2]>,
+        shadow_color: impl Into<Color>,
+    ) -> Result<LayeredQuads> {
+        let mut out = LayeredQuads::default();
+        self.shadowed_quads_into(pos, text, offset, shadow_color, &mut out)?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,181 @@
+/*!
+[egui](https://github.com/emilk/egui) integration, enabled with the `egui` feature
+
+[`EguiAtlas`] uploads the atlas as a texture managed by egui, and [`append_quads`] converts quads
+into an [`egui::Mesh`] sampling it, so that text laid out by FontStash (e.g. CJK with fallback
+fonts, or shaped glyphs of [`crate::shape`]) is composited in egui UIs:
+
+```text
+// once
+stash.set_update_aggregation(true);
+let mut atlas = EguiAtlas::new(ctx, &stash)?;
+
+// every frame
+let mesh = text_mesh(&stash, atlas.texture_id(), [10.0, 10.0], "こんにちは", Color::WHITE)?;
+// upload the glyphs rasterized while laying out the text
+atlas.update(&stash)?;
+ui.painter().add(egui::Shape::mesh(mesh));
+```
+
+Positions are used as egui points. For sharp text on high DPI displays, set the font size
+multiplied by [`egui::Context::pixels_per_point`] and scale the quads by its inverse with
+[`Quad::scale`].
+
+Meshes can also sample a texture registered to the egui backend (`TextureId::User`) if you upload
+the atlas yourself; pass its ID to [`append_quads`] instead.
+*/
+
+use alloc::vec::Vec;
+
+use ::egui::{
+    epaint::Vertex, pos2, Color32, ColorImage, Context, Mesh, TextureHandle, TextureId,
+    TextureOptions,
+};
+
+use crate::{
+    vertex::{self, QUAD_INDICES},
+    AlphaMode, Color, FontStash, Quad, Result,
+};
+
+/// The atlas uploaded to egui as a managed texture
+///
+/// It's premultiplied (see [`AlphaMode::Premultiplied`]) as egui expects.
+pub struct EguiAtlas {
+    handle: TextureHandle,
+    size: [u32; 2],
+    options: TextureOptions,
+    rgba: Vec<u8>,
+}
+
+impl core::fmt::Debug for EguiAtlas {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EguiAtlas")
+            .field("texture_id", &self.handle.id())
+            .field("size", &self.size)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl EguiAtlas {
+    /// Uploads the whole atlas as a new texture with linear filtering
+    pub fn new(ctx: &Context, stash: &FontStash) -> Result<Self> {
+        Self::with_options(ctx, stash, TextureOptions::LINEAR)
+    }
+
+    pub fn with_options(ctx: &Context, stash: &FontStash, options: TextureOptions) -> Result<Self> {
+        let mut rgba = Vec::new();
+        let size = stash.pixels_rgba_into(AlphaMode::Premultiplied, &mut rgba)?;
+        let image = ColorImage::from_rgba_premultiplied(self::usize2(size), &rgba);
+        // the dirty region is included in the whole upload
+        stash.take_dirty_rect();
+
+        Ok(Self {
+            handle: ctx.load_texture("fontstash", image, options),
+            size,
+            options,
+            rgba,
+        })
+    }
+
+    pub fn texture_id(&self) -> TextureId {
+        self.handle.id()
+    }
+
+    /// `[width, height]` of the uploaded texture
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Uploads the changes of the atlas. Call it after laying out the text of the frame
+    ///
+    /// Only the region of [`FontStash::take_dirty_rect`] is uploaded if the atlas size is not
+    /// changed. Without [`FontStash::set_update_aggregation`], there's no dirty region, so the
+    /// whole atlas is uploaded every time.
+    pub fn update(&mut self, stash: &FontStash) -> Result<()> {
+        let size = stash.atlas_size();
+        let dirty = stash.take_dirty_rect();
+
+        match dirty {
+            Some(rect) if size == self.size => {
+                let [x0, y0, x1, y1] = rect;
+                let region = [x1 - x0, y1 - y0];
+                self.rgba.clear();
+                self.rgba.resize((region[0] * region[1] * 4) as usize, 0);
+                stash.copy_texture_region_rgba(rect, AlphaMode::Premultiplied, &mut self.rgba)?;
+
+                let image = ColorImage::from_rgba_premultiplied(self::usize2(region), &self.rgba);
+                self.handle
+                    .set_partial(self::usize2([x0, y0]), image, self.options);
+            }
+            _ => {
+                self.size = stash.pixels_rgba_into(AlphaMode::Premultiplied, &mut self.rgba)?;
+                let image =
+                    ColorImage::from_rgba_premultiplied(self::usize2(self.size), &self.rgba);
+                self.handle.set(image, self.options);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn usize2([x, y]: [u32; 2]) -> [usize; 2] {
+    [x as usize, y as usize]
+}
+
+/// Vertex color of egui, which is premultiplied
+pub fn color32(color: impl Into<Color>) -> Color32 {
+    let c = color.into();
+    Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a)
+}
+
+/// Appends two triangles per quad to `mesh`. The texture of the mesh has to be the atlas
+///
+/// Sheared quads (see [`Quad::sheared`]) keep the slant.
+pub fn append_quads(
+    mesh: &mut Mesh,
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    color: impl Into<Color>,
+) {
+    let color = self::color32(color);
+    for q in quads {
+        let q = q.into();
+        if q.is_empty() {
+            continue;
+        }
+
+        let base = mesh.vertices.len() as u32;
+        for (pos, uv) in vertex::corners(&q) {
+            mesh.vertices.push(Vertex {
+                pos: pos2(pos[0], pos[1]),
+                uv: pos2(uv[0], uv[1]),
+                color,
+            });
+        }
+        mesh.indices.extend(QUAD_INDICES.iter().map(|i| base + i));
+    }
+}
+
+/// Creates a mesh sampling `texture` from the quads
+pub fn mesh_from_quads(
+    texture: TextureId,
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    color: impl Into<Color>,
+) -> Mesh {
+    let mut mesh = Mesh::with_texture(texture);
+    self::append_quads(&mut mesh, quads, color);
+    mesh
+}
+
+/// Lays out one line of text with [`FontStash::text_iter_at`] into a mesh sampling `texture`
+pub fn text_mesh(
+    stash: &FontStash,
+    texture: TextureId,
+    pos: impl Into<[f32; 2]>,
+    text: &str,
+    color: impl Into<Color>,
+) -> Result<Mesh> {
+    let iter = stash.text_iter_at(pos, text)?;
+    Ok(self::mesh_from_quads(texture, iter, color))
+}
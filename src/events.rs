@@ -0,0 +1,77 @@
+/*!
+Atlas lifecycle events registered with [`FontStash::on_atlas_event`]
+
+Events are fired by the Rust wrapper after the operation, not from inside the C callbacks, so
+the listeners can call back into the stash.
+*/
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::FontStash;
+
+/// Change of the atlas notified to [`FontStash::on_atlas_event`] listeners
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasEvent {
+    /// The texture was recreated with the size (`renderResize`). Fired before
+    /// [`AtlasEvent::Expanded`], [`AtlasEvent::Reset`] and [`AtlasEvent::Repacked`]
+    Resized { width: u32, height: u32 },
+    /// The atlas was expanded keeping the glyphs, with [`FontStash::expand_atlas`] or by
+    /// `renderExpand` while iterating text
+    Expanded { width: u32, height: u32 },
+    /// The atlas was cleared with [`FontStash::reset_atlas`]
+    Reset { width: u32, height: u32 },
+    /// The glyphs were moved with [`FontStash::repack`]. Quads made before it are invalid
+    Repacked { width: u32, height: u32 },
+    /// Stale glyphs were dropped from the atlas by [`FontStash::evict_stale_glyphs`] freeing
+    /// their regions. Quads of them made before it are invalid
+    Evicted { glyphs: u32 },
+    /// A glyph didn't fit in the atlas. Fired once per text iterator
+    Full,
+}
+
+/// Listeners, which are not `Debug`
+#[derive(Default)]
+pub(crate) struct AtlasListeners(Vec<Box<dyn FnMut(AtlasEvent)>>);
+
+impl core::fmt::Debug for AtlasListeners {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AtlasListeners({})", self.0.len())
+    }
+}
+
+/// Atlas events
+impl FontStash {
+    /// Registers a listener called on resize, expansion, reset, eviction and overflow of the atlas,
+    /// e.g. to recreate bind groups for the new texture
+    pub fn on_atlas_event(&self, f: impl FnMut(AtlasEvent) + 'static) {
+        self.fons.atlas_listeners.borrow_mut().0.push(Box::new(f));
+    }
+
+    /// Removes all the listeners
+    pub fn clear_atlas_listeners(&self) {
+        self.fons.atlas_listeners.borrow_mut().0.clear();
+    }
+
+    pub(crate) fn fire_atlas_event(&self, event: AtlasEvent) {
+        // taken out so that listeners can register listeners
+        let mut listeners = core::mem::take(&mut self.fons.atlas_listeners.borrow_mut().0);
+        for f in &mut listeners {
+            f(event);
+        }
+
+        let mut slot = self.fons.atlas_listeners.borrow_mut();
+        listeners.append(&mut slot.0);
+        slot.0 = listeners;
+    }
+
+    /// Fires the events for the atlas expanded by `renderExpand`, if any
+    pub(crate) fn fire_pending_atlas_events(&self) {
+        if !self.fons.counters.take_expanded() {
+            return;
+        }
+
+        let [width, height] = self.atlas_size();
+        self.fire_atlas_event(AtlasEvent::Resized { width, height });
+        self.fire_atlas_event(AtlasEvent::Expanded { width, height });
+    }
+}
@@ -0,0 +1,310 @@
+/*!
+Glyph eviction based on the last frame each glyph was used
+
+With [`AtlasPacker::Guillotine`], the regions of stale glyphs are freed in place and the other
+glyphs keep their positions. The skyline and shelf packers can't free the region of a glyph, so
+eviction resets the atlas and rasterizes only the glyphs used recently again.
+*/
+
+use alloc::{string::String, vec::Vec};
+use core::ffi::{c_int, c_short, c_uint, c_void};
+
+use crate::{events::AtlasEvent, sys, AtlasPacker, FontStash, Map, Result};
+
+/// Configuration of glyph eviction, which is run by [`FontStash::end_frame`]
+///
+/// * `max_age`: glyphs not used in the last `max_age` frames are evicted
+/// * `occupancy`: eviction runs when [`crate::AtlasStats::occupancy`] reaches this value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphEviction {
+    pub max_age: u32,
+    pub occupancy: f32,
+}
+
+impl Default for GlyphEviction {
+    fn default() -> Self {
+        Self {
+            max_age: 60,
+            occupancy: 0.9,
+        }
+    }
+}
+
+/// Glyph in the atlas is identified with these values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct GlyphKey {
+    pub font: usize,
+    pub codepoint: u32,
+    pub isize: i16,
+    pub iblur: i16,
+}
+
+/// Last-use frames of glyphs
+#[derive(Debug, Default)]
+pub(crate) struct GlyphUsage {
+    eviction: Option<GlyphEviction>,
+    frame: u32,
+    last_use: Map<GlyphKey, u32>,
+}
+
+/// Glyph eviction
+impl FontStash {
+    /// Enables or disables glyph eviction. Glyph usage is tracked only while it's enabled.
+    pub fn set_glyph_eviction(&self, eviction: Option<GlyphEviction>) {
+        let mut usage = self.fons.usage.borrow_mut();
+        usage.eviction = eviction;
+        if eviction.is_none() {
+            usage.last_use.clear();
+        }
+    }
+
+    /// Ticks the frame counter and evicts stale glyphs if the atlas is occupied enough
+    pub fn end_frame(&self) -> Result<()> {
+        let needs_eviction = {
+            let mut usage = self.fons.usage.borrow_mut();
+            usage.frame = usage.frame.wrapping_add(1);
+            match usage.eviction {
+                Some(eviction) => self.atlas_stats().occupancy() >= eviction.occupancy,
+                None => false,
+            }
+        };
+
+        if needs_eviction {
+            self.evict_stale_glyphs()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Drops the glyphs not used in the last `max_age` frames from the atlas
+    ///
+    /// With [`AtlasPacker::Guillotine`], only the regions of the stale glyphs are freed and
+    /// [`AtlasEvent::Evicted`] is fired. Otherwise the atlas is reset and the recently used glyphs
+    /// are rasterized again right away, so the next frame doesn't have to do it. Does nothing if
+    /// glyph eviction is disabled.
+    pub fn evict_stale_glyphs(&self) -> Result<()> {
+        let live = {
+            let mut usage = self.fons.usage.borrow_mut();
+            let (frame, max_age) = match usage.eviction {
+                Some(eviction) => (usage.frame, eviction.max_age),
+                None => return Ok(()),
+            };
+            usage
+                .last_use
+                .retain(|_, last| frame.wrapping_sub(*last) <= max_age);
+            usage.last_use.keys().copied().collect::<Vec<_>>()
+        };
+
+        if self.atlas_packer() == AtlasPacker::Guillotine {
+            self.free_stale_glyphs(&live);
+            return Ok(());
+        }
+
+        let [w, h] = self.atlas_size();
+        self.reset_atlas(w, h)?;
+
+        // group the glyphs by the state they're rasterized with
+        let mut runs = Map::<(usize, i16, i16), String>::new();
+        for key in live {
+            if let Some(c) = core::char::from_u32(key.codepoint) {
+                runs.entry((key.font, key.isize, key.iblur))
+                    .or_default()
+                    .push(c);
+            }
+        }
+
+        // the keys are in physical pixels, so the states are set without the DPI scale and the
+        // glyphs are rasterized without the Rust-side options
+        self.scoped_state(|| unsafe {
+            for ((font, isize, iblur), text) in runs {
+                let ix = sys::fonsGetFontIndex(self.raw(), font as *mut sys::FONSfont);
+                if ix == sys::FONS_INVALID {
+                    continue;
+                }
+
+                sys::fonsSetFont(self.raw(), ix);
+                sys::fonsSetSize(self.raw(), isize as f32 / 10.0);
+                sys::fonsSetBlur(self.raw(), iblur as f32);
+                self.rasterize_raw(&text);
+            }
+            Ok(())
+        })
+    }
+
+    /// Frees the regions of the glyphs not in `live` with `fonsEvictGlyphs`
+    fn free_stale_glyphs(&self, live: &[GlyphKey]) {
+        /// `keep` callback of `fonsEvictGlyphs`
+        unsafe extern "C" fn keep(
+            uptr: *mut c_void,
+            font: c_int,
+            codepoint: c_uint,
+            isize: c_short,
+            iblur: c_short,
+        ) -> c_int {
+            let live = &*(uptr as *const Vec<GlyphKey>);
+            let key = GlyphKey {
+                font: font as usize,
+                codepoint,
+                isize,
+                iblur,
+            };
+            live.binary_search(&key).is_ok() as c_int
+        }
+
+        // the keys are by the font pointers of the iterators, and `keep` gets the font indices
+        let mut live = live
+            .iter()
+            .filter_map(|key| {
+                let ix =
+                    unsafe { sys::fonsGetFontIndex(self.raw(), key.font as *mut sys::FONSfont) };
+                (ix != sys::FONS_INVALID).then_some(GlyphKey {
+                    font: ix as usize,
+                    ..*key
+                })
+            })
+            .collect::<Vec<_>>();
+        live.sort_unstable();
+
+        let uptr = &mut live as *mut Vec<GlyphKey> as *mut c_void;
+        let glyphs = unsafe { sys::fonsEvictGlyphs(self.raw(), Some(keep), uptr) };
+        if glyphs > 0 {
+            log_debug!("fontstash: evicted {} glyphs", glyphs);
+            self.clear_quad_cache();
+            self.fire_atlas_event(AtlasEvent::Evicted {
+                glyphs: glyphs as u32,
+            });
+        }
+    }
+
+    /// Iterates through `text` with `fonsTextIterNext` only to rasterize it with the current states
+    fn rasterize_raw(&self, text: &str) {
+        unsafe {
+            let start = text.as_ptr() as *const _;
+            let end = text.as_ptr().add(text.len()) as *const _;
+
+            let mut iter: sys::FONStextIter = core::mem::zeroed();
+            let mut quad: sys::FONSquad = core::mem::zeroed();
+            if sys::fonsTextIterInit(self.raw(), &mut iter, 0.0, 0.0, start, end) == 0 {
+                return;
+            }
+            while sys::fonsTextIterNext(self.raw(), &mut iter, &mut quad) != 0 {}
+        }
+    }
+
+    /// Forgets the glyphs used so far, e.g. when their fonts are freed
+    pub(crate) fn clear_glyph_usage(&self) {
+        self.fons.usage.borrow_mut().last_use.clear();
+    }
+
+    /// Records the last-use frame of the glyph `iter` is pointing at
+    pub(crate) fn mark_glyph_used(&self, iter: &sys::FONStextIter) {
+        let mut usage = self.fons.usage.borrow_mut();
+        if usage.eviction.is_none() {
+            return;
+        }
+
+        let key = GlyphKey {
+            font: iter.font as usize,
+            codepoint: iter.codepoint,
+            isize: iter.isize,
+            iblur: iter.iblur,
+        };
+        let frame = usage.frame;
+        usage.last_use.insert(key, frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_font, AtlasPacker};
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    fn stash(packer: AtlasPacker) -> FontStash {
+        let stash = FontStash::builder(512, 512).packer(packer).build().unwrap();
+        let font = stash.add_font_mem_copy("mono", &test_font::data()).unwrap();
+        stash.set_font(font);
+        stash.set_size(20.0);
+        stash.set_glyph_eviction(Some(GlyphEviction {
+            max_age: 1,
+            occupancy: 1.0,
+        }));
+        stash
+    }
+
+    fn uvs(stash: &FontStash, text: &str) -> Vec<[f32; 4]> {
+        stash
+            .text_iter(text)
+            .unwrap()
+            .map(|q| q.uv_rect())
+            .collect()
+    }
+
+    /// Draws "ab" and "xyz", then only "ab" for two frames, so that "xyz" is stale
+    fn age_glyphs(stash: &FontStash) -> (Vec<[f32; 4]>, Vec<[f32; 4]>) {
+        let live = self::uvs(stash, "ab");
+        let stale = self::uvs(stash, "xyz");
+        for _ in 0..2 {
+            stash.end_frame().unwrap();
+            self::uvs(stash, "ab");
+        }
+        assert_eq!(stash.atlas_stats().glyph_count, 5);
+        (live, stale)
+    }
+
+    #[test]
+    fn guillotine_frees_stale_glyphs() {
+        let stash = self::stash(AtlasPacker::Guillotine);
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&events);
+        stash.on_atlas_event(move |e| sink.borrow_mut().push(e));
+
+        let (live, stale) = self::age_glyphs(&stash);
+        stash.evict_stale_glyphs().unwrap();
+
+        assert_eq!(stash.atlas_stats().glyph_count, 2);
+        assert_eq!(*events.borrow(), [AtlasEvent::Evicted { glyphs: 3 }]);
+        // the live glyphs are not moved or rasterized again
+        assert_eq!(self::uvs(&stash, "ab"), live);
+        assert_eq!(stash.atlas_stats().glyph_count, 2);
+
+        // the regions of the stale glyphs are cleared
+        stash
+            .with_pixels(|pixels, w, h| {
+                for [s0, t0, s1, t1] in &stale {
+                    let (x0, x1) = ((s0 * w as f32) as usize, (s1 * w as f32) as usize);
+                    let (y0, y1) = ((t0 * h as f32) as usize, (t1 * h as f32) as usize);
+                    for y in y0..y1 {
+                        let row = &pixels[y * w as usize..][x0..x1];
+                        assert!(row.iter().all(|&p| p == 0));
+                    }
+                }
+            })
+            .unwrap();
+
+        self::uvs(&stash, "xyz");
+        assert_eq!(stash.atlas_stats().glyph_count, 5);
+    }
+
+    #[test]
+    fn skyline_resets_keeping_live_glyphs() {
+        let stash = self::stash(AtlasPacker::Skyline);
+        let (live, _) = self::age_glyphs(&stash);
+        stash.evict_stale_glyphs().unwrap();
+
+        // the live glyphs are rasterized again right away
+        assert_eq!(stash.atlas_stats().glyph_count, 2);
+        assert_eq!(self::uvs(&stash, "ab").len(), live.len());
+        assert_eq!(stash.atlas_stats().glyph_count, 2);
+    }
+
+    #[test]
+    fn eviction_disabled() {
+        let stash = self::stash(AtlasPacker::Guillotine);
+        self::age_glyphs(&stash);
+        stash.set_glyph_eviction(None);
+        stash.evict_stale_glyphs().unwrap();
+        assert_eq!(stash.atlas_stats().glyph_count, 5);
+    }
+}
@@ -0,0 +1,305 @@
+/*!
+Fallback fonts registered per Unicode script or range
+
+A [`FallbackChain`] picks a font for each character and splits text into runs of the same font.
+Characters that no range covers are drawn with the base font. [`FallbackChain::configure`] also
+registers the fonts as FontStash fallbacks of each other, so characters missing in the picked font
+are still drawn if another font in the chain has them.
+
+Unified Han ideographs are shared by Japanese, Chinese and Korean, but their glyphs differ per
+locale. Register a font per locale with [`FallbackChain::han`] and pick one with
+[`FallbackChain::set_locale`].
+*/
+
+use core::ops::{Range, RangeInclusive};
+
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{Align, FonsTextIter, FontIx, FontStash, Quad, Result};
+
+/// Predefined set of Unicode ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    /// Basic Latin to Latin Extended-B and Latin Extended Additional
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    /// Han, kana, CJK symbols and full-width forms
+    Cjk,
+    Hangul,
+    /// Pictographs, emoticons and miscellaneous symbols
+    Emoji,
+}
+
+impl Script {
+    /// Unicode ranges of the script
+    pub fn ranges(self) -> &'static [RangeInclusive<char>] {
+        match self {
+            Self::Latin => &['\u{0000}'..='\u{024F}', '\u{1E00}'..='\u{1EFF}'],
+            Self::Greek => &['\u{0370}'..='\u{03FF}', '\u{1F00}'..='\u{1FFF}'],
+            Self::Cyrillic => &['\u{0400}'..='\u{052F}'],
+            Self::Hebrew => &['\u{0590}'..='\u{05FF}'],
+            Self::Arabic => &['\u{0600}'..='\u{06FF}', '\u{0750}'..='\u{077F}'],
+            Self::Cjk => &[
+                '\u{2E80}'..='\u{30FF}',
+                '\u{3400}'..='\u{4DBF}',
+                '\u{4E00}'..='\u{9FFF}',
+                '\u{F900}'..='\u{FAFF}',
+                '\u{FF00}'..='\u{FFEF}',
+                '\u{20000}'..='\u{2FA1F}',
+            ],
+            Self::Hangul => &[
+                '\u{1100}'..='\u{11FF}',
+                '\u{3130}'..='\u{318F}',
+                '\u{AC00}'..='\u{D7AF}',
+            ],
+            Self::Emoji => &['\u{2600}'..='\u{27BF}', '\u{1F300}'..='\u{1FAFF}'],
+        }
+    }
+}
+
+/// CJK Unified Ideographs and their extensions and compatibility ideographs
+fn is_han(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+/// How well the locale `tag` matches `locale`: 2 for the same tag, 1 for the same language and 0
+/// for another language. `_` is the same as `-` and the case is ignored
+fn locale_score(tag: &str, locale: &str) -> u8 {
+    let subtags = |s: &str| {
+        s.split(['-', '_'])
+            .map(|sub| sub.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+    };
+    let [tag, locale] = [subtags(tag), subtags(locale)];
+
+    if tag == locale {
+        2
+    } else if tag.first() == locale.first() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Fonts registered per Unicode range. Earlier registrations take priority
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackChain {
+    base: FontIx,
+    ranges: Vec<(RangeInclusive<char>, FontIx)>,
+    /// `(locale tag, font)` for Han ideographs
+    han: Vec<(String, FontIx)>,
+    /// Font picked from `han` with the current locale
+    han_font: Option<FontIx>,
+    locale: String,
+}
+
+impl FallbackChain {
+    pub fn new(base: FontIx) -> Self {
+        Self {
+            base,
+            ranges: Vec::new(),
+            han: Vec::new(),
+            han_font: None,
+            locale: String::new(),
+        }
+    }
+
+    /// Registers a font for Han ideographs in the locale, e.g. `"ja"`, `"zh-Hans"`, `"zh-TW"` or
+    /// `"ko"`
+    ///
+    /// The font of the current locale takes priority over the ranges. The locale tags are
+    /// compared by the whole tag and then by the language.
+    pub fn han(mut self, locale: &str, font: FontIx) -> Self {
+        self.han.push((locale.into(), font));
+        self.update_han_font();
+        self
+    }
+
+    /// [`FallbackChain::set_locale`] in the builder style
+    pub fn locale(mut self, locale: &str) -> Self {
+        self.set_locale(locale);
+        self
+    }
+
+    /// Sets the locale that picks the font for Han ideographs (see [`FallbackChain::han`]).
+    /// Without a matching font, Han ideographs are picked with the ranges
+    pub fn set_locale(&mut self, locale: &str) {
+        self.locale.clear();
+        self.locale.push_str(locale);
+        self.update_han_font();
+    }
+
+    pub fn current_locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn update_han_font(&mut self) {
+        let mut best = (0, None);
+        for (tag, font) in &self.han {
+            let score = self::locale_score(tag, &self.locale);
+            if score > best.0 {
+                best = (score, Some(*font));
+            }
+        }
+        self.han_font = best.1;
+    }
+
+    pub fn range(mut self, range: RangeInclusive<char>, font: FontIx) -> Self {
+        self.ranges.push((range, font));
+        self
+    }
+
+    pub fn script(mut self, script: Script, font: FontIx) -> Self {
+        for range in script.ranges() {
+            self.ranges.push((range.clone(), font));
+        }
+        self
+    }
+
+    pub fn base(&self) -> FontIx {
+        self.base
+    }
+
+    /// Font registered for the character, or the base font
+    pub fn font_for(&self, c: char) -> FontIx {
+        if let Some(font) = self.han_font {
+            if self::is_han(c) {
+                return font;
+            }
+        }
+
+        self.ranges
+            .iter()
+            .find(|(range, _)| range.contains(&c))
+            .map_or(self.base, |(_, font)| *font)
+    }
+
+    /// Fonts in the chain without duplicates, starting with the base font and the Han font of
+    /// the current locale
+    pub fn fonts(&self) -> Vec<FontIx> {
+        let mut fonts = vec![self.base];
+        fonts.extend(self.han_font.filter(|&font| font != self.base));
+        let others = self.han.iter().map(|(_, font)| font);
+        for font in self.ranges.iter().map(|(_, font)| font).chain(others) {
+            if !fonts.contains(font) {
+                fonts.push(*font);
+            }
+        }
+        fonts
+    }
+
+    /// Registers every font in the chain as FontStash fallbacks of the others in the chain order
+    ///
+    /// Call it once; FontStash doesn't remove duplicate fallbacks.
+    pub fn configure(&self, stash: &FontStash) -> Result<()> {
+        let fonts = self.fonts();
+        for &font in &fonts {
+            for &fallback in &fonts {
+                if fallback != font {
+                    stash.add_fallback_font(font, fallback)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits text into runs of the same font
+    pub fn runs<'a>(&'a self, text: &'a str) -> Runs<'a> {
+        Runs {
+            chain: self,
+            text,
+            pos: 0,
+        }
+    }
+}
+
+/// Iterator of `(byte range, font)` created with [`FallbackChain::runs`]
+#[derive(Debug, Clone)]
+pub struct Runs<'a> {
+    chain: &'a FallbackChain,
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = (Range<usize>, FontIx);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.text[self.pos..];
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let font = self.chain.font_for(first);
+
+        let len = chars
+            .find(|(_, c)| self.chain.font_for(*c) != font)
+            .map_or(rest.len(), |(i, _)| i);
+
+        let start = self.pos;
+        self.pos += len;
+        Some((start..self.pos, font))
+    }
+}
+
+/// Fallback chain
+impl FontStash {
+    /// Lays out one line of text switching fonts with the chain
+    ///
+    /// Clears `out` and fills it with the quadliterals. Returns the advance of the text. The text
+    /// is aligned with the current alignment as a whole, and the state is restored after the layout.
+    pub fn chain_quads_into(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        chain: &FallbackChain,
+        out: &mut Vec<Quad>,
+    ) -> Result<f32> {
+        let pos = pos.into();
+        out.clear();
+
+        let align = self.align();
+        let advance = self.with_left_align(|| self.chain_quads_impl(pos, text, chain, out))?;
+        let dx = if align.contains(Align::RIGHT) {
+            -advance
+        } else if align.contains(Align::CENTER) {
+            -advance / 2.0
+        } else {
+            0.0
+        };
+
+        if dx != 0.0 {
+            for q in out.iter_mut() {
+                *q = q.translate(dx, 0.0);
+            }
+        }
+
+        Ok(advance)
+    }
+
+    fn chain_quads_impl(
+        &self,
+        pos: [f32; 2],
+        text: &str,
+        chain: &FallbackChain,
+        out: &mut Vec<Quad>,
+    ) -> Result<f32> {
+        let mut x = pos[0];
+        for (range, font) in chain.runs(text) {
+            self.set_font(font);
+            let mut iter = FonsTextIter::from_text_at(self, [x, pos[1]], &text[range])?;
+            while let Some(glyph) = iter.next_glyph() {
+                out.push(glyph.quad);
+            }
+            x = iter.next_x();
+        }
+        Ok(x - pos[0])
+    }
+}
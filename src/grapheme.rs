@@ -0,0 +1,71 @@
+/*!
+Grapheme-cluster-aware iteration (`unicode-segmentation` feature)
+
+Emoji with ZWJ sequences or characters with combining marks are made of multiple codepoints, and
+FontStash yields a glyph for each codepoint. [`ClusterIter`] groups them per grapheme cluster so
+that caret movement and hit testing can treat a cluster as one character.
+*/
+
+use std::{iter::Peekable, ops::Range};
+
+use unicode_segmentation::{GraphemeIndices, UnicodeSegmentation};
+
+use crate::{FontStash, GlyphIter, GlyphQuad, Result};
+
+/// Glyphs of a grapheme cluster
+///
+/// * `range`: byte range of the cluster in the source text
+/// * `advance`: sum of the advances of the glyphs
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub range: Range<usize>,
+    pub glyphs: Vec<GlyphQuad>,
+    pub advance: f32,
+}
+
+/// Iterator of [`Cluster`]s
+pub struct ClusterIter<'a> {
+    graphemes: GraphemeIndices<'a>,
+    glyphs: Peekable<GlyphIter<'a>>,
+}
+
+impl<'a> Iterator for ClusterIter<'a> {
+    type Item = Cluster;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, grapheme) = self.graphemes.next()?;
+        let range = start..start + grapheme.len();
+
+        let mut cluster = Cluster {
+            range: range.clone(),
+            glyphs: Vec::new(),
+            advance: 0.0,
+        };
+
+        while let Some(glyph) = self.glyphs.peek() {
+            if glyph.byte_offset >= range.end {
+                break;
+            }
+            cluster.advance += glyph.x_advance;
+            cluster.glyphs.extend(self.glyphs.next());
+        }
+
+        Some(cluster)
+    }
+}
+
+/// Grapheme clusters
+impl FontStash {
+    /// Iterator of glyphs grouped per extended grapheme cluster
+    pub fn cluster_iter_at<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+    ) -> Result<ClusterIter<'a>> {
+        let pos = pos.into();
+        Ok(ClusterIter {
+            graphemes: text.grapheme_indices(true),
+            glyphs: self.glyph_iter_at(pos, text)?.peekable(),
+        })
+    }
+}
@@ -0,0 +1,81 @@
+/*!
+Text shaping with [HarfBuzz](https://harfbuzz.github.io/) via `harfbuzz_rs`, enabled with the
+`harfbuzz` feature
+
+See [`crate::shape`] for how shaped glyphs are drawn. The [`harfbuzz_rs::Font`] has to be made
+from the same font data (and face index) as the current font of the stash.
+
+OpenType features (e.g. disabling `liga`) are passed as [`harfbuzz_rs::Feature`]s.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{
+    shape::{PlacedGlyph, ShapedIter},
+    FonsError, FontStash, Result,
+};
+
+/// Shaping with HarfBuzz
+impl FontStash {
+    /// Shapes one line of text with HarfBuzz and iterates the quadliterals of the shaped glyphs
+    ///
+    /// The script, language and direction are guessed from the text. The text is aligned with the
+    /// current alignment as a whole. [`crate::shape::ShapedQuad::cluster`] maps each glyph back
+    /// to the byte offset in `text`. [`FontStash::set_font_features`] are applied before
+    /// `features`.
+    pub fn shaped_iter<'a>(
+        &'a self,
+        font: &harfbuzz_rs::Font,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        features: &[harfbuzz_rs::Feature],
+    ) -> Result<ShapedIter<'a>> {
+        let pos = pos.into();
+
+        let scale = self.pixel_scale();
+        if scale == 0.0 {
+            return Err(FonsError::FoundNoFont());
+        }
+
+        // positions are in font units unless the scale of the font is changed
+        let (x_scale, y_scale) = font.scale();
+        let (x_units, y_units) = {
+            let upem = font.face().upem() as f32;
+            (upem / x_scale as f32, upem / y_scale as f32)
+        };
+
+        // the features of the call override the ones of the state
+        let mut all = self
+            .font_features()
+            .iter()
+            .map(|([a, b, c, d], value)| {
+                let tag = harfbuzz_rs::Tag::new(a as char, b as char, c as char, d as char);
+                harfbuzz_rs::Feature::new(tag, value, ..)
+            })
+            .collect::<Vec<_>>();
+        all.extend_from_slice(features);
+
+        let buffer = harfbuzz_rs::UnicodeBuffer::new()
+            .add_str(text)
+            .guess_segment_properties();
+        let shaped = harfbuzz_rs::shape(font, buffer, &all);
+
+        let glyphs = shaped
+            .get_glyph_infos()
+            .iter()
+            .zip(shaped.get_glyph_positions())
+            .map(|(info, p)| -> PlacedGlyph {
+                let (sx, sy) = (x_units * scale, y_units * scale);
+                (
+                    info.codepoint,
+                    info.cluster as usize,
+                    // font units are y-up
+                    [p.x_offset as f32 * sx, -p.y_offset as f32 * sy],
+                    [p.x_advance as f32 * sx, -p.y_advance as f32 * sy],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(ShapedIter::new(self, pos, glyphs))
+    }
+}
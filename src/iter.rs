@@ -0,0 +1,1043 @@
+/*!
+Text iterators yielding quadliterals
+*/
+
+use core::ffi::c_int;
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use crate::{
+    batch::PageId, eviction, floor, quad_cache, resume_callback_panic, round, state::SourceOffsets,
+    sys, unscale_quad, Align, AtlasEvent, FonsError, FonsQuad, FontStash, MissingGlyphPolicy,
+    PixelSnap, Quad, Result, StyleOverrides, TabWidth, TextOptions,
+};
+
+/// Draw
+impl FontStash {
+    /// Iterator of quadliterals aligned with [`Align`]
+    pub fn text_iter<'a>(&'a self, text: &'a str) -> Result<FonsTextIter<'a>> {
+        FonsTextIter::from_text(self, text)
+    }
+
+    /// Iterator of quadliterals aligned with [`Align`] relative to `pos`
+    pub fn text_iter_at<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+    ) -> Result<FonsTextIter<'a>> {
+        let pos = pos.into();
+        FonsTextIter::from_text_at(self, pos, text)
+    }
+
+    /// [`FontStash::text_iter_at`] for bytes that may not be UTF-8, e.g. untrusted logs
+    ///
+    /// Invalid sequences are rendered as U+FFFD REPLACEMENT CHARACTER. Byte offsets of
+    /// [`GlyphQuad`] are into the replaced text if the bytes are not valid UTF-8.
+    pub fn text_iter_bytes<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        bytes: &'a [u8],
+    ) -> Result<FonsTextIter<'a>> {
+        let text = String::from_utf8_lossy(bytes);
+        FonsTextIter::from_cow_at(self, pos.into(), text)
+    }
+
+    /// [`FontStash::text_iter_at`] with some states overridden, e.g. a blurred glow pass of the same
+    /// string
+    ///
+    /// The iterator reads the states on creation, so the states are restored before returning it.
+    /// Returns [`FonsError::StatesOverflow`] if the state stack is full.
+    pub fn text_iter_with<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+        overrides: StyleOverrides,
+    ) -> Result<FonsTextIter<'a>> {
+        let pos = pos.into();
+        self.scoped_state(|| self.text_iter_with_impl(pos, text, &overrides))
+    }
+
+    fn text_iter_with_impl<'a>(
+        &'a self,
+        pos: [f32; 2],
+        text: &'a str,
+        overrides: &StyleOverrides,
+    ) -> Result<FonsTextIter<'a>> {
+        if let Some(font) = overrides.font {
+            self.set_font(font);
+        }
+        if let Some(size) = overrides.size {
+            self.set_size(size);
+        }
+        if let Some(blur) = overrides.blur {
+            self.set_blur(blur);
+        }
+        if let Some(spacing) = overrides.spacing {
+            self.set_spacing(spacing);
+        }
+        FonsTextIter::from_text_at(self, pos, text)
+    }
+
+    /// Clears `out` and fills it with the quadliterals of `text`. Reuse `out` to avoid allocations
+    pub fn quads_into(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        out: &mut Vec<Quad>,
+    ) -> Result<()> {
+        let pos = pos.into();
+        out.clear();
+        out.extend(self.text_iter_at(pos, text)?);
+        Ok(())
+    }
+
+    /// Iterator of quadliterals of text with multiple lines
+    ///
+    /// Lines are split with `\n` and each line is placed `line_height` below the previous one.
+    /// It defaults to the line height of the current font.
+    pub fn multiline_iter<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+        line_height: Option<f32>,
+    ) -> Result<MultilineIter<'a>> {
+        let pos = pos.into();
+        MultilineIter::new(self, pos, text, line_height)
+    }
+
+    /// Iterator of [`GlyphQuad`]s, i.e., quadliterals with the characters they're made from
+    pub fn glyph_iter<'a>(&'a self, text: &'a str) -> Result<GlyphIter<'a>> {
+        self.glyph_iter_at([0.0, 0.0], text)
+    }
+
+    /// Iterator of [`GlyphQuad`]s relative to `pos`
+    pub fn glyph_iter_at<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+    ) -> Result<GlyphIter<'a>> {
+        let pos = pos.into();
+        self.text_iter_at(pos, text).map(FonsTextIter::glyphs)
+    }
+
+    /// Iterator of `(char, Quad)`, e.g. for syntax highlighting or per-character animations
+    pub fn chars_with_quads<'a>(&'a self, text: &'a str) -> Result<CharsWithQuads<'a>> {
+        self.chars_with_quads_at([0.0, 0.0], text)
+    }
+
+    /// Iterator of `(char, Quad)` relative to `pos`
+    pub fn chars_with_quads_at<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+    ) -> Result<CharsWithQuads<'a>> {
+        let pos = pos.into();
+        self.glyph_iter_at(pos, text)
+            .map(|inner| CharsWithQuads { inner })
+    }
+
+    /// Quadliteral of a glyph of the current font with the pen at `pos`
+    ///
+    /// The glyph is specified with its glyph index in the font, e.g. from a shaping engine
+    /// (see `shape`). It's rasterized with the current size and blur and aligned vertically with
+    /// the current [`Align`]. Returns `None` if there's no font or the glyph didn't fit in the atlas.
+    pub fn glyph_quad(&self, glyph_index: u32, pos: impl Into<[f32; 2]>) -> Option<Quad> {
+        trace_span!("fontstash::glyph_quad", glyph_index);
+        let pos = pos.into();
+        let dpi = self.dpi_scale();
+        let mut quad = FonsQuad::default();
+        let res = unsafe {
+            sys::fonsGetGlyphQuad(
+                self.raw(),
+                glyph_index as c_int,
+                pos[0] * dpi,
+                pos[1] * dpi,
+                &mut quad,
+            )
+        };
+        self::resume_callback_panic();
+        if res == 0 {
+            None
+        } else {
+            self::unscale_quad(&mut quad, dpi);
+            Some(quad.into())
+        }
+    }
+
+    /// Scale from font units to pixels for the current font and size (`0.0` if there's no font)
+    pub fn pixel_scale(&self) -> f32 {
+        unsafe { sys::fonsGetPixelScale(self.raw()) / self.dpi_scale() }
+    }
+}
+
+/// Quadliteral with the character it's made from, yielded by [`GlyphIter`]
+///
+/// * `byte_offset`: offset of the character in the source text
+/// * `pen`: position of the pen (origin of the glyph)
+/// * `x_advance`: horizontal distance to the next glyph
+/// * `is_missing`: true if no font (including fallbacks) has the character
+/// * `is_colored`: true if the quad samples the RGBA page of the color glyphs instead of the atlas,
+///   which shouldn't be tinted with the text color (see `color_glyph`)
+/// * `is_deferred`: true if the quad is an empty placeholder of a glyph queued for rasterization
+///   (see [`FontStash::set_deferred_glyphs`])
+/// * `page`: texture the quad samples, which is the dedicated one if the font has it (see
+///   [`FontStash::dedicate_font`])
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphQuad {
+    pub quad: Quad,
+    pub codepoint: char,
+    pub byte_offset: usize,
+    pub pen: [f32; 2],
+    pub x_advance: f32,
+    pub is_missing: bool,
+    pub is_colored: bool,
+    pub is_deferred: bool,
+    pub page: PageId,
+}
+
+/// Iterator of text quads
+///
+/// It borrows the text because `FONStextIter` holds pointers to it. It also borrows the stash so
+/// that the state can't be pushed or popped while iterating.
+pub struct FonsTextIter<'a> {
+    pub(crate) stash: &'a FontStash,
+    /// Owned if the text is changed (see [`FontStash::set_control_policy`]). `iter` points to its
+    /// heap buffer, which doesn't move with the iterator
+    pub(crate) text: Cow<'a, str>,
+    /// Offsets of the characters of `text` in the given text
+    offsets: SourceOffsets,
+    pub(crate) iter: sys::FONStextIter,
+    pub(crate) is_running: bool,
+    pub(crate) opts: TextOptions,
+    /// Pen position at the start, where tab stops are measured from
+    origin_x: f32,
+    tab_px: f32,
+    /// `Some(atlas size)` if the quad cache is enabled
+    cache_atlas: Option<[f32; 2]>,
+    /// Dedicated atlas of the font (see [`FontStash::dedicate_font`])
+    page: Option<FontStash>,
+    /// Lines yielded after the glyphs (see [`FontStash::decoration_lines`])
+    decorations: [Option<[f32; 2]>; 2],
+    /// If the last glyph didn't fit in the atlas
+    pub(crate) is_atlas_full: bool,
+    /// If [`AtlasEvent::Full`] was fired by this iterator
+    fired_full: bool,
+    /// Second quad of the last glyph with faux bold
+    bold_strike: Option<Quad>,
+    /// `(quad, glyph index)` of the last glyph that's not a combining mark
+    mark_base: Option<(FonsQuad, i32)>,
+}
+
+impl<'a> FonsTextIter<'a> {
+    pub fn from_text(stash: &'a FontStash, text: &'a str) -> Result<Self> {
+        Self::from_text_at(stash, [0.0, 0.0], text)
+    }
+
+    pub fn from_text_at(
+        stash: &'a FontStash,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+    ) -> Result<Self> {
+        Self::from_cow_at(stash, pos.into(), Cow::Borrowed(text))
+    }
+
+    fn from_cow_at(stash: &'a FontStash, pos: [f32; 2], text: Cow<'a, str>) -> Result<Self> {
+        let opts = stash.options();
+        let align = stash.align();
+        if !opts.adjusts_advance() || !align.intersects(Align::CENTER | Align::RIGHT) {
+            return Self::init_cow(stash, pos, text, opts);
+        }
+
+        // FontStash aligns text without our adjustments, so align it by ourselves
+        let advance = stash.with_left_align(|| -> Result<f32> {
+            let mut iter = Self::init_cow(stash, [0.0, 0.0], text.clone(), opts)?;
+            while iter.next_glyph().is_some() {}
+            Ok(iter.next_x())
+        })?;
+
+        let x = if align.contains(Align::RIGHT) {
+            pos[0] - advance
+        } else {
+            pos[0] - advance / 2.0
+        };
+
+        stash.with_left_align(|| Self::init_cow(stash, [x, pos[1]], text, opts))
+    }
+
+    pub(crate) fn init(
+        stash: &'a FontStash,
+        pos: [f32; 2],
+        text: &'a str,
+        opts: TextOptions,
+    ) -> Result<Self> {
+        Self::init_cow(stash, pos, Cow::Borrowed(text), opts)
+    }
+
+    fn init_cow(
+        stash: &'a FontStash,
+        pos: [f32; 2],
+        text: Cow<'a, str>,
+        opts: TextOptions,
+    ) -> Result<Self> {
+        trace_span!("fontstash::text_iter", len = text.len());
+        let dpi = opts.dpi_scale;
+        let tab_px = match opts.tab_width {
+            Some(TabWidth::Pixels(px)) => px * dpi,
+            Some(TabWidth::Columns(n)) => {
+                let space = stash.glyph_bounds(' ').map_or(0.0, |b| b.advance);
+                n as f32 * (space + stash.spacing()) * dpi
+            }
+            None => 0.0,
+        };
+
+        let (text, offsets) = stash.prepared_text(text);
+
+        unsafe {
+            // `FONStextIter` iterates through [start, end)
+            let start = text.as_ptr() as *const _;
+            let end = text.as_ptr().add(text.len()) as *const _;
+
+            let mut iter: sys::FONStextIter = core::mem::zeroed();
+            let [x, y] = [pos[0] * dpi, pos[1] * dpi];
+            let res = sys::fonsTextIterInit(stash.raw(), &mut iter as *mut _, x, y, start, end);
+
+            if res == 0 {
+                // failed
+                return Err(FonsError::FoundNoFont());
+            }
+
+            let page = stash.font_page(stash.current_font());
+            Ok(Self {
+                stash,
+                text,
+                offsets,
+                iter,
+                is_running: res == 1,
+                opts,
+                origin_x: iter.x,
+                tab_px,
+                // the cache is keyed by the atlas of the stash
+                cache_atlas: if page.is_none() && stash.fons.quad_cache.borrow().enabled {
+                    let [w, h] = stash.atlas_size();
+                    Some([w as f32, h as f32])
+                } else {
+                    None
+                },
+                page,
+                decorations: stash.decoration_lines(opts.decoration),
+                is_atlas_full: false,
+                fired_full: false,
+                bold_strike: None,
+                mark_base: None,
+            })
+        }
+    }
+
+    /// Solid quad of the next decoration line from the start to the end of the text
+    fn next_decoration(&mut self) -> Option<Quad> {
+        let [top, thickness] = self.decorations.iter_mut().find_map(Option::take)?;
+        if self.iter.nextx == self.origin_x {
+            self.decorations = [None, None];
+            return None;
+        }
+
+        let [w, h] = self.page.as_ref().unwrap_or(self.stash).atlas_size();
+        // center of the 2x2 white rectangle
+        let [u, v] = [1.0 / w as f32, 1.0 / h as f32];
+        let y = self.iter.nexty + top;
+        let mut quad = FonsQuad {
+            x0: self.origin_x,
+            y0: y,
+            x1: self.iter.nextx,
+            y1: y + thickness,
+            s0: u,
+            t0: v,
+            s1: u,
+            t1: v,
+        };
+        self::unscale_quad(&mut quad, self.opts.dpi_scale);
+
+        let quad = Quad::from(quad);
+        Some(match self.opts.clip_rect {
+            Some(rect) => quad.clipped(rect),
+            None => quad,
+        })
+    }
+
+    /// Continues the iteration over `text`, keeping the pen, kerning and the Rust-side state
+    pub(crate) fn continue_with(&mut self, text: Cow<'a, str>) {
+        (self.text, self.offsets) = self.stash.prepared_text(text);
+        let start = self.text.as_ptr() as *const _;
+        self.iter.str = start;
+        self.iter.next = start;
+        self.iter.end = unsafe { start.add(self.text.len()) };
+        self.is_running = true;
+    }
+
+    /// Texture the quads sample, which is the dedicated one if the font has it (see
+    /// [`FontStash::dedicate_font`])
+    pub fn page_id(&self) -> PageId {
+        self.page.as_ref().unwrap_or(self.stash).page_id()
+    }
+
+    /// Pen position after the last glyph in logical pixels
+    pub(crate) fn next_x(&self) -> f32 {
+        self.iter.nextx / self.opts.dpi_scale
+    }
+
+    /// Baseline of the pen in logical pixels
+    pub(crate) fn next_y(&self) -> f32 {
+        self.iter.nexty / self.opts.dpi_scale
+    }
+
+    /// Number of characters left. Each character yields one quad (an empty one if it's missing)
+    fn remaining(&self) -> usize {
+        if !self.is_running {
+            return 0;
+        }
+        let pos = self.iter.next as usize - self.text.as_ptr() as usize;
+        self.text[pos..].chars().count()
+    }
+
+    /// Converts the iterator into one that yields [`GlyphQuad`]s
+    pub fn glyphs(self) -> GlyphIter<'a> {
+        GlyphIter { inner: self }
+    }
+
+    pub(crate) fn next_glyph(&mut self) -> Option<GlyphQuad> {
+        if !self.is_running {
+            return None;
+        }
+
+        let next = match self.cache_atlas {
+            Some(_) => self.next_cached().map(|(quad, glyph)| (quad, Some(glyph))),
+            None => self.next_raw().map(|quad| (quad, None)),
+        };
+        let (mut quad, cached) = match next {
+            Some(next) => next,
+            None => {
+                // don't call `fonsTextIterNext` after the end
+                self.is_running = false;
+                return None;
+            }
+        };
+
+        self.stash.mark_glyph_used(&self.iter);
+
+        // the options below can also reset it
+        self.is_atlas_full = self.iter.prevGlyphIndex == -1;
+        if self.is_atlas_full {
+            // no glyph was found (the atlas is full)
+            quad = self.empty_quad();
+            if !self.fired_full {
+                self.fired_full = true;
+                self.stash.fire_atlas_event(AtlasEvent::Full);
+            }
+        }
+        let is_replaced = self.apply_options(&mut quad);
+        let is_missing = !is_replaced
+            && cached.map_or_else(
+                || unsafe {
+                    sys::fonsTextIterIsMissing(self.stash.raw(), &mut self.iter as *mut _) == 1
+                },
+                |[is_missing, _, _]| is_missing,
+            );
+        let is_colored = !is_replaced
+            && !self.is_atlas_full
+            && cached.map_or_else(
+                || unsafe {
+                    sys::fonsTextIterIsColored(self.stash.raw(), &mut self.iter as *mut _) == 1
+                },
+                |[_, is_colored, _]| is_colored,
+            );
+        let is_deferred = !is_replaced
+            && cached.map_or_else(
+                || unsafe {
+                    sys::fonsTextIterIsDeferred(self.stash.raw(), &mut self.iter as *mut _) == 1
+                },
+                |[_, _, is_deferred]| is_deferred,
+            );
+        if is_missing {
+            self.apply_missing_policy(&mut quad);
+        }
+        let is_mark = if is_replaced {
+            self.mark_base = None;
+            false
+        } else {
+            self.opts.mark_positioning && self.apply_mark(&mut quad, is_missing)
+        };
+        if !is_replaced && !is_mark {
+            self.apply_fixed_advance(&mut quad);
+        }
+        if self.opts.subpixel && !is_replaced {
+            // glyph offsets are integers, so FontStash floors only the pen position
+            let dx = self.iter.x - floor(self.iter.x);
+            let dy = self.iter.y - floor(self.iter.y);
+            quad.x0 += dx;
+            quad.x1 += dx;
+            quad.y0 += dy;
+            quad.y1 += dy;
+        }
+        self.snap(&mut quad);
+        let dpi = self.opts.dpi_scale;
+        self::unscale_quad(&mut quad, dpi);
+        let mut quad = Quad::from(quad);
+        if let Some(rect) = self.opts.clip_rect {
+            quad = quad.clipped(rect);
+        }
+        if self.opts.faux_italic != 0.0 && !is_replaced {
+            quad = quad.sheared(self.opts.faux_italic, self.iter.y / dpi);
+        }
+
+        Some(GlyphQuad {
+            quad,
+            codepoint: core::char::from_u32(self.iter.codepoint)
+                .unwrap_or(core::char::REPLACEMENT_CHARACTER),
+            byte_offset: self
+                .offsets
+                .get(self.iter.str as usize - self.text.as_ptr() as usize),
+            pen: [self.iter.x / dpi, self.iter.y / dpi],
+            x_advance: (self.iter.nextx - self.iter.x) / dpi,
+            is_missing,
+            is_colored,
+            is_deferred,
+            page: self.page_id(),
+        })
+    }
+
+    fn next_raw(&mut self) -> Option<FonsQuad> {
+        // covers the rasterization of the glyph if it's not cached yet
+        trace_span!("fontstash::next_glyph");
+        let mut quad = unsafe { core::mem::zeroed() };
+
+        let res = unsafe {
+            sys::fonsTextIterNext(
+                self.stash.raw(),
+                &mut self.iter as *mut _,
+                &mut quad as *mut _,
+            )
+        };
+        // rasterizing glyphs can call `renderExpand` and `renderUpdate`
+        resume_callback_panic();
+
+        if res != 1 {
+            // end
+            return None;
+        }
+
+        self.stash.count_glyph();
+        self.stash.fire_pending_atlas_events();
+        Some(quad)
+    }
+
+    /// `fonsTextIterNext` on the Rust side. Returns the quad and if the glyph is missing, colored
+    /// and deferred
+    fn next_cached(&mut self) -> Option<(FonsQuad, [bool; 3])> {
+        if self.iter.next == self.iter.end {
+            return None;
+        }
+
+        let pos = self.iter.next as usize - self.text.as_ptr() as usize;
+        let c = self.text[pos..].chars().next()?;
+
+        let key = eviction::GlyphKey {
+            font: self.iter.font as usize,
+            codepoint: c as u32,
+            isize: self.iter.isize,
+            iblur: self.iter.iblur,
+        };
+        let cached = self
+            .stash
+            .fons
+            .quad_cache
+            .borrow()
+            .glyphs
+            .get(&key)
+            .copied();
+        let glyph = match cached {
+            Some(glyph) => glyph,
+            None => {
+                let glyph = self.probe(c.len_utf8())?;
+                // placeholders are replaced once the glyph is rasterized
+                if glyph.index != -1 && !glyph.is_deferred {
+                    let mut cache = self.stash.fons.quad_cache.borrow_mut();
+                    cache.glyphs.insert(key, glyph);
+                }
+                glyph
+            }
+        };
+
+        self.iter.x = self.iter.nextx;
+        self.iter.y = self.iter.nexty;
+        self.iter.codepoint = c as u32;
+        self.iter.str = self.iter.next;
+        self.iter.next = unsafe { self.iter.next.add(c.len_utf8()) };
+
+        // same as `fons__getQuad`
+        let mut quad = FonsQuad::default();
+        if glyph.index != -1 {
+            if self.iter.prevGlyphIndex != -1 {
+                let kern = self.kern_cached(self.iter.prevGlyphIndex, glyph.index);
+                self.iter.nextx += (kern + self.iter.spacing + 0.5) as i32 as f32;
+            }
+
+            let x0 = floor(self.iter.nextx + glyph.offset[0]);
+            let y0 = floor(self.iter.nexty + glyph.offset[1]);
+            let [w, h] = self.cache_atlas.unwrap_or([1.0, 1.0]);
+            quad = FonsQuad {
+                x0,
+                y0,
+                x1: x0 + glyph.size[0],
+                y1: y0 + glyph.size[1],
+                s0: glyph.tex[0] / w,
+                t0: glyph.tex[1] / h,
+                s1: glyph.tex[2] / w,
+                t1: glyph.tex[3] / h,
+            };
+
+            self.iter.nextx += glyph.advance;
+        }
+        self.iter.prevGlyphIndex = glyph.index;
+
+        Some((
+            quad,
+            [glyph.is_missing, glyph.is_colored, glyph.is_deferred],
+        ))
+    }
+
+    /// Gets metrics of the next glyph from C with the pen at the origin
+    fn probe(&mut self, len: usize) -> Option<quad_cache::CachedGlyph> {
+        let mut probe = self.iter;
+        probe.nextx = 0.0;
+        probe.nexty = 0.0;
+        probe.prevGlyphIndex = -1;
+        probe.utf8state = 0;
+        probe.end = unsafe { probe.next.add(len) };
+
+        let mut q = FonsQuad::default();
+        let res = unsafe { sys::fonsTextIterNext(self.stash.raw(), &mut probe, &mut q) };
+        resume_callback_panic();
+        if res != 1 {
+            return None;
+        }
+        self.stash.count_glyph();
+        self.stash.fire_pending_atlas_events();
+
+        let is_missing = unsafe { sys::fonsTextIterIsMissing(self.stash.raw(), &mut probe) == 1 };
+        let is_colored = unsafe { sys::fonsTextIterIsColored(self.stash.raw(), &mut probe) == 1 };
+        let is_deferred = unsafe { sys::fonsTextIterIsDeferred(self.stash.raw(), &mut probe) == 1 };
+
+        // the atlas can be expanded while rasterizing the glyph
+        let [w, h] = self.stash.atlas_size();
+        let [w, h] = [w as f32, h as f32];
+        self.cache_atlas = Some([w, h]);
+
+        Some(quad_cache::CachedGlyph {
+            index: probe.prevGlyphIndex,
+            offset: [q.x0, q.y0],
+            size: [q.x1 - q.x0, q.y1 - q.y0],
+            tex: [
+                round(q.s0 * w),
+                round(q.t0 * h),
+                round(q.s1 * w),
+                round(q.t1 * h),
+            ],
+            advance: probe.nextx,
+            is_missing,
+            is_colored,
+            is_deferred,
+        })
+    }
+
+    fn kern_cached(&mut self, prev: i32, index: i32) -> f32 {
+        let key = (self.iter.font as usize, self.iter.isize, prev, index);
+        if let Some(kern) = self.stash.fons.quad_cache.borrow().kerns.get(&key) {
+            return *kern;
+        }
+
+        let kern = unsafe { sys::fonsTextIterKern(self.stash.raw(), &mut self.iter, prev, index) };
+        self.stash
+            .fons
+            .quad_cache
+            .borrow_mut()
+            .kerns
+            .insert(key, kern);
+        kern
+    }
+
+    fn snap(&mut self, quad: &mut FonsQuad) {
+        if self.opts.pixel_snap == PixelSnap::Off {
+            return;
+        }
+
+        let dx = round(quad.x0) - quad.x0;
+        let dy = round(quad.y0) - quad.y0;
+        quad.x0 += dx;
+        quad.x1 += dx;
+        quad.y0 += dy;
+        quad.y1 += dy;
+
+        if self.opts.pixel_snap == PixelSnap::QuadsAndAdvances {
+            self.iter.nextx = round(self.iter.nextx);
+        }
+    }
+
+    /// Zero-sized quad at the pen position
+    fn empty_quad(&self) -> FonsQuad {
+        FonsQuad {
+            x0: self.iter.x,
+            y0: self.iter.y,
+            x1: self.iter.x,
+            y1: self.iter.y,
+            ..Default::default()
+        }
+    }
+
+    /// Places the current glyph over the base glyph if it's a combining mark. Returns true if it's
+    /// placed as a mark
+    fn apply_mark(&mut self, quad: &mut FonsQuad, is_missing: bool) -> bool {
+        let c = core::char::from_u32(self.iter.codepoint).unwrap_or_default();
+        let (base, index) = match self.mark_base {
+            Some(base) if !is_missing && self::is_combining_mark(c) => base,
+            _ => {
+                self.mark_base = Some((*quad, self.iter.prevGlyphIndex));
+                return false;
+            }
+        };
+
+        let dx = (base.x0 + base.x1) / 2.0 - (quad.x0 + quad.x1) / 2.0;
+        let gap = self.iter.isize as f32 / 10.0 / 16.0;
+        let dy = if self::is_mark_below(c) {
+            (base.y1 + gap - quad.y0).max(0.0)
+        } else {
+            (base.y0 - gap - quad.y1).min(0.0)
+        };
+        quad.x0 += dx;
+        quad.x1 += dx;
+        quad.y0 += dy;
+        quad.y1 += dy;
+
+        // stack the next mark on this one
+        self.mark_base = Some((
+            FonsQuad {
+                x0: base.x0,
+                x1: base.x1,
+                y0: base.y0.min(quad.y0),
+                y1: base.y1.max(quad.y1),
+                ..base
+            },
+            index,
+        ));
+        self.iter.nextx = self.iter.x;
+        self.iter.prevGlyphIndex = index;
+        true
+    }
+
+    /// Centers the current glyph in a cell of [`FontStash::set_fixed_advance`]
+    fn apply_fixed_advance(&mut self, quad: &mut FonsQuad) {
+        let cell = match self.opts.fixed_advance {
+            Some(advance) => advance * self.opts.dpi_scale,
+            None => return,
+        };
+
+        let dx = (cell - (self.iter.nextx - self.iter.x)) / 2.0;
+        quad.x0 += dx;
+        quad.x1 += dx;
+        self.iter.nextx = self.iter.x + cell;
+        // no kerning nor spacing for the next glyph
+        self.iter.prevGlyphIndex = -1;
+    }
+
+    /// Applies [`MissingGlyphPolicy`] to the current glyph, which is missing
+    fn apply_missing_policy(&mut self, quad: &mut FonsQuad) {
+        let c = match self.opts.missing_glyph {
+            MissingGlyphPolicy::NotDef => return,
+            MissingGlyphPolicy::Skip => None,
+            MissingGlyphPolicy::Replacement(c) => Some(c),
+        };
+
+        if let Some(c) = c {
+            // run `fonsTextIterNext` over the replacement with the pen of the missing glyph
+            let mut buf = [0; 4];
+            let s = c.encode_utf8(&mut buf);
+            let mut probe = self.iter;
+            probe.nextx = self.iter.x;
+            probe.nexty = self.iter.y;
+            probe.prevGlyphIndex = -1;
+            probe.utf8state = 0;
+            probe.next = s.as_ptr() as *const _;
+            probe.end = unsafe { probe.next.add(s.len()) };
+
+            let mut q = FonsQuad::default();
+            let res = unsafe { sys::fonsTextIterNext(self.stash.raw(), &mut probe, &mut q) };
+            resume_callback_panic();
+            if res == 1 && probe.prevGlyphIndex != -1 {
+                self.stash.mark_glyph_used(&probe);
+                *quad = q;
+                self.iter.nextx = probe.nextx;
+                self.iter.prevGlyphIndex = probe.prevGlyphIndex;
+                return;
+            }
+        }
+
+        self.iter.nextx = self.iter.x;
+        self.iter.prevGlyphIndex = -1;
+        *quad = self.empty_quad();
+    }
+
+    /// Applies [`TextOptions`] to the current glyph. Returns true if the glyph is replaced with an
+    /// empty quad
+    fn apply_options(&mut self, quad: &mut FonsQuad) -> bool {
+        if self.iter.codepoint == '\t' as u32 && self.opts.tab_width.is_some() && self.tab_px > 0.0
+        {
+            let n_stops = floor((self.iter.x - self.origin_x) / self.tab_px) + 1.0;
+            self.iter.nextx = self.origin_x + n_stops * self.tab_px;
+            // no kerning across tabs
+            self.iter.prevGlyphIndex = -1;
+            *quad = self.empty_quad();
+            return true;
+        }
+
+        let is_whitespace =
+            matches!(core::char::from_u32(self.iter.codepoint), Some(c) if c.is_whitespace());
+        if is_whitespace {
+            self.iter.nextx += self.opts.word_spacing * self.opts.dpi_scale;
+        }
+        self.iter.nextx += self.opts.faux_bold * self.opts.dpi_scale;
+
+        false
+    }
+}
+
+impl<'a> Iterator for FonsTextIter<'a> {
+    type Item = Quad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(strike) = self.bold_strike.take() {
+            return Some(strike);
+        }
+
+        match self.next_glyph() {
+            Some(glyph) => {
+                let bold = self.opts.faux_bold;
+                if bold > 0.0 && !glyph.quad.is_empty() {
+                    self.bold_strike = Some(glyph.quad.translate(bold, 0.0));
+                }
+                Some(glyph.quad)
+            }
+            None => self.next_decoration(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.remaining();
+        let lines = self.decorations.iter().flatten().count();
+        let strike = self.bold_strike.is_some() as usize;
+        let strikes = if self.opts.faux_bold > 0.0 { n } else { 0 };
+        // `probe` can end the iteration early
+        (0, Some(n + strike + strikes + lines))
+    }
+}
+
+impl<'a> core::iter::FusedIterator for FonsTextIter<'a> {}
+
+/// Combining Diacritical Marks and their supplements and extended blocks
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Combining marks placed below the base in the Combining Diacritical Marks block
+fn is_mark_below(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0316}'..='\u{0319}'
+            | '\u{031C}'..='\u{0333}'
+            | '\u{0339}'..='\u{033C}'
+            | '\u{0345}'
+            | '\u{0347}'..='\u{0349}'
+            | '\u{034D}'..='\u{034E}'
+            | '\u{0353}'..='\u{0356}'
+            | '\u{0359}'..='\u{035A}'
+    )
+}
+
+/// Iterator of quadliterals of multiple lines of text
+pub struct MultilineIter<'a> {
+    stash: &'a FontStash,
+    lines: core::str::Split<'a, char>,
+    pos: [f32; 2],
+    line_height: f32,
+    line: FonsTextIter<'a>,
+}
+
+impl<'a> MultilineIter<'a> {
+    pub fn new(
+        stash: &'a FontStash,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+        line_height: Option<f32>,
+    ) -> Result<Self> {
+        let pos = pos.into();
+        let line_height = line_height.unwrap_or_else(|| stash.vert_metrics().line_height);
+        let mut lines = text.split('\n');
+        let first = lines.next().unwrap_or("");
+        let line = FonsTextIter::from_text_at(stash, pos, crate::trim_cr(first))?;
+
+        Ok(Self {
+            stash,
+            lines,
+            pos,
+            line_height,
+            line,
+        })
+    }
+}
+
+impl<'a> Iterator for MultilineIter<'a> {
+    type Item = Quad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(quad) = self.line.next() {
+                return Some(quad);
+            }
+
+            let line = self.lines.next()?;
+            self.pos[1] += self.line_height;
+            self.line =
+                FonsTextIter::from_text_at(self.stash, self.pos, crate::trim_cr(line)).ok()?;
+        }
+    }
+}
+
+/// Iterator of [`GlyphQuad`]s
+pub struct GlyphIter<'a> {
+    inner: FonsTextIter<'a>,
+}
+
+impl<'a> Iterator for GlyphIter<'a> {
+    type Item = GlyphQuad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next_glyph()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> core::iter::FusedIterator for GlyphIter<'a> {}
+
+/// Iterator of quadliterals with the characters they're made from, created with
+/// [`FontStash::chars_with_quads`]
+pub struct CharsWithQuads<'a> {
+    inner: GlyphIter<'a>,
+}
+
+impl<'a> Iterator for CharsWithQuads<'a> {
+    type Item = (char, Quad);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let glyph = self.inner.next()?;
+        Some((glyph.codepoint, glyph.quad))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> core::iter::FusedIterator for CharsWithQuads<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font;
+
+    /// Stash with the test font at 20 pixels, so that every character is 10 pixels wide
+    fn stash() -> FontStash {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_size(20.0);
+        stash
+    }
+
+    fn pens(stash: &FontStash, text: &str) -> Vec<f32> {
+        stash
+            .glyph_iter_at([5.0, 0.0], text)
+            .unwrap()
+            .map(|g| g.pen[0])
+            .collect()
+    }
+
+    #[test]
+    fn tab_stops() {
+        let stash = self::stash();
+        // FontStash draws the glyph of `\t`
+        assert_eq!(self::pens(&stash, "a\tb"), [5.0, 15.0, 25.0]);
+
+        stash.set_tab_width(Some(TabWidth::Columns(4)));
+        assert_eq!(self::pens(&stash, "a\tb"), [5.0, 15.0, 45.0]);
+        assert_eq!(
+            self::pens(&stash, "abcd\tb"),
+            [5.0, 15.0, 25.0, 35.0, 45.0, 85.0]
+        );
+        assert_eq!(stash.text_size_oneline("a\tb")[0], 50.0);
+
+        stash.set_tab_width(Some(TabWidth::Pixels(25.0)));
+        assert_eq!(self::pens(&stash, "a\t\tb"), [5.0, 15.0, 30.0, 55.0]);
+        assert_eq!(stash.text_size_oneline("a\t\tb")[0], 60.0);
+
+        // the tabs are empty quads
+        let quads = stash.text_iter("\t").unwrap().collect::<Vec<_>>();
+        assert!(quads.iter().all(Quad::is_empty));
+    }
+
+    #[test]
+    fn size_hint() {
+        let stash = self::stash();
+        let iter = stash.text_iter("abc").unwrap();
+        // the iteration ends early if FontStash fails to get a glyph
+        assert_eq!(iter.size_hint(), (0, Some(3)));
+        assert_eq!(iter.count(), 3);
+    }
+
+    #[test]
+    fn clip_rect() {
+        let stash = self::stash();
+        let unclipped = stash.text_iter("abc").unwrap().collect::<Vec<_>>();
+        assert_eq!(unclipped[0].position_rect(), [0.0, -15.0, 10.0, 1.0]);
+
+        stash.set_clip_rect(Some([5.0, -10.0, 15.0, 0.0]));
+        let quads = stash.text_iter("abc").unwrap().collect::<Vec<_>>();
+        assert_eq!(quads.len(), 3);
+
+        // the UVs are cut at the same ratios as the positions
+        let [s0, t0, s1, t1] = unclipped[0].uv_rect();
+        let t = |ratio: f32| t0 + (t1 - t0) * ratio;
+        assert_eq!(quads[0].position_rect(), [5.0, -10.0, 10.0, 0.0]);
+        assert_eq!(
+            quads[0].uv_rect(),
+            [(s0 + s1) / 2.0, t(5.0 / 16.0), s1, t(15.0 / 16.0)]
+        );
+        assert_eq!(quads[1].position_rect(), [10.0, -10.0, 15.0, 0.0]);
+        // glyphs out of the rectangle are empty
+        assert!(quads[2].is_empty());
+
+        // measurement is not affected
+        assert_eq!(stash.text_size_oneline("abc")[0], 30.0);
+        stash.set_clip_rect(None);
+        assert_eq!(
+            stash.text_iter("abc").unwrap().collect::<Vec<_>>(),
+            unclipped
+        );
+    }
+}
@@ -0,0 +1,1483 @@
+/*!
+Layout of text on the Rust side
+
+Line breaking follows [UAX #14] with the `unicode-linebreak` feature. Otherwise text is broken
+after whitespace.
+
+[UAX #14]: https://www.unicode.org/reports/tr14/
+*/
+
+use core::ops::Range;
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    Align, FonsError, FonsTextIter, FontStash, MultilineIter, PixelSnap, Quad, Result, TextStyle,
+};
+
+/// Kind of a line break opportunity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Break {
+    /// The line has to be broken, e.g. after `\n`
+    Mandatory,
+    /// The line can be broken
+    Allowed,
+}
+
+/// Byte positions where lines can be broken. A line starting there is valid.
+///
+/// The end of the text is always a mandatory break.
+#[cfg(feature = "unicode-linebreak")]
+pub fn break_opportunities(text: &str) -> impl Iterator<Item = (usize, Break)> + '_ {
+    // `unicode_linebreak` yields nothing for an empty text
+    let empty_end = text.is_empty().then_some((0, Break::Mandatory));
+    unicode_linebreak::linebreaks(text)
+        .map(|(pos, opportunity)| {
+            let brk = match opportunity {
+                unicode_linebreak::BreakOpportunity::Mandatory => Break::Mandatory,
+                unicode_linebreak::BreakOpportunity::Allowed => Break::Allowed,
+            };
+            (pos, brk)
+        })
+        .chain(empty_end)
+}
+
+/// Byte positions where lines can be broken. A line starting there is valid.
+///
+/// The end of the text is always a mandatory break.
+#[cfg(not(feature = "unicode-linebreak"))]
+pub fn break_opportunities(text: &str) -> impl Iterator<Item = (usize, Break)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    let mut is_done = false;
+
+    core::iter::from_fn(move || loop {
+        let (i, c) = match chars.next() {
+            Some(x) => x,
+            None if is_done => return None,
+            None => {
+                is_done = true;
+                return Some((text.len(), Break::Mandatory));
+            }
+        };
+
+        let next = i + c.len_utf8();
+        if next == text.len() {
+            continue;
+        }
+
+        if c == '\n' {
+            return Some((next, Break::Mandatory));
+        }
+
+        let next_is_space = matches!(chars.peek(), Some((_, c)) if c.is_whitespace());
+        if c.is_whitespace() && !next_is_space && c != '\u{a0}' {
+            return Some((next, Break::Allowed));
+        }
+    })
+}
+
+/// Rules of line breaking used by [`FontStash::wrap_lines_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakPolicy {
+    /// [`break_opportunities`]
+    #[default]
+    Default,
+    /// [`break_opportunities`] with Japanese and Chinese line breaking rules (kinsoku shori):
+    /// lines can be broken between any two CJK characters, but they don't start with closing
+    /// brackets, small kana or punctuation such as `。` and `、`, and they don't end with opening
+    /// brackets
+    Kinsoku,
+}
+
+/// Ideographs, kana, CJK punctuation and fullwidth forms
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2E80}'..='\u{2FDF}'
+            | '\u{3001}'..='\u{30FF}'
+            | '\u{3100}'..='\u{31FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF01}'..='\u{FFEF}'
+            | '\u{20000}'..='\u{3FFFF}'
+    )
+}
+
+/// Characters that can't start a line: closing brackets, punctuation, small kana and prolonged
+/// sound marks
+fn is_kinsoku_head(c: char) -> bool {
+    const HEAD: &str =
+        "、。，．・：；？！゛゜ヽヾゝゞ々ー‐゠–〜…‥’”）〕］｝〉》」』】〙〗〟｠,.!?:;)]}\
+                        ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ";
+    HEAD.contains(c) || matches!(c, '\u{31F0}'..='\u{31FF}')
+}
+
+/// Characters that can't end a line
+fn is_kinsoku_tail(c: char) -> bool {
+    "‘“（〔［｛〈《「『【〘〖〝｟([{".contains(c)
+}
+
+/// [`break_opportunities`] with the rules of `policy`
+pub fn break_opportunities_with(
+    text: &str,
+    policy: BreakPolicy,
+) -> impl Iterator<Item = (usize, Break)> + '_ {
+    let mut breaks = self::break_opportunities(text).collect::<Vec<_>>();
+
+    if policy == BreakPolicy::Kinsoku {
+        let chars = text.char_indices().collect::<Vec<_>>();
+        breaks.extend(chars.windows(2).filter_map(|w| {
+            let [(_, prev), (pos, next)] = [w[0], w[1]];
+            (self::is_cjk(prev) && self::is_cjk(next)).then_some((pos, Break::Allowed))
+        }));
+        // mandatory breaks come first and win
+        breaks.sort_by_key(|&(pos, brk)| (pos, brk != Break::Mandatory));
+        breaks.dedup_by_key(|&mut (pos, _)| pos);
+
+        breaks.retain(|&(pos, brk)| {
+            if brk == Break::Mandatory {
+                return true;
+            }
+            let next = text[pos..].chars().next();
+            let prev = text[..pos].chars().next_back();
+            !next.is_some_and(self::is_kinsoku_head) && !prev.is_some_and(self::is_kinsoku_tail)
+        });
+    }
+
+    breaks.into_iter()
+}
+
+/// Strips trailing whitespace (including `\n`) of a line
+fn trim_line(text: &str, range: Range<usize>) -> Range<usize> {
+    let line = text[range.clone()].trim_end();
+    range.start..range.start + line.len()
+}
+
+/// Word wrapping
+impl FontStash {
+    /// Breaks text into lines that fit in `max_width` with the current font and size
+    ///
+    /// Returns byte ranges of the lines without trailing whitespace. A word wider than
+    /// `max_width` overflows.
+    pub fn wrap_lines(&self, text: &str, max_width: f32) -> Vec<Range<usize>> {
+        self.wrap_lines_with(text, max_width, BreakPolicy::Default)
+    }
+
+    /// [`FontStash::wrap_lines`] with the line breaking rules of `policy`
+    pub fn wrap_lines_with(
+        &self,
+        text: &str,
+        max_width: f32,
+        policy: BreakPolicy,
+    ) -> Vec<Range<usize>> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        // the last break opportunity that fits in the width
+        let mut last_fit: Option<usize> = None;
+
+        for (pos, brk) in self::break_opportunities_with(text, policy) {
+            loop {
+                let line = self::trim_line(text, start..pos);
+                if self.text_size_oneline(&text[line])[0] <= max_width {
+                    break;
+                }
+
+                match last_fit.take() {
+                    Some(fit) => {
+                        lines.push(self::trim_line(text, start..fit));
+                        start = fit;
+                    }
+                    None => break,
+                }
+            }
+
+            match brk {
+                Break::Mandatory => {
+                    lines.push(self::trim_line(text, start..pos));
+                    start = pos;
+                    last_fit = None;
+                }
+                Break::Allowed => {
+                    last_fit = Some(pos);
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+/// Truncation
+impl FontStash {
+    /// Returns the byte length of `text` that fits in `max_width` with `ellipsis` appended, and
+    /// the width of the truncated text (including the ellipsis)
+    ///
+    /// If the whole text fits, it's not truncated and no ellipsis is appended.
+    pub fn truncate_to_width(&self, text: &str, max_width: f32, ellipsis: &str) -> (usize, f32) {
+        let width = self.text_size_oneline(text)[0];
+        if width <= max_width {
+            return (text.len(), width);
+        }
+
+        let ellipsis_width = self.text_size_oneline(ellipsis)[0];
+        let mut fit = (0, ellipsis_width);
+
+        let glyphs = match self.glyph_iter(text) {
+            Ok(glyphs) => glyphs,
+            Err(_) => return fit,
+        };
+
+        let mut origin = None;
+        for glyph in glyphs {
+            let origin = *origin.get_or_insert(glyph.pen[0]);
+            let w = glyph.pen[0] - origin + ellipsis_width;
+            if w > max_width {
+                break;
+            }
+            fit = (glyph.byte_offset, w);
+        }
+
+        fit
+    }
+
+    /// Iterator of quadliterals of text truncated with [`FontStash::truncate_to_width`]
+    ///
+    /// The truncated text and the ellipsis are aligned as one string.
+    pub fn truncated_iter_at<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+        max_width: f32,
+        ellipsis: &'a str,
+    ) -> Result<core::iter::Chain<FonsTextIter<'a>, FonsTextIter<'a>>> {
+        let pos = pos.into();
+        let (len, width) = self.truncate_to_width(text, max_width, ellipsis);
+        let (text, ellipsis) = if len == text.len() {
+            (text, "")
+        } else {
+            (&text[..len], ellipsis)
+        };
+
+        let align = self.align();
+        let x = if align.contains(Align::RIGHT) {
+            pos[0] - width
+        } else if align.contains(Align::CENTER) {
+            pos[0] - width / 2.0
+        } else {
+            pos[0]
+        };
+
+        // `fonsTextIterInit` applies the alignment, so we can restore it right after that
+        self.with_left_align(|| {
+            let head = self.text_iter_at([x, pos[1]], text)?;
+            let head_width = self.text_size_oneline(text)[0];
+            let tail = self.text_iter_at([x + head_width, pos[1]], ellipsis)?;
+            Ok(head.chain(tail))
+        })
+    }
+}
+
+/// Horizontal alignment of lines in a rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RectHAlign {
+    Left,
+    Center,
+    Right,
+    /// Stretches spaces so that lines fill the width. The last line of a paragraph is aligned
+    /// left.
+    Justify,
+}
+
+/// Vertical alignment of lines in a rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RectVAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// If the line ends a paragraph (it's followed by a newline or the end of the text)
+fn ends_paragraph(text: &str, line: &Range<usize>) -> bool {
+    let rest = text[line.end..].trim_start_matches(&[' ', '\t'][..]);
+    rest.is_empty() || rest.starts_with('\n') || rest.starts_with('\r')
+}
+
+/// Block layout
+impl FontStash {
+    /// Wraps text into `rect` (`[left, top, right, bottom]`) and aligns the lines in it
+    ///
+    /// Clears `out` and fills it with the quadliterals. Lines overflowing the rectangle are not
+    /// clipped. The state and the word spacing are restored after the layout.
+    pub fn layout_in_rect(
+        &self,
+        rect: [f32; 4],
+        text: &str,
+        h_align: RectHAlign,
+        v_align: RectVAlign,
+        out: &mut Vec<Quad>,
+    ) -> Result<()> {
+        out.clear();
+
+        let options = self.options();
+        let res = self.scoped_state(|| {
+            self.set_align(Align::LEFT | Align::TOP);
+            self.layout_in_rect_impl(rect, text, h_align, v_align, out)
+        });
+        self.update_options(|opts| *opts = options);
+
+        res
+    }
+
+    fn layout_in_rect_impl(
+        &self,
+        rect: [f32; 4],
+        text: &str,
+        h_align: RectHAlign,
+        v_align: RectVAlign,
+        out: &mut Vec<Quad>,
+    ) -> Result<()> {
+        let width = rect[2] - rect[0];
+        let lines = self.wrap_lines(text, width);
+        let line_height = self.vert_metrics().line_height;
+
+        let height = line_height * lines.len() as f32;
+        let mut y = match v_align {
+            RectVAlign::Top => rect[1],
+            RectVAlign::Middle => (rect[1] + rect[3] - height) / 2.0,
+            RectVAlign::Bottom => rect[3] - height,
+        };
+
+        let base_word_spacing = self.options().word_spacing;
+        for range in lines {
+            let line = &text[range.clone()];
+            let line_width = self.text_size_oneline(line)[0];
+
+            let x = match h_align {
+                RectHAlign::Left | RectHAlign::Justify => rect[0],
+                RectHAlign::Center => (rect[0] + rect[2] - line_width) / 2.0,
+                RectHAlign::Right => rect[2] - line_width,
+            };
+
+            let n_spaces = line.chars().filter(|c| c.is_whitespace()).count();
+            let word_spacing = if h_align == RectHAlign::Justify
+                && n_spaces > 0
+                && !self::ends_paragraph(text, &range)
+            {
+                base_word_spacing + (width - line_width) / n_spaces as f32
+            } else {
+                base_word_spacing
+            };
+            self.set_word_spacing(word_spacing);
+
+            out.extend(self.text_iter_at([x, y], line)?);
+            y += line_height;
+        }
+
+        Ok(())
+    }
+}
+
+/// Position of text in a rectangle, used by [`FontStash::quads_in_rect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// `[x, y]` ratios of the anchor in a rectangle (`0.0`, `0.5` or `1.0`)
+    fn ratios(self) -> [f32; 2] {
+        match self {
+            Self::TopLeft => [0.0, 0.0],
+            Self::Top => [0.5, 0.0],
+            Self::TopRight => [1.0, 0.0],
+            Self::Left => [0.0, 0.5],
+            Self::Center => [0.5, 0.5],
+            Self::Right => [1.0, 0.5],
+            Self::BottomLeft => [0.0, 1.0],
+            Self::Bottom => [0.5, 1.0],
+            Self::BottomRight => [1.0, 1.0],
+        }
+    }
+}
+
+/// Anchored text
+impl FontStash {
+    /// Places text at `anchor` in `rect` (`[left, top, right, bottom]`) using its bounds
+    ///
+    /// Lines are split with `\n` and aligned left. Text larger than the rectangle is not clipped.
+    /// The state is restored after the layout.
+    pub fn quads_in_rect(&self, rect: [f32; 4], text: &str, anchor: Anchor) -> Result<Vec<Quad>> {
+        self.scoped_state(|| {
+            self.set_align(Align::LEFT | Align::TOP);
+
+            let bounds = self.text_bounds([0.0, 0.0], text);
+            let [rx, ry] = anchor.ratios();
+            let w = bounds[2] - bounds[0];
+            let h = bounds[3] - bounds[1];
+            let x = rect[0] + (rect[2] - rect[0] - w) * rx - bounds[0];
+            let y = rect[1] + (rect[3] - rect[1] - h) * ry - bounds[1];
+            self.multiline_iter([x, y], text, None)
+                .map(|iter| iter.collect())
+        })
+    }
+}
+
+/// Direction of text flow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Left to right, lines advancing downwards
+    Horizontal,
+    /// Top to bottom, columns advancing right to left (CJK style)
+    Vertical,
+}
+
+/// Iterator of quadliterals returned by [`FontStash::text_iter_directed`]
+// not boxed to avoid allocating per iterator
+#[allow(clippy::large_enum_variant)]
+pub enum DirectedIter<'a> {
+    Horizontal(MultilineIter<'a>),
+    Vertical(VerticalIter<'a>),
+}
+
+impl<'a> Iterator for DirectedIter<'a> {
+    type Item = Quad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Horizontal(iter) => iter.next(),
+            Self::Vertical(iter) => iter.next(),
+        }
+    }
+}
+
+/// Iterator of quadliterals of vertical text
+///
+/// Glyphs are centered in columns as wide as the line height and advance by the em height
+/// (`ascender - descender`). Glyphs are not rotated or replaced with vertical forms. Each glyph is
+/// placed in a pushed state, so the iterator ends early if the state stack is full.
+pub struct VerticalIter<'a> {
+    stash: &'a FontStash,
+    text: &'a str,
+    chars: core::str::CharIndices<'a>,
+    top: f32,
+    pen: [f32; 2],
+    column_width: f32,
+    advance: f32,
+}
+
+impl<'a> VerticalIter<'a> {
+    /// `pos` is the right-up corner of the first column
+    pub fn new(stash: &'a FontStash, pos: impl Into<[f32; 2]>, text: &'a str) -> Result<Self> {
+        let pos = pos.into();
+        let metrics = stash.vert_metrics();
+        // fail early if there's no font
+        FonsTextIter::from_text(stash, "")?;
+
+        Ok(Self {
+            stash,
+            text,
+            chars: text.char_indices(),
+            top: pos[1],
+            pen: [pos[0] - metrics.line_height / 2.0, pos[1]],
+            column_width: metrics.line_height,
+            advance: metrics.ascender - metrics.descender,
+        })
+    }
+}
+
+impl<'a> Iterator for VerticalIter<'a> {
+    type Item = Quad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (i, c) = self.chars.next()?;
+            match c {
+                '\n' => {
+                    self.pen = [self.pen[0] - self.column_width, self.top];
+                    continue;
+                }
+                '\r' => continue,
+                _ => {}
+            }
+
+            let c = &self.text[i..i + c.len_utf8()];
+
+            // clip after placing the glyph
+            let mut opts = self.stash.options();
+            let clip_rect = opts.clip_rect.take();
+            let glyph = self
+                .stash
+                .scoped_state(|| {
+                    self.stash.set_align(Align::LEFT | Align::TOP);
+                    FonsTextIter::init(self.stash, [0.0, 0.0], c, opts)
+                        .map(|iter| iter.glyphs().next())
+                })
+                .ok()
+                .flatten();
+
+            let glyph = glyph?;
+            let dx = self.pen[0] - glyph.x_advance / 2.0;
+            let dy = self.pen[1];
+            self.pen[1] += self.advance;
+
+            let q = glyph.quad.translate(dx, dy);
+            return Some(match clip_rect {
+                Some(rect) => q.clipped(rect),
+                None => q,
+            });
+        }
+    }
+}
+
+/// Directed layout
+impl FontStash {
+    /// Iterator of quadliterals of text flowing in `direction`
+    ///
+    /// For [`Direction::Horizontal`], it's same as [`FontStash::multiline_iter`]. For
+    /// [`Direction::Vertical`], `pos` is the right-up corner of the first column (see
+    /// [`VerticalIter`]).
+    pub fn text_iter_directed<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+        direction: Direction,
+    ) -> Result<DirectedIter<'a>> {
+        let pos = pos.into();
+        Ok(match direction {
+            Direction::Horizontal => {
+                DirectedIter::Horizontal(self.multiline_iter(pos, text, None)?)
+            }
+            Direction::Vertical => DirectedIter::Vertical(VerticalIter::new(self, pos, text)?),
+        })
+    }
+
+    /// Returns `[left_x, top_y, right_x, bottom_y]` of text flowing in `direction`
+    ///
+    /// For [`Direction::Vertical`], it's the box of the columns.
+    pub fn text_bounds_directed(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        direction: Direction,
+    ) -> [f32; 4] {
+        let pos = pos.into();
+        match direction {
+            Direction::Horizontal => self.text_bounds(pos, text),
+            Direction::Vertical => {
+                let metrics = self.vert_metrics();
+                let advance = metrics.ascender - metrics.descender;
+                let n_columns = text.split('\n').count();
+                let n_rows = text
+                    .split('\n')
+                    .map(|column| crate::trim_cr(column).chars().count())
+                    .max()
+                    .unwrap_or(0);
+
+                [
+                    pos[0] - metrics.line_height * n_columns as f32,
+                    pos[1],
+                    pos[0],
+                    pos[1] + advance * n_rows as f32,
+                ]
+            }
+        }
+    }
+}
+
+/// Line of a [`TextLayout`]
+///
+/// * `range`: byte range of the line in the source text (without trailing whitespace)
+/// * `rect`: `[left_x, top_y, right_x, bottom_y]` of the line relative to the layout origin
+/// * `baseline`: y of the baseline relative to the layout origin, with the vertical alignment
+///   applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBox {
+    pub range: Range<usize>,
+    pub rect: [f32; 4],
+    pub baseline: f32,
+}
+
+/// Text laid out once with [`FontStash::prepare`] and drawn many times
+///
+/// Quads are relative to the origin the text was laid out at. They refer to the atlas at the time
+/// of the layout, so prepare the text again after the atlas is reset or expanded (texture
+/// coordinates are normalized by the atlas size).
+///
+/// The layout keeps a copy of the text so that it can be edited with [`TextLayout::edit`].
+#[derive(Debug, Clone, Default)]
+pub struct TextLayout {
+    quads: Vec<Quad>,
+    lines: Vec<LineBox>,
+    /// Range of `quads` of each line
+    line_quads: Vec<Range<usize>>,
+    /// `(byte index, x)` of the caret stops of each line, including the end of the line
+    pub(crate) carets: Vec<Vec<(usize, f32)>>,
+    bounds: [f32; 4],
+    text: String,
+    /// `None` if the layout was not made with [`FontStash::prepare`]
+    source: Option<LayoutSource>,
+}
+
+/// Line of a [`TextLayout`] before it's added
+struct LaidOutLine {
+    quads: Vec<Quad>,
+    line: LineBox,
+    carets: Vec<(usize, f32)>,
+}
+
+/// Parameters of [`FontStash::prepare`] kept for [`TextLayout::edit`]
+#[derive(Debug, Clone, Copy)]
+struct LayoutSource {
+    style: TextStyle,
+    max_width: Option<f32>,
+}
+
+/// Shape of the caret made by [`TextLayout::caret_rect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaretStyle {
+    /// Covers the character after the caret
+    Block,
+    /// Vertical line in front of the character
+    #[default]
+    Beam,
+    /// Horizontal line under the character
+    Underline,
+}
+
+impl TextLayout {
+    pub fn quads(&self) -> &[Quad] {
+        &self.quads
+    }
+
+    /// The text laid out, including the edits made with [`TextLayout::edit`]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn lines(&self) -> &[LineBox] {
+        &self.lines
+    }
+
+    /// `[left_x, top_y, right_x, bottom_y]` of the lines relative to the origin
+    pub fn bounds(&self) -> [f32; 4] {
+        self.bounds
+    }
+
+    /// `[width, height]` of the bounds
+    pub fn size(&self) -> [f32; 2] {
+        [
+            self.bounds[2] - self.bounds[0],
+            self.bounds[3] - self.bounds[1],
+        ]
+    }
+
+    /// Iterator of the quads placed at `pos`. They're not clipped with
+    /// [`FontStash::set_clip_rect`]
+    pub fn quads_at(&self, pos: impl Into<[f32; 2]>) -> impl Iterator<Item = Quad> + '_ {
+        let pos = pos.into();
+        self.quads.iter().map(move |q| q.translate(pos[0], pos[1]))
+    }
+
+    /// Baseline of the first line relative to the origin (`0.0` if there's no line)
+    pub fn first_baseline(&self) -> f32 {
+        self.lines.first().map_or(0.0, |line| line.baseline)
+    }
+
+    /// [`TextLayout::quads_at`] with the baseline of the first line at `pos`, whatever the
+    /// vertical alignment was, e.g. for placing text next to inline images and icons
+    pub fn quads_from_baseline(&self, pos: impl Into<[f32; 2]>) -> impl Iterator<Item = Quad> + '_ {
+        let pos = pos.into();
+        self.quads_at([pos[0], pos[1] - self.first_baseline()])
+    }
+
+    /// Appends the quads intersecting `viewport` (`[left_x, top_y, right_x, bottom_y]` relative
+    /// to the origin) to `out`, e.g. for a long document in a scroll view
+    ///
+    /// Visible lines are binary-searched, so the cost doesn't grow with the lines out of the
+    /// viewport.
+    pub fn emit_visible(&self, viewport: [f32; 4], out: &mut Vec<Quad>) {
+        let first = self
+            .lines
+            .partition_point(|line| line.rect[3] <= viewport[1]);
+        let visible = self.lines[first..]
+            .iter()
+            .take_while(|line| line.rect[1] < viewport[3])
+            .count();
+
+        for range in &self.line_quads[first..first + visible] {
+            out.extend(self.quads[range.clone()].iter().filter(|q| {
+                let [x0, y0, x1, y1] = q.position_rect();
+                x1 > viewport[0] && x0 < viewport[2] && y1 > viewport[1] && y0 < viewport[3]
+            }));
+        }
+    }
+
+    /// `(line index, caret stop index)` of the caret in front of `byte_index`
+    ///
+    /// A byte index in the whitespace a line is wrapped at is placed at the end of the line.
+    fn caret_stop(&self, byte_index: usize) -> Option<(usize, usize)> {
+        let line_ix = self
+            .lines
+            .iter()
+            .position(|line| byte_index <= line.range.end)
+            .unwrap_or(self.lines.len().checked_sub(1)?);
+        let stops = &self.carets[line_ix];
+        let stop_ix = stops
+            .iter()
+            .position(|&(i, _)| i >= byte_index)
+            .unwrap_or(stops.len() - 1);
+        Some((line_ix, stop_ix))
+    }
+
+    /// One rectangle `[left_x, top_y, right_x, bottom_y]` per line covered by the selection of
+    /// `byte_range`, relative to the origin
+    ///
+    /// Rectangles span the line boxes vertically. A line that the selection continues past is
+    /// extended by half the line height for the line break, so that selected empty lines are
+    /// visible.
+    pub fn selection_rects(&self, byte_range: Range<usize>) -> Vec<[f32; 4]> {
+        let mut rects = Vec::new();
+        if byte_range.start >= byte_range.end {
+            return rects;
+        }
+
+        for (i, (line, stops)) in self.lines.iter().zip(&self.carets).enumerate() {
+            let next_start = self.lines.get(i + 1).map(|next| next.range.start);
+            let line_end = next_start.unwrap_or(line.range.end);
+            if byte_range.end <= line.range.start
+                || byte_range.start >= line_end.max(line.range.end)
+            {
+                continue;
+            }
+
+            let x_at = |byte_index: usize| {
+                stops
+                    .iter()
+                    .find(|&&(ix, _)| ix >= byte_index)
+                    .map_or(stops[stops.len() - 1].1, |&(_, x)| x)
+            };
+            let [_, top, _, bottom] = line.rect;
+            let x0 = x_at(byte_range.start.max(line.range.start));
+            let mut x1 = x_at(byte_range.end.min(line.range.end));
+            if next_start.is_some() && byte_range.end > line.range.end {
+                x1 += (bottom - top) / 2.0;
+            }
+            if x1 > x0 {
+                rects.push([x0, top, x1, bottom]);
+            }
+        }
+
+        rects
+    }
+
+    /// Byte index of the caret stop nearest to `pos` relative to the origin, e.g. for placing
+    /// the caret at a click. `None` if the layout has no line
+    ///
+    /// The line is picked by y, clamped to the first and the last lines.
+    pub fn index_at(&self, pos: impl Into<[f32; 2]>) -> Option<usize> {
+        let [x, y] = pos.into();
+        let last = self.lines.len().checked_sub(1)?;
+        let line_ix = self
+            .lines
+            .partition_point(|line| line.rect[3] <= y)
+            .min(last);
+        self.carets[line_ix]
+            .iter()
+            .min_by(|a, b| (a.1 - x).abs().total_cmp(&(b.1 - x).abs()))
+            .map(|&(i, _)| i)
+    }
+
+    /// `[left_x, top_y, right_x, bottom_y]` of the caret in front of `byte_index` relative to the
+    /// origin, or `None` if the layout has no line
+    ///
+    /// The caret spans the line box. Lines are `line_height / 16` pixels thick (at least one
+    /// pixel). A block caret at the end of a line is half the line height wide.
+    pub fn caret_rect(&self, byte_index: usize, style: CaretStyle) -> Option<[f32; 4]> {
+        let (line_ix, stop_ix) = self.caret_stop(byte_index)?;
+        let [_, top, _, bottom] = self.lines[line_ix].rect;
+        let stops = &self.carets[line_ix];
+        let x = stops[stop_ix].1;
+        let height = bottom - top;
+        let thickness = (height / 16.0).max(1.0);
+        let width = match stops.get(stop_ix + 1) {
+            Some(&(_, next_x)) => next_x - x,
+            None => height / 2.0,
+        };
+
+        Some(match style {
+            CaretStyle::Block => [x, top, x + width, bottom],
+            CaretStyle::Beam => [x, top, x + thickness, bottom],
+            CaretStyle::Underline => [x, bottom - thickness, x + width, bottom],
+        })
+    }
+
+    /// Replaces `range` of the text with `replacement` and lays out only the lines of the
+    /// paragraphs that the edit touches, e.g. on every keystroke of a text editor
+    ///
+    /// The lines after the edit are moved, keeping their quads. Returns the range of the lines
+    /// laid out again in the new layout, which is the part to redraw (with the lines after it if
+    /// the number of lines changed).
+    ///
+    /// The paragraphs are laid out with the style and the width given to [`FontStash::prepare`]
+    /// and the current options of `stash` (DPI scale, pixel snapping, ...), which should be the
+    /// ones the layout was prepared with. Returns [`FonsError::InvalidParams`] if `range` is out of
+    /// the text or not on character boundaries, or if the layout was not made with
+    /// [`FontStash::prepare`].
+    pub fn edit(
+        &mut self,
+        stash: &FontStash,
+        range: Range<usize>,
+        replacement: &str,
+    ) -> Result<Range<usize>> {
+        let source = self.source.ok_or(FonsError::InvalidParams(
+            "the layout was not made with `FontStash::prepare`",
+        ))?;
+        if range.start > range.end
+            || !self.text.is_char_boundary(range.start)
+            || !self.text.is_char_boundary(range.end)
+        {
+            return Err(FonsError::InvalidParams(
+                "invalid range of the text to edit",
+            ));
+        }
+
+        // paragraphs touched by the edit. Lines are wrapped from scratch after `\n`
+        let start = self.text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let mut end = self.text[range.end..]
+            .find('\n')
+            .map_or(self.text.len(), |i| range.end + i + 1);
+        let old_len = self.text.len();
+        self.text.replace_range(range.clone(), replacement);
+        let grown = self.text.len() as isize - old_len as isize;
+
+        // lines starting in the paragraphs (the text ends with the only line of an empty text)
+        let first = self.lines.partition_point(|line| line.range.start < start);
+        let mut last = if end == old_len {
+            self.lines.len()
+        } else {
+            self.lines.partition_point(|line| line.range.start < end)
+        };
+
+        let text = &self.text;
+        let (lines, line_height) = stash.with_layout_style(&source.style, |stash| {
+            let max_width = source.max_width.unwrap_or(f32::INFINITY);
+            let new_end = (end as isize + grown) as usize;
+            let mut ranges = stash.wrap_lines(&text[start..new_end], max_width);
+            // the text ending with `\n` has no empty line at the end
+            if start == new_end && start != 0 {
+                ranges.clear();
+            }
+            let line_height = stash.vert_metrics().line_height;
+
+            // moved quads would be snapped differently, so lay out the rest too
+            let dy = line_height * (ranges.len() as f32 - (last - first) as f32);
+            let is_snapped = stash.options().pixel_snap != PixelSnap::Off;
+            if is_snapped && dy != crate::round(dy) && end != old_len {
+                end = old_len;
+                last = self.lines.len();
+                ranges = stash.wrap_lines(&text[start..], max_width);
+            }
+
+            let lines = ranges
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| stash.layout_line(text, start + r.start..start + r.end, first + i))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((lines, line_height))
+        })?;
+
+        // move the lines after the edit
+        let dy = line_height * (lines.len() as f32 - (last - first) as f32);
+        let quads_end = self
+            .line_quads
+            .get(last)
+            .map_or(self.quads.len(), |r| r.start);
+        let quads_start = self.line_quads.get(first).map_or(quads_end, |r| r.start);
+        let n_quads = lines.iter().map(|line| line.quads.len()).sum::<usize>();
+        let quads_grown = n_quads as isize - (quads_end - quads_start) as isize;
+        let shift = |i: usize, delta: isize| (i as isize + delta) as usize;
+
+        for q in &mut self.quads[quads_end..] {
+            *q = q.translate(0.0, dy);
+        }
+        for line in &mut self.lines[last..] {
+            line.range = shift(line.range.start, grown)..shift(line.range.end, grown);
+            line.rect[1] += dy;
+            line.rect[3] += dy;
+            line.baseline += dy;
+        }
+        for r in &mut self.line_quads[last..] {
+            *r = shift(r.start, quads_grown)..shift(r.end, quads_grown);
+        }
+        for stops in &mut self.carets[last..] {
+            for stop in stops {
+                stop.0 = shift(stop.0, grown);
+            }
+        }
+
+        // replace the lines of the paragraphs
+        let n_lines = lines.len();
+        let mut quads = Vec::with_capacity(n_quads);
+        let mut line_quads = Vec::with_capacity(n_lines);
+        let mut carets = Vec::with_capacity(n_lines);
+        let mut boxes = Vec::with_capacity(n_lines);
+        for line in lines {
+            let start = quads_start + quads.len();
+            quads.extend(line.quads);
+            line_quads.push(start..quads_start + quads.len());
+            carets.push(line.carets);
+            boxes.push(line.line);
+        }
+        self.quads.splice(quads_start..quads_end, quads);
+        self.line_quads.splice(first..last, line_quads);
+        self.carets.splice(first..last, carets);
+        self.lines.splice(first..last, boxes);
+
+        self.update_bounds();
+        Ok(first..first + n_lines)
+    }
+
+    /// Unions the rectangles of the lines
+    fn update_bounds(&mut self) {
+        let mut rects = self.lines.iter().map(|line| line.rect);
+        self.bounds = match rects.next() {
+            Some(first) => rects.fold(first, |u, r| {
+                [
+                    u[0].min(r[0]),
+                    u[1].min(r[1]),
+                    u[2].max(r[2]),
+                    u[3].max(r[3]),
+                ]
+            }),
+            None => [0.0; 4],
+        };
+    }
+}
+
+/// Prepared layout
+impl FontStash {
+    /// Lays out text with `style` so that it can be drawn every frame without iterating FontStash
+    ///
+    /// Lines are broken at `\n`, and also wrapped at `max_width` if it's given. Each line is
+    /// aligned horizontally to the origin with the alignment of the style. The state is restored
+    /// after the layout.
+    pub fn prepare(
+        &self,
+        text: &str,
+        style: &TextStyle,
+        max_width: Option<f32>,
+    ) -> Result<TextLayout> {
+        let mut layout = TextLayout {
+            text: text.into(),
+            source: Some(LayoutSource {
+                style: *style,
+                max_width,
+            }),
+            ..Default::default()
+        };
+
+        self.with_layout_style(style, |stash| {
+            let ranges = stash.wrap_lines(text, max_width.unwrap_or(f32::INFINITY));
+            for (i, range) in ranges.into_iter().enumerate() {
+                let line = stash.layout_line(text, range, i)?;
+                let start = layout.quads.len();
+                layout.quads.extend(line.quads);
+                layout.line_quads.push(start..layout.quads.len());
+                layout.carets.push(line.carets);
+                layout.lines.push(line.line);
+            }
+            Ok(())
+        })?;
+
+        layout.update_bounds();
+        Ok(layout)
+    }
+
+    /// Runs `f` with the style applied and without the clip rectangle, restoring the state after
+    fn with_layout_style<T>(
+        &self,
+        style: &TextStyle,
+        f: impl FnOnce(&Self) -> Result<T>,
+    ) -> Result<T> {
+        self.scoped_state(|| {
+            self.apply_style(style);
+            // quads are clipped when they're drawn
+            let clip_rect = self.clip_rect();
+            self.set_clip_rect(None);
+
+            let res = f(self);
+
+            self.set_clip_rect(clip_rect);
+            res
+        })
+    }
+
+    /// Lays out the `i`-th line at `range` of `text`
+    fn layout_line(&self, text: &str, range: Range<usize>, i: usize) -> Result<LaidOutLine> {
+        let align = self.align();
+        let line_height = self.vert_metrics().line_height;
+
+        let line = &text[range.clone()];
+        let y = line_height * i as f32;
+        let iter = self.text_iter_at([0.0, y], line)?;
+        // the pen is on the baseline
+        let baseline = iter.next_y();
+        let quads = iter.collect::<Vec<_>>();
+        let carets = self.caret_stops(range.clone(), line, y)?;
+
+        let width = self.text_size_oneline(line)[0];
+        let left = if align.contains(Align::RIGHT) {
+            -width
+        } else if align.contains(Align::CENTER) {
+            -width / 2.0
+        } else {
+            0.0
+        };
+        let [top, bottom] = self.line_bounds(y);
+        let line = LineBox {
+            range,
+            rect: [left, top, left + width, bottom],
+            baseline,
+        };
+
+        Ok(LaidOutLine {
+            quads,
+            line,
+            carets,
+        })
+    }
+
+    /// Caret stops of a line at `range` of the source text
+    fn caret_stops(&self, range: Range<usize>, line: &str, y: f32) -> Result<Vec<(usize, f32)>> {
+        let mut stops = Vec::new();
+        // pen positions consider the horizontal alignment
+        let mut end_x = None;
+        for glyph in self.glyph_iter_at([0.0, y], line)? {
+            stops.push((range.start + glyph.byte_offset, glyph.pen[0]));
+            end_x = Some(glyph.pen[0] + glyph.x_advance);
+        }
+
+        // an empty line is aligned to the origin whatever the alignment is
+        stops.push((range.end, end_x.unwrap_or(0.0)));
+        Ok(stops)
+    }
+}
+
+/// Carets
+impl FontStash {
+    /// Solid quad of the caret in front of `byte_index` in `layout` placed at `pos`, mapped to the
+    /// white rectangle at the left-up corner of the atlas
+    ///
+    /// See [`TextLayout::caret_rect`].
+    pub fn caret_quad(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        layout: &TextLayout,
+        byte_index: usize,
+        style: CaretStyle,
+    ) -> Option<Quad> {
+        let pos = pos.into();
+        let [x0, y0, x1, y1] = layout.caret_rect(byte_index, style)?;
+        let [w, h] = self.atlas_size();
+        // center of the 2x2 white rectangle
+        let [u, v] = [1.0 / w as f32, 1.0 / h as f32];
+        Some(Quad::new(
+            [x0 + pos[0], y0 + pos[1], x1 + pos[0], y1 + pos[1]],
+            [u, v, u, v],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font;
+
+    /// Stash with the test font at 20 pixels, so that every character is 10 pixels wide
+    fn stash() -> FontStash {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_size(20.0);
+        stash
+    }
+
+    fn lines<'a>(text: &'a str, ranges: &[Range<usize>]) -> Vec<&'a str> {
+        ranges.iter().map(|r| &text[r.clone()]).collect()
+    }
+
+    fn breaks(text: &str) -> Vec<(usize, Break)> {
+        self::break_opportunities(text).collect()
+    }
+
+    #[test]
+    fn break_after_spaces() {
+        use Break::*;
+        assert_eq!(self::breaks(""), [(0, Mandatory)]);
+        assert_eq!(
+            self::breaks("ab  cd\nef"),
+            [(4, Allowed), (7, Mandatory), (9, Mandatory)]
+        );
+        // no break at a no-break space
+        assert_eq!(self::breaks("a\u{a0}b c"), [(5, Allowed), (6, Mandatory)]);
+    }
+
+    #[cfg(feature = "unicode-linebreak")]
+    #[test]
+    fn break_uax14() {
+        use Break::*;
+        // between ideographs and after hyphens
+        assert_eq!(
+            self::breaks("漢字か"),
+            [(3, Allowed), (6, Allowed), (9, Mandatory)]
+        );
+        assert_eq!(self::breaks("well-known"), [(5, Allowed), (10, Mandatory)]);
+        // not before closing punctuation
+        assert_eq!(self::breaks("字。"), [(6, Mandatory)]);
+
+        let stash = self::stash();
+        let text = "漢字漢字漢";
+        assert_eq!(
+            lines(text, &stash.wrap_lines(text, 25.0)),
+            ["漢字", "漢字", "漢"]
+        );
+    }
+
+    #[test]
+    fn truncate() {
+        let stash = self::stash();
+        let text = "hello world";
+        assert_eq!(stash.truncate_to_width(text, 200.0, "..."), (11, 110.0));
+        assert_eq!(stash.truncate_to_width(text, 110.0, "..."), (11, 110.0));
+        assert_eq!(stash.truncate_to_width(text, 55.0, "..."), (2, 50.0));
+        // not even the ellipsis fits
+        assert_eq!(stash.truncate_to_width(text, 20.0, "..."), (0, 30.0));
+    }
+
+    #[test]
+    fn truncated_quads() {
+        let stash = self::stash();
+        for align in [Align::LEFT, Align::CENTER, Align::RIGHT] {
+            stash.set_align(align | Align::BASELINE);
+            let truncated = stash
+                .truncated_iter_at([100.0, 0.0], "hello world", 55.0, "...")
+                .unwrap()
+                .collect::<Vec<_>>();
+            let expected = stash
+                .text_iter_at([100.0, 0.0], "he...")
+                .unwrap()
+                .collect::<Vec<_>>();
+            assert_eq!(truncated, expected, "{:?}", align);
+            assert_eq!(stash.align(), align | Align::BASELINE);
+        }
+    }
+
+    #[test]
+    fn wrap_words() {
+        let stash = self::stash();
+        let text = "hello world foo";
+        assert_eq!(stash.wrap_lines(text, 55.0), [0..5, 6..11, 12..15]);
+        assert_eq!(
+            lines(text, &stash.wrap_lines(text, 115.0)),
+            ["hello world", "foo"]
+        );
+        assert_eq!(lines(text, &stash.wrap_lines(text, f32::INFINITY)), [text]);
+
+        // trailing whitespace is not counted in the width
+        let text = "abcd   efgh  ";
+        assert_eq!(lines(text, &stash.wrap_lines(text, 40.0)), ["abcd", "efgh"]);
+    }
+
+    #[test]
+    fn wrap_mandatory_breaks() {
+        let stash = self::stash();
+        let text = "ab\n\ncd ef\n";
+        assert_eq!(stash.wrap_lines(text, f32::INFINITY), [0..2, 3..3, 4..9]);
+        assert_eq!(
+            lines(text, &stash.wrap_lines(text, 25.0)),
+            ["ab", "", "cd", "ef"]
+        );
+        assert_eq!(lines("", &stash.wrap_lines("", 25.0)), [""]);
+    }
+
+    #[test]
+    fn wrap_overflow() {
+        let stash = self::stash();
+        // words wider than the width have lines of their own
+        let text = "abcdefghij k lmnopqrstu";
+        assert_eq!(
+            lines(text, &stash.wrap_lines(text, 55.0)),
+            ["abcdefghij", "k", "lmnopqrstu"]
+        );
+        assert_eq!(lines("abc", &stash.wrap_lines("abc", 0.0)), ["abc"]);
+    }
+
+    #[test]
+    fn kinsoku() {
+        let stash = self::stash();
+        let wrap = |text: &'static str| {
+            let ranges = stash.wrap_lines_with(text, 55.0, BreakPolicy::Kinsoku);
+            lines(text, &ranges)
+        };
+
+        // between any two CJK characters
+        assert_eq!(wrap("あいうえおかきくけこ"), ["あいうえお", "かきくけこ"]);
+        // punctuation and small kana don't start lines
+        assert_eq!(wrap("あいうえお。かきく"), ["あいうえ", "お。かきく"]);
+        assert_eq!(wrap("あいうえおっか"), ["あいうえ", "おっか"]);
+        assert_eq!(wrap("あいうえお」か"), ["あいうえ", "お」か"]);
+        // opening brackets don't end lines
+        assert_eq!(wrap("あいうえ「かき」"), ["あいうえ", "「かき」"]);
+        // words of other scripts are kept
+        assert_eq!(wrap("ab cdefgh"), ["ab", "cdefgh"]);
+        assert_eq!(
+            stash.wrap_lines_with("hello world", 55.0, BreakPolicy::Kinsoku),
+            stash.wrap_lines("hello world", 55.0)
+        );
+    }
+
+    #[test]
+    fn kinsoku_unbreakable() {
+        let stash = self::stash();
+        // no break is allowed after the second character, so the rest overflows
+        let text = "ああ。。。。。";
+        let ranges = stash.wrap_lines_with(text, 35.0, BreakPolicy::Kinsoku);
+        assert_eq!(lines(text, &ranges), ["あ", "あ。。。。。"]);
+
+        let text = "「「「「「「";
+        let ranges = stash.wrap_lines_with(text, 35.0, BreakPolicy::Kinsoku);
+        assert_eq!(lines(text, &ranges), [text]);
+    }
+
+    #[test]
+    fn layout_in_rect() {
+        let stash = self::stash();
+        let rect = [0.0, 0.0, 56.0, 100.0];
+        let mut out = Vec::new();
+        let mut left_tops = |h_align, v_align| {
+            stash
+                .layout_in_rect(rect, "ab cd ef", h_align, v_align, &mut out)
+                .unwrap();
+            out.iter().map(|q| q.left_top()).collect::<Vec<_>>()
+        };
+
+        // "ab cd" and "ef", 20 pixels apart
+        let xs = [0.0, 10.0, 20.0, 30.0, 40.0, 0.0, 10.0];
+        let at = |dx: [f32; 2], y: f32| {
+            xs.iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    let line = (i >= 5) as usize;
+                    [x + dx[line], y + 1.0 + 20.0 * line as f32]
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            left_tops(RectHAlign::Left, RectVAlign::Top),
+            at([0.0, 0.0], 0.0)
+        );
+        assert_eq!(
+            left_tops(RectHAlign::Right, RectVAlign::Bottom),
+            at([6.0, 36.0], 60.0)
+        );
+        assert_eq!(
+            left_tops(RectHAlign::Center, RectVAlign::Middle),
+            at([3.0, 18.0], 30.0)
+        );
+
+        // the space is stretched except in the last line of the paragraph
+        let mut justified = at([0.0, 0.0], 0.0);
+        justified[3][0] += 6.0;
+        justified[4][0] += 6.0;
+        assert_eq!(left_tops(RectHAlign::Justify, RectVAlign::Top), justified);
+
+        // the state and the word spacing are restored
+        stash.set_align(Align::RIGHT | Align::BASELINE);
+        stash.set_word_spacing(3.0);
+        left_tops(RectHAlign::Justify, RectVAlign::Top);
+        assert_eq!(stash.align(), Align::RIGHT | Align::BASELINE);
+        assert_eq!(stash.options().word_spacing, 3.0);
+    }
+
+    #[test]
+    fn prepare() {
+        let (stash, font) = test_font::stash();
+        let mut style = TextStyle::new(font, 20.0);
+        style.align = Align::CENTER | Align::TOP;
+        let layout = stash.prepare("ab\ncdef", &style, None).unwrap();
+
+        let lines = layout.lines();
+        assert_eq!(lines[0].range, 0..2);
+        assert_eq!(lines[0].rect, [-10.0, 0.0, 10.0, 20.0]);
+        assert_eq!(lines[0].baseline, 16.0);
+        assert_eq!(lines[1].range, 3..7);
+        assert_eq!(lines[1].rect, [-20.0, 20.0, 20.0, 40.0]);
+        assert_eq!(layout.bounds(), [-20.0, 0.0, 20.0, 40.0]);
+        assert_eq!(layout.size(), [40.0, 40.0]);
+
+        // the quads are the ones of the iterator, translated to the position
+        stash.apply_style(&style);
+        let expected = stash
+            .text_iter_at([100.0, 50.0], "ab")
+            .unwrap()
+            .chain(stash.text_iter_at([100.0, 70.0], "cdef").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(layout.quads_at([100.0, 50.0]).collect::<Vec<_>>(), expected);
+        let from_baseline = layout.quads_from_baseline([100.0, 66.0]);
+        assert_eq!(from_baseline.collect::<Vec<_>>(), expected);
+
+        // only the second line is visible
+        let mut visible = Vec::new();
+        layout.emit_visible([-100.0, 25.0, 100.0, 100.0], &mut visible);
+        assert_eq!(visible, &layout.quads()[2..]);
+
+        // wrapped at the width, with the state restored
+        stash.set_size(30.0);
+        let layout = stash.prepare("ab cd", &style, Some(25.0)).unwrap();
+        assert_eq!(layout.lines().len(), 2);
+        assert_eq!(stash.size(), 30.0);
+    }
+
+    #[test]
+    fn caret_rects() {
+        let (stash, font) = test_font::stash();
+        let layout = stash
+            .prepare("ab\ncd", &TextStyle::new(font, 20.0), None)
+            .unwrap();
+
+        // lines are 20 pixels high, so the beam is 1.25 pixels thick
+        assert_eq!(
+            layout.caret_rect(1, CaretStyle::Beam),
+            Some([10.0, -16.0, 11.25, 4.0])
+        );
+        assert_eq!(
+            layout.caret_rect(1, CaretStyle::Block),
+            Some([10.0, -16.0, 20.0, 4.0])
+        );
+        assert_eq!(
+            layout.caret_rect(0, CaretStyle::Underline),
+            Some([0.0, 2.75, 10.0, 4.0])
+        );
+        // at the ends of lines
+        assert_eq!(
+            layout.caret_rect(2, CaretStyle::Block),
+            Some([20.0, -16.0, 30.0, 4.0])
+        );
+        assert_eq!(
+            layout.caret_rect(5, CaretStyle::Beam),
+            Some([20.0, 4.0, 21.25, 24.0])
+        );
+        assert_eq!(
+            layout.caret_rect(99, CaretStyle::Beam),
+            layout.caret_rect(5, CaretStyle::Beam)
+        );
+        assert_eq!(TextLayout::default().caret_rect(0, CaretStyle::Beam), None);
+
+        // mapped to the white rectangle of the atlas
+        let quad = stash
+            .caret_quad([100.0, 50.0], &layout, 3, CaretStyle::Beam)
+            .unwrap();
+        assert_eq!(quad.position_rect(), [100.0, 54.0, 101.25, 74.0]);
+        let texel = 1.0 / 512.0;
+        assert_eq!(quad.uv_rect(), [texel, texel, texel, texel]);
+    }
+
+    #[test]
+    fn selection_rects() {
+        let (stash, font) = test_font::stash();
+        let layout = stash
+            .prepare("ab\n\ncd", &TextStyle::new(font, 20.0), None)
+            .unwrap();
+
+        assert_eq!(layout.selection_rects(1..2), [[10.0, -16.0, 20.0, 4.0]]);
+        assert!(layout.selection_rects(1..1).is_empty());
+        // lines continued past are extended for the line breaks, even the empty one
+        assert_eq!(
+            layout.selection_rects(1..5),
+            [
+                [10.0, -16.0, 30.0, 4.0],
+                [0.0, 4.0, 10.0, 24.0],
+                [0.0, 24.0, 10.0, 44.0],
+            ]
+        );
+        // but not the last line
+        assert_eq!(layout.selection_rects(4..6), [[0.0, 24.0, 20.0, 44.0]]);
+        // the line break of the first line only
+        assert_eq!(layout.selection_rects(2..3), [[20.0, -16.0, 30.0, 4.0]]);
+    }
+
+    /// Checks that the edited layout is the same as the one prepared with the edited text
+    fn assert_relayout(stash: &FontStash, layout: &mut TextLayout, range: Range<usize>, s: &str) {
+        let mut text = String::from(layout.text());
+        text.replace_range(range.clone(), s);
+        let redrawn = layout.edit(stash, range.clone(), s).unwrap();
+
+        let source = layout.source.unwrap();
+        let expected = stash
+            .prepare(&text, &source.style, source.max_width)
+            .unwrap();
+        let edit = format!("{:?} -> {:?} of {:?}", range, s, text);
+        assert_eq!(layout.text, expected.text, "{}", edit);
+        assert_eq!(layout.lines, expected.lines, "{}", edit);
+        assert_eq!(layout.quads, expected.quads, "{}", edit);
+        assert_eq!(layout.line_quads, expected.line_quads, "{}", edit);
+        assert_eq!(layout.carets, expected.carets, "{}", edit);
+        assert_eq!(layout.bounds, expected.bounds, "{}", edit);
+        assert!(redrawn.end <= layout.lines.len(), "{}", edit);
+    }
+
+    #[test]
+    fn edit_equals_relayout() {
+        let (stash, font) = test_font::stash();
+        let style = TextStyle::new(font, 20.0);
+        let text = "hello world\nfoo bar baz\n\nqux";
+
+        // (range, replacement) applied in order
+        let edits: &[(Range<usize>, &str)] = &[
+            // in a line
+            (1..1, "i"),
+            (0..3, ""),
+            // wrapping and unwrapping the paragraph
+            (4..4, " and more words"),
+            (4..19, ""),
+            // across line breaks
+            (7..14, ""),
+            (3..3, "\n\n"),
+            (0..0, "\n"),
+            // at the end of the text
+            (22..22, "!"),
+            (23..23, "\n"),
+            (24..24, "end"),
+            (20..27, ""),
+            // everything
+            (0..20, ""),
+            (0..0, "new\ntext"),
+        ];
+
+        for max_width in [None, Some(75.0)] {
+            let mut layout = stash.prepare(text, &style, max_width).unwrap();
+            for (range, s) in edits {
+                self::assert_relayout(&stash, &mut layout, range.clone(), s);
+            }
+        }
+    }
+
+    #[test]
+    fn edit_invalid_range() {
+        let (stash, font) = test_font::stash();
+        let mut layout = stash
+            .prepare("あい", &TextStyle::new(font, 20.0), None)
+            .unwrap();
+        assert!(layout.edit(&stash, 1..1, "x").is_err());
+        assert!(layout.edit(&stash, 3..7, "x").is_err());
+        assert!(TextLayout::default().edit(&stash, 0..0, "x").is_err());
+    }
+}
@@ -6,48 +6,338 @@ Wrapper of [FontStash] (actually a [fork] of it)
 
 # Custom renderer
 
-Pull [`FonsQuad`]s via [`FonsTextIter`] and make draw calls.
-
-NOTE: `fontstash-rs` doesn't handle multiple lines of text. You would need your own layer for
-measuring or drawing multiple lines of text.
+Pull [`Quad`]s via [`FonsTextIter`] and make draw calls. [`vertex`] helps you to convert them
+into vertices.
+
+Import the common types with `use fontstash::prelude::*;`. Items are defined in modules but
+re-exported at the crate root, which is their public path.
+
+Positions are taken as `impl Into<[f32; 2]>`, so `mint::Point2<f32>`, `glam::Vec2` and friends can
+be passed as they are. Returned arrays convert back with `From` (e.g. `Vec2::from(size)`).
+
+NOTE: FontStash doesn't handle multiple lines of text. [`FontStash::multiline_iter`] and
+[`FontStash::text_bounds`] split text with newlines on the Rust side.
+
+# Features
+
+* `std` (default): without it, the crate is `no_std` + `alloc`. File loading
+  ([`FontStash::add_font_file`]), [`bmfont`], [`svg`] and [`catch_callback_panic`] need `std`
+* `image`: enables `FontStash::atlas_image` and `FontStash::dump_atlas_png` for debugging the
+  atlas
+* `unicode-linebreak`: breaks lines following UAX #14 (see [`layout`])
+* `unicode-bidi`: reorders bidirectional text (see `bidi`)
+* `unicode-segmentation`: iterates glyphs per grapheme cluster (see `grapheme`)
+* `unicode-normalization`: normalizes text to NFC before iteration (see
+  `FontStash::set_normalize_nfc`)
+* `arabic-shaping`: replaces Arabic letters with their contextual forms before iteration (see
+  `arabic`)
+* `fontdb`: loads platform fonts (see `system`)
+* `rustybuzz`: shapes text with rustybuzz (ligatures, marks and complex scripts; see `shape`)
+* `harfbuzz`: shapes text with HarfBuzz via `harfbuzz_rs` (see `harfbuzz`)
+* `egui`: uploads the atlas as an egui texture and converts quads into `egui::Mesh` (see `egui`)
+* `notan`: keeps the atlas in a notan `Texture` and emits vertices for a notan pipeline (see
+  `notan`)
+* `piet`: implements the text traits of `piet` with the stash and [`FontStash::prepare`] (see
+  `piet`)
+* `serde`: derives `Serialize` and `Deserialize` for [`FontIx`], [`Align`], [`Flags`],
+  [`TextStyle`], [`LineMetrics`] and [`StateSnapshot`]
+* `freetype`: rasterizes fonts with FreeType instead of `stb_truetype`, so that CFF-outline OpenType
+  (`.otf`) fonts load. Requires FreeType installed (see [`FontStash::backend`]). Hinting is set with
+  [`FontStash::set_freetype_hinting`]
+* `color-glyphs`: rasterizes the layers of `COLR`/`CPAL` color fonts into an RGBA page (see
+  `color_glyph`)
+* `rust-alloc`: `fontstash` allocates with the global allocator of Rust instead of `malloc`.
+  Install another [`GlobalAlloc`](core::alloc::GlobalAlloc) with `sys::set_allocator` before
+  creating the stash
+* `test-util`: enables `mock`, a [`Renderer`] recording the callbacks for tests
+* `log`: reports atlas resets and failures of the renderer callbacks via the `log` crate (see
+  `log_error_callback` for the errors of fontstash)
+* `tracing`: enters `tracing` spans (trace level) around text iteration, glyph rasterization and
+  atlas resizing
+* `simd`: converts atlas pixels into RGBA8 with SSE2 on `x86_64` (see [`convert`])
+* `dlopen`: resolves the functions of the C library from a shared library at runtime instead of
+  linking it (see `FontStash::load_library`). Creating a stash fails with
+  `FonsError::FailedToLoadLibrary` if the library is absent
+* `generate-bindings`: runs bindgen (requires libclang) instead of using the pregenerated bindings
+
+# WebAssembly
+
+`wasm32-unknown-unknown` is supported; `fontstash-sys` compiles the C sources with clang (has to
+be in your `PATH`) and implements the libc functions in Rust. There's no file system, so load fonts
+with [`FontStash::add_font_mem`] (`include_bytes!` or fetched data). [`Renderer`] callbacks are
+plain Rust functions, so they can call WebGL/WebGPU bindings.
 
 # Alternatives
 
 * [fontdue](https://docs.rs/fontdue/latest/fontdue/) is similar to FontStash; they both align
-quadliterals and rasterize characters into pixels.
+  quadliterals and rasterize characters into pixels. It's pure Rust, which this crate won't be:
+  the atlas, the glyph cache and the extensions in `fontstash-sys` are C calling `stb_truetype`
+  (or FreeType) directly, so swapping the rasterizer for `ab_glyph` or `fontdue` would still need
+  a C compiler. bindgen isn't needed either way, as the bindings are pregenerated.
 * SDF might be preferable to rasterizing characters into pixels. You would get quadliterals with
-font shaping engine such as rustybuzz and then draw characters with SDF fonts.
-
-# TODOs
-
-* support state push/pop
+  font shaping engine such as rustybuzz and then draw characters with SDF fonts. FontStash
+  itself can also store the glyphs as signed distance fields (see [`sdf`]).
 */
 
 #![allow(unused_variables)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub use fontstash_sys as sys;
 
-pub type Result<T> = std::result::Result<T, FonsError>;
+pub type Result<T> = core::result::Result<T, FonsError>;
 
-use std::fmt;
+use core::fmt;
 
-/// Quadliteral
-///
-/// * `s0`, `t0`: left-up coner in the font texture
-/// * `s1`, `t1`: right-down coner in the font texture
-/// * `x0`, `y0`: left-up coner of the target position
-/// * `x1`, `y1`: right-down coner of the target position
-#[doc(inline)]
-pub type FonsQuad = sys::FONSquad;
+/// `log::warn!` with the `log` feature, nothing without it
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::warn!($($arg)*);
+    };
+}
+
+/// `log::debug!` with the `log` feature, nothing without it
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+    };
+}
+
+/// Enters a `tracing::trace_span!` until the end of the block with the `tracing` feature
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($($arg)*).entered();
+    };
+}
+
+#[cfg(feature = "arabic-shaping")]
+pub mod arabic;
+pub mod batch;
+#[cfg(feature = "unicode-bidi")]
+pub mod bidi;
+#[cfg(feature = "std")]
+pub mod bmfont;
+pub mod chunks;
+#[cfg(feature = "color-glyphs")]
+pub mod color_glyph;
+pub mod convert;
+pub mod effect;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod fallback;
+#[cfg(feature = "unicode-segmentation")]
+pub mod grapheme;
+#[cfg(feature = "harfbuzz")]
+pub mod harfbuzz;
+pub mod layout;
+pub mod markup;
+pub mod mesh;
+#[cfg(feature = "test-util")]
+pub mod mock;
+#[cfg(feature = "notan")]
+pub mod notan;
+pub mod outline;
+#[cfg(feature = "piet")]
+pub mod piet;
+pub mod prelude;
+pub mod raster;
+pub mod rich;
+pub mod sdf;
+#[cfg(any(feature = "rustybuzz", feature = "harfbuzz"))]
+pub mod shape;
+#[cfg(feature = "std")]
+pub mod svg;
+#[cfg(feature = "fontdb")]
+pub mod system;
+pub mod transform;
+pub mod vertex;
+#[cfg(feature = "std")]
+pub mod worker;
+
+mod atlas;
+mod builder;
+mod dirty;
+mod events;
+mod eviction;
+mod iter;
+#[cfg(feature = "std")]
+mod load;
+mod measure_cache;
+mod quad_cache;
+mod render;
+mod sfnt;
+mod state;
+mod stats;
+#[cfg(test)]
+mod test_font;
+
+pub use atlas::{AlphaMode, AtlasPacker, AtlasStats, TextureView};
+pub use builder::StashBuilder;
+pub use events::AtlasEvent;
+pub use eviction::GlyphEviction;
+pub use iter::{CharsWithQuads, FonsTextIter, GlyphIter, GlyphQuad, MultilineIter};
+#[cfg(feature = "std")]
+pub use load::FontFileFuture;
+pub(crate) use render::resume_callback_panic;
+pub use render::{catch_callback_panic, FonsQuad, Quad, Renderer};
+pub(crate) use state::TextOptions;
+pub use state::{
+    Color, ControlPolicy, DecorationMetrics, FontFeatures, MeasureMode, MissingGlyphPolicy,
+    PixelSnap, StateDump, StateGuard, StateSnapshot, StyleOverrides, TabWidth, TextStyle,
+};
+pub use stats::Stats;
+
+use core::{
+    ffi::{c_int, c_void},
+    ptr::NonNull,
+};
+
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// `HashMap` needs `std` (for the random state)
+#[cfg(feature = "std")]
+pub(crate) type Map<K, V> = std::collections::HashMap<K, V>;
+#[cfg(not(feature = "std"))]
+pub(crate) type Map<K, V> = alloc::collections::BTreeMap<K, V>;
+
+// `f32::floor` and `f32::round` need `std`
+#[cfg(feature = "std")]
+fn floor(x: f32) -> f32 {
+    x.floor()
+}
+
+#[cfg(not(feature = "std"))]
+fn floor(x: f32) -> f32 {
+    let t = x as i32 as f32;
+    if t > x {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+#[cfg(feature = "std")]
+fn round(x: f32) -> f32 {
+    x.round()
+}
+
+#[cfg(not(feature = "std"))]
+fn round(x: f32) -> f32 {
+    // rounds half away from zero like `f32::round`
+    if x < 0.0 {
+        -floor(-x + 0.5)
+    } else {
+        floor(x + 0.5)
+    }
+}
+
+#[cfg(feature = "std")]
+fn sqrt(x: f32) -> f32 {
+    x.sqrt()
+}
+
+#[cfg(not(feature = "std"))]
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    // initial guess from the exponent bits, then Newton's method
+    let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1fbd_1df5);
+    for _ in 0..3 {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
 
-use std::os::raw::{c_int, c_uchar, c_void};
+/// Converts the position of a quad from physical pixels to logical pixels
+fn unscale_quad(quad: &mut FonsQuad, dpi_scale: f32) {
+    if dpi_scale != 1.0 {
+        quad.x0 /= dpi_scale;
+        quad.y0 /= dpi_scale;
+        quad.x1 /= dpi_scale;
+        quad.y1 /= dpi_scale;
+    }
+}
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum FonsError {
     FailedToAllocFont(),
     FoundNoFont(),
     // `renderResize` returned `1`
     RenderResizeError(),
+    /// Failed to write the atlas into an image file
+    FailedToSaveAtlas(String),
+    /// The state stack is full
+    StatesOverflow(),
+    /// The base font has `FONS_MAX_FALLBACKS` fallbacks already
+    TooManyFallbacks(),
+    /// The font data is invalid
+    FailedToLoadFont(),
+    /// A glyph didn't fit in the atlas (in pixels, including the padding)
+    AtlasFull {
+        needed: u32,
+        available: u32,
+    },
+    /// Font names have to be shorter than 64 bytes
+    InvalidFontName(String),
+    #[cfg(feature = "std")]
+    IoError(std::sync::Arc<std::io::Error>),
+    /// A string passed to FontStash contained a NUL byte
+    NulError(alloc::ffi::NulError),
+    /// The font index is out of the fonts in the stash
+    InvalidFontIx(FontIx),
+    /// The font was removed with [`FontStash::clear_fonts`]
+    StaleFontHandle(FontIx),
+    /// Font size out of `(0.0, 3276.8)`
+    InvalidSize(f32),
+    /// The data is not a font file (or not the face of the font collection)
+    InvalidFontData(),
+    /// The font has color glyphs only in bitmap tables, which are not rasterized (see
+    /// `color_glyph`)
+    UnsupportedColorTables(ColorTables),
+    /// `fonsGetTextureData` returned null
+    NoTextureData(),
+    /// `fonsCreateInternal` returned null, or the stash couldn't be set up (see
+    /// [`StashBuilder::build`])
+    FailedToCreate(),
+    /// Markup couldn't be parsed at the byte position (see [`markup`])
+    InvalidMarkup {
+        pos: usize,
+        reason: &'static str,
+    },
+    /// A BMFont descriptor couldn't be parsed at the line (see [`bmfont`])
+    #[cfg(feature = "std")]
+    InvalidBmFont {
+        line: usize,
+        reason: &'static str,
+    },
+    /// The rectangle `[x0, y0, x1, y1]` is not in the atlas
+    InvalidRegion([u32; 4]),
+    /// The output buffer is smaller than the data
+    BufferTooSmall {
+        needed: usize,
+        available: usize,
+    },
+    /// An OpenType feature tag is not four ASCII characters, or there are too many features (see
+    /// [`FontFeatures::parse`])
+    InvalidFontFeature(String),
+    /// The rendering backend failed to create or update the atlas texture
+    TextureError(String),
+    /// The combination of [`StashBuilder`] parameters is invalid
+    InvalidParams(&'static str),
+    /// The shared library couldn't be loaded (the `dlopen` feature)
+    #[cfg(feature = "dlopen")]
+    FailedToLoadLibrary(std::sync::Arc<sys::LoadError>),
 }
 
 impl fmt::Display for FonsError {
@@ -62,11 +352,127 @@ impl fmt::Display for FonsError {
             Self::RenderResizeError() => {
                 write!(f, "FontStash detected `renderResize` returned `1`")
             }
+            Self::FailedToSaveAtlas(err) => {
+                write!(f, "FontStash failed to save the atlas: {}", err)
+            }
+            Self::StatesOverflow() => {
+                write!(f, "FontStash state stack is full")
+            }
+            Self::TooManyFallbacks() => {
+                write!(f, "FontStash font has too many fallback fonts")
+            }
+            Self::FailedToLoadFont() => {
+                write!(f, "FontStash failed to load font data")
+            }
+            Self::AtlasFull { needed, available } => {
+                write!(
+                    f,
+                    "FontStash atlas is full: needed {} pixels, {} available",
+                    needed, available
+                )
+            }
+            Self::InvalidFontName(name) => {
+                write!(f, "FontStash font name is too long: {}", name)
+            }
+            #[cfg(feature = "std")]
+            Self::IoError(err) => {
+                write!(f, "FontStash failed to read a file: {}", err)
+            }
+            Self::NulError(err) => {
+                write!(f, "FontStash was given a string with NUL: {}", err)
+            }
+            Self::InvalidFontIx(font) => {
+                write!(f, "FontStash has no font at index {}", font.0)
+            }
+            Self::StaleFontHandle(font) => {
+                write!(
+                    f,
+                    "FontStash font {} is from removed fonts (generation {})",
+                    font.0, font.1
+                )
+            }
+            Self::InvalidSize(size) => {
+                write!(f, "FontStash can't use font size {}", size)
+            }
+            Self::InvalidFontData() => {
+                write!(f, "FontStash was given invalid font data")
+            }
+            Self::UnsupportedColorTables(tables) => {
+                write!(f, "FontStash can't rasterize the color tables {:?}", tables)
+            }
+            Self::NoTextureData() => {
+                write!(f, "FontStash has no texture data")
+            }
+            Self::FailedToCreate() => {
+                write!(f, "FontStash failed to create the context")
+            }
+            Self::InvalidMarkup { pos, reason } => {
+                write!(
+                    f,
+                    "FontStash failed to parse markup at byte {}: {}",
+                    pos, reason
+                )
+            }
+            #[cfg(feature = "std")]
+            Self::InvalidBmFont { line, reason } => {
+                write!(
+                    f,
+                    "FontStash failed to parse BMFont descriptor at line {}: {}",
+                    line, reason
+                )
+            }
+            Self::InvalidRegion(rect) => {
+                write!(f, "FontStash atlas doesn't contain the region {:?}", rect)
+            }
+            Self::BufferTooSmall { needed, available } => {
+                write!(
+                    f,
+                    "FontStash was given a too small buffer: needed {} bytes, {} available",
+                    needed, available
+                )
+            }
+            Self::InvalidFontFeature(tag) => {
+                write!(f, "FontStash was given an invalid font feature: {}", tag)
+            }
+            Self::TextureError(err) => {
+                write!(f, "FontStash failed to update the texture: {}", err)
+            }
+            Self::InvalidParams(reason) => {
+                write!(f, "FontStash was given invalid parameters: {}", reason)
+            }
+            #[cfg(feature = "dlopen")]
+            Self::FailedToLoadLibrary(err) => {
+                write!(f, "FontStash failed to load the fontstash library: {}", err)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FonsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IoError(err) => Some(err.as_ref()),
+            Self::NulError(err) => Some(err),
+            #[cfg(feature = "dlopen")]
+            Self::FailedToLoadLibrary(err) => Some(err.as_ref()),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for FonsError {}
+#[cfg(feature = "std")]
+impl From<std::io::Error> for FonsError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(std::sync::Arc::new(err))
+    }
+}
+
+impl From<alloc::ffi::NulError> for FonsError {
+    fn from(err: alloc::ffi::NulError) -> Self {
+        Self::NulError(err)
+    }
+}
 
 unsafe impl Send for FonsError {}
 
@@ -95,6 +501,11 @@ impl ErrorCode {
 /// The `error` argument is actually [`ErrorCode`]
 pub type ErrorCallback = unsafe extern "C" fn(uptr: *mut c_void, error: c_int, val: c_int);
 
+/// Sets the error callback of a raw `FONScontext` (see also [`StashBuilder::error_callback`])
+///
+/// `raw_stash` has to be a live `FONScontext`, e.g. [`FontStash::raw`]. It's not `unsafe` for
+/// compatibility.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn set_error_callback(
     raw_stash: *mut sys::FONScontext,
     callback: ErrorCallback,
@@ -105,44 +516,94 @@ pub fn set_error_callback(
     }
 }
 
-/// Set of callbacks
-///
-/// * `uptr`: user data pointer, which is usually the implementation of [`Renderer`]
+/// [`ErrorCallback`] that reports the errors with `log::warn!`
 ///
-/// Return non-zero to represent success.
-pub unsafe trait Renderer {
-    /// Creates font texture
-    unsafe extern "C" fn create(uptr: *mut c_void, width: c_int, height: c_int) -> c_int;
-
-    /// Create new texture
-    ///
-    /// User of [`Renderer`] should not call it directly; it's used to implement
-    /// `FontStash::expand_atlas` and `FontStash::reset_atlas`.
-    unsafe extern "C" fn resize(uptr: *mut c_void, width: c_int, height: c_int) -> c_int;
-
-    /// Try to expand texture while the atlas is full
-    unsafe extern "C" fn expand(uptr: *mut c_void) -> c_int;
-
-    /// Update texture
-    unsafe extern "C" fn update(uptr: *mut c_void, rect: *mut c_int, data: *const c_uchar)
-        -> c_int;
+/// `val` is the size of the scratch buffer or the state depth.
+#[cfg(feature = "log")]
+pub extern "C" fn log_error_callback(_uptr: *mut c_void, error: c_int, val: c_int) {
+    match ErrorCode::from_u32(error as u32) {
+        Some(code) => log::warn!("fontstash error: {:?} ({})", code, val),
+        None => log::warn!("fontstash error: unknown code {} ({})", error, val),
+    }
 }
 
+/// Owner of a `FONScontext` shared by [`FontStash`] handles
 #[derive(Debug)]
-struct FonsContextDrop {
-    raw: *mut sys::FONScontext,
+pub struct FonsContextDrop {
+    raw: NonNull<sys::FONScontext>,
+    usage: core::cell::RefCell<eviction::GlyphUsage>,
+    options: core::cell::Cell<TextOptions>,
+    quad_cache: core::cell::RefCell<quad_cache::QuadCache>,
+    measure_cache: core::cell::RefCell<measure_cache::MeasureCache>,
+    aggregator: core::cell::RefCell<Option<Box<dirty::Aggregator>>>,
+    /// Generation of [`FontIx`], bumped when the fonts are removed
+    font_generation: core::cell::Cell<u32>,
+    /// Interposed callbacks, which have to outlive the `FONScontext`
+    counters: Box<stats::Counters>,
+    atlas_listeners: core::cell::RefCell<events::AtlasListeners>,
+    coverage_lut: core::cell::RefCell<Option<convert::CoverageLut>>,
+    /// Round atlas sizes up to powers of two (see [`StashBuilder::power_of_two`])
+    power_of_two: bool,
+    /// Dedicated atlases of fonts (see [`FontStash::dedicate_font`]), kept alive while in use
+    pages: core::cell::RefCell<Vec<(FontIx, FontStash)>>,
+}
+
+impl FonsContextDrop {
+    fn new(raw: NonNull<sys::FONScontext>) -> Self {
+        Self {
+            raw,
+            usage: Default::default(),
+            options: Default::default(),
+            quad_cache: Default::default(),
+            measure_cache: Default::default(),
+            aggregator: Default::default(),
+            font_generation: Default::default(),
+            counters: unsafe { stats::Counters::install(raw) },
+            atlas_listeners: Default::default(),
+            coverage_lut: Default::default(),
+            power_of_two: false,
+            pages: Default::default(),
+        }
+    }
 }
 
 impl Drop for FonsContextDrop {
     fn drop(&mut self) {
         unsafe {
-            if !self.raw.is_null() {
-                sys::fonsDeleteInternal(self.raw);
-            }
+            sys::fonsDeleteInternal(self.raw.as_ptr());
         }
     }
 }
 
+/// State of a [`FontStash`]: [`Uninit`] or [`Ready`]
+pub trait StashState: sealed::Sealed {
+    /// What the stash holds in the state
+    type Handle: core::fmt::Debug;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::Uninit {}
+    impl Sealed for super::Ready {}
+}
+
+/// Marker of a [`FontStash`] that is not created yet. It has no method but [`FontStash::init`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uninit {}
+
+impl StashState for Uninit {
+    type Handle = ();
+}
+
+/// Marker of a created [`FontStash`], which is the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ready {}
+
+impl StashState for Ready {
+    /// Non-null `FONScontext` deleted when the last handle is dropped
+    type Handle = alloc::rc::Rc<FonsContextDrop>;
+}
+
 /// Shared ownership of font stash
 ///
 /// # Hack for creation
@@ -150,43 +611,129 @@ impl Drop for FonsContextDrop {
 /// [`Renderer`] needs fixed memory position so that `fontstash::sys` can call callback methods
 /// of it.
 ///
-/// 1. Create [`Renderer`] in a `Box` with `FontStash` being `uninitialized`
-/// 2. Call [`FontStash::init_mut`] to initialize `FontStash`
+/// 1. Create [`Renderer`] in a `Box` and `FontStash<Uninit>` with [`FontStash::uninitialized`]
+/// 2. Call [`FontStash::init`] to turn it into `FontStash<Ready>` (`FontStash`)
+///
+/// Methods are only on `FontStash<Ready>`, so using a stash before initializing it doesn't
+/// compile. A `FontStash<Ready>` always has a valid `FONScontext`.
 #[derive(Debug)]
-pub struct FontStash {
-    fons: std::rc::Rc<FonsContextDrop>,
+pub struct FontStash<S: StashState = Ready> {
+    fons: S::Handle,
+}
+
+/// Weak reference to [`FontStash`] created with [`FontStash::downgrade`]
+///
+/// Hold it in caches or renderer-owned callbacks to avoid reference cycles.
+#[derive(Debug, Clone, Default)]
+pub struct FontStashWeak {
+    fons: alloc::rc::Weak<FonsContextDrop>,
+}
+
+impl FontStashWeak {
+    /// Returns `None` if all the [`FontStash`] handles are dropped
+    pub fn upgrade(&self) -> Option<FontStash> {
+        self.fons.upgrade().map(FontStash::from_fons)
+    }
 }
 
 /// Hack for creation
-impl FontStash {
+impl FontStash<Uninit> {
     pub fn uninitialized() -> Self {
-        FontStash {
-            fons: std::rc::Rc::new(FonsContextDrop {
-                raw: std::ptr::null_mut(),
-            }),
-        }
+        FontStash { fons: () }
     }
 
-    pub fn init_mut<R: Renderer>(&mut self, w: u32, h: u32, renderer: *mut R) {
-        self.fons = std::rc::Rc::new(Self::create(w, h, renderer));
+    /// Creates `FONScontext` with the `renderer`, which has to have consistant memory position
+    ///
+    /// Returns [`FonsError::FailedToCreate`] if `fonsCreateInternal` failed (including
+    /// `renderCreate` returning `0`).
+    pub fn init<R: Renderer>(self, w: u32, h: u32, renderer: *mut R) -> Result<FontStash> {
+        let fons = FontStash::create(w, h, renderer);
+        resume_callback_panic();
+        Ok(FontStash::from_fons(alloc::rc::Rc::new(fons?)))
     }
 }
 
 impl FontStash {
+    /// Creates `FONScontext` with full control of the parameters, e.g. callbacks written in C.
+    /// Prefer [`StashBuilder`], which validates the parameters
+    ///
+    /// Returns [`FonsError::FailedToCreate`] if `fonsCreateInternal` failed (including
+    /// `renderCreate` returning `0`).
+    ///
+    /// # Safety
+    ///
+    /// `userPtr` and the callbacks have to be valid while the stash (and its clones) are alive.
+    /// Rust-side layout assumes [`Flags::TopLeft`].
+    pub unsafe fn from_params(params: sys::FONSparams) -> Result<Self> {
+        #[cfg(feature = "dlopen")]
+        sys::load_default().map_err(|err| FonsError::FailedToLoadLibrary(err.into()))?;
+        let mut params = params;
+        let raw = sys::fonsCreateInternal(&mut params);
+        resume_callback_panic();
+        let raw = match NonNull::new(raw) {
+            Some(raw) => raw,
+            None => {
+                log_warn!("fontstash: fonsCreateInternal failed");
+                return Err(FonsError::FailedToCreate());
+            }
+        };
+        Ok(Self::from_fons(alloc::rc::Rc::new(FonsContextDrop::new(
+            raw,
+        ))))
+    }
+
+    fn from_fons(fons: alloc::rc::Rc<FonsContextDrop>) -> Self {
+        FontStash { fons }
+    }
+}
+
+/// Dynamic loading
+#[cfg(feature = "dlopen")]
+impl FontStash {
+    /// Loads the `fontstash` library at `path` instead of the one in the library search path
+    ///
+    /// Creating a stash loads `libfontstash.so` (`.dylib`, or `fontstash.dll`) if no library is
+    /// loaded yet, so call it before creating any stash. Associated functions such as
+    /// [`FontStash::backend`] panic if no library can be loaded.
+    ///
+    /// # Safety
+    ///
+    /// The library has to be built from `fontstash.c` of `fontstash-sys`. Its initialization
+    /// routines are run.
+    pub unsafe fn load_library(path: impl AsRef<std::ffi::OsStr>) -> Result<()> {
+        sys::load(path).map_err(|err| FonsError::FailedToLoadLibrary(err.into()))
+    }
+
+    /// If the `fontstash` library is loaded
+    pub fn is_library_loaded() -> bool {
+        sys::is_loaded()
+    }
+}
+
+/// Another handle to the same `FONScontext`, which is released with the last handle
+impl Clone for FontStash {
+    fn clone(&self) -> Self {
+        Self::from_fons(self.fons.clone())
+    }
+}
+
+impl FontStash {
+    /// The `FONScontext`, which is never null
     pub fn raw(&self) -> *mut sys::FONScontext {
-        self.fons.raw
+        self.fons.raw.as_ptr()
     }
 
-    pub fn clone(&self) -> Self {
-        FontStash {
-            fons: self.fons.clone(),
+    /// Creates a non-owning handle, which doesn't keep the `FONScontext` alive
+    pub fn downgrade(&self) -> FontStashWeak {
+        FontStashWeak {
+            fons: alloc::rc::Rc::downgrade(&self.fons),
         }
     }
 
     /// Creates `FONScontext`
     ///
     /// The `renderer` has to have consistant memory position. Maybe put in in a `Box`.
-    fn create<R: Renderer>(w: u32, h: u32, renderer: *mut R) -> FonsContextDrop {
+    fn create<R: Renderer>(w: u32, h: u32, renderer: *mut R) -> Result<FonsContextDrop> {
         let flags = Flags::TopLeft;
         let params = sys::FONSparams {
             width: w as c_int,
@@ -201,243 +748,840 @@ impl FontStash {
             renderDelete: None,
         };
 
-        FonsContextDrop {
-            raw: unsafe { sys::fonsCreateInternal(&params as *const _ as *mut _) },
-        }
+        #[cfg(feature = "dlopen")]
+        unsafe { sys::load_default() }.map_err(|err| FonsError::FailedToLoadLibrary(err.into()))?;
+
+        let raw = unsafe { sys::fonsCreateInternal(&params as *const _ as *mut _) };
+        let raw = match NonNull::new(raw) {
+            Some(raw) => raw,
+            None => {
+                log_warn!("fontstash: fonsCreateInternal failed ({}x{})", w, h);
+                return Err(FonsError::FailedToCreate());
+            }
+        };
+        Ok(FonsContextDrop::new(raw))
     }
 }
 
-/// Font index
+/// Font index with the generation of the fonts it was created in
+///
+/// A handle from before [`FontStash::clear_fonts`] is stale. Functions returning `Result` fail
+/// with [`FonsError::StaleFontHandle`] for it.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct FontIx(u32);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontIx(u32, u32);
 
 impl FontIx {
     pub fn raw(&self) -> u32 {
         self.0
     }
 
-    pub unsafe fn from_raw(ix: u32) -> Self {
-        Self(ix)
+    pub fn generation(&self) -> u32 {
+        self.1
+    }
+
+    /// Handle from [`FontIx::raw`] and [`FontIx::generation`], e.g. of a deserialized scene
+    ///
+    /// # Safety
+    ///
+    /// The values have to be of a handle of the stash it's used with. The C side checks the index,
+    /// so any other value is memory-safe, but it selects whatever font has the index in that
+    /// generation (or none). Stale generations fail with [`FonsError::StaleFontHandle`] as usual.
+    pub unsafe fn from_raw(ix: u32, generation: u32) -> Self {
+        Self(ix, generation)
     }
 }
 
 /// Font storage. Each font is keyed with `name` string.
 impl FontStash {
+    /// Adds a font. `data` has to outlive the stash
+    ///
+    /// The sfnt header is checked on the Rust side and [`FonsError::InvalidFontData`] is returned if
+    /// it's not a font.
     pub fn add_font_mem(&self, name: &str, data: &[u8]) -> Result<FontIx> {
-        let name = std::ffi::CString::new(name).unwrap();
+        self.add_font_mem_indexed(name, data, 0)
+    }
+
+    /// Adds the face at `face_index` of a font collection (`.ttc`) such as `msgothic.ttc`
+    pub fn add_font_mem_indexed(&self, name: &str, data: &[u8], face_index: u32) -> Result<FontIx> {
+        let name = Self::font_name(name)?;
+        sfnt::validate(data, face_index)?;
 
         let ix = unsafe {
-            sys::fonsAddFontMem(
+            sys::fonsAddFontMemAt(
                 self.raw(),
                 name.as_ptr() as *const _,
                 data as *const _ as *mut _,
                 data.len() as i32,
                 false as i32,
+                face_index as i32,
+            )
+        };
+
+        self.to_font_ix(ix)
+    }
+
+    /// Copies the font data into the stash, so that `data` doesn't have to outlive the stash
+    pub fn add_font_mem_copy(&self, name: &str, data: &[u8]) -> Result<FontIx> {
+        self.add_font_mem_copy_indexed(name, data, 0)
+    }
+
+    /// [`FontStash::add_font_mem_copy`] for a face of a font collection
+    pub fn add_font_mem_copy_indexed(
+        &self,
+        name: &str,
+        data: &[u8],
+        face_index: u32,
+    ) -> Result<FontIx> {
+        let name = Self::font_name(name)?;
+        sfnt::validate(data, face_index)?;
+
+        let ix = unsafe {
+            sys::fonsAddFontMemCopy(
+                self.raw(),
+                name.as_ptr() as *const _,
+                data.as_ptr(),
+                data.len() as i32,
+                face_index as i32,
             )
         };
 
+        self.to_font_ix(ix)
+    }
+
+    /// Reads a font file and copies it into the stash
+    #[cfg(feature = "std")]
+    pub fn add_font_file(&self, name: &str, path: impl AsRef<std::path::Path>) -> Result<FontIx> {
+        let data = std::fs::read(path)?;
+        self.add_font_mem_copy(name, &data)
+    }
+
+    /// FontStash truncates names into 63 bytes, so reject longer names
+    fn font_name(name: &str) -> Result<alloc::ffi::CString> {
+        if name.len() >= 64 {
+            return Err(FonsError::InvalidFontName(name.to_string()));
+        }
+        Ok(alloc::ffi::CString::new(name)?)
+    }
+
+    fn to_font_ix(&self, ix: c_int) -> Result<FontIx> {
         if ix == sys::FONS_INVALID {
             Err(FonsError::FailedToAllocFont())
         } else {
-            Ok(FontIx(ix as u32))
+            Ok(self.font_ix(ix as u32))
         }
     }
 
-    // extern "C" {
-    //     pub fn fonsAddFallbackFont(
-    //         stash: *mut FONScontext,
-    //         base: c_int,
-    //         fallback: c_int,
-    //     ) -> c_int;
-    // }
+    /// Font handle of the current generation
+    pub(crate) fn font_ix(&self, ix: u32) -> FontIx {
+        FontIx(ix, self.fons.font_generation.get())
+    }
 
-    pub fn set_font(&self, font: FontIx) {
-        unsafe {
-            sys::fonsSetFont(self.raw(), font.0 as i32);
+    /// Fails with [`FonsError::StaleFontHandle`] if the font is from removed fonts
+    pub fn check_font(&self, font: FontIx) -> Result<()> {
+        if font.1 == self.fons.font_generation.get() {
+            Ok(())
+        } else {
+            Err(FonsError::StaleFontHandle(font))
         }
     }
 
-    pub fn font_ix_by_name(&self, name: &str) -> Option<FontIx> {
-        let name = std::ffi::CString::new(name).ok()?;
-        let ix = unsafe { sys::fonsGetFontByName(self.raw(), name.as_ptr()) };
-        if ix == sys::FONS_INVALID {
-            None
+    /// Font index passed to C, or `FONS_INVALID` for a stale handle so that no font is selected
+    pub(crate) fn checked_font(&self, font: FontIx) -> c_int {
+        if self.check_font(font).is_ok() {
+            font.0 as c_int
         } else {
-            Some(FontIx(ix as u32))
+            sys::FONS_INVALID
         }
     }
-}
 
-/// Atlas
-impl FontStash {
-    pub fn atlas_size(&self) -> [u32; 2] {
-        let [mut x, mut y] = [0, 0];
+    /// Removes all the fonts and resets the atlas. [`FontIx`]es created before become stale
+    ///
+    /// Fonts added after it start from index `0` again. Returns the error of
+    /// [`FontStash::reset_atlas`].
+    pub fn clear_fonts(&self) -> Result<()> {
         unsafe {
-            sys::fonsGetAtlasSize(self.raw(), &mut x, &mut y);
+            sys::fonsClearFonts(self.raw());
         }
-        [x as u32, y as u32]
+        let generation = self.fons.font_generation.get().wrapping_add(1);
+        self.fons.font_generation.set(generation);
+        // glyph usage is keyed by the freed fonts
+        self.clear_glyph_usage();
+        // `fonsClearFonts` released the pages of the fonts
+        self.fons.pages.borrow_mut().clear();
+
+        let [w, h] = self.atlas_size();
+        self.reset_atlas(w, h)
     }
 
-    /// Creates fontstash atlas size copying the previous data
-    pub fn expand_atlas(&self, w: u32, h: u32) -> Result<()> {
-        if unsafe { sys::fonsExpandAtlas(self.raw(), w as i32, h as i32) } != 0 {
-            Ok(())
+    /// If the font itself has a glyph for the character. Fallback fonts are not considered, and a
+    /// stale font has no glyphs
+    pub fn has_glyph(&self, font: FontIx, c: char) -> bool {
+        unsafe { sys::fonsHasGlyph(self.raw(), self.checked_font(font), c as u32) != 0 }
+    }
+
+    /// [`FontStash::has_glyph`] for each character
+    pub fn coverage(&self, font: FontIx, chars: impl Iterator<Item = char>) -> Vec<bool> {
+        chars.map(|c| self.has_glyph(font, c)).collect()
+    }
+
+    /// Characters of `text` that neither the current font nor its fallbacks have, with their
+    /// byte offsets, e.g. for checking translations against the bundled fonts
+    ///
+    /// Control characters (such as `\n`) are not reported. Every character is reported if no font
+    /// is set. Glyphs are not rasterized.
+    pub fn check_coverage(&self, text: &str) -> Vec<(usize, char)> {
+        text.char_indices()
+            .filter(|&(_, c)| !c.is_control() && self.glyph_bounds(c).is_none())
+            .collect()
+    }
+
+    /// Color glyph tables the font has (none for a stale font). The layers of `COLR` are
+    /// rasterized in color with the `color-glyphs` feature (see `color_glyph`). Glyphs of `CBDT`
+    /// and `sbix` are rasterized from their outlines, which bitmap fonts usually don't have
+    pub fn color_tables(&self, font: FontIx) -> ColorTables {
+        let bits = unsafe { sys::fonsGetColorTables(self.raw(), self.checked_font(font)) };
+        ColorTables::from_bits_truncate(bits as u32)
+    }
+
+    /// Font rasterizer the C library is built with
+    pub fn backend() -> Backend {
+        if unsafe { sys::fonsUsesFreetype() } != 0 {
+            Backend::FreeType
         } else {
-            Err(FonsError::RenderResizeError())
+            Backend::StbTruetype
         }
     }
 
-    /// Creates new fontstash atlas with size without copying the previous data
-    pub fn reset_atlas(&self, w: u32, h: u32) -> Result<()> {
-        unsafe {
-            if sys::fonsResetAtlas(self.raw(), w as i32, h as i32) == 1 {
-                Ok(())
-            } else {
-                Err(FonsError::RenderResizeError())
-            }
-        }
+    /// Sets how glyphs are hinted by the FreeType backend. `autohint` uses the FreeType
+    /// autohinter instead of the hinting instructions of the fonts (the default is
+    /// [`Hinting::Full`] with the autohinter)
+    ///
+    /// It applies to all the stashes in the process, and glyphs already in the atlas are kept, so
+    /// reset the atlases after changing it. Returns `false` without the `freetype` feature.
+    pub fn set_freetype_hinting(hinting: Hinting, autohint: bool) -> bool {
+        let hinting = match hinting {
+            Hinting::None => sys::FONShinting_FONS_HINTING_NONE,
+            Hinting::Slight => sys::FONShinting_FONS_HINTING_SLIGHT,
+            Hinting::Full => sys::FONShinting_FONS_HINTING_FULL,
+        };
+        unsafe { sys::fonsSetFreetypeHinting(hinting as c_int, autohint as c_int) != 0 }
     }
-}
 
-/// States
-impl FontStash {
-    /// NOTE: DPI scaling?
-    pub fn set_size(&self, size: f32) {
-        unsafe {
-            sys::fonsSetSize(self.raw(), size);
+    /// Metadata of a font read from the font tables, or `None` for a stale or invalid font
+    pub fn font_info(&self, font: FontIx) -> Option<FontInfo> {
+        let mut info: sys::FONSfontInfo = unsafe { core::mem::zeroed() };
+        let ix = self.checked_font(font);
+        if unsafe { sys::fonsGetFontInfo(self.raw(), ix, &mut info) } == 0 {
+            return None;
         }
+
+        let name = unsafe { core::ffi::CStr::from_ptr(info.name) }
+            .to_string_lossy()
+            .into_owned();
+
+        let family = if info.family.is_null() {
+            None
+        } else {
+            let bytes = unsafe {
+                core::slice::from_raw_parts(info.family as *const u8, info.familyLength as usize)
+            };
+            Some(if info.familyIsUtf16 != 0 {
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                    .collect::<Vec<_>>();
+                String::from_utf16_lossy(&units)
+            } else {
+                // Mac Roman; it's same as ASCII for most family names
+                bytes.iter().map(|&b| b as char).collect()
+            })
+        };
+
+        Some(FontInfo {
+            name,
+            family,
+            units_per_em: info.unitsPerEm as u32,
+            glyph_count: info.glyphCount as u32,
+            ascent: info.ascent,
+            descent: info.descent,
+            line_gap: info.lineGap,
+        })
     }
 
-    pub fn set_color(&self, color: u32) {
-        unsafe {
-            sys::fonsSetColor(self.raw(), color);
+    /// Replaces the font data keeping the [`FontIx`], e.g., to reload a font file being edited
+    ///
+    /// The data is copied into the stash. Cached glyphs of the font are invalidated, but their
+    /// pixels stay in the atlas until it's reset (see [`FontStash::reset_atlas`]). The old data is
+    /// kept if `data` can't be loaded. The face at the index the font was added with is loaded
+    /// (see [`FontStash::add_font_mem_indexed`]), so `data` of a collection has to keep the order.
+    pub fn reload_font(&self, font: FontIx, data: &[u8]) -> Result<()> {
+        self.check_font(font)?;
+        let mut face_index = 0;
+        let ptr = unsafe {
+            sys::fonsGetFontData(
+                self.raw(),
+                font.0 as c_int,
+                core::ptr::null_mut(),
+                &mut face_index,
+            )
+        };
+        if ptr.is_null() {
+            return Err(FonsError::InvalidFontIx(font));
         }
-    }
+        sfnt::validate(data, face_index as u32)?;
 
-    /// Horizontal space between characters?
-    pub fn set_spacing(&self, spacing: f32) {
-        unsafe {
-            sys::fonsSetSpacing(self.raw(), spacing);
+        let res = unsafe {
+            sys::fonsReloadFontMem(
+                self.raw(),
+                font.0 as c_int,
+                data.as_ptr(),
+                data.len() as i32,
+                face_index,
+            )
+        };
+
+        if res == 0 {
+            Err(FonsError::FailedToLoadFont())
+        } else {
+            self.clear_quad_cache();
+            self.invalidate_measure_cache();
+            Ok(())
         }
     }
 
-    pub fn set_blur(&self, blur: f32) {
+    /// Variation axes of a variable font. Empty for a static font or with
+    /// [`Backend::StbTruetype`], which always rasterizes the default instance
+    pub fn font_axes(&self, font: FontIx) -> Result<Vec<FontAxis>> {
+        self.check_font(font)?;
+        let n =
+            unsafe { sys::fonsGetFontAxes(self.raw(), font.0 as c_int, core::ptr::null_mut(), 0) };
+        if n < 0 {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+
+        let mut axes = alloc::vec![sys::FONSfontAxis::default(); n as usize];
         unsafe {
-            sys::fonsSetBlur(self.raw(), blur);
+            sys::fonsGetFontAxes(self.raw(), font.0 as c_int, axes.as_mut_ptr(), n);
         }
+        Ok(axes
+            .into_iter()
+            .map(|a| FontAxis {
+                tag: a.tag.to_be_bytes(),
+                min: a.minValue,
+                default: a.defaultValue,
+                max: a.maxValue,
+                value: a.value,
+            })
+            .collect())
     }
 
-    /// Sets alignment of quadliterals returned with [`FontStash::text_iter`]
-    pub fn set_align(&self, align: Align) {
-        unsafe {
-            sys::fonsSetAlign(self.raw(), align.bits() as i32);
+    /// Sets the coordinates of variation axes of a variable font, e.g. `&[(*b"wght", 700.0)]`.
+    /// They're clamped to the ranges of the axes, and the other axes are kept
+    ///
+    /// Cached glyphs of the font are invalidated as with [`FontStash::reload_font`], which resets
+    /// the coordinates. Returns [`FonsError::InvalidParams`] if the font doesn't have one of the
+    /// axes or with [`Backend::StbTruetype`].
+    pub fn set_font_variation(&self, font: FontIx, axes: &[([u8; 4], f32)]) -> Result<()> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+        if Self::backend() != Backend::FreeType {
+            return Err(FonsError::InvalidParams(
+                "variable fonts need the FreeType backend",
+            ));
+        }
+
+        let tags = axes
+            .iter()
+            .map(|(tag, _)| u32::from_be_bytes(*tag))
+            .collect::<Vec<_>>();
+        let values = axes.iter().map(|&(_, value)| value).collect::<Vec<_>>();
+        let res = unsafe {
+            sys::fonsSetFontVariation(
+                self.raw(),
+                font.0 as c_int,
+                tags.as_ptr(),
+                values.as_ptr(),
+                axes.len() as c_int,
+            )
+        };
+
+        match res {
+            1 => {
+                self.clear_quad_cache();
+                self.invalidate_measure_cache();
+                Ok(())
+            }
+            -1 => Err(FonsError::InvalidParams(
+                "the font doesn't have the variation axis",
+            )),
+            _ => Err(FonsError::FailedToLoadFont()),
         }
     }
-}
 
-/// Texture
-impl FontStash {
-    /// NOTE: Pixel is in one byte (8 bits alpha channel only)
-    pub fn with_pixels(&self, mut f: impl FnMut(&[u8], u32, u32)) {
-        let (mut w, mut h) = (0, 0);
-        let ptr = unsafe { sys::fonsGetTextureData(self.raw(), &mut w, &mut h) };
-        if !ptr.is_null() {
-            let pixels = unsafe { std::slice::from_raw_parts(ptr, (w * h) as usize) };
-            f(pixels, w as u32, h as u32);
+    /// Makes `fallback` searched for characters missing in `base`. See also [`fallback`]
+    pub fn add_fallback_font(&self, base: FontIx, fallback: FontIx) -> Result<()> {
+        self.check_font(base)?;
+        self.check_font(fallback)?;
+        let res =
+            unsafe { sys::fonsAddFallbackFont(self.raw(), base.0 as c_int, fallback.0 as c_int) };
+        if res == 0 {
+            Err(FonsError::TooManyFallbacks())
         } else {
-            eprintln!("fontstash-rs: fonsGetTextureData returned null");
+            self.invalidate_measure_cache();
+            Ok(())
         }
     }
 
-    // FIXME: what's this
-    // pub fn dirty(&self) -> (bool, i32) {
-    //     let mut dirty_flags = 0;
-    //     let x = unsafe { sys::fonsValidateTexture(self.raw(), &mut dirty_flags) };
-    //     (x == 1, dirty_flags)
-    // }
-}
-
-/// State stack
-impl FontStash {
-    pub fn push_state(&mut self) {
+    /// Sets the current font. A stale font (see [`FontStash::clear_fonts`]) sets no font, so the
+    /// text iterators fail with [`FonsError::FoundNoFont`] instead of drawing another font
+    pub fn set_font(&self, font: FontIx) {
+        let ix = self.checked_font(font);
+        if ix == sys::FONS_INVALID {
+            log_warn!("fontstash: stale font handle {:?}", font);
+        }
         unsafe {
-            sys::fonsPushState(self.raw());
+            sys::fonsSetFont(self.raw(), ix);
         }
     }
 
-    pub fn pop_state(&mut self) {
-        unsafe {
-            sys::fonsPopState(self.raw());
+    /// [`FontStash::set_font`] that fails if the font is not in this stash
+    pub fn try_set_font(&self, font: FontIx) -> Result<()> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
         }
+        self.set_font(font);
+        Ok(())
     }
 
-    pub fn clear_state(&mut self) {
-        unsafe {
-            sys::fonsClearState(self.raw());
+    /// Number of fonts added to the stash
+    pub fn font_count(&self) -> usize {
+        unsafe { sys::fonsGetFontCount(self.raw()) as usize }
+    }
+
+    pub fn font_ix_by_name(&self, name: &str) -> Option<FontIx> {
+        let name = alloc::ffi::CString::new(name).ok()?;
+        let ix = unsafe { sys::fonsGetFontByName(self.raw(), name.as_ptr()) };
+        if ix == sys::FONS_INVALID {
+            None
+        } else {
+            Some(self.font_ix(ix as u32))
         }
     }
 }
 
-/// Draw
-impl FontStash {
-    /// Iterator of quadliterals aligned with [`Align`]
-    pub fn text_iter(&self, text: &str) -> Result<FonsTextIter> {
-        FonsTextIter::from_text(self.clone(), text)
+bitflags::bitflags! {
+    /// Color glyph tables of a font returned by [`FontStash::color_tables`]
+    pub struct ColorTables: u32 {
+        /// Layered vector glyphs
+        const COLR = sys::FONScolorTable_FONS_COLOR_COLR;
+        /// Embedded color bitmaps (Google)
+        const CBDT = sys::FONScolorTable_FONS_COLOR_CBDT;
+        /// Embedded color bitmaps (Apple)
+        const SBIX = sys::FONScolorTable_FONS_COLOR_SBIX;
     }
 }
 
+/// Hinting of the FreeType backend (see [`FontStash::set_freetype_hinting`])
+///
+/// Hinting makes small text crisper on low-DPI screens by snapping outlines to the pixel grid,
+/// at the cost of the shapes and the spacing of the glyphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Hinting {
+    None,
+    /// Vertical snapping only, which keeps the glyph shapes
+    Slight,
+    #[default]
+    Full,
+}
+
+/// Font rasterizer the C library is built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    StbTruetype,
+    /// Enabled with `freetype` feature
+    FreeType,
+}
+
+/// Metadata of a font
+///
+/// * `name`: the name given to [`FontStash::add_font_mem`]
+/// * `family`: family name in the `name` table of the font, if any
+/// * `ascent`, `descent`, `line_gap`: vertical metrics in font units (`descent` is negative)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontInfo {
+    pub name: String,
+    pub family: Option<String>,
+    pub units_per_em: u32,
+    pub glyph_count: u32,
+    pub ascent: i32,
+    pub descent: i32,
+    pub line_gap: i32,
+}
+
+/// Variation axis of a variable font returned by [`FontStash::font_axes`]
+///
+/// * `tag`: e.g. `*b"wght"` (weight), `*b"wdth"` (width) or `*b"slnt"` (slant)
+/// * `min`, `default`, `max`: range of the axis in design coordinates
+/// * `value`: the current coordinate (see [`FontStash::set_font_variation`])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontAxis {
+    pub tag: [u8; 4],
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+    pub value: f32,
+}
+
 // FIXME: understand the difference between `advance` and `bounds` width
 
 /// Measure
 impl FontStash {
     /// Returns `[left_x, top_y, right_x, bottom_y]`. Note that **it doesn't handle multiple lines
     /// of text**. You need custom layer for multi-line text!
-    pub fn text_bounds_oneline(&self, pos: [f32; 2], text: &str) -> [f32; 4] {
+    pub fn text_bounds_oneline(&self, pos: impl Into<[f32; 2]>, text: &str) -> [f32; 4] {
+        let pos = pos.into();
+        if self.options().adjusts_advance() {
+            return self.measure_adjusted(pos, text).1;
+        }
+
+        let dpi = self.dpi_scale();
         let mut bounds = [0.0; 4];
+        let _advance = self.text_bounds_raw([pos[0] * dpi, pos[1] * dpi], text, &mut bounds);
 
-        let _advance = unsafe {
-            let start = text.as_ptr() as *const _;
-            let end = text.as_ptr().add(text.len()) as *const _;
-            sys::fonsTextBounds(self.raw(), pos[0], pos[1], start, end, bounds.as_mut_ptr())
-        };
+        bounds.map(|v| v / dpi)
+    }
 
-        bounds
+    /// `fonsTextBounds` in physical pixels, or its non-rasterizing version with
+    /// [`MeasureMode::Metrics`]. Returns the advance
+    fn text_bounds_raw(&self, pos: [f32; 2], text: &str, bounds: &mut [f32; 4]) -> f32 {
+        let (text, _) = self.prepared_text(Cow::Borrowed(text));
+        let start = text.as_ptr() as *const _;
+        let end = unsafe { text.as_ptr().add(text.len()) } as *const _;
+        let [x, y] = pos;
+        match self.measure_mode() {
+            MeasureMode::Rasterize => unsafe {
+                sys::fonsTextBounds(self.raw(), x, y, start, end, bounds.as_mut_ptr())
+            },
+            MeasureMode::Metrics => unsafe {
+                sys::fonsTextBoundsNoBitmap(self.raw(), x, y, start, end, bounds.as_mut_ptr())
+            },
+        }
     }
 
     /// Returns `[width, height]`. Note that **it doesn't handle multiple lines of text**.
     /// You need custom layer for multi-line text!
     pub fn text_size_oneline(&self, text: &str) -> [f32; 2] {
-        let mut bounds = [0.0; 4];
+        if self.options().adjusts_advance() {
+            let (advance, bounds) = self.measure_adjusted([0.0, 0.0], text);
+            return [advance, bounds[3] - bounds[1]];
+        }
 
-        let advance = unsafe {
-            let start = text.as_ptr() as *const _;
-            let end = text.as_ptr().add(text.len()) as *const _;
-            sys::fonsTextBounds(self.raw(), 0.0, 0.0, start, end, bounds.as_mut_ptr())
-        };
+        let mut bounds = [0.0; 4];
+        let advance = self.text_bounds_raw([0.0, 0.0], text, &mut bounds);
 
         // let w = bounds[2] - bounds[0];
         let h = bounds[3] - bounds[1];
 
         // [w, h]
-        [advance, h]
+        let dpi = self.dpi_scale();
+        [advance / dpi, h / dpi]
     }
 
-    // extern "C" {
-    //     pub fn fonsLineBounds(s: *mut FONScontext, y: f32, miny: *mut f32, maxy: *mut f32);
-    // }
+    /// Returns `(advance, bounds)` measured on the Rust side, applying [`TextOptions`]
+    fn measure_adjusted(&self, pos: [f32; 2], text: &str) -> (f32, [f32; 4]) {
+        let align = self.align();
+        let mut bounds = [pos[0], pos[1], pos[0], pos[1]];
 
-    // extern "C" {
-    //     pub fn fonsVertMetrics(
-    //         s: *mut FONScontext,
-    //         ascender: *mut f32,
-    //         descender: *mut f32,
-    //         lineh: *mut f32,
-    //     );
-    // }
+        let opts = TextOptions {
+            clip_rect: None,
+            ..self.options()
+        };
+        let init = self.with_left_align(|| FonsTextIter::init(self, pos, text, opts));
+        let mut iter = match init {
+            Ok(iter) => iter,
+            Err(_) => return (0.0, bounds),
+        };
+
+        let start_x = iter.next_x();
+        let start_y = iter.iter.y / iter.opts.dpi_scale;
+        bounds = [start_x, start_y, start_x, start_y];
+        while let Some(glyph) = iter.next_glyph() {
+            let [x0, y0, x1, y1] = glyph.quad.position_rect();
+            bounds[0] = bounds[0].min(x0.min(x1));
+            bounds[1] = bounds[1].min(y0.min(y1));
+            bounds[2] = bounds[2].max(x0.max(x1));
+            bounds[3] = bounds[3].max(y0.max(y1));
+        }
+        let advance = iter.next_x() - start_x;
+
+        let shift = if align.contains(Align::RIGHT) {
+            advance
+        } else if align.contains(Align::CENTER) {
+            advance / 2.0
+        } else {
+            0.0
+        };
+        bounds[0] -= shift;
+        bounds[2] -= shift;
+
+        (advance, bounds)
+    }
+
+    /// Metrics of a glyph of the current font and size, or `None` if no font has it
+    ///
+    /// It doesn't rasterize the glyph.
+    pub fn glyph_bounds(&self, c: char) -> Option<GlyphBounds> {
+        let mut bounds: sys::FONSglyphBounds = unsafe { core::mem::zeroed() };
+        if unsafe { sys::fonsGetGlyphBounds(self.raw(), c as u32, &mut bounds) } == 0 {
+            return None;
+        }
+
+        let dpi = self.dpi_scale();
+        Some(GlyphBounds {
+            bearing: [bounds.bearingX / dpi, bounds.bearingY / dpi],
+            size: [bounds.width / dpi, bounds.height / dpi],
+            advance: bounds.advance / dpi,
+        })
+    }
+
+    /// Kerning adjustment between two characters for the current font and size
+    ///
+    /// FontStash rounds `kern + spacing` to integer pixels when it advances the pen.
+    pub fn kern(&self, left: char, right: char) -> f32 {
+        let kern = unsafe { sys::fonsGetKerning(self.raw(), left as u32, right as u32) };
+        kern / self.dpi_scale()
+    }
+
+    /// Returns `[min_y, max_y]` of a line placed at `y`, considering the vertical alignment
+    pub fn line_bounds(&self, y: f32) -> [f32; 2] {
+        let dpi = self.dpi_scale();
+        let [mut min_y, mut max_y] = [0.0, 0.0];
+        unsafe {
+            sys::fonsLineBounds(self.raw(), y * dpi, &mut min_y, &mut max_y);
+        }
+        [min_y / dpi, max_y / dpi]
+    }
+
+    /// Returns `[left_x, top_y, right_x, bottom_y]` of text with multiple lines
+    ///
+    /// Lines are split with `\n` and placed as [`FontStash::multiline_iter`] does.
+    pub fn text_bounds(&self, pos: impl Into<[f32; 2]>, text: &str) -> [f32; 4] {
+        let pos = pos.into();
+        let line_height = self.vert_metrics().line_height;
+        let mut union: Option<[f32; 4]> = None;
+
+        for (i, line) in text.split('\n').map(self::trim_cr).enumerate() {
+            let y = pos[1] + line_height * i as f32;
+            let b = self.text_bounds_oneline([pos[0], y], line);
+            union = Some(match union {
+                None => b,
+                Some(u) => [
+                    u[0].min(b[0]),
+                    u[1].min(b[1]),
+                    u[2].max(b[2]),
+                    u[3].max(b[3]),
+                ],
+            });
+        }
+
+        union.unwrap_or([pos[0], pos[1], pos[0], pos[1]])
+    }
+
+    /// Returns `[width, height]` of text with multiple lines. The width is the maximum advance of
+    /// the lines
+    pub fn text_size(&self, text: &str) -> [f32; 2] {
+        let w = text
+            .split('\n')
+            .map(|line| self.text_size_oneline(self::trim_cr(line))[0])
+            .fold(0.0, f32::max);
+
+        let bounds = self.text_bounds([0.0, 0.0], text);
+        [w, bounds[3] - bounds[1]]
+    }
+
+    /// Height of lowercase letters for the current font and size, e.g. for centering text with
+    /// icons vertically. `None` if there's no font
+    ///
+    /// It's `sxHeight` of the `OS/2` table, or measured from the glyph of `x` if the font doesn't
+    /// have it.
+    pub fn x_height(&self) -> Option<f32> {
+        self.cap_metric(|[x, _cap]| x, 'x')
+    }
+
+    /// Height of capital letters for the current font and size. `None` if there's no font
+    ///
+    /// It's `sCapHeight` of the `OS/2` table, or measured from the glyph of `H` if the font
+    /// doesn't have it.
+    pub fn cap_height(&self) -> Option<f32> {
+        self.cap_metric(|[_x, cap]| cap, 'H')
+    }
+
+    fn cap_metric(&self, pick: impl FnOnce([c_int; 2]) -> c_int, fallback: char) -> Option<f32> {
+        let (mut x, mut cap) = (0, 0);
+        let font = self.state_info().font;
+        let units = match unsafe { sys::fonsGetCapMetrics(self.raw(), font, &mut x, &mut cap) } {
+            0 => 0,
+            _ => pick([x, cap]),
+        };
+
+        if units > 0 {
+            Some(units as f32 * self.pixel_scale())
+        } else {
+            // the top of the glyph is above the baseline
+            self.glyph_bounds(fallback).map(|b| -b.bearing[1])
+        }
+    }
+
+    /// Vertical metrics of the current font and size
+    pub fn vert_metrics(&self) -> LineMetrics {
+        let mut metrics = LineMetrics::default();
+        unsafe {
+            sys::fonsVertMetrics(
+                self.raw(),
+                &mut metrics.ascender,
+                &mut metrics.descender,
+                &mut metrics.line_height,
+            );
+        }
+        let dpi = self.dpi_scale();
+        metrics.ascender /= dpi;
+        metrics.descender /= dpi;
+        metrics.line_height /= dpi;
+        metrics
+    }
+}
+
+/// Metrics of a glyph returned by [`FontStash::glyph_bounds`]
+///
+/// * `bearing`: offset from the pen position (on the baseline) to the left-up corner of the glyph
+/// * `size`: `[width, height]` of the glyph bitmap
+/// * `advance`: horizontal distance to the next pen position (without spacing or kerning)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphBounds {
+    pub bearing: [f32; 2],
+    pub size: [f32; 2],
+    pub advance: f32,
+}
+
+/// Vertical metrics of a font at some size
+///
+/// * `ascender`: distance from the baseline to the top (positive)
+/// * `descender`: distance from the baseline to the bottom (usually negative)
+/// * `line_height`: distance between baselines of two lines
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineMetrics {
+    pub ascender: f32,
+    pub descender: f32,
+    pub line_height: f32,
+}
+
+/// Caret
+///
+/// Text is laid out as [`FontStash::multiline_iter`] does.
+impl FontStash {
+    /// Returns the byte index of the caret closest to `point`
+    pub fn byte_index_at(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        point: impl Into<[f32; 2]>,
+    ) -> usize {
+        let pos = pos.into();
+        let point = point.into();
+        let line_height = self.vert_metrics().line_height;
+        let lines = self::line_ranges(text).collect::<Vec<_>>();
+
+        let mut line_ix = 0;
+        for i in 0..lines.len() {
+            let top = self.line_bounds(pos[1] + line_height * i as f32)[0];
+            if point[1] >= top {
+                line_ix = i;
+            }
+        }
+
+        let range = lines[line_ix].clone();
+        let y = pos[1] + line_height * line_ix as f32;
+        let glyphs = match self.glyph_iter_at([pos[0], y], &text[range.clone()]) {
+            Ok(glyphs) => glyphs,
+            Err(_) => return range.start,
+        };
+
+        for glyph in glyphs {
+            if point[0] < glyph.pen[0] + glyph.x_advance / 2.0 {
+                return range.start + glyph.byte_offset;
+            }
+        }
+
+        range.end
+    }
+
+    /// Returns the position of the caret in front of `byte_index`. The y coordinate is the `y`
+    /// the line is placed at.
+    pub fn caret_position(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        byte_index: usize,
+    ) -> [f32; 2] {
+        let pos = pos.into();
+        let line_height = self.vert_metrics().line_height;
+
+        let (line_ix, range) = self::line_ranges(text)
+            .enumerate()
+            .find(|(_, range)| byte_index <= range.end)
+            .unwrap_or_else(|| {
+                let n = self::line_ranges(text).count();
+                (n - 1, text.len()..text.len())
+            });
+
+        let y = pos[1] + line_height * line_ix as f32;
+        let mut x = pos[0];
+        if let Ok(glyphs) = self.glyph_iter_at([pos[0], y], &text[range.clone()]) {
+            // pen positions consider the horizontal alignment
+            for glyph in glyphs {
+                if range.start + glyph.byte_offset >= byte_index {
+                    return [glyph.pen[0], y];
+                }
+                x = glyph.pen[0] + glyph.x_advance;
+            }
+        }
+
+        [x, y]
+    }
+}
+
+/// Strips `\r` of `\r\n`
+fn trim_cr(line: &str) -> &str {
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Byte ranges of lines split with `\n` (excluding `\r` of `\r\n`)
+fn line_ranges(text: &str) -> impl Iterator<Item = core::ops::Range<usize>> + '_ {
+    let mut start = 0;
+    text.split('\n').map(move |line| {
+        let range = start..start + self::trim_cr(line).len();
+        start += line.len() + 1;
+        range
+    })
+}
+
+bitflags::bitflags! {
+    /// Lines drawn with text, set with [`FontStash::set_decoration`]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Decoration: u32 {
+        const UNDERLINE = 1 << 0;
+        const STRIKETHROUGH = 1 << 1;
+    }
 }
 
 bitflags::bitflags! {
     /// Alignment of each quadliteral returned by [`FontStash::text_iter`]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Align: u32 {
         const BASELINE = sys::FONSalign_FONS_ALIGN_BASELINE;
         const BOTTOM = sys::FONSalign_FONS_ALIGN_BOTTOM;
@@ -449,68 +1593,146 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum Flags {
-    BottomLeft = sys::FONSflags_FONS_ZERO_BOTTOMLEFT as u8,
-    TopLeft = sys::FONSflags_FONS_ZERO_TOPLEFT as u8,
+/// Horizontal part of [`Align`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HAlign {
+    Left,
+    Center,
+    Right,
 }
 
-/// Iterator of text quads
-pub struct FonsTextIter {
-    stash: FontStash,
-    iter: sys::FONStextIter,
-    is_running: bool,
+/// Vertical part of [`Align`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VAlign {
+    Top,
+    Middle,
+    Bottom,
+    Baseline,
 }
 
-impl FonsTextIter {
-    pub fn from_text(stash: FontStash, text: &str) -> Result<Self> {
-        unsafe {
-            // `FONStextIter` iterates through [start, end)
-            let start = text.as_ptr() as *const _;
-            let end = text.as_ptr().add(text.len()) as *const _;
-
-            let mut iter: sys::FONStextIter = std::mem::zeroed();
-            let res = sys::fonsTextIterInit(stash.raw(), &mut iter as *mut _, 0.0, 0.0, start, end);
+impl Align {
+    const H_MASK: Self =
+        Self::from_bits_truncate(Self::LEFT.bits() | Self::CENTER.bits() | Self::RIGHT.bits());
+    const V_MASK: Self = Self::from_bits_truncate(
+        Self::TOP.bits() | Self::MID.bits() | Self::BOTTOM.bits() | Self::BASELINE.bits(),
+    );
+
+    /// Alignment with exactly one horizontal and one vertical flag. Prefer it to combining the
+    /// flags by hand, which can produce combinations such as `LEFT | RIGHT`
+    pub const fn new(h: HAlign, v: VAlign) -> Self {
+        let h = match h {
+            HAlign::Left => Self::LEFT,
+            HAlign::Center => Self::CENTER,
+            HAlign::Right => Self::RIGHT,
+        };
+        let v = match v {
+            VAlign::Top => Self::TOP,
+            VAlign::Middle => Self::MID,
+            VAlign::Bottom => Self::BOTTOM,
+            VAlign::Baseline => Self::BASELINE,
+        };
+        Self::from_bits_truncate(h.bits() | v.bits())
+    }
 
-            if res == 0 {
-                // failed
-                return Err(FonsError::FoundNoFont());
-            }
+    /// The horizontal flag, or `None` if there's none or more than one of them
+    pub fn h_align(self) -> Option<HAlign> {
+        let h = self & Self::H_MASK;
+        if h == Self::LEFT {
+            Some(HAlign::Left)
+        } else if h == Self::CENTER {
+            Some(HAlign::Center)
+        } else if h == Self::RIGHT {
+            Some(HAlign::Right)
+        } else {
+            None
+        }
+    }
 
-            Ok(Self {
-                stash: stash.clone(),
-                iter,
-                is_running: res == 1,
-            })
+    /// The vertical flag, or `None` if there's none or more than one of them
+    pub fn v_align(self) -> Option<VAlign> {
+        let v = self & Self::V_MASK;
+        if v == Self::TOP {
+            Some(VAlign::Top)
+        } else if v == Self::MID {
+            Some(VAlign::Middle)
+        } else if v == Self::BOTTOM {
+            Some(VAlign::Bottom)
+        } else if v == Self::BASELINE {
+            Some(VAlign::Baseline)
+        } else {
+            None
         }
     }
+
+    /// True if it has exactly one horizontal and one vertical flag (see [`Align::new`])
+    pub fn is_valid(self) -> bool {
+        self.h_align().is_some() && self.v_align().is_some()
+    }
 }
 
-impl Iterator for FonsTextIter {
-    type Item = FonsQuad;
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum Flags {
+    BottomLeft = sys::FONSflags_FONS_ZERO_BOTTOMLEFT as u8,
+    TopLeft = sys::FONSflags_FONS_ZERO_TOPLEFT as u8,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if !self.is_running {
-            return None;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: [(HAlign, Align); 3] = [
+        (HAlign::Left, Align::LEFT),
+        (HAlign::Center, Align::CENTER),
+        (HAlign::Right, Align::RIGHT),
+    ];
+    const V: [(VAlign, Align); 4] = [
+        (VAlign::Top, Align::TOP),
+        (VAlign::Middle, Align::MID),
+        (VAlign::Bottom, Align::BOTTOM),
+        (VAlign::Baseline, Align::BASELINE),
+    ];
+
+    #[test]
+    fn align_new() {
+        for (h, h_flag) in H {
+            for (v, v_flag) in V {
+                let align = Align::new(h, v);
+                assert_eq!(align, h_flag | v_flag);
+                assert_eq!((align.h_align(), align.v_align()), (Some(h), Some(v)));
+                assert!(align.is_valid());
+            }
         }
+    }
 
-        let mut quad = unsafe { std::mem::zeroed() };
-
-        let res = unsafe {
-            sys::fonsTextIterNext(
-                self.stash.raw(),
-                &mut self.iter as *mut _,
-                &mut quad as *mut _,
-            )
-        };
-
-        if res == 1 {
-            // continue
-            Some(quad)
-        } else {
-            // end
-            None
+    #[test]
+    fn align_invalid() {
+        let invalid = [
+            Align::empty(),
+            Align::LEFT,
+            Align::BASELINE,
+            Align::LEFT | Align::RIGHT | Align::TOP,
+            Align::LEFT | Align::CENTER | Align::MID,
+            Align::CENTER | Align::TOP | Align::BOTTOM,
+            Align::RIGHT | Align::MID | Align::BASELINE,
+            Align::all(),
+        ];
+        for align in invalid {
+            assert!(!align.is_valid(), "{:?}", align);
         }
+
+        let align = Align::LEFT | Align::RIGHT | Align::TOP;
+        assert_eq!(
+            (align.h_align(), align.v_align()),
+            (None, Some(VAlign::Top))
+        );
+        let align = Align::CENTER | Align::TOP | Align::BOTTOM;
+        assert_eq!(
+            (align.h_align(), align.v_align()),
+            (Some(HAlign::Center), None)
+        );
     }
 }
@@ -0,0 +1,86 @@
+/*!
+Font files read on a thread, returned by [`FontStash::add_font_file_async`]
+
+It's runtime-agnostic: the file is read on a `std` thread and the future is woken when it's done.
+The font is added to the stash when the future is polled, so it stays on the thread of the stash.
+*/
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use std::{
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{FontIx, FontStash, Result};
+
+#[derive(Debug, Default)]
+struct Shared {
+    data: Option<io::Result<Vec<u8>>>,
+    waker: Option<Waker>,
+}
+
+/// Future of a font file being read. See [`FontStash::add_font_file_async`]
+#[derive(Debug)]
+pub struct FontFileFuture<'a> {
+    stash: &'a FontStash,
+    name: String,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl<'a> Future for FontFileFuture<'a> {
+    type Output = Result<FontIx>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut shared = this.shared.lock().unwrap_or_else(|e| e.into_inner());
+        match shared.data.take() {
+            Some(data) => {
+                drop(shared);
+                Poll::Ready(
+                    data.map_err(From::from)
+                        .and_then(|data| this.stash.add_font_mem_copy(&this.name, &data)),
+                )
+            }
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Async loading
+impl FontStash {
+    /// [`FontStash::add_font_file`] that doesn't block while the file is read
+    ///
+    /// Reading starts on a new thread right away. Await (or poll) the future on the thread of
+    /// the stash to add the font.
+    pub fn add_font_file_async(&self, name: &str, path: impl Into<PathBuf>) -> FontFileFuture<'_> {
+        let path = path.into();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let sender = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            let data = std::fs::read(path);
+            let mut shared = sender.lock().unwrap_or_else(|e| e.into_inner());
+            shared.data = Some(data);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+
+        FontFileFuture {
+            stash: self,
+            name: name.into(),
+            shared,
+        }
+    }
+}
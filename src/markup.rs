@@ -0,0 +1,259 @@
+/*!
+Mini-markup parsed into [`rich`](crate::rich) text
+
+```text
+Press [color=#ff0000]A[/color] to [b]jump[/b], [size=32]BIG[/size] [[not a tag]
+```
+
+* `[b]...[/b]`: bold font given with [`Markup::bold`]
+* `[color=#rrggbb]...[/color]` or `#rrggbbaa`
+* `[size=20]...[/size]`
+* `[blur=2]...[/blur]`
+* `[sup]...[/sup]`, `[sub]...[/sub]`: superscript and subscript
+* `[[`: literal `[`
+
+Tags have to be closed in the reverse order they're opened. Spans borrow the source text.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{
+    rich::{RichQuad, Span, Text},
+    Color, FonsError, FontIx, FontStash, Result,
+};
+
+/// Base style and fonts of markup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Markup {
+    pub font: FontIx,
+    pub bold: Option<FontIx>,
+    pub size: f32,
+    pub color: Color,
+    pub blur: f32,
+}
+
+impl Markup {
+    /// White text without blur nor bold font
+    pub fn new(font: FontIx, size: f32) -> Self {
+        Self {
+            font,
+            bold: None,
+            size,
+            color: Color::WHITE,
+            blur: 0.0,
+        }
+    }
+
+    pub fn bold(mut self, font: FontIx) -> Self {
+        self.bold = Some(font);
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<Color>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    pub fn blur(mut self, blur: f32) -> Self {
+        self.blur = blur;
+        self
+    }
+
+    /// Parses `src` into spans. Fails with [`FonsError::InvalidMarkup`]
+    pub fn parse<'a>(&self, src: &'a str) -> Result<Text<'a>> {
+        let mut text = Text::new();
+        // (tag name, position of the tag, style before the tag)
+        let mut stack: Vec<(&str, usize, Span<'a>)> = Vec::new();
+        let mut style = Span {
+            text: "",
+            font: self.font,
+            size: self.size,
+            color: self.color,
+            blur: self.blur,
+            baseline_shift: 0.0,
+        };
+
+        let push = |text: &mut Text<'a>, style: &Span<'a>, s: &'a str| {
+            if !s.is_empty() {
+                text.push(Span { text: s, ..*style });
+            }
+        };
+
+        let mut start = 0;
+        while let Some(offset) = src[start..].find('[') {
+            let open = start + offset;
+            push(&mut text, &style, &src[start..open]);
+
+            if src[open + 1..].starts_with('[') {
+                push(&mut text, &style, &src[open..open + 1]);
+                start = open + 2;
+                continue;
+            }
+
+            let close = match src[open..].find(']') {
+                Some(len) => open + len,
+                None => return Err(self::error(open, "unterminated tag")),
+            };
+            let tag = &src[open + 1..close];
+            start = close + 1;
+
+            if let Some(name) = tag.strip_prefix('/') {
+                match stack.pop() {
+                    Some((opened, _, prev)) if opened == name => style = prev,
+                    _ => return Err(self::error(open, "closing tag doesn't match")),
+                }
+                continue;
+            }
+
+            let (name, value) = match tag.find('=') {
+                Some(i) => (&tag[..i], Some(&tag[i + 1..])),
+                None => (tag, None),
+            };
+            let prev = style;
+            match (name, value) {
+                ("b", None) => {
+                    style.font = self.bold.ok_or_else(|| self::error(open, "no bold font"))?;
+                }
+                ("color", Some(v)) => {
+                    style.color =
+                        self::parse_color(v).ok_or_else(|| self::error(open, "invalid color"))?;
+                }
+                ("size", Some(v)) => {
+                    style.size = v.parse().map_err(|_| self::error(open, "invalid size"))?;
+                }
+                ("sup", None) => style = style.superscript(),
+                ("sub", None) => style = style.subscript(),
+                ("blur", Some(v)) => {
+                    style.blur = v.parse().map_err(|_| self::error(open, "invalid blur"))?;
+                }
+                _ => return Err(self::error(open, "unknown tag")),
+            }
+            stack.push((name, open, prev));
+        }
+        push(&mut text, &style, &src[start..]);
+
+        if let Some((_, pos, _)) = stack.last() {
+            return Err(self::error(*pos, "unclosed tag"));
+        }
+
+        Ok(text)
+    }
+}
+
+fn error(pos: usize, reason: &'static str) -> FonsError {
+    FonsError::InvalidMarkup { pos, reason }
+}
+
+/// `#rrggbb` or `#rrggbbaa`
+fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#')?;
+    // `from_str_radix` takes a sign
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bits = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(Color::from_hex(bits << 8 | 0xff)),
+        8 => Some(Color::from_hex(bits)),
+        _ => None,
+    }
+}
+
+/// Markup
+impl FontStash {
+    /// Parses the markup and lays it out with [`FontStash::layout_rich`]
+    pub fn layout_markup(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        src: &str,
+        markup: &Markup,
+        out: &mut Vec<RichQuad>,
+    ) -> Result<[f32; 2]> {
+        let text = markup.parse(src)?;
+        self.layout_rich(pos, &text, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: FontIx = FontIx(0, 0);
+    const BOLD: FontIx = FontIx(1, 0);
+
+    fn markup() -> Markup {
+        Markup::new(FONT, 20.0).bold(BOLD)
+    }
+
+    fn texts<'a>(text: &Text<'a>) -> Vec<&'a str> {
+        text.spans.iter().map(|s| s.text).collect()
+    }
+
+    fn error_of(src: &str) -> (usize, &'static str) {
+        match markup().parse(src) {
+            Err(FonsError::InvalidMarkup { pos, reason }) => (pos, reason),
+            res => panic!("{:?} for {:?}", res, src),
+        }
+    }
+
+    #[test]
+    fn nested_tags() {
+        let text = markup()
+            .parse("a[b]b[color=#ff000080]c[size=10]d[/size][/color]e[/b]f")
+            .unwrap();
+        assert_eq!(texts(&text), ["a", "b", "c", "d", "e", "f"]);
+
+        let fonts: Vec<_> = text.spans.iter().map(|s| s.font).collect();
+        assert_eq!(fonts, [FONT, BOLD, BOLD, BOLD, BOLD, FONT]);
+        let red = Color::rgba(255, 0, 0, 128);
+        assert_eq!(text.spans[2].color, red);
+        assert_eq!(text.spans[3].color, red);
+        assert_eq!(text.spans[3].size, 10.0);
+        // restored when the tags are closed
+        assert_eq!(text.spans[4].color, Color::WHITE);
+        assert_eq!(text.spans[4].size, 20.0);
+    }
+
+    #[test]
+    fn escaped_bracket() {
+        let text = markup().parse("[[b] [[[b]x[/b]").unwrap();
+        assert_eq!(texts(&text), ["[", "b] ", "[", "x"]);
+        assert_eq!(text.spans[3].font, BOLD);
+        assert_eq!(texts(&markup().parse("a]b").unwrap()), ["a]b"]);
+    }
+
+    #[test]
+    fn invalid_tags() {
+        assert_eq!(error_of("ab[b]c[/color]"), (6, "closing tag doesn't match"));
+        assert_eq!(
+            error_of("[b][sup]x[/b][/sup]"),
+            (9, "closing tag doesn't match")
+        );
+        assert_eq!(error_of("x[/b]"), (1, "closing tag doesn't match"));
+        assert_eq!(error_of("a[b]b[sup]c[/sup]"), (1, "unclosed tag"));
+        assert_eq!(error_of("abc[b"), (3, "unterminated tag"));
+        assert_eq!(error_of("a[i]b[/i]"), (1, "unknown tag"));
+        assert_eq!(error_of("[size]a[/size]"), (0, "unknown tag"));
+        assert_eq!(error_of("[b=1]a[/b]"), (0, "unknown tag"));
+        assert_eq!(error_of("[size=big]a[/size]"), (0, "invalid size"));
+        assert_eq!(
+            Markup::new(FONT, 20.0)
+                .parse("[b]a[/b]")
+                .unwrap_err()
+                .to_string(),
+            error(0, "no bold font").to_string()
+        );
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff8000"), Some(Color::rgba(255, 128, 0, 255)));
+        assert_eq!(parse_color("#FF800040"), Some(Color::rgba(255, 128, 0, 64)));
+        for s in [
+            "ff8000", "#ff800", "#ff80001", "#gg8000", "#+f8000", "#-f8000", "#ff 000",
+        ] {
+            assert_eq!(parse_color(s), None, "{}", s);
+        }
+        assert_eq!(error_of("[color=#+fffff]a[/color]"), (0, "invalid color"));
+    }
+}
@@ -0,0 +1,193 @@
+/*!
+Rust-side cache of text sizes and line metrics, used by [`FontStash::measure_cached`] and
+[`FontStash::line_metrics`]
+
+Immediate-mode UIs measure the same labels every frame. Cached sizes are keyed by the current
+font, size and spacing and a hash of the text. They're invalidated by bumping the generation when
+the atlas is reset, a font is reloaded, a fallback font is added or the text options are changed.
+*/
+
+use crate::{sys, FontIx, FontStash, LineMetrics, Map};
+
+/// Cached sizes are dropped at once when there are this many of them
+const CAPACITY: usize = 4096;
+
+/// `(font, size bits, spacing bits, hash of the text)`
+pub(crate) type MeasureKey = (i32, u32, u32, u64);
+
+/// `(font, size in physical pixels quantized as FontStash does)`
+pub(crate) type MetricsKey = (u32, i32);
+
+#[derive(Debug, Default)]
+pub(crate) struct MeasureCache {
+    pub generation: u64,
+    /// Key -> `(generation, [width, height])`
+    pub sizes: Map<MeasureKey, (u64, [f32; 2])>,
+    /// Key -> `(generation, metrics in physical pixels)`
+    pub line_metrics: Map<MetricsKey, (u64, LineMetrics)>,
+}
+
+impl MeasureCache {
+    /// Makes all the cached sizes stale
+    pub fn invalidate(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn get(&self, key: &MeasureKey) -> Option<[f32; 2]> {
+        match self.sizes.get(key) {
+            Some(&(gen, size)) if gen == self.generation => Some(size),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: MeasureKey, size: [f32; 2]) {
+        if self.sizes.len() >= CAPACITY {
+            self.sizes.clear();
+        }
+        self.sizes.insert(key, (self.generation, size));
+    }
+
+    pub fn get_metrics(&self, key: &MetricsKey) -> Option<LineMetrics> {
+        match self.line_metrics.get(key) {
+            Some(&(gen, metrics)) if gen == self.generation => Some(metrics),
+            _ => None,
+        }
+    }
+
+    pub fn insert_metrics(&mut self, key: MetricsKey, metrics: LineMetrics) {
+        if self.line_metrics.len() >= CAPACITY {
+            self.line_metrics.clear();
+        }
+        self.line_metrics.insert(key, (self.generation, metrics));
+    }
+}
+
+/// FNV-1a, which is available without `std`
+fn hash_text(text: &str) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for &b in text.as_bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Measure cache
+impl FontStash {
+    /// [`FontStash::text_size_oneline`] memoized on the Rust side
+    ///
+    /// Don't change fonts through `sys` directly, or clear the cache with
+    /// [`FontStash::clear_measure_cache`] after that.
+    pub fn measure_cached(&self, text: &str) -> [f32; 2] {
+        let info = self.state_info();
+        let key = (
+            info.font,
+            info.size.to_bits(),
+            info.spacing.to_bits(),
+            self::hash_text(text),
+        );
+
+        if let Some(size) = self.fons.measure_cache.borrow().get(&key) {
+            return size;
+        }
+
+        let size = self.text_size_oneline(text);
+        self.fons.measure_cache.borrow_mut().insert(key, size);
+        size
+    }
+
+    /// Vertical metrics of `font` at `size`, memoized on the Rust side
+    ///
+    /// Sizes are quantized to 0.1 physical pixels as FontStash does. The current state is not
+    /// changed.
+    pub fn line_metrics(&self, font: FontIx, size: f32) -> LineMetrics {
+        let dpi = self.dpi_scale();
+        let isize = (size * dpi * 10.0) as i32;
+        // metrics of a stale font are of no font
+        let key = (self.checked_font(font) as u32, isize);
+
+        let cached = self.fons.measure_cache.borrow().get_metrics(&key);
+        let metrics = match cached {
+            Some(metrics) => metrics,
+            None => {
+                let metrics = self.line_metrics_uncached(font, isize as f32 / 10.0);
+                let mut cache = self.fons.measure_cache.borrow_mut();
+                cache.insert_metrics(key, metrics);
+                metrics
+            }
+        };
+
+        LineMetrics {
+            ascender: metrics.ascender / dpi,
+            descender: metrics.descender / dpi,
+            line_height: metrics.line_height / dpi,
+        }
+    }
+
+    /// Metrics in physical pixels at the physical size
+    fn line_metrics_uncached(&self, font: FontIx, size: f32) -> LineMetrics {
+        let info = self.state_info();
+        let mut metrics = LineMetrics::default();
+        // restore the font and the size instead of pushing a state, which can overflow
+        unsafe {
+            sys::fonsSetFont(self.raw(), self.checked_font(font));
+            sys::fonsSetSize(self.raw(), size);
+            sys::fonsVertMetrics(
+                self.raw(),
+                &mut metrics.ascender,
+                &mut metrics.descender,
+                &mut metrics.line_height,
+            );
+            sys::fonsSetFont(self.raw(), info.font);
+            sys::fonsSetSize(self.raw(), info.size);
+        }
+        metrics
+    }
+
+    /// Drops the cached text sizes and line metrics
+    pub fn clear_measure_cache(&self) {
+        let mut cache = self.fons.measure_cache.borrow_mut();
+        cache.invalidate();
+        cache.sizes.clear();
+        cache.line_metrics.clear();
+    }
+
+    pub(crate) fn invalidate_measure_cache(&self) {
+        self.fons.measure_cache.borrow_mut().invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_font;
+
+    #[test]
+    fn measure_cached() {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_size(20.0);
+
+        let size = stash.text_size_oneline("a b");
+        assert_eq!(stash.measure_cached("a b"), size);
+        assert_eq!(stash.measure_cached("a b"), size);
+        assert_eq!(stash.fons.measure_cache.borrow().sizes.len(), 1);
+
+        // keyed by the size
+        stash.set_size(40.0);
+        assert_eq!(stash.measure_cached("a b")[0], 60.0);
+        stash.set_size(20.0);
+        assert_eq!(stash.measure_cached("a b"), size);
+
+        // invalidated by the text options and atlas resets
+        stash.set_word_spacing(5.0);
+        assert_eq!(stash.measure_cached("a b")[0], 35.0);
+        stash.set_word_spacing(0.0);
+        let generation = stash.fons.measure_cache.borrow().generation;
+        stash.reset_atlas(256, 256).unwrap();
+        assert!(stash.fons.measure_cache.borrow().generation != generation);
+        assert_eq!(stash.measure_cached("a b"), size);
+
+        stash.clear_measure_cache();
+        assert!(stash.fons.measure_cache.borrow().sizes.is_empty());
+    }
+}
@@ -0,0 +1,560 @@
+/*!
+Triangle meshes of glyph outlines, e.g. for extruded 3D text
+
+```text
+let mesh = stash.glyph_mesh(font, 'A', 4.0)?;
+// front and back faces: `mesh.vertices` at two depths with `mesh.indices`
+// side walls: two triangles between each pair of consecutive points of `mesh.contours`
+```
+
+Curves of the outline (see [`crate::outline`]) are flattened within the tolerance, and the
+polygons are triangulated by ear clipping with the holes bridged to the contours around them.
+Contours overlapping each other (e.g. components of some composite glyphs) are triangulated
+separately, so their triangles overlap too.
+*/
+
+use core::cmp::Ordering;
+
+use alloc::{vec, vec::Vec};
+
+use crate::{outline::PathSegment, FontIx, FontStash, Result};
+
+/// Maximum number of line segments a curve is flattened into
+const MAX_SUBDIVISIONS: usize = 64;
+
+/// Triangulated glyph
+///
+/// * `vertices`: points of the flattened outline
+/// * `indices`: triangles, three indices into `vertices` each
+/// * `contours`: closed loops of indices into `vertices`. Outer contours have positive signed
+///   area (counter-clockwise in y-up coordinates) and holes have negative signed area, and the
+///   triangles are oriented like the outer contours
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+    pub contours: Vec<Vec<u32>>,
+}
+
+impl Mesh {
+    /// Flattens and triangulates the outline. `tolerance` is the maximum distance between the
+    /// curves and their line segments, in the unit of the outline
+    ///
+    /// Contours are filled with the non-zero rule assuming they don't intersect: the orientation
+    /// of the largest contour is taken as the one of outer contours, and contours of the other
+    /// orientation are holes of the smallest outer contour containing them.
+    pub fn from_outline(outline: &[PathSegment], tolerance: f32) -> Self {
+        let mut mesh = Self::default();
+        let contours = self::flatten(outline, tolerance, &mut mesh.vertices);
+
+        let areas = contours
+            .iter()
+            .map(|c| self::signed_area(&mesh.vertices, c))
+            .collect::<Vec<_>>();
+        let outer_sign = match areas
+            .iter()
+            .copied()
+            .max_by(|a, b| abs(*a).partial_cmp(&abs(*b)).unwrap_or(Ordering::Equal))
+        {
+            Some(area) if area > 0.0 => 1.0,
+            Some(area) if area < 0.0 => -1.0,
+            _ => return mesh,
+        };
+
+        // `(contour, |area|)` with the orientations normalized
+        let mut outers = Vec::new();
+        let mut holes = Vec::new();
+        for (mut contour, area) in contours.into_iter().zip(areas) {
+            if area == 0.0 {
+                continue;
+            }
+            let is_outer = area * outer_sign > 0.0;
+            if (area > 0.0) != is_outer {
+                contour.reverse();
+            }
+            if is_outer {
+                outers.push((contour, abs(area)));
+            } else {
+                holes.push((contour, abs(area)));
+            }
+        }
+
+        let mut holes_of = vec![Vec::new(); outers.len()];
+        for (hole, _) in &holes {
+            let p = mesh.vertices[hole[0] as usize];
+            let container = outers
+                .iter()
+                .enumerate()
+                .filter(|(_, (outer, _))| self::contains(&mesh.vertices, outer, p))
+                .min_by(|(_, (_, a)), (_, (_, b))| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                .map(|(i, _)| i);
+            // holes outside of any outer contour don't cover anything
+            if let Some(i) = container {
+                holes_of[i].push(hole.as_slice());
+            }
+        }
+
+        for ((outer, _), holes) in outers.iter().zip(&mut holes_of) {
+            let polygon = self::bridge_holes(&mesh.vertices, outer, holes);
+            self::ear_clip(&mesh.vertices, &polygon, &mut mesh.indices);
+        }
+
+        mesh.contours = outers.into_iter().chain(holes).map(|(c, _)| c).collect();
+        mesh
+    }
+
+    /// Number of triangles
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// Meshes
+impl FontStash {
+    /// Triangulated [`FontStash::glyph_outline`] in font units (y-up), with `tolerance` in font
+    /// units
+    ///
+    /// Scale it by `size / units_per_em` (see [`FontStash::font_info`]) for the size, or make a
+    /// mesh of [`FontStash::glyph_outline_at`] with [`Mesh::from_outline`] for pixels.
+    pub fn glyph_mesh(&self, font: FontIx, c: char, tolerance: f32) -> Result<Mesh> {
+        let outline = self.glyph_outline(font, c)?;
+        Ok(Mesh::from_outline(&outline, tolerance))
+    }
+}
+
+fn abs(x: f64) -> f64 {
+    if x < 0.0 {
+        -x
+    } else {
+        x
+    }
+}
+
+fn point(vertices: &[[f32; 2]], i: u32) -> [f64; 2] {
+    let [x, y] = vertices[i as usize];
+    [x as f64, y as f64]
+}
+
+/// Twice the signed area of the triangle, positive if it's counter-clockwise in y-up coordinates
+fn orient(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn signed_area(vertices: &[[f32; 2]], contour: &[u32]) -> f64 {
+    let n = contour.len();
+    let sum: f64 = (0..n)
+        .map(|i| {
+            let [x0, y0] = self::point(vertices, contour[i]);
+            let [x1, y1] = self::point(vertices, contour[(i + 1) % n]);
+            x0 * y1 - x1 * y0
+        })
+        .sum();
+    sum / 2.0
+}
+
+/// Even-odd test of the point against the contour
+fn contains(vertices: &[[f32; 2]], contour: &[u32], p: [f32; 2]) -> bool {
+    let [px, py] = [p[0] as f64, p[1] as f64];
+    let n = contour.len();
+    let mut is_inside = false;
+    for i in 0..n {
+        let [x0, y0] = self::point(vertices, contour[i]);
+        let [x1, y1] = self::point(vertices, contour[(i + 1) % n]);
+        if (y0 > py) != (y1 > py) && px < x0 + (py - y0) * (x1 - x0) / (y1 - y0) {
+            is_inside = !is_inside;
+        }
+    }
+    is_inside
+}
+
+/// If `p` is in the triangle or on its edges, whatever its orientation is
+fn in_triangle(a: [f64; 2], b: [f64; 2], c: [f64; 2], p: [f64; 2]) -> bool {
+    let [d0, d1, d2] = [orient(a, b, p), orient(b, c, p), orient(c, a, p)];
+    let has_neg = d0 < 0.0 || d1 < 0.0 || d2 < 0.0;
+    let has_pos = d0 > 0.0 || d1 > 0.0 || d2 > 0.0;
+    !(has_neg && has_pos)
+}
+
+/// Closed polylines of the outline. Their points are appended to `vertices`
+fn flatten(outline: &[PathSegment], tolerance: f32, vertices: &mut Vec<[f32; 2]>) -> Vec<Vec<u32>> {
+    let tolerance = if tolerance > 0.0 {
+        tolerance
+    } else {
+        f32::EPSILON
+    };
+    // number of segments for a curve whose distance to `n` segments is at most `d / n²`
+    let subdivisions = |d: f32| {
+        let n = crate::sqrt(d / tolerance);
+        (n as usize + 1).clamp(1, MAX_SUBDIVISIONS)
+    };
+    let len = |[x, y]: [f32; 2]| crate::sqrt(x * x + y * y);
+
+    let mut contours = Vec::new();
+    let mut points = Vec::new();
+    let mut pen = [0.0, 0.0];
+    for seg in outline {
+        match *seg {
+            PathSegment::MoveTo(p) => {
+                self::close(&mut points, vertices, &mut contours);
+                points.push(p);
+                pen = p;
+            }
+            PathSegment::LineTo(p) => {
+                points.push(p);
+                pen = p;
+            }
+            PathSegment::QuadTo(c, p) => {
+                // the distance is at most `|p0 - 2c + p1| / 4n²`
+                let dd = len([pen[0] - 2.0 * c[0] + p[0], pen[1] - 2.0 * c[1] + p[1]]);
+                let n = subdivisions(dd / 4.0);
+                for i in 1..=n {
+                    let t = i as f32 / n as f32;
+                    let u = 1.0 - t;
+                    let [a, b, d] = [u * u, 2.0 * u * t, t * t];
+                    points.push([
+                        a * pen[0] + b * c[0] + d * p[0],
+                        a * pen[1] + b * c[1] + d * p[1],
+                    ]);
+                }
+                pen = p;
+            }
+            PathSegment::CubicTo(c0, c1, p) => {
+                // the distance is at most `3 max(|p0 - 2c0 + c1|, |c0 - 2c1 + p1|) / 4n²`
+                let dd0 = len([pen[0] - 2.0 * c0[0] + c1[0], pen[1] - 2.0 * c0[1] + c1[1]]);
+                let dd1 = len([c0[0] - 2.0 * c1[0] + p[0], c0[1] - 2.0 * c1[1] + p[1]]);
+                let n = subdivisions(0.75 * dd0.max(dd1));
+                for i in 1..=n {
+                    let t = i as f32 / n as f32;
+                    let u = 1.0 - t;
+                    let [a, b, d, e] = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+                    points.push([
+                        a * pen[0] + b * c0[0] + d * c1[0] + e * p[0],
+                        a * pen[1] + b * c0[1] + d * c1[1] + e * p[1],
+                    ]);
+                }
+                pen = p;
+            }
+            PathSegment::Close => self::close(&mut points, vertices, &mut contours),
+        }
+    }
+    self::close(&mut points, vertices, &mut contours);
+
+    contours
+}
+
+/// Moves the points of a contour into `vertices` without repeated points
+fn close(points: &mut Vec<[f32; 2]>, vertices: &mut Vec<[f32; 2]>, contours: &mut Vec<Vec<u32>>) {
+    points.dedup();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() >= 3 {
+        let start = vertices.len() as u32;
+        vertices.append(points);
+        contours.push((start..vertices.len() as u32).collect());
+    }
+    points.clear();
+}
+
+/// Merges the holes into the outer contour with bridges of two edges each, from the rightmost
+/// hole (the same as `earcut`, mirrored)
+fn bridge_holes(vertices: &[[f32; 2]], outer: &[u32], holes: &mut [&[u32]]) -> Vec<u32> {
+    let max_x = |hole: &[u32]| {
+        hole.iter()
+            .map(|&i| vertices[i as usize][0])
+            .fold(f32::NEG_INFINITY, f32::max)
+    };
+    holes.sort_by(|a, b| max_x(b).partial_cmp(&max_x(a)).unwrap_or(Ordering::Equal));
+
+    let mut polygon = outer.to_vec();
+    for hole in holes.iter() {
+        // rightmost vertex of the hole
+        let m_ix = (0..hole.len())
+            .max_by(|&a, &b| {
+                let [pa, pb] = [point(vertices, hole[a]), point(vertices, hole[b])];
+                pa[0].partial_cmp(&pb[0]).unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(0);
+        let m = point(vertices, hole[m_ix]);
+
+        let k = match self::find_bridge(vertices, &polygon, m) {
+            Some(k) => k,
+            None => continue,
+        };
+
+        let mut merged = Vec::with_capacity(polygon.len() + hole.len() + 2);
+        merged.extend_from_slice(&polygon[..=k]);
+        merged.extend_from_slice(&hole[m_ix..]);
+        merged.extend_from_slice(&hole[..=m_ix]);
+        merged.extend_from_slice(&polygon[k..]);
+        polygon = merged;
+    }
+
+    polygon
+}
+
+/// Position of the polygon vertex visible from `m` to the right
+fn find_bridge(vertices: &[[f32; 2]], polygon: &[u32], m: [f64; 2]) -> Option<usize> {
+    let n = polygon.len();
+    let p = |i: usize| point(vertices, polygon[i % n]);
+
+    // nearest edge hit by the ray to the right. The interior is on the left of the edges, so
+    // edges facing the hole go upwards
+    let mut hit = None;
+    let mut hit_x = f64::INFINITY;
+    for i in 0..n {
+        let [a, b] = [p(i), p(i + 1)];
+        if a[1] <= m[1] && m[1] <= b[1] && a[1] != b[1] {
+            let x = a[0] + (m[1] - a[1]) * (b[0] - a[0]) / (b[1] - a[1]);
+            if m[0] <= x && x < hit_x {
+                hit_x = x;
+                hit = Some(if a[0] > b[0] { i } else { (i + 1) % n });
+            }
+        }
+    }
+    let mut k = hit?;
+    if hit_x == m[0] {
+        // the hole touches the edge
+        return Some(k);
+    }
+
+    // vertices in the triangle of the ray and the endpoint could hide it. Take the one with the
+    // smallest angle to the ray
+    let i = [hit_x, m[1]];
+    let end = p(k);
+    let mut min_tan = f64::INFINITY;
+    for j in 0..n {
+        let q = p(j);
+        if q[0] < m[0] || q[0] > end[0] || q[0] == m[0] || !in_triangle(m, i, end, q) {
+            continue;
+        }
+        let tan = abs(q[1] - m[1]) / (q[0] - m[0]);
+        let is_closer = tan < min_tan || (tan == min_tan && q[0] < p(k)[0]);
+        if is_closer && self::locally_inside(p(j + n - 1), q, p(j + 1), m) {
+            k = j;
+            min_tan = tan;
+        }
+    }
+
+    Some(k)
+}
+
+/// If `m` is on the interior side of the corner `a`-`p`-`b` of a polygon with positive area
+fn locally_inside(a: [f64; 2], p: [f64; 2], b: [f64; 2], m: [f64; 2]) -> bool {
+    if orient(a, p, b) >= 0.0 {
+        orient(p, b, m) >= 0.0 && orient(p, m, a) >= 0.0
+    } else {
+        orient(p, b, m) >= 0.0 || orient(p, m, a) >= 0.0
+    }
+}
+
+/// Triangulates the simple polygon with positive area into `indices`
+fn ear_clip(vertices: &[[f32; 2]], polygon: &[u32], indices: &mut Vec<u32>) {
+    let n = polygon.len();
+    if n < 3 {
+        return;
+    }
+    let p = |i: usize| point(vertices, polygon[i]);
+    let mut prev = (0..n).map(|i| (i + n - 1) % n).collect::<Vec<_>>();
+    let mut next = (0..n).map(|i| (i + 1) % n).collect::<Vec<_>>();
+
+    let mut remaining = n;
+    let mut i = 0;
+    // vertices tested since the last clip
+    let mut stall = 0;
+    // clips convex vertices even if they're not ears, after a round without ears (degenerate
+    // polygons)
+    let mut force = false;
+    while remaining > 3 {
+        let [a, c] = [prev[i], next[i]];
+        let area = orient(p(a), p(i), p(c));
+        let is_ear = area > 0.0 && {
+            let [pa, pb, pc] = [p(a), p(i), p(c)];
+            let mut j = next[c];
+            let mut is_blocked = false;
+            while j != a {
+                let q = p(j);
+                if q != pa && q != pb && q != pc && in_triangle(pa, pb, pc, q) {
+                    is_blocked = true;
+                    break;
+                }
+                j = next[j];
+            }
+            !is_blocked
+        };
+
+        // collinear points are removed without a triangle
+        if area == 0.0 || is_ear || (force && area > 0.0) {
+            if area > 0.0 {
+                indices.extend_from_slice(&[polygon[a], polygon[i], polygon[c]]);
+            }
+            next[a] = c;
+            prev[c] = a;
+            remaining -= 1;
+            i = c;
+            stall = 0;
+            force = false;
+        } else {
+            i = c;
+            stall += 1;
+            if stall > remaining {
+                if force {
+                    // no convex vertex is left
+                    return;
+                }
+                force = true;
+                stall = 0;
+            }
+        }
+    }
+
+    let [a, c] = [prev[i], next[i]];
+    if orient(p(a), p(i), p(c)) > 0.0 {
+        indices.extend_from_slice(&[polygon[a], polygon[i], polygon[c]]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Closed polygon through the points
+    fn polygon(points: &[[f32; 2]]) -> Vec<PathSegment> {
+        let mut outline = vec![PathSegment::MoveTo(points[0])];
+        outline.extend(points[1..].iter().map(|&p| PathSegment::LineTo(p)));
+        outline.push(PathSegment::Close);
+        outline
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<[f32; 2]> {
+        vec![[x, y], [x + size, y], [x + size, y + size], [x, y + size]]
+    }
+
+    /// Twice the signed areas of the triangles
+    fn triangle_areas(mesh: &Mesh) -> Vec<f64> {
+        mesh.indices
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| point(&mesh.vertices, i));
+                orient(a, b, c)
+            })
+            .collect()
+    }
+
+    /// Checks that the triangles are counter-clockwise and returns their total area
+    fn covered_area(mesh: &Mesh) -> f64 {
+        let areas = triangle_areas(mesh);
+        assert!(areas.iter().all(|&a| a > 0.0), "{:?}", areas);
+        areas.iter().sum::<f64>() / 2.0
+    }
+
+    #[test]
+    fn convex() {
+        let mesh = Mesh::from_outline(&polygon(&square(0.0, 0.0, 2.0)), 0.1);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(covered_area(&mesh), 4.0);
+        assert_eq!(mesh.contours, [vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn concave() {
+        let l = [
+            [0.0, 0.0],
+            [3.0, 0.0],
+            [3.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 3.0],
+            [0.0, 3.0],
+        ];
+        let mesh = Mesh::from_outline(&polygon(&l), 0.1);
+        assert_eq!(mesh.triangle_count(), 4);
+        assert_eq!(covered_area(&mesh), 5.0);
+    }
+
+    #[test]
+    fn winding_order() {
+        let mut cw = square(0.0, 0.0, 2.0);
+        cw.reverse();
+        let mesh = Mesh::from_outline(&polygon(&cw), 0.1);
+        // normalized to counter-clockwise
+        assert_eq!(covered_area(&mesh), 4.0);
+        assert!(signed_area(&mesh.vertices, &mesh.contours[0]) > 0.0);
+    }
+
+    #[test]
+    fn hole() {
+        let mut inner = square(1.0, 1.0, 2.0);
+        inner.reverse();
+        let mut outline = polygon(&square(0.0, 0.0, 4.0));
+        outline.extend(polygon(&inner));
+
+        // both the counter-clockwise convention and the clockwise one of TrueType
+        let reversed = outline
+            .iter()
+            .map(|seg| seg.map(|[x, y]| [x, -y]))
+            .collect::<Vec<_>>();
+        for outline in [outline, reversed] {
+            let mesh = Mesh::from_outline(&outline, 0.1);
+            assert_eq!(covered_area(&mesh), 12.0);
+            assert_eq!(mesh.contours.len(), 2);
+            assert!(signed_area(&mesh.vertices, &mesh.contours[0]) > 0.0);
+            assert!(signed_area(&mesh.vertices, &mesh.contours[1]) < 0.0);
+
+            // no triangle covers the hole
+            let hole = &mesh.contours[1];
+            for t in mesh.indices.chunks(3) {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.vertices[i as usize]);
+                let center = [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0];
+                assert!(!contains(&mesh.vertices, hole, center), "{:?}", t);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate() {
+        // repeated points, collinear points and the first point repeated at the end
+        let points = [
+            [0.0, 0.0],
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 2.0],
+            [2.0, 2.0],
+            [1.0, 2.0],
+            [0.0, 2.0],
+            [0.0, 1.0],
+            [0.0, 0.0],
+        ];
+        let mesh = Mesh::from_outline(&polygon(&points), 0.1);
+        assert_eq!(mesh.vertices.len(), 7);
+        assert_eq!(covered_area(&mesh), 4.0);
+
+        // no area
+        let line = Mesh::from_outline(&polygon(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]]), 0.1);
+        assert!(line.indices.is_empty() && line.contours.is_empty());
+        let point = polygon(&[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0]]);
+        assert_eq!(Mesh::from_outline(&point, 0.1), Mesh::default());
+        assert_eq!(Mesh::from_outline(&[], 0.1), Mesh::default());
+    }
+
+    #[test]
+    fn curves() {
+        // circle of two quadratic curves, flattened within the tolerance
+        let outline = [
+            PathSegment::MoveTo([-1.0, 0.0]),
+            PathSegment::QuadTo([-1.0, -1.0], [0.0, -1.0]),
+            PathSegment::QuadTo([1.0, -1.0], [1.0, 0.0]),
+            PathSegment::QuadTo([1.0, 1.0], [0.0, 1.0]),
+            PathSegment::QuadTo([-1.0, 1.0], [-1.0, 0.0]),
+            PathSegment::Close,
+        ];
+        let coarse = Mesh::from_outline(&outline, 0.1);
+        let fine = Mesh::from_outline(&outline, 0.001);
+        assert!(coarse.vertices.len() < fine.vertices.len());
+        // the diamond of the end points and four parabolic segments of 2/3 of their triangles
+        let exact = 2.0 + 4.0 / 3.0;
+        assert!((covered_area(&fine) - exact).abs() < 0.01);
+        assert!(covered_area(&coarse) < covered_area(&fine));
+    }
+}
@@ -0,0 +1,117 @@
+/*!
+[`Renderer`] that records the callbacks, for testing text pipelines without a GPU
+
+```text
+let mut renderer = Box::new(MockRenderer::default());
+let stash = FontStash::uninitialized().init(512, 512, renderer.as_mut())?;
+// .. draw text ..
+assert!(renderer.calls.iter().any(|c| matches!(c, MockCall::Update { .. })));
+```
+*/
+
+use core::ffi::{c_int, c_uchar, c_void};
+
+use alloc::vec::Vec;
+
+use crate::Renderer;
+
+/// Callback recorded by [`MockRenderer`]
+///
+/// * `rect`: `[x0, y0, x1, y1]` of the updated region
+/// * `checksum`: FNV-1a hash of the pixels in the region (row by row)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockCall {
+    Create { width: u32, height: u32 },
+    Resize { width: u32, height: u32 },
+    Expand,
+    Update { rect: [i32; 4], checksum: u32 },
+}
+
+/// [`Renderer`] that records every call into `calls`
+///
+/// Set the `fail_*` flags to make the callbacks return failure. `expand` can't reach the stash to
+/// expand the atlas, so it always fails (the atlas stays full).
+#[derive(Debug, Clone, Default)]
+pub struct MockRenderer {
+    pub calls: Vec<MockCall>,
+    pub fail_create: bool,
+    pub fail_resize: bool,
+    pub fail_update: bool,
+    /// Width of the texture, used to read updated regions
+    width: u32,
+}
+
+impl MockRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of the recorded [`MockCall::Update`]s
+    pub fn update_count(&self) -> usize {
+        self.calls
+            .iter()
+            .filter(|c| matches!(c, MockCall::Update { .. }))
+            .count()
+    }
+}
+
+unsafe fn mock<'a>(uptr: *mut c_void) -> &'a mut MockRenderer {
+    &mut *(uptr as *mut MockRenderer)
+}
+
+fn checksum(rect: [i32; 4], data: *const c_uchar, width: u32) -> u32 {
+    let mut hash = 0x811c_9dc5_u32;
+    if data.is_null() {
+        return hash;
+    }
+    for y in rect[1]..rect[3] {
+        for x in rect[0]..rect[2] {
+            let b = unsafe { *data.add((y as u32 * width + x as u32) as usize) };
+            hash ^= b as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+    }
+    hash
+}
+
+unsafe impl Renderer for MockRenderer {
+    unsafe extern "C" fn create(uptr: *mut c_void, width: c_int, height: c_int) -> c_int {
+        let mock = self::mock(uptr);
+        mock.calls.push(MockCall::Create {
+            width: width as u32,
+            height: height as u32,
+        });
+        mock.width = width as u32;
+        !mock.fail_create as c_int
+    }
+
+    unsafe extern "C" fn resize(uptr: *mut c_void, width: c_int, height: c_int) -> c_int {
+        let mock = self::mock(uptr);
+        mock.calls.push(MockCall::Resize {
+            width: width as u32,
+            height: height as u32,
+        });
+        mock.width = width as u32;
+        !mock.fail_resize as c_int
+    }
+
+    unsafe extern "C" fn expand(uptr: *mut c_void) -> c_int {
+        self::mock(uptr).calls.push(MockCall::Expand);
+        0
+    }
+
+    unsafe extern "C" fn update(
+        uptr: *mut c_void,
+        rect: *mut c_int,
+        data: *const c_uchar,
+    ) -> c_int {
+        let mock = self::mock(uptr);
+        let r = core::slice::from_raw_parts(rect, 4);
+        let rect = [r[0], r[1], r[2], r[3]];
+        mock.calls.push(MockCall::Update {
+            rect,
+            checksum: self::checksum(rect, data, mock.width),
+        });
+        !mock.fail_update as c_int
+    }
+}
@@ -0,0 +1,182 @@
+/*!
+[notan](https://github.com/Nazariglez/notan) integration, enabled with the `notan` feature
+
+[`NotanAtlas`] keeps the atlas in a notan [`Texture`], and [`append_quads`] turns quads into vertex
+and index data for a pipeline of the app made with [`vertex_info`]:
+
+```text
+// once
+stash.set_update_aggregation(true);
+let mut atlas = NotanAtlas::new(gfx, &stash)?;
+let pipeline = gfx.create_pipeline().from(&vert, &frag).with_vertex_info(&vertex_info())
+    .with_color_blend(BlendMode::NORMAL).with_texture_location(0, "u_texture").build()?;
+
+// every frame
+let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+let quads = stash.text_iter_at([10.0, 10.0], "こんにちは")?.collect::<Vec<_>>();
+append_quads(&mut vertices, &mut indices, quads, Color::WHITE);
+// upload the glyphs rasterized while laying out the text
+atlas.update(gfx, &stash)?;
+// .. draw `vertices` and `indices` with the pipeline, binding `atlas.texture()` ..
+```
+
+The module only depends on the graphics API of notan. `notan::draw` is not used because it
+compiles its GLSL shaders at build time (`glsl-to-spirv` or `shaderc`, which need CMake and a C++
+toolchain). Apps using `notan::draw` anyway can draw the atlas texture with `Draw::image` and
+the texture coordinates of the quads.
+
+The texture is RGBA8 with straight alpha (see [`AlphaMode::Straight`]), so blend with
+`BlendMode::NORMAL`.
+*/
+
+use alloc::vec::Vec;
+
+use ::notan::prelude::{Color as NotanColor, Graphics, Texture, VertexFormat, VertexInfo};
+
+use crate::{
+    vertex::{self, QUAD_INDICES},
+    AlphaMode, Color, FonsError, FontStash, Quad, Result,
+};
+
+/// The atlas uploaded to a notan [`Texture`]
+#[derive(Debug)]
+pub struct NotanAtlas {
+    texture: Texture,
+    size: [u32; 2],
+    rgba: Vec<u8>,
+}
+
+impl NotanAtlas {
+    /// Uploads the whole atlas as a new texture
+    ///
+    /// Fails with [`FonsError::TextureError`] if notan failed to create the texture.
+    pub fn new(gfx: &mut Graphics, stash: &FontStash) -> Result<Self> {
+        let mut rgba = Vec::new();
+        let size = stash.pixels_rgba_into(AlphaMode::Straight, &mut rgba)?;
+        let texture = self::create_texture(gfx, &rgba, size)?;
+        // the dirty region is included in the whole upload
+        stash.take_dirty_rect();
+
+        Ok(Self {
+            texture,
+            size,
+            rgba,
+        })
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// `[width, height]` of the texture
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Uploads the changes of the atlas. Call it after laying out the text of the frame
+    ///
+    /// Only the region of [`FontStash::take_dirty_rect`] is uploaded if the atlas size is not
+    /// changed. The texture is recreated when the atlas is resized. Without
+    /// [`FontStash::set_update_aggregation`], there's no dirty region, so the whole atlas is
+    /// uploaded every time.
+    pub fn update(&mut self, gfx: &mut Graphics, stash: &FontStash) -> Result<()> {
+        let size = stash.atlas_size();
+        let dirty = stash.take_dirty_rect();
+
+        if size != self.size {
+            self.size = stash.pixels_rgba_into(AlphaMode::Straight, &mut self.rgba)?;
+            self.texture = self::create_texture(gfx, &self.rgba, self.size)?;
+            return Ok(());
+        }
+
+        let [x0, y0, x1, y1] = match dirty {
+            Some(rect) => rect,
+            None => [0, 0, size[0], size[1]],
+        };
+        let [w, h] = [x1 - x0, y1 - y0];
+        self.rgba.clear();
+        self.rgba.resize((w * h * 4) as usize, 0);
+        stash.copy_texture_region_rgba([x0, y0, x1, y1], AlphaMode::Straight, &mut self.rgba)?;
+
+        gfx.update_texture(&mut self.texture)
+            .with_data(&self.rgba)
+            .with_x_offset(x0)
+            .with_y_offset(y0)
+            .with_width(w)
+            .with_height(h)
+            .update()
+            .map_err(FonsError::TextureError)
+    }
+}
+
+fn create_texture(gfx: &mut Graphics, rgba: &[u8], size: [u32; 2]) -> Result<Texture> {
+    gfx.create_texture()
+        .from_bytes(rgba, size[0], size[1])
+        .build()
+        .map_err(FonsError::TextureError)
+}
+
+pub fn notan_color(color: impl Into<Color>) -> NotanColor {
+    let c = color.into();
+    NotanColor::from_bytes(c.r, c.g, c.b, c.a)
+}
+
+/// Layout of the vertices of [`append_quads`]: the position (`Float32x2`) at location 0, the
+/// texture coordinates (`Float32x2`) at location 1 and the straight RGBA color (`Float32x4`) at
+/// location 2
+pub fn vertex_info() -> VertexInfo {
+    VertexInfo::new()
+        .attr(0, VertexFormat::Float32x2)
+        .attr(1, VertexFormat::Float32x2)
+        .attr(2, VertexFormat::Float32x4)
+}
+
+/// Appends four vertices per quad to `vertices` (`[x, y, u, v, r, g, b, a]` each, see
+/// [`vertex_info`]) and two triangles per quad to `indices`
+pub fn append_quads(
+    vertices: &mut Vec<f32>,
+    indices: &mut Vec<u32>,
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    color: impl Into<Color>,
+) {
+    let c = color.into();
+    let rgba = [c.r, c.g, c.b, c.a].map(|x| x as f32 / 255.0);
+
+    for q in quads {
+        let q = q.into();
+        if q.is_empty() {
+            continue;
+        }
+
+        let base = (vertices.len() / 8) as u32;
+        for (pos, uv) in vertex::corners(&q) {
+            vertices.extend_from_slice(&pos);
+            vertices.extend_from_slice(&uv);
+            vertices.extend_from_slice(&rgba);
+        }
+        indices.extend(QUAD_INDICES.iter().map(|i| base + i));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_vertices() {
+        let quad = Quad::new([1.0, 2.0, 3.0, 4.0], [0.0, 0.5, 0.25, 1.0]);
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        append_quads(
+            &mut vertices,
+            &mut indices,
+            [Quad::default(), quad, quad],
+            Color::WHITE,
+        );
+
+        // the empty quad is skipped
+        assert_eq!(vertices.len(), 2 * 4 * 8);
+        assert_eq!(indices, [0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+        assert_eq!(&vertices[..8], [1.0, 2.0, 0.0, 0.5, 1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(&vertices[16..20], [3.0, 4.0, 0.25, 1.0]);
+    }
+}
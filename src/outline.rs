@@ -0,0 +1,143 @@
+/*!
+Vector outlines of glyphs, e.g. for tessellation, path effects or vector export
+
+```text
+for seg in stash.glyph_outline_at(font, 'g', 48.0)? {
+    match seg {
+        PathSegment::MoveTo(p) => path.move_to(p),
+        PathSegment::LineTo(p) => path.line_to(p),
+        PathSegment::QuadTo(c, p) => path.quad_to(c, p),
+        PathSegment::CubicTo(c0, c1, p) => path.cubic_to(c0, c1, p),
+        PathSegment::Close => path.close(),
+    }
+}
+```
+
+The outlines are read from the font data of the stash (`stbtt_GetGlyphShape` or
+`FT_Outline_Decompose` with the `freetype` feature), so no font parser is needed on the Rust
+side. TrueType fonts have quadratic curves and CFF fonts have cubic curves.
+*/
+
+use core::ffi::c_int;
+
+use alloc::vec::Vec;
+
+use crate::{sys, FonsError, FontIx, FontStash, Result};
+
+/// Segment of a glyph outline. Points are `[x, y]`
+///
+/// * `QuadTo(control, to)`
+/// * `CubicTo(control0, control1, to)`
+/// * `Close`: closes the contour started by the last `MoveTo`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    QuadTo([f32; 2], [f32; 2]),
+    CubicTo([f32; 2], [f32; 2], [f32; 2]),
+    Close,
+}
+
+impl PathSegment {
+    /// Maps the points of the segment with `f`
+    pub fn map(self, mut f: impl FnMut([f32; 2]) -> [f32; 2]) -> Self {
+        match self {
+            Self::MoveTo(p) => Self::MoveTo(f(p)),
+            Self::LineTo(p) => Self::LineTo(f(p)),
+            Self::QuadTo(c, p) => Self::QuadTo(f(c), f(p)),
+            Self::CubicTo(c0, c1, p) => Self::CubicTo(f(c0), f(c1), f(p)),
+            Self::Close => Self::Close,
+        }
+    }
+
+    /// End point of the segment (`None` for [`PathSegment::Close`])
+    pub fn to(&self) -> Option<[f32; 2]> {
+        match *self {
+            Self::MoveTo(p) | Self::LineTo(p) | Self::QuadTo(_, p) | Self::CubicTo(_, _, p) => {
+                Some(p)
+            }
+            Self::Close => None,
+        }
+    }
+}
+
+/// Outlines
+impl FontStash {
+    /// Outline of the glyph of `c` in font units, y-up from the baseline with the pen at the
+    /// origin
+    ///
+    /// Fallback fonts are not considered. Missing glyphs and glyphs without outlines (e.g.
+    /// spaces or bitmap glyphs) have no segment. Each contour ends with [`PathSegment::Close`].
+    pub fn glyph_outline(&self, font: FontIx, c: char) -> Result<Vec<PathSegment>> {
+        self.check_font(font)?;
+        let n = unsafe {
+            sys::fonsGetGlyphOutline(
+                self.raw(),
+                font.0 as c_int,
+                c as u32,
+                core::ptr::null_mut(),
+                0,
+            )
+        };
+        if n < 0 {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+
+        let mut raw = Vec::with_capacity(n as usize);
+        unsafe {
+            let n = sys::fonsGetGlyphOutline(
+                self.raw(),
+                font.0 as c_int,
+                c as u32,
+                raw.as_mut_ptr(),
+                n,
+            );
+            raw.set_len(n.max(0).min(raw.capacity() as c_int) as usize);
+        }
+
+        let mut segments = Vec::with_capacity(raw.len() + raw.len() / 4);
+        for seg in &raw {
+            let to = [seg.x, seg.y];
+            let c0 = [seg.cx0, seg.cy0];
+            let c1 = [seg.cx1, seg.cy1];
+            let seg = match seg.verb as u32 {
+                sys::FONSoutlineVerb_FONS_OUTLINE_MOVE => {
+                    if !segments.is_empty() {
+                        segments.push(PathSegment::Close);
+                    }
+                    PathSegment::MoveTo(to)
+                }
+                sys::FONSoutlineVerb_FONS_OUTLINE_LINE => PathSegment::LineTo(to),
+                sys::FONSoutlineVerb_FONS_OUTLINE_QUAD => PathSegment::QuadTo(c0, to),
+                sys::FONSoutlineVerb_FONS_OUTLINE_CUBIC => PathSegment::CubicTo(c0, c1, to),
+                _ => continue,
+            };
+            segments.push(seg);
+        }
+        if !segments.is_empty() {
+            segments.push(PathSegment::Close);
+        }
+
+        Ok(segments)
+    }
+
+    /// [`FontStash::glyph_outline`] scaled to `size` in pixels, y-down with the pen at the origin
+    /// like quads
+    ///
+    /// Add the pen position (e.g. [`crate::GlyphQuad::pen`]) to place it. The outline is not
+    /// hinted, and faux bold and italic are not applied.
+    pub fn glyph_outline_at(&self, font: FontIx, c: char, size: f32) -> Result<Vec<PathSegment>> {
+        let mut segments = self.glyph_outline(font, c)?;
+
+        let scale = self.scoped_state(|| unsafe {
+            sys::fonsSetFont(self.raw(), font.0 as c_int);
+            sys::fonsSetSize(self.raw(), size);
+            Ok(sys::fonsGetPixelScale(self.raw()))
+        })?;
+
+        for seg in &mut segments {
+            *seg = seg.map(|[x, y]| [x * scale, -y * scale]);
+        }
+        Ok(segments)
+    }
+}
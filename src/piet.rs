@@ -0,0 +1,395 @@
+/*!
+[piet](https://github.com/linebender/piet) text backend, enabled with the `piet` feature
+
+[`PietText`] implements [`piet::Text`] with a [`FontStash`], and [`PietTextLayout`] implements
+[`piet::TextLayout`] on a [`TextLayout`] prepared with [`FontStash::prepare`]. Glyphs are cached
+in the atlas of the stash, so draw [`PietTextLayout::quads_at`] with the atlas texture in your
+render context:
+
+```text
+let mut text = PietText::new(stash.clone());
+let layout = text
+    .new_text_layout("Hello, 世界")
+    .font(FontFamily::SANS_SERIF, 24.0)
+    .max_width(200.0)
+    .build()?;
+
+for quad in layout.quads_at([10.0, 10.0]) {
+    // draw the quad with `layout.color()`
+}
+```
+
+Generic families (e.g. [`piet::FontFamily::SANS_SERIF`]) resolve to the font that was current when
+the [`PietText`] was made. Bold and italic are synthesized with [`FontStash::set_faux_bold`] and
+[`FontStash::set_faux_italic`].
+
+Only the default attributes are applied to the layout. Range attributes, underlines and
+strikethroughs are ignored.
+*/
+
+use core::{cell::RefCell, ops::RangeBounds};
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+
+use ::piet::{
+    kurbo::{Point, Rect, Size},
+    Error, FontFamily, FontStyle, FontWeight, HitTestPoint, HitTestPosition, LineMetric,
+    TextAlignment, TextAttribute, TextStorage,
+};
+
+use crate::{layout::TextLayout, Align, Color, FontIx, FontStash, Quad, TextStyle};
+
+/// Slant of synthesized italic
+const ITALIC_SLANT: f32 = 0.2;
+
+/// Faux bold in pixels per font size
+const BOLD_RATIO: f32 = 1.0 / 24.0;
+
+/// [`piet::Text`] implementation sharing a [`FontStash`]
+///
+/// Clones share the stash and the fonts loaded with [`piet::Text::load_font`].
+#[derive(Debug)]
+pub struct PietText {
+    stash: FontStash,
+    /// Font of generic families
+    default_font: FontIx,
+    /// Family names of the fonts loaded from memory
+    families: Rc<RefCell<Vec<(String, FontIx)>>>,
+}
+
+impl Clone for PietText {
+    fn clone(&self) -> Self {
+        Self {
+            stash: self.stash.clone(),
+            default_font: self.default_font,
+            families: self.families.clone(),
+        }
+    }
+}
+
+impl PietText {
+    /// Uses the current font of the stash for generic families
+    pub fn new(stash: FontStash) -> Self {
+        let default_font = stash.current_font();
+        Self {
+            stash,
+            default_font,
+            families: Default::default(),
+        }
+    }
+
+    pub fn stash(&self) -> &FontStash {
+        &self.stash
+    }
+
+    fn resolve(&self, family: &FontFamily) -> Option<FontIx> {
+        if family.is_generic() {
+            return Some(self.default_font);
+        }
+
+        let families = self.families.borrow();
+        families
+            .iter()
+            .find(|(name, _)| name == family.name())
+            .map(|&(_, font)| font)
+            .or_else(|| self.stash.font_ix_by_name(family.name()))
+    }
+}
+
+impl ::piet::Text for PietText {
+    type TextLayoutBuilder = PietTextLayoutBuilder;
+    type TextLayout = PietTextLayout;
+
+    fn font_family(&mut self, family_name: &str) -> Option<FontFamily> {
+        let family = FontFamily::new_unchecked(family_name);
+        self.resolve(&family).map(|_| family)
+    }
+
+    /// Adds the font to the stash and returns the family name in its `name` table
+    fn load_font(&mut self, data: &[u8]) -> Result<FontFamily, Error> {
+        let name = format!("piet-{}", self.stash.font_count());
+        let font = self
+            .stash
+            .add_font_mem_copy(&name, data)
+            .map_err(|_| Error::FontLoadingFailed)?;
+        let family = self
+            .stash
+            .font_info(font)
+            .and_then(|info| info.family)
+            .unwrap_or(name);
+
+        self.families.borrow_mut().push((family.clone(), font));
+        Ok(FontFamily::new_unchecked(family))
+    }
+
+    fn new_text_layout(&mut self, text: impl TextStorage) -> Self::TextLayoutBuilder {
+        let mut style = TextStyle::new(self.default_font, 12.0);
+        style.align = Align::LEFT | Align::TOP;
+
+        PietTextLayoutBuilder {
+            text: self.clone(),
+            source: Rc::new(text),
+            style,
+            alignment: TextAlignment::Start,
+            max_width: None,
+        }
+    }
+}
+
+/// [`piet::TextLayoutBuilder`] made with [`piet::Text::new_text_layout`]
+pub struct PietTextLayoutBuilder {
+    text: PietText,
+    source: Rc<dyn TextStorage>,
+    style: TextStyle,
+    alignment: TextAlignment,
+    max_width: Option<f32>,
+}
+
+impl core::fmt::Debug for PietTextLayoutBuilder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PietTextLayoutBuilder")
+            .field("text", &self.source.as_str())
+            .field("style", &self.style)
+            .field("max_width", &self.max_width)
+            .finish()
+    }
+}
+
+impl ::piet::TextLayoutBuilder for PietTextLayoutBuilder {
+    type Out = PietTextLayout;
+
+    fn max_width(mut self, width: f64) -> Self {
+        self.max_width = Some(width as f32).filter(|w| w.is_finite());
+        self
+    }
+
+    fn alignment(mut self, alignment: TextAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    fn default_attribute(mut self, attribute: impl Into<TextAttribute>) -> Self {
+        match attribute.into() {
+            TextAttribute::FontFamily(family) => {
+                if let Some(font) = self.text.resolve(&family) {
+                    self.style.font = font;
+                }
+            }
+            TextAttribute::FontSize(size) => self.style.size = size as f32,
+            TextAttribute::Weight(weight) => {
+                self.style.faux_bold = if weight.to_raw() >= FontWeight::SEMI_BOLD.to_raw() {
+                    self.style.size * BOLD_RATIO
+                } else {
+                    0.0
+                };
+            }
+            TextAttribute::TextColor(color) => {
+                let (r, g, b, a) = color.as_rgba8();
+                self.style.color = Color::rgba(r, g, b, a);
+            }
+            TextAttribute::Style(style) => {
+                self.style.faux_italic = match style {
+                    FontStyle::Italic => ITALIC_SLANT,
+                    FontStyle::Regular => 0.0,
+                };
+            }
+            _ => {}
+        }
+        self
+    }
+
+    fn range_attribute(
+        self,
+        _range: impl RangeBounds<usize>,
+        _attribute: impl Into<TextAttribute>,
+    ) -> Self {
+        self
+    }
+
+    fn build(self) -> Result<PietTextLayout, Error> {
+        let Self {
+            text,
+            source,
+            mut style,
+            alignment,
+            max_width,
+        } = self;
+        let stash = &text.stash;
+
+        style.align = Align::TOP
+            | match alignment {
+                TextAlignment::End => Align::RIGHT,
+                TextAlignment::Center => Align::CENTER,
+                TextAlignment::Start | TextAlignment::Justified => Align::LEFT,
+            };
+
+        let layout = stash
+            .prepare(source.as_str(), &style, max_width)
+            .map_err(|_| Error::InvalidInput)?;
+
+        // piet places the layout box at the origin
+        let width = max_width.unwrap_or_else(|| layout.size()[0]);
+        let offset = if style.align.contains(Align::RIGHT) {
+            width
+        } else if style.align.contains(Align::CENTER) {
+            width / 2.0
+        } else {
+            0.0
+        };
+
+        let mut stash = stash.clone();
+        let trailing_width = stash
+            .with_state(|stash| {
+                stash.apply_style(&style);
+                let str = source.as_str();
+                let mut ends = layout.lines().iter().skip(1).map(|line| line.range.start);
+                layout
+                    .lines()
+                    .iter()
+                    .map(|line| {
+                        let end = ends.next().unwrap_or(str.len());
+                        stash.text_size_oneline(&str[line.range.start..end])[0]
+                    })
+                    .fold(0.0, f32::max)
+            })
+            .map_err(|_| Error::InvalidInput)?;
+
+        Ok(PietTextLayout {
+            source,
+            layout,
+            color: style.color,
+            offset,
+            trailing_width,
+        })
+    }
+}
+
+/// [`piet::TextLayout`] on a prepared [`TextLayout`]
+#[derive(Clone)]
+pub struct PietTextLayout {
+    source: Rc<dyn TextStorage>,
+    layout: TextLayout,
+    color: Color,
+    /// x of the layout origin in the layout box
+    offset: f32,
+    trailing_width: f32,
+}
+
+impl core::fmt::Debug for PietTextLayout {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PietTextLayout")
+            .field("text", &self.source.as_str())
+            .field("layout", &self.layout)
+            .field("color", &self.color)
+            .finish()
+    }
+}
+
+impl PietTextLayout {
+    /// The prepared layout relative to its origin (see [`PietTextLayout::quads_at`])
+    pub fn layout(&self) -> &TextLayout {
+        &self.layout
+    }
+
+    /// Color of the text attributes
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Quads with the left-up corner of the layout box at `pos`
+    pub fn quads_at(&self, pos: impl Into<[f32; 2]>) -> impl Iterator<Item = Quad> + '_ {
+        let pos = pos.into();
+        self.layout.quads_at([pos[0] + self.offset, pos[1]])
+    }
+
+    fn rect(&self, rect: [f32; 4]) -> Rect {
+        let [x0, y0, x1, y1] = rect;
+        Rect::new(
+            (x0 + self.offset) as f64,
+            y0 as f64,
+            (x1 + self.offset) as f64,
+            y1 as f64,
+        )
+    }
+
+    /// Index of the line containing `idx`, same as the caret of [`TextLayout::caret_rect`]
+    fn line_of(&self, idx: usize) -> usize {
+        let lines = self.layout.lines();
+        lines
+            .iter()
+            .position(|line| idx <= line.range.end)
+            .unwrap_or(lines.len().saturating_sub(1))
+    }
+}
+
+impl ::piet::TextLayout for PietTextLayout {
+    fn size(&self) -> Size {
+        let [w, h] = self.layout.size();
+        Size::new(w as f64, h as f64)
+    }
+
+    fn trailing_whitespace_width(&self) -> f64 {
+        self.trailing_width as f64
+    }
+
+    fn image_bounds(&self) -> Rect {
+        self.rect(self.layout.bounds())
+    }
+
+    fn text(&self) -> &str {
+        self.source.as_str()
+    }
+
+    fn line_text(&self, line_number: usize) -> Option<&str> {
+        let metric = self.line_metric(line_number)?;
+        self.source
+            .as_str()
+            .get(metric.start_offset..metric.end_offset)
+    }
+
+    fn line_metric(&self, line_number: usize) -> Option<LineMetric> {
+        let lines = self.layout.lines();
+        let line = lines.get(line_number)?;
+        let end_offset = lines
+            .get(line_number + 1)
+            .map_or(self.source.as_str().len(), |next| next.range.start);
+        let [_, top, _, bottom] = line.rect;
+
+        Some(LineMetric {
+            start_offset: line.range.start,
+            end_offset,
+            trailing_whitespace: end_offset - line.range.end,
+            baseline: (line.baseline - top) as f64,
+            height: (bottom - top) as f64,
+            y_offset: top as f64,
+        })
+    }
+
+    fn line_count(&self) -> usize {
+        self.layout.lines().len()
+    }
+
+    fn hit_test_point(&self, point: Point) -> HitTestPoint {
+        let pos = [point.x as f32 - self.offset, point.y as f32];
+        let idx = self.layout.index_at(pos).unwrap_or(0);
+        let is_inside = self.layout.lines().iter().any(|line| {
+            let [x0, y0, x1, y1] = line.rect;
+            x0 <= pos[0] && pos[0] < x1 && y0 <= pos[1] && pos[1] < y1
+        });
+        HitTestPoint::new(idx, is_inside)
+    }
+
+    fn hit_test_text_position(&self, idx: usize) -> HitTestPosition {
+        let line = self.line_of(idx);
+        let x = self
+            .layout
+            .caret_rect(idx, Default::default())
+            .map_or(0.0, |rect| rect[0]);
+        let y = self
+            .layout
+            .lines()
+            .get(line)
+            .map_or(0.0, |line| line.baseline);
+        HitTestPosition::new(Point::new((x + self.offset) as f64, y as f64), line)
+    }
+}
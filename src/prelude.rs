@@ -0,0 +1,10 @@
+/*!
+Types needed by most users: `use fontstash::prelude::*;`
+*/
+
+pub use crate::{
+    layout::TextLayout,
+    vertex::{QuadVertex, Vertex},
+    Align, Color, FonsError, FonsTextIter, FontIx, FontStash, GlyphQuad, Quad, Renderer,
+    StashBuilder, TextStyle,
+};
@@ -0,0 +1,62 @@
+/*!
+Rust-side cache of glyph quads, enabled with [`FontStash::set_quad_cache`]
+
+FontStash looks up glyphs in C for every `fonsTextIterNext` call. With the cache enabled,
+[`crate::FonsTextIter`] asks C only for glyphs it hasn't seen before and computes the rest of the
+quads in the same way as `fons__getQuad`.
+
+Cached glyphs are cleared when the atlas is reset (including glyph eviction) and when a font is
+reloaded. Don't reset the atlas through `sys` directly while the cache is enabled.
+*/
+
+use crate::{eviction::GlyphKey, FontStash, Map};
+
+/// Glyph metrics got from a `fonsTextIterNext` call with the pen at the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CachedGlyph {
+    /// Glyph index in the font (or `-1` if the atlas was full)
+    pub index: i32,
+    /// Left-up corner of the quad relative to the pen
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    /// `[x0, y0, x1, y1]` in the atlas, in pixels
+    pub tex: [f32; 4],
+    /// Advance rounded by FontStash
+    pub advance: f32,
+    pub is_missing: bool,
+    pub is_colored: bool,
+    pub is_deferred: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct QuadCache {
+    pub enabled: bool,
+    pub glyphs: Map<GlyphKey, CachedGlyph>,
+    /// `(font, isize, previous glyph index, glyph index)` -> kerning in pixels
+    pub kerns: Map<(usize, i16, i32, i32), f32>,
+}
+
+impl QuadCache {
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.kerns.clear();
+    }
+}
+
+/// Quad cache
+impl FontStash {
+    /// Caches glyph quads on the Rust side so that text iteration doesn't cross the FFI boundary
+    /// for each glyph. Disabled by default
+    pub fn set_quad_cache(&self, enabled: bool) {
+        let mut cache = self.fons.quad_cache.borrow_mut();
+        cache.enabled = enabled;
+        if !enabled {
+            cache.clear();
+        }
+    }
+
+    /// Clears the cached glyph quads
+    pub fn clear_quad_cache(&self) {
+        self.fons.quad_cache.borrow_mut().clear();
+    }
+}
@@ -0,0 +1,250 @@
+/*!
+Glyphs rasterized apart from the stash, e.g. on other threads
+
+A [`Rasterizer`] has its own copy of a font, so it can be sent to another thread and rasterize
+glyphs while the stash is used. The bitmaps are inserted into the atlas on the thread of the
+stash:
+
+```text
+let mut rasterizer = stash.rasterizer(font)?;
+let bitmap = std::thread::spawn(move || rasterizer.rasterize('字', 24.0)).join().unwrap();
+if let Some(bitmap) = bitmap {
+    stash.insert_glyph_bitmap(font, '字', 24.0, &bitmap)?;
+}
+```
+
+The bitmaps are the same as the glyphs rasterized by the stash without blur, so text drawn after
+the insertion makes the same quads. Sizes are in atlas pixels, i.e., the text is drawn with
+`size / dpi_scale()` in logical pixels. `crate::worker` does it for the glyphs missing while
+drawing, and [`FontStash::prewarm_parallel`] for the glyphs known ahead of time.
+*/
+
+use core::{ffi::c_int, ptr::NonNull};
+
+use alloc::vec::Vec;
+
+use crate::{resume_callback_panic, sys, AtlasEvent, FonsError, FontIx, FontStash, Result};
+
+/// Rasterizer of the glyphs of a font, independent of the stash (see [`FontStash::rasterizer`])
+///
+/// Reloading the font doesn't change the rasterizer, so create it again afterwards.
+#[derive(Debug)]
+pub struct Rasterizer {
+    raw: NonNull<sys::FONSrasterizer>,
+}
+
+// it owns the copy of the font data (and the FreeType library) and is used by one thread at a
+// time
+unsafe impl Send for Rasterizer {}
+
+impl Drop for Rasterizer {
+    fn drop(&mut self) {
+        unsafe {
+            sys::fonsDeleteRasterizer(self.raw.as_ptr());
+        }
+    }
+}
+
+impl Rasterizer {
+    /// Rasterizes the glyph of the font (not of its fallbacks) at `size` in atlas pixels. Returns
+    /// `None` if the font doesn't have the glyph
+    pub fn rasterize(&mut self, c: char, size: f32) -> Option<GlyphBitmap> {
+        let mut bitmap = sys::FONSglyphBitmap::default();
+        let ptr =
+            unsafe { sys::fonsRasterizeGlyph(self.raw.as_ptr(), c as u32, size, &mut bitmap) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        let len = (bitmap.width * bitmap.height) as usize;
+        let pixels = unsafe { core::slice::from_raw_parts(ptr, len) };
+        Some(GlyphBitmap {
+            pixels: pixels.to_vec(),
+            width: bitmap.width as u32,
+            height: bitmap.height as u32,
+            offset: [bitmap.xoff, bitmap.yoff],
+            x_advance: bitmap.xadvance,
+        })
+    }
+}
+
+/// Coverage of a glyph rasterized by a [`Rasterizer`], with the empty pixel around it
+///
+/// * `pixels`: `width * height` bytes
+/// * `offset`: position of the top-left pixel from the pen at the baseline
+/// * `x_advance`: horizontal distance to the next glyph in pixels
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GlyphBitmap {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub offset: [i32; 2],
+    pub x_advance: f32,
+}
+
+/// Rasterization apart from the stash
+impl FontStash {
+    /// Rasterizer with a copy of the data of `font`
+    ///
+    /// Returns [`FonsError::FailedToAllocFont`] if the font couldn't be copied.
+    pub fn rasterizer(&self, font: FontIx) -> Result<Rasterizer> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+        self.rasterizer_at(font.0 as c_int)
+            .ok_or(FonsError::FailedToAllocFont())
+    }
+
+    pub(crate) fn rasterizer_at(&self, font: c_int) -> Option<Rasterizer> {
+        let raw = unsafe { sys::fonsCreateRasterizer(self.raw(), font) };
+        NonNull::new(raw).map(|raw| Rasterizer { raw })
+    }
+
+    /// Inserts a glyph of `font` rasterized by a [`Rasterizer`] at `size` in atlas pixels into the
+    /// atlas. Returns `Ok(false)` if the glyph is cached already
+    ///
+    /// Returns [`FonsError::InvalidParams`] for an SDF atlas (see [`crate::sdf`]) and
+    /// [`FonsError::AtlasFull`] if the glyph didn't fit even after `renderExpand`.
+    pub fn insert_glyph_bitmap(
+        &self,
+        font: FontIx,
+        c: char,
+        size: f32,
+        bitmap: &GlyphBitmap,
+    ) -> Result<bool> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+        if self.sdf_params().is_some() {
+            return Err(FonsError::InvalidParams(
+                "bitmaps can't be inserted into an SDF atlas",
+            ));
+        }
+        Self::check_bitmap(bitmap)?;
+
+        let res = unsafe {
+            sys::fonsAddGlyphBitmap(
+                self.raw(),
+                font.0 as c_int,
+                c as u32,
+                size,
+                bitmap.pixels.as_ptr(),
+                bitmap.width as c_int,
+                bitmap.height as c_int,
+                bitmap.width as c_int,
+                bitmap.offset[0],
+                bitmap.offset[1],
+                bitmap.x_advance,
+            )
+        };
+        self.inserted(res, (bitmap.width + 2) * (bitmap.height + 2))
+    }
+
+    /// [`FontStash::prewarm`] rasterizing the glyphs on up to `threads` threads, each with a
+    /// [`Rasterizer`]. The bitmaps are inserted into the atlas serially
+    ///
+    /// Glyphs of the fallbacks are rasterized serially. So is everything with blur, in an SDF atlas
+    /// or for a font with color glyphs (see [`FontStash::color_tables`]).
+    #[cfg(feature = "std")]
+    pub fn prewarm_parallel(
+        &self,
+        font: FontIx,
+        size: f32,
+        chars: impl Iterator<Item = char>,
+        threads: usize,
+    ) -> Result<()> {
+        trace_span!("fontstash::prewarm_parallel", size, threads);
+        self.check_font(font)?;
+        let mut chars = chars.collect::<Vec<_>>();
+        if threads <= 1
+            || self.blur() != 0.0
+            || self.sdf_params().is_some()
+            || !self.color_tables(font).is_empty()
+        {
+            return self.prewarm(font, size, chars.into_iter());
+        }
+
+        chars.sort_unstable();
+        chars.dedup();
+        let (own, mut rest): (Vec<_>, Vec<_>) =
+            chars.into_iter().partition(|&c| self.has_glyph(font, c));
+
+        // the stash rasterizes `size` in logical pixels
+        let atlas_size = size * self.dpi_scale();
+        let threads = threads.min(own.len()).max(1);
+        let rasterizers = (0..threads)
+            .map(|_| self.rasterizer(font))
+            .collect::<Result<Vec<_>>>()?;
+
+        let bitmaps = std::thread::scope(|scope| {
+            let chunk = own.len().div_ceil(threads);
+            let handles = own
+                .chunks(chunk.max(1))
+                .zip(rasterizers)
+                .map(|(chars, mut rasterizer)| {
+                    scope.spawn(move || {
+                        chars
+                            .iter()
+                            .map(|&c| (c, rasterizer.rasterize(c, atlas_size)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect::<Vec<_>>()
+        });
+
+        for (c, bitmap) in bitmaps {
+            match bitmap {
+                Some(bitmap) => {
+                    self.insert_glyph_bitmap(font, c, atlas_size, &bitmap)?;
+                }
+                None => rest.push(c),
+            }
+        }
+        if rest.is_empty() {
+            Ok(())
+        } else {
+            self.prewarm(font, size, rest.into_iter())
+        }
+    }
+
+    fn check_bitmap(bitmap: &GlyphBitmap) -> Result<()> {
+        let needed = (bitmap.width * bitmap.height) as usize;
+        if bitmap.pixels.len() < needed {
+            return Err(FonsError::BufferTooSmall {
+                needed,
+                available: bitmap.pixels.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Handles the result of `fonsAddGlyphBitmap` for a glyph of `needed` pixels in the atlas
+    pub(crate) fn inserted(&self, res: c_int, needed: u32) -> Result<bool> {
+        // `renderExpand` can be called
+        resume_callback_panic();
+        self.fire_pending_atlas_events();
+
+        match res {
+            1 => {
+                // text may have been measured with the placeholder (see `crate::worker`)
+                self.invalidate_measure_cache();
+                Ok(true)
+            }
+            -1 => {
+                self.fire_atlas_event(AtlasEvent::Full);
+                let stats = self.atlas_stats();
+                Err(FonsError::AtlasFull {
+                    needed,
+                    available: stats.total_pixels.saturating_sub(stats.used_pixels),
+                })
+            }
+            _ => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+/*!
+Quadliterals and the render callbacks of `FONScontext`
+*/
+
+use core::ffi::{c_int, c_uchar, c_void};
+
+use crate::sys;
+
+/// Quadliteral
+///
+/// * `s0`, `t0`: left-up coner in the font texture
+/// * `s1`, `t1`: right-down coner in the font texture
+/// * `x0`, `y0`: left-up coner of the target position
+/// * `x1`, `y1`: right-down coner of the target position
+pub type FonsQuad = sys::FONSquad;
+
+/// Quadliteral yielded by the text iterators
+///
+/// Rectangles are `[left_x, top_y, right_x, bottom_y]`. Convert from/into [`FonsQuad`] with
+/// `From`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Quad {
+    position: [f32; 4],
+    uv: [f32; 4],
+    /// Horizontal offsets of the top and the bottom edges (see [`Quad::sheared`])
+    slant: [f32; 2],
+}
+
+impl Quad {
+    pub fn new(position_rect: [f32; 4], uv_rect: [f32; 4]) -> Self {
+        Self {
+            position: position_rect,
+            uv: uv_rect,
+            slant: [0.0, 0.0],
+        }
+    }
+
+    /// Shears the quad horizontally by `slant` pixels per pixel above `baseline_y`, e.g. for
+    /// faux italic. The rectangles are kept; the offsets are applied by
+    /// [`vertex::corners`](crate::vertex::corners)
+    pub fn sheared(mut self, slant: f32, baseline_y: f32) -> Self {
+        self.slant[0] += slant * (baseline_y - self.position[1]);
+        self.slant[1] += slant * (baseline_y - self.position[3]);
+        self
+    }
+
+    /// Horizontal offsets of the top and the bottom edges added by [`Quad::sheared`]
+    pub fn slant_offsets(&self) -> [f32; 2] {
+        self.slant
+    }
+
+    /// Target rectangle in pixels
+    pub fn position_rect(&self) -> [f32; 4] {
+        self.position
+    }
+
+    /// Source rectangle in the font texture, normalized to `[0.0, 1.0]`
+    pub fn uv_rect(&self) -> [f32; 4] {
+        self.uv
+    }
+
+    /// Left-up corner of the target rectangle
+    pub fn left_top(&self) -> [f32; 2] {
+        [self.position[0], self.position[1]]
+    }
+
+    /// `[width, height]` of the target rectangle
+    pub fn size(&self) -> [f32; 2] {
+        [
+            self.position[2] - self.position[0],
+            self.position[3] - self.position[1],
+        ]
+    }
+
+    /// If the quad has no area, e.g. a space
+    pub fn is_empty(&self) -> bool {
+        !(self.position[2] > self.position[0] && self.position[3] > self.position[1])
+    }
+
+    /// Moves the target rectangle
+    pub fn translate(mut self, dx: f32, dy: f32) -> Self {
+        self.position[0] += dx;
+        self.position[1] += dy;
+        self.position[2] += dx;
+        self.position[3] += dy;
+        self
+    }
+
+    /// Scales the target rectangle about the origin. UVs are kept
+    pub fn scale(mut self, f: f32) -> Self {
+        for x in self.position.iter_mut().chain(&mut self.slant) {
+            *x *= f;
+        }
+        self
+    }
+
+    /// Maps the target rectangle from pixels in a viewport of `[width, height]` to normalized
+    /// device coordinates (`[-1.0, 1.0]`, y-up), for renderers without a projection matrix
+    ///
+    /// The top y becomes greater than the bottom y, so [`Quad::is_empty`] and [`Quad::size`] don't
+    /// make sense for the result.
+    pub fn to_ndc(mut self, viewport: [f32; 2]) -> Self {
+        let [w, h] = viewport;
+        let [x0, y0, x1, y1] = self.position;
+        self.position = [
+            x0 / w * 2.0 - 1.0,
+            1.0 - y0 / h * 2.0,
+            x1 / w * 2.0 - 1.0,
+            1.0 - y1 / h * 2.0,
+        ];
+        self.slant = [self.slant[0] / w * 2.0, self.slant[1] / w * 2.0];
+        self
+    }
+
+    /// Cuts the quad to fit in `rect` adjusting UVs proportionally. It's empty if the quad is
+    /// outside of the rectangle
+    ///
+    /// Sheared quads are clipped by their rectangles, so the slanted edges can stick out.
+    pub fn clipped(self, rect: [f32; 4]) -> Self {
+        let [x0, y0, x1, y1] = self.position;
+        let [s0, t0, s1, t1] = self.uv;
+
+        let clamp = |v: f32, min: f32, max: f32| v.max(min).min(max);
+        // point on the UV range at `p` in the position range
+        let lerp = |a: f32, b: f32, p0: f32, p1: f32, p: f32| {
+            if p1 == p0 {
+                a
+            } else {
+                a + (b - a) * (p - p0) / (p1 - p0)
+            }
+        };
+
+        let [cx0, cx1] = [clamp(x0, rect[0], rect[2]), clamp(x1, rect[0], rect[2])];
+        let [cy0, cy1] = [clamp(y0, rect[1], rect[3]), clamp(y1, rect[1], rect[3])];
+
+        let [top, bottom] = self.slant;
+        Self {
+            slant: [
+                lerp(top, bottom, y0, y1, cy0),
+                lerp(top, bottom, y0, y1, cy1),
+            ],
+            ..Self::new(
+                [cx0, cy0, cx1, cy1],
+                [
+                    lerp(s0, s1, x0, x1, cx0),
+                    lerp(t0, t1, y0, y1, cy0),
+                    lerp(s0, s1, x0, x1, cx1),
+                    lerp(t0, t1, y0, y1, cy1),
+                ],
+            )
+        }
+    }
+}
+
+impl From<FonsQuad> for Quad {
+    fn from(q: FonsQuad) -> Self {
+        Self::new([q.x0, q.y0, q.x1, q.y1], [q.s0, q.t0, q.s1, q.t1])
+    }
+}
+
+impl From<Quad> for FonsQuad {
+    fn from(q: Quad) -> Self {
+        let ([x0, y0, x1, y1], [s0, t0, s1, t1]) = (q.position, q.uv);
+        FonsQuad {
+            x0,
+            y0,
+            x1,
+            y1,
+            s0,
+            t0,
+            s1,
+            t1,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static CALLBACK_PANIC: core::cell::RefCell<Option<Box<dyn std::any::Any + Send>>> =
+        Default::default();
+}
+
+/// Runs the body of a [`Renderer`] callback catching panics, which must not unwind into C
+///
+/// A panic is translated into `0` (failure) and resumed when the call into C returns to the
+/// `FontStash` method.
+///
+/// Without the `std` feature, `f` is just called (build with `panic = "abort"`).
+#[cfg(feature = "std")]
+pub fn catch_callback_panic(f: impl FnOnce() -> c_int) -> c_int {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(ret) => ret,
+        Err(payload) => {
+            CALLBACK_PANIC.with(|p| *p.borrow_mut() = Some(payload));
+            0
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub fn catch_callback_panic(f: impl FnOnce() -> c_int) -> c_int {
+    f()
+}
+
+/// Resumes the panic caught by [`catch_callback_panic`], if any
+pub(crate) fn resume_callback_panic() {
+    #[cfg(feature = "std")]
+    if let Some(payload) = CALLBACK_PANIC.with(|p| p.borrow_mut().take()) {
+        log_warn!("fontstash: resuming a panic in a renderer callback");
+        std::panic::resume_unwind(payload);
+    }
+}
+
+/// Set of callbacks
+///
+/// * `uptr`: user data pointer, which is usually the implementation of [`Renderer`]
+///
+/// Return non-zero to represent success. Panicking in the callbacks is undefined behavior, so wrap
+/// them with [`catch_callback_panic`].
+///
+/// # Safety
+///
+/// The callbacks are called by `fontstash` with the `uptr` given to the stash, so the
+/// implementation has to be able to take the pointer and must not unwind into C.
+pub unsafe trait Renderer {
+    /// Creates font texture
+    ///
+    /// # Safety
+    ///
+    /// `uptr` is the pointer given to the stash; only `fontstash` calls it.
+    unsafe extern "C" fn create(uptr: *mut c_void, width: c_int, height: c_int) -> c_int;
+
+    /// Create new texture
+    ///
+    /// User of [`Renderer`] should not call it directly; it's used to implement
+    /// `FontStash::expand_atlas` and `FontStash::reset_atlas`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Renderer::create`].
+    unsafe extern "C" fn resize(uptr: *mut c_void, width: c_int, height: c_int) -> c_int;
+
+    /// Try to expand texture while the atlas is full
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Renderer::create`].
+    unsafe extern "C" fn expand(uptr: *mut c_void) -> c_int;
+
+    /// Update texture
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Renderer::create`]. `rect` points to `[x0, y0, x1, y1]` and `data` to the whole
+    /// atlas during the call.
+    unsafe extern "C" fn update(uptr: *mut c_void, rect: *mut c_int, data: *const c_uchar)
+        -> c_int;
+}
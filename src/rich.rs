@@ -0,0 +1,197 @@
+/*!
+Rich text made of [`Span`]s with different styles
+
+Spans are laid out as one flow. Glyphs of a line share one baseline and the line is as high as the
+highest span in it.
+*/
+
+use alloc::{vec, vec::Vec};
+
+use crate::{Align, Color, FonsTextIter, FontIx, FontStash, LineMetrics, Quad, Result};
+
+/// Size of superscript and subscript spans relative to the base size
+const SCRIPT_SCALE: f32 = 0.6;
+
+/// Piece of text with its own style
+///
+/// * `size`, `blur`: in logical pixels like [`crate::TextStyle`] (scaled with the DPI scale)
+/// * `baseline_shift`: offset of the baseline in pixels. Positive values raise the span
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub font: FontIx,
+    pub size: f32,
+    pub color: Color,
+    pub blur: f32,
+    pub baseline_shift: f32,
+}
+
+impl<'a> Span<'a> {
+    /// White span without blur
+    pub fn new(text: &'a str, font: FontIx, size: f32) -> Self {
+        Self {
+            text,
+            font,
+            size,
+            color: Color::WHITE,
+            blur: 0.0,
+            baseline_shift: 0.0,
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<Color>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    pub fn blur(mut self, blur: f32) -> Self {
+        self.blur = blur;
+        self
+    }
+
+    pub fn baseline_shift(mut self, shift: f32) -> Self {
+        self.baseline_shift = shift;
+        self
+    }
+
+    /// Scales the size down and raises the span, e.g. for "x²" or footnote markers
+    pub fn superscript(mut self) -> Self {
+        self.baseline_shift += self.size * 0.35;
+        self.size *= SCRIPT_SCALE;
+        self
+    }
+
+    /// Scales the size down and lowers the span, e.g. for chemical formulas
+    pub fn subscript(mut self) -> Self {
+        self.baseline_shift -= self.size * 0.15;
+        self.size *= SCRIPT_SCALE;
+        self
+    }
+}
+
+/// Paragraph made of [`Span`]s
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Text<'a> {
+    pub spans: Vec<Span<'a>>,
+}
+
+impl<'a> Text<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn span(mut self, span: Span<'a>) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn push(&mut self, span: Span<'a>) {
+        self.spans.push(span);
+    }
+}
+
+/// Quadliteral of rich text
+///
+/// * `span`: index of the span the glyph belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct RichQuad {
+    pub quad: Quad,
+    pub color: Color,
+    pub span: usize,
+}
+
+/// Rich text
+impl FontStash {
+    fn apply_span(&self, span: &Span) {
+        self.set_font(span.font);
+        self.set_size(span.size);
+        self.set_color(span.color);
+        self.set_blur(span.blur);
+    }
+
+    /// Lays out the spans with `pos` being the left-up corner of the paragraph
+    ///
+    /// Clears `out` and fills it with the quadliterals. Returns `[width, height]` of the paragraph.
+    /// The state is restored after the layout.
+    pub fn layout_rich(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &Text,
+        out: &mut Vec<RichQuad>,
+    ) -> Result<[f32; 2]> {
+        let pos = pos.into();
+        out.clear();
+
+        self.scoped_state(|| {
+            self.set_align(Align::LEFT | Align::BASELINE);
+            self.layout_rich_impl(pos, text, out)
+        })
+    }
+
+    fn layout_rich_impl(
+        &self,
+        pos: [f32; 2],
+        text: &Text,
+        out: &mut Vec<RichQuad>,
+    ) -> Result<[f32; 2]> {
+        // (span index, line index, text)
+        let mut segments = Vec::new();
+        let mut n_lines = 1;
+        for (i, span) in text.spans.iter().enumerate() {
+            for (j, segment) in span.text.split('\n').enumerate() {
+                if j > 0 {
+                    n_lines += 1;
+                }
+                segments.push((i, n_lines - 1, crate::trim_cr(segment)));
+            }
+        }
+
+        let mut lines = vec![LineMetrics::default(); n_lines];
+        for &(i, line, _) in &segments {
+            let span = &text.spans[i];
+            self.apply_span(span);
+            let m = self.vert_metrics();
+            let shift = span.baseline_shift;
+            let l = &mut lines[line];
+            l.ascender = l.ascender.max(m.ascender + shift);
+            l.descender = l.descender.min(m.descender + shift);
+            // shifted spans make the line higher
+            l.line_height = l.line_height.max(m.line_height + shift.abs());
+        }
+
+        let mut baselines = Vec::with_capacity(n_lines);
+        let mut y = pos[1];
+        for line in &lines {
+            baselines.push(y + line.ascender);
+            y += line.line_height;
+        }
+
+        let mut width = 0.0f32;
+        let mut x = pos[0];
+        let mut current_line = 0;
+        for &(i, line, segment) in &segments {
+            if line != current_line {
+                current_line = line;
+                x = pos[0];
+            }
+
+            let span = &text.spans[i];
+            self.apply_span(span);
+
+            let baseline = baselines[line] - span.baseline_shift;
+            let mut iter = FonsTextIter::from_text_at(self, [x, baseline], segment)?;
+            while let Some(glyph) = iter.next_glyph() {
+                out.push(RichQuad {
+                    quad: glyph.quad,
+                    color: span.color,
+                    span: i,
+                });
+            }
+
+            x = iter.next_x();
+            width = width.max(x - pos[0]);
+        }
+
+        Ok([width, y - pos[1]])
+    }
+}
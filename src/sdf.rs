@@ -0,0 +1,138 @@
+/*!
+Signed distance field atlas
+
+With [`StashBuilder::sdf`], glyphs are stored as signed distance fields instead of coverage, so
+that a glyph rasterized at one size can be drawn crisp at other sizes, or with outlines and glows
+in the shader:
+
+```text
+let stash = StashBuilder::new(1024, 1024).sdf(SdfParams::default()).build()?;
+let uniforms = stash.sdf_uniforms().unwrap();
+// in the shader: `dist = (texel - uniforms.on_edge) / uniforms.dist_scale` (in atlas pixels)
+```
+
+The field is the exact Euclidean distance to the outline of the rasterized coverage (cut at the
+half), so rasterize at a large size (e.g. `48.0`) and scale the quads for smaller text.
+
+[`SdfParams::spread`] replaces the blur of the glyphs, so [`FontStash::set_blur`] has no effect.
+Bitmaps can't be imported into an SDF atlas (e.g. [`FontStash::import_bmfont`]).
+
+[`StashBuilder::sdf`]: crate::StashBuilder::sdf
+*/
+
+use core::ffi::{c_int, c_uchar};
+
+use crate::{sys, FonsError, FontStash, Result};
+
+/// Parameters of the signed distance field atlas, same as `stbtt_GetGlyphSDF`
+///
+/// * `spread`: distance in atlas pixels covered around the glyphs (`1` to `20`)
+/// * `on_edge`: texel value on the outline
+/// * `pixel_dist_scale`: change of the texel value per atlas pixel, increasing inside the glyphs
+///
+/// Texels saturate at `on_edge / pixel_dist_scale` pixels outside of the glyph, so keep it at
+/// most `spread` to use the whole spread.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfParams {
+    pub spread: u8,
+    pub on_edge: u8,
+    pub pixel_dist_scale: f32,
+}
+
+impl Default for SdfParams {
+    /// Spread of `8` pixels with the outline at the middle of the range
+    fn default() -> Self {
+        Self {
+            spread: 8,
+            on_edge: 128,
+            pixel_dist_scale: 16.0,
+        }
+    }
+}
+
+impl SdfParams {
+    /// Returns [`FonsError::InvalidParams`] if the spread is out of `1..=20` or the scale is not
+    /// positive
+    pub fn validate(&self) -> Result<()> {
+        if self.spread == 0 || self.spread > 20 {
+            return Err(FonsError::InvalidParams("SDF spread has to be in `1..=20`"));
+        }
+        if !(self.pixel_dist_scale > 0.0 && self.pixel_dist_scale.is_finite()) {
+            return Err(FonsError::InvalidParams(
+                "SDF pixel distance scale has to be positive",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The parameters normalized for shaders sampling the atlas as `0.0..=1.0`
+    pub fn uniforms(&self) -> SdfUniforms {
+        SdfUniforms {
+            on_edge: self.on_edge as f32 / 255.0,
+            dist_scale: self.pixel_dist_scale / 255.0,
+            spread: self.spread as f32,
+            _pad: 0.0,
+        }
+    }
+}
+
+/// [`SdfParams`] for shaders, laid out as a `vec4` uniform
+///
+/// * `on_edge`: normalized texel value on the outline
+/// * `dist_scale`: change of the normalized texel value per atlas pixel
+/// * `spread`: distance in atlas pixels covered around the glyphs
+///
+/// The distance to the outline in atlas pixels is `(texel - on_edge) / dist_scale`. Multiply it
+/// by the screen pixels per atlas pixel (e.g. `1.0 / fwidth(uv * atlas_size)`) for anti-aliasing
+/// at any size, and offset it for outlines.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct SdfUniforms {
+    pub on_edge: f32,
+    pub dist_scale: f32,
+    pub spread: f32,
+    pub _pad: f32,
+}
+
+/// SDF atlas
+impl FontStash {
+    /// Parameters of the SDF atlas, or `None` for a coverage atlas
+    pub fn sdf_params(&self) -> Option<SdfParams> {
+        let mut on_edge: c_uchar = 0;
+        let mut pixel_dist_scale = 0.0;
+        let spread = unsafe { sys::fonsGetSdf(self.raw(), &mut on_edge, &mut pixel_dist_scale) };
+        if spread == 0 {
+            return None;
+        }
+        Some(SdfParams {
+            spread: spread as u8,
+            on_edge,
+            pixel_dist_scale,
+        })
+    }
+
+    /// [`SdfParams::uniforms`] of the SDF atlas
+    pub fn sdf_uniforms(&self) -> Option<SdfUniforms> {
+        self.sdf_params().map(|p| p.uniforms())
+    }
+
+    /// Switches between the SDF atlas (`Some`) and the coverage atlas (`None`) and resets the
+    /// atlas, because the cached glyphs are of the previous mode. Prefer
+    /// [`crate::StashBuilder::sdf`]
+    ///
+    /// Returns [`FonsError::InvalidParams`] (see [`SdfParams::validate`]).
+    pub fn set_sdf(&self, params: Option<SdfParams>) -> Result<()> {
+        if let Some(p) = &params {
+            p.validate()?;
+        }
+        let p = params.unwrap_or(SdfParams {
+            spread: 0,
+            ..SdfParams::default()
+        });
+        unsafe {
+            sys::fonsSetSdf(self.raw(), p.spread as c_int, p.on_edge, p.pixel_dist_scale);
+        }
+        let [w, h] = self.atlas_size();
+        self.reset_atlas(w, h)
+    }
+}
@@ -0,0 +1,176 @@
+/*!
+Sanity check of font data before passing it to `stb_truetype`, which trusts the offsets in the
+data and can read out of bounds
+
+Only the header and the table directory are checked; corrupted tables can still fail in C.
+Tables are also looked up here for the Rust-side decoders (see `color_glyph`).
+*/
+
+use crate::{FonsError, Result};
+
+/// Tables `stb_truetype` requires
+const REQUIRED_TABLES: [&[u8; 4]; 4] = [b"cmap", b"head", b"hhea", b"hmtx"];
+
+pub(crate) fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Checks the sfnt header and the table directory of the face
+pub(crate) fn validate(data: &[u8], face_index: u32) -> Result<()> {
+    self::validate_impl(data, face_index).ok_or(FonsError::InvalidFontData())
+}
+
+/// The table of the face, if it's within the data
+#[cfg(feature = "color-glyphs")]
+pub(crate) fn find_table<'a>(data: &'a [u8], face_index: u32, tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let offset = self::face_offset(data, face_index)?;
+    let n_tables = read_u16(data, offset + 4)? as usize;
+    (0..n_tables).find_map(|i| {
+        let record = offset + 12 + 16 * i;
+        if data.get(record..record + 4)? != tag {
+            return None;
+        }
+        let start = read_u32(data, record + 8)? as usize;
+        let len = read_u32(data, record + 12)? as usize;
+        data.get(start..start.checked_add(len)?)
+    })
+}
+
+/// Offset of the table directory of the face
+fn face_offset(data: &[u8], face_index: u32) -> Option<usize> {
+    let offset = match data.get(0..4)? {
+        b"ttcf" => {
+            let n_fonts = read_u32(data, 8)?;
+            if face_index >= n_fonts {
+                return None;
+            }
+            read_u32(data, 12 + 4 * face_index as usize)? as usize
+        }
+        [0, 1, 0, 0] | b"true" | b"OTTO" | b"typ1" if face_index == 0 => 0,
+        _ => return None,
+    };
+
+    match data.get(offset..offset + 4)? {
+        [0, 1, 0, 0] | b"true" | b"OTTO" | b"typ1" => Some(offset),
+        _ => None,
+    }
+}
+
+fn validate_impl(data: &[u8], face_index: u32) -> Option<()> {
+    let offset = self::face_offset(data, face_index)?;
+
+    let n_tables = read_u16(data, offset + 4)? as usize;
+    // bit `i` for `REQUIRED_TABLES[i]`, so that duplicated tags don't count twice
+    let mut seen = 0u8;
+    for i in 0..n_tables {
+        let record = offset + 12 + 16 * i;
+        let tag = data.get(record..record + 4)?;
+        let table_offset = read_u32(data, record + 8)? as usize;
+        let table_len = read_u32(data, record + 12)? as usize;
+
+        if table_offset.checked_add(table_len)? > data.len() {
+            return None;
+        }
+        if let Some(bit) = REQUIRED_TABLES.iter().position(|t| &t[..] == tag) {
+            seen |= 1 << bit;
+        }
+    }
+
+    if seen == (1 << REQUIRED_TABLES.len()) - 1 {
+        Some(())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Face with empty tables of the tags at `offset` of the data
+    fn face(offset: usize, tags: &[&[u8; 4]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 1, 0, 0]);
+        data.extend_from_slice(&(tags.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0; 6]);
+        let end = (offset + 12 + 16 * tags.len()) as u32;
+        for tag in tags {
+            data.extend_from_slice(&tag[..]);
+            data.extend_from_slice(&[0; 4]);
+            data.extend_from_slice(&end.to_be_bytes());
+            data.extend_from_slice(&0u32.to_be_bytes());
+        }
+        data
+    }
+
+    fn set_u32(data: &mut [u8], pos: usize, x: u32) {
+        data[pos..pos + 4].copy_from_slice(&x.to_be_bytes());
+    }
+
+    #[test]
+    fn required_tables() {
+        assert!(validate(&face(0, &REQUIRED_TABLES), 0).is_ok());
+        assert!(validate(&face(0, &[b"cmap", b"head", b"hhea", b"glyf", b"hmtx"]), 0).is_ok());
+        assert!(validate(&face(0, &REQUIRED_TABLES[..3]), 0).is_err());
+        assert!(validate(&face(0, &[b"cmap", b"head", b"hhea", b"hhea"]), 0).is_err());
+        assert!(validate(&face(0, &REQUIRED_TABLES), 1).is_err());
+    }
+
+    #[test]
+    fn truncated_header() {
+        let data = face(0, &REQUIRED_TABLES);
+        for len in [0, 3, 5, 12, 12 + 16 * 3 + 8, data.len() - 1] {
+            assert!(validate(&data[..len], 0).is_err(), "{}", len);
+        }
+        assert!(validate(b"wOFF\0\0\0\0\0\0\0\0", 0).is_err());
+    }
+
+    #[test]
+    fn table_out_of_range() {
+        let mut data = face(0, &REQUIRED_TABLES);
+        let len = data.len() as u32;
+        // `hmtx` ending one byte after the data
+        set_u32(&mut data, 12 + 16 * 3 + 12, 1);
+        assert!(validate(&data, 0).is_err());
+
+        // overflowing `offset + len`
+        set_u32(&mut data, 12 + 16 * 3 + 8, len);
+        set_u32(&mut data, 12 + 16 * 3 + 12, u32::MAX);
+        assert!(validate(&data, 0).is_err());
+
+        set_u32(&mut data, 12 + 16 * 3 + 12, 0);
+        assert!(validate(&data, 0).is_ok());
+    }
+
+    #[test]
+    fn collection() {
+        // header with two faces followed by the faces
+        let mut data = Vec::new();
+        data.extend_from_slice(b"ttcf");
+        data.extend_from_slice(&[0, 1, 0, 0]);
+        data.extend_from_slice(&2u32.to_be_bytes());
+        let first = 20;
+        let second = first + face(0, &REQUIRED_TABLES).len();
+        data.extend_from_slice(&(first as u32).to_be_bytes());
+        data.extend_from_slice(&(second as u32).to_be_bytes());
+        data.extend(face(first, &REQUIRED_TABLES));
+        data.extend(face(second, &REQUIRED_TABLES[1..]));
+
+        assert!(validate(&data, 0).is_ok());
+        assert!(validate(&data, 1).is_err());
+        assert!(validate(&data, 2).is_err());
+        assert!(validate(&data, u32::MAX).is_err());
+
+        // face offset out of the data
+        let len = data.len() as u32;
+        set_u32(&mut data, 12, len);
+        assert!(validate(&data, 0).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+/*!
+Shaped text laid out with FontStash
+
+FontStash maps each character to one glyph, so ligatures, mark positioning and complex scripts are
+drawn wrong. With a shaping engine, text is shaped first and then the shaped glyphs are rasterized
+by their glyph indices ([`FontStash::glyph_quad`]), so FontStash is still the glyph cache and the
+rasterizer:
+
+* `rustybuzz` feature: [`FontStash::shape_quads_into`]
+* `harfbuzz` feature: [`FontStash::shaped_iter`](crate::harfbuzz)
+
+The face given to the shaper has to be made from the same font data (and face index) as the
+current font of the stash. Fallback fonts are not used for shaped text.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{Align, FontStash, Quad};
+
+/// Quadliteral of a shaped glyph
+///
+/// * `glyph_index`: index of the glyph in the font
+/// * `cluster`: byte offset of the cluster in the source text the glyph is made from
+/// * `pen`: position of the pen, including the offset from the shaper
+/// * `x_advance`: horizontal distance to the next glyph
+#[derive(Debug, Clone, Copy)]
+pub struct ShapedQuad {
+    pub quad: Quad,
+    pub glyph_index: u32,
+    pub cluster: usize,
+    pub pen: [f32; 2],
+    pub x_advance: f32,
+}
+
+/// Glyph placed by a shaping engine, in pixels: `(glyph_index, cluster, offset, advance)`
+pub(crate) type PlacedGlyph = (u32, usize, [f32; 2], [f32; 2]);
+
+/// Shaping with [rustybuzz](https://docs.rs/rustybuzz)
+#[cfg(feature = "rustybuzz")]
+impl FontStash {
+    /// Shapes one line of text with rustybuzz and lays out the glyphs
+    ///
+    /// Clears `out` and fills it with the quadliterals. The script, language and direction are
+    /// guessed from the text. [`FontStash::set_font_features`] are applied before `features`.
+    /// The text is aligned with the current alignment as a whole. Glyphs that didn't fit in the
+    /// atlas are skipped. Returns the advance of the text.
+    pub fn shape_quads_into(
+        &self,
+        face: &rustybuzz::Face,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        features: &[rustybuzz::Feature],
+        out: &mut Vec<ShapedQuad>,
+    ) -> crate::Result<f32> {
+        let pos = pos.into();
+        out.clear();
+
+        let scale = self.pixel_scale();
+        if scale == 0.0 {
+            return Err(crate::FonsError::FoundNoFont());
+        }
+
+        // the features of the call override the ones of the state
+        let mut all = self
+            .font_features()
+            .iter()
+            .map(|(tag, value)| {
+                rustybuzz::Feature::new(rustybuzz::Tag::from_bytes(&tag), value, ..)
+            })
+            .collect::<Vec<_>>();
+        all.extend_from_slice(features);
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        let shaped = rustybuzz::shape(face, &all, buffer);
+
+        let glyphs = shaped
+            .glyph_infos()
+            .iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, p)| {
+                (
+                    // the glyph index after shaping
+                    info.codepoint,
+                    info.cluster as usize,
+                    // font units are y-up
+                    [p.x_offset as f32 * scale, -p.y_offset as f32 * scale],
+                    [p.x_advance as f32 * scale, -p.y_advance as f32 * scale],
+                )
+            });
+
+        let iter = ShapedIter::new(self, pos, glyphs.collect());
+        let advance = iter.advance;
+        out.extend(iter);
+        Ok(advance)
+    }
+}
+
+/// Iterator of [`ShapedQuad`]s, which rasterizes the shaped glyphs lazily
+///
+/// Glyphs that didn't fit in the atlas are skipped.
+pub struct ShapedIter<'a> {
+    stash: &'a FontStash,
+    glyphs: alloc::vec::IntoIter<PlacedGlyph>,
+    pen: [f32; 2],
+    advance: f32,
+}
+
+impl<'a> ShapedIter<'a> {
+    /// Aligns the glyphs from `pos` with the current alignment
+    pub(crate) fn new(stash: &'a FontStash, pos: [f32; 2], glyphs: Vec<PlacedGlyph>) -> Self {
+        let advance = glyphs.iter().map(|(_, _, _, adv)| adv[0]).sum::<f32>();
+
+        let align = stash.align();
+        let mut pen = pos;
+        if align.contains(Align::RIGHT) {
+            pen[0] -= advance;
+        } else if align.contains(Align::CENTER) {
+            pen[0] -= advance / 2.0;
+        }
+
+        Self {
+            stash,
+            glyphs: glyphs.into_iter(),
+            pen,
+            advance,
+        }
+    }
+
+    /// Horizontal advance of the whole text
+    pub fn advance(&self) -> f32 {
+        self.advance
+    }
+}
+
+impl<'a> Iterator for ShapedIter<'a> {
+    type Item = ShapedQuad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (glyph_index, cluster, offset, adv) in &mut self.glyphs {
+            let pen = [self.pen[0] + offset[0], self.pen[1] + offset[1]];
+            self.pen[0] += adv[0];
+            self.pen[1] += adv[1];
+
+            if let Some(quad) = self.stash.glyph_quad(glyph_index, pen) {
+                return Some(ShapedQuad {
+                    quad,
+                    glyph_index,
+                    cluster,
+                    pen,
+                    x_advance: adv[0],
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "rustybuzz"))]
+mod tests {
+    use crate::test_font;
+
+    #[test]
+    fn rustybuzz_glyphs() {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_size(20.0);
+
+        let data = test_font::data();
+        let face = rustybuzz::Face::from_slice(&data, 0).unwrap();
+        let mut quads = Vec::new();
+        let advance = stash.shape_quads_into(&face, [0.0, 0.0], "aé", &[], &mut quads);
+        assert_eq!(advance.unwrap(), 20.0);
+
+        let glyphs = quads
+            .iter()
+            .map(|q| (q.glyph_index, q.cluster, q.pen[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(glyphs, [(1, 0, 0.0), (1, 1, 10.0)]);
+    }
+}
@@ -0,0 +1,1357 @@
+/*!
+States of FontStash (font, size, color, ...), styles and the Rust-side text options
+*/
+
+use core::ffi::c_int;
+
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{sys, Align, Decoration, FonsError, FontIx, FontStash, Result};
+
+/// States
+impl FontStash {
+    pub(crate) fn state_info(&self) -> sys::FONSstateInfo {
+        let mut info: sys::FONSstateInfo = unsafe { core::mem::zeroed() };
+        unsafe {
+            sys::fonsGetStateInfo(self.raw(), &mut info);
+        }
+        info
+    }
+
+    /// Sets the font size in logical pixels. Glyphs are rasterized at `size * dpi_scale` (see
+    /// [`FontStash::set_dpi_scale`])
+    pub fn set_size(&self, size: f32) {
+        unsafe {
+            sys::fonsSetSize(self.raw(), size * self.dpi_scale());
+        }
+    }
+
+    /// [`FontStash::set_size`] that fails if the size is not positive or too big. FontStash stores
+    /// `size * 10` in `short`, so the size multiplied with the DPI scale has to be less than
+    /// `3276.8`
+    pub fn try_set_size(&self, size: f32) -> Result<()> {
+        let scaled = size * self.dpi_scale();
+        if !(scaled > 0.0 && scaled * 10.0 < i16::MAX as f32) {
+            return Err(FonsError::InvalidSize(size));
+        }
+        self.set_size(size);
+        Ok(())
+    }
+
+    /// Sets the color of text. `u32`s are taken as packed colors (see [`Color`])
+    pub fn set_color(&self, color: impl Into<Color>) {
+        unsafe {
+            sys::fonsSetColor(self.raw(), color.into().packed());
+        }
+    }
+
+    /// Horizontal space between characters?
+    pub fn set_spacing(&self, spacing: f32) {
+        unsafe {
+            sys::fonsSetSpacing(self.raw(), spacing * self.dpi_scale());
+        }
+    }
+
+    pub fn set_blur(&self, blur: f32) {
+        unsafe {
+            sys::fonsSetBlur(self.raw(), blur * self.dpi_scale());
+        }
+    }
+
+    /// Sets alignment of quadliterals returned with [`FontStash::text_iter`]
+    ///
+    /// The flags are passed to fontstash as they are. Build them with [`Align::new`] to avoid
+    /// combinations that fontstash resolves silently, e.g. `LEFT | RIGHT` being left-aligned.
+    pub fn set_align(&self, align: Align) {
+        unsafe {
+            sys::fonsSetAlign(self.raw(), align.bits() as i32);
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.state_info().size / self.dpi_scale()
+    }
+
+    pub fn color(&self) -> Color {
+        Color::from_packed(self.state_info().color)
+    }
+
+    pub fn spacing(&self) -> f32 {
+        self.state_info().spacing / self.dpi_scale()
+    }
+
+    pub fn blur(&self) -> f32 {
+        self.state_info().blur / self.dpi_scale()
+    }
+
+    pub fn align(&self) -> Align {
+        Align::from_bits_truncate(self.state_info().align as u32)
+    }
+
+    pub fn current_font(&self) -> FontIx {
+        self.font_ix(self.state_info().font as u32)
+    }
+
+    /// Runs `f` with the horizontal alignment temporarily set to [`Align::LEFT`]
+    pub(crate) fn with_left_align<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let align = self.align();
+        self.scoped_state(|| {
+            self.set_align((align - Align::CENTER - Align::RIGHT) | Align::LEFT);
+            f()
+        })
+    }
+
+    /// Runs `f` with a pushed state and pops it after, even if `f` panics
+    ///
+    /// Fails with [`FonsError::StatesOverflow`] without running `f` if the state stack is full, so
+    /// that the pop never removes a state of the caller.
+    pub(crate) fn scoped_state<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        if self.state_depth() >= Self::max_states() {
+            return Err(FonsError::StatesOverflow());
+        }
+        unsafe {
+            sys::fonsPushState(self.raw());
+        }
+        let _pop = PopStateOnDrop(self);
+        f()
+    }
+}
+
+/// Pops the state pushed by [`FontStash::scoped_state`]
+struct PopStateOnDrop<'a>(&'a FontStash);
+
+impl<'a> Drop for PopStateOnDrop<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::fonsPopState(self.0.raw());
+        }
+    }
+}
+
+/// Color of text
+///
+/// FontStash stores colors packed as `0xAABBGGRR`, i.e., `[r, g, b, a]` bytes in little-endian
+/// memory (same as `glfonsRGBA`). Use [`Color::rgba`] or [`Color::from_hex`] instead of writing
+/// packed values by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Self = Self::rgba(255, 255, 255, 255);
+    pub const BLACK: Self = Self::rgba(0, 0, 0, 255);
+    pub const TRANSPARENT: Self = Self::rgba(0, 0, 0, 0);
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Opaque color
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    /// From `0xRRGGBBAA` (CSS `#RRGGBBAA` order)
+    pub const fn from_hex(hex: u32) -> Self {
+        let [r, g, b, a] = hex.to_be_bytes();
+        Self::rgba(r, g, b, a)
+    }
+
+    /// `0xRRGGBBAA`
+    pub const fn to_hex(self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// From the packed format of FontStash (`0xAABBGGRR`)
+    pub const fn from_packed(packed: u32) -> Self {
+        let [r, g, b, a] = packed.to_le_bytes();
+        Self::rgba(r, g, b, a)
+    }
+
+    /// The packed format of FontStash (`0xAABBGGRR`)
+    pub const fn packed(self) -> u32 {
+        u32::from_le_bytes([self.r, self.g, self.b, self.a])
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+/// Packed color (`0xAABBGGRR`)
+impl From<u32> for Color {
+    fn from(packed: u32) -> Self {
+        Self::from_packed(packed)
+    }
+}
+
+/// Packed color (`0xAABBGGRR`)
+impl From<Color> for u32 {
+    fn from(color: Color) -> Self {
+        color.packed()
+    }
+}
+
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+/// Set of states applied with [`FontStash::apply_style`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextStyle {
+    pub font: FontIx,
+    pub size: f32,
+    pub color: Color,
+    pub blur: f32,
+    pub spacing: f32,
+    pub align: Align,
+    /// Pixels of faux bold (see [`FontStash::set_faux_bold`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub faux_bold: f32,
+    /// Slant of faux italic (see [`FontStash::set_faux_italic`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub faux_italic: f32,
+    /// OpenType features of the shaping backends (see [`FontStash::set_font_features`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub font_features: FontFeatures,
+}
+
+impl TextStyle {
+    /// Style with FontStash's default states: white, no blur, no spacing and aligned to the
+    /// left and the baseline, without faux bold nor italic
+    pub fn new(font: FontIx, size: f32) -> Self {
+        Self {
+            font,
+            size,
+            color: Color::WHITE,
+            blur: 0.0,
+            spacing: 0.0,
+            align: Align::LEFT | Align::BASELINE,
+            faux_bold: 0.0,
+            faux_italic: 0.0,
+            font_features: FontFeatures::new(),
+        }
+    }
+}
+
+/// OpenType features applied by the shaping backends, e.g. `smcp` (small caps), `tnum` (tabular
+/// numbers) or `dlig` (discretionary ligatures)
+///
+/// Features are `(tag, value)`, where `value` is `0` to disable and `1` to enable (or an index of
+/// the alternates). It's `Copy` and holds up to [`FontFeatures::CAPACITY`] features so that it's a
+/// part of [`TextStyle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontFeatures {
+    features: [([u8; 4], u32); FontFeatures::CAPACITY],
+    len: u8,
+}
+
+impl FontFeatures {
+    pub const CAPACITY: usize = 8;
+
+    pub const fn new() -> Self {
+        Self {
+            features: [([0; 4], 0); Self::CAPACITY],
+            len: 0,
+        }
+    }
+
+    /// Parses `(tag, value)` pairs such as `[("liga", 1), ("smcp", 1)]`
+    ///
+    /// Returns [`FonsError::InvalidFontFeature`] if a tag is not four printable ASCII characters
+    /// or there are more than [`FontFeatures::CAPACITY`] features.
+    pub fn parse(features: &[(&str, u32)]) -> Result<Self> {
+        let mut out = Self::new();
+        for &(tag, value) in features {
+            let bytes = tag.as_bytes();
+            let is_valid = bytes.len() == 4 && bytes.iter().all(|b| (0x20..0x7f).contains(b));
+            if !is_valid || !out.push([bytes[0], bytes[1], bytes[2], bytes[3]], value) {
+                return Err(FonsError::InvalidFontFeature(tag.to_string()));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Adds a feature overriding the same tag. Returns `false` if it's full
+    pub fn push(&mut self, tag: [u8; 4], value: u32) -> bool {
+        let len = self.len as usize;
+        if let Some(f) = self.features[..len].iter_mut().find(|f| f.0 == tag) {
+            f.1 = value;
+            return true;
+        }
+        if len == Self::CAPACITY {
+            return false;
+        }
+        self.features[len] = (tag, value);
+        self.len += 1;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `(tag, value)` in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = ([u8; 4], u32)> + '_ {
+        self.features[..self.len()].iter().copied()
+    }
+}
+
+/// States overriding the current ones for one call, given to [`FontStash::text_iter_with`]
+///
+/// `None` keeps the current state. There's no color because quads don't have colors; pick the
+/// color when writing vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StyleOverrides {
+    pub font: Option<FontIx>,
+    pub size: Option<f32>,
+    pub blur: Option<f32>,
+    pub spacing: Option<f32>,
+}
+
+/// Style
+impl FontStash {
+    /// Current states as a [`TextStyle`]
+    pub fn style(&self) -> TextStyle {
+        let info = self.state_info();
+        let dpi = self.dpi_scale();
+        TextStyle {
+            font: self.font_ix(info.font as u32),
+            size: info.size / dpi,
+            color: Color::from_packed(info.color),
+            blur: info.blur / dpi,
+            spacing: info.spacing / dpi,
+            align: Align::from_bits_truncate(info.align as u32),
+            faux_bold: self.faux_bold(),
+            faux_italic: self.faux_italic(),
+            font_features: self.font_features(),
+        }
+    }
+
+    /// Sets all the states in the style
+    pub fn apply_style(&self, style: &TextStyle) {
+        self.set_font(style.font);
+        self.set_size(style.size);
+        self.set_color(style.color);
+        self.set_blur(style.blur);
+        self.set_spacing(style.spacing);
+        self.set_align(style.align);
+        self.set_faux_bold(style.faux_bold);
+        self.set_faux_italic(style.faux_italic);
+        self.update_options(|opts| opts.font_features = style.font_features);
+    }
+
+    /// [`FontStash::apply_style`] that skips the states already set, e.g. for drawing many
+    /// strings with a few styles
+    ///
+    /// The current states are read with one FFI call (`fonsGetStateInfo`), so it also works after
+    /// the states are changed by other functions or popped.
+    pub fn set_state(&self, style: &TextStyle) {
+        let info = self.state_info();
+        let dpi = self.dpi_scale();
+        unsafe {
+            // a stale font sets no font as `set_font` does
+            let font = self.checked_font(style.font);
+            if info.font != font {
+                sys::fonsSetFont(self.raw(), font);
+            }
+            if info.size != style.size * dpi {
+                sys::fonsSetSize(self.raw(), style.size * dpi);
+            }
+            if info.color != style.color.packed() {
+                sys::fonsSetColor(self.raw(), style.color.packed());
+            }
+            if info.blur != style.blur * dpi {
+                sys::fonsSetBlur(self.raw(), style.blur * dpi);
+            }
+            if info.spacing != style.spacing * dpi {
+                sys::fonsSetSpacing(self.raw(), style.spacing * dpi);
+            }
+            if info.align != style.align.bits() as i32 {
+                sys::fonsSetAlign(self.raw(), style.align.bits() as i32);
+            }
+        }
+        // Rust-side options are compared by `update_options`
+        self.set_faux_bold(style.faux_bold);
+        self.set_faux_italic(style.faux_italic);
+        self.update_options(|opts| opts.font_features = style.font_features);
+    }
+}
+
+/// Snapshot of the stash returned by [`FontStash::debug_state`]
+///
+/// * `font_name`: `None` if the current font is invalid (e.g. no font is set)
+/// * `state_depth`: number of pushed states (see [`FontStash::state_depth`])
+/// * `glyph_count`: number of glyphs cached in the atlas
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateDump {
+    pub font: FontIx,
+    pub font_name: Option<String>,
+    pub size: f32,
+    pub color: Color,
+    pub blur: f32,
+    pub spacing: f32,
+    pub align: Align,
+    pub state_depth: usize,
+    pub atlas_size: [u32; 2],
+    pub glyph_count: u32,
+}
+
+/// Debug
+impl FontStash {
+    /// Dumps the current states and the atlas, e.g. to find out why text doesn't appear
+    pub fn debug_state(&self) -> StateDump {
+        let style = self.style();
+        StateDump {
+            font: style.font,
+            font_name: self.font_info(style.font).map(|info| info.name),
+            size: style.size,
+            color: style.color,
+            blur: style.blur,
+            spacing: style.spacing,
+            align: style.align,
+            state_depth: self.state_depth(),
+            atlas_size: self.atlas_size(),
+            glyph_count: self.atlas_stats().glyph_count,
+        }
+    }
+}
+
+/// Width of tab stops set with [`FontStash::set_tab_width`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TabWidth {
+    /// Multiple of the advance of a space
+    Columns(u32),
+    Pixels(f32),
+}
+
+/// Pixel snapping set with [`FontStash::set_pixel_snap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PixelSnap {
+    /// Quads are left as FontStash emits them
+    #[default]
+    Off,
+    /// Quads are moved to integer positions. Their sizes are kept
+    Quads,
+    /// The pen position is also rounded after each glyph
+    QuadsAndAdvances,
+}
+
+/// How measurement functions get glyph boxes, set with [`FontStash::set_measure_mode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MeasureMode {
+    /// Glyphs are rasterized into the atlas as FontStash does
+    #[default]
+    Rasterize,
+    /// Glyph boxes are read from the font without rasterizing. The atlas is not touched, but
+    /// bounds of blurred text don't include the blur padding
+    Metrics,
+}
+
+/// Offsets in the source text of the characters of [`FontStash::prepared_text`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceOffsets {
+    /// `(offset, source offset)` of the characters, or `None` if the text is the source text
+    offsets: Option<Vec<(usize, usize)>>,
+}
+
+impl SourceOffsets {
+    /// Encodes characters with their source offsets into a string
+    fn collect(chars: Vec<(usize, char)>) -> (String, Self) {
+        let mut s = String::with_capacity(chars.len());
+        let mut offsets = Vec::with_capacity(chars.len());
+        for (source, c) in chars {
+            offsets.push((s.len(), source));
+            s.push(c);
+        }
+        (
+            s,
+            Self {
+                offsets: Some(offsets),
+            },
+        )
+    }
+
+    /// Source offset of the character at `offset` of the prepared text
+    pub fn get(&self, offset: usize) -> usize {
+        let offsets = match &self.offsets {
+            Some(offsets) => offsets,
+            None => return offset,
+        };
+        match offsets.binary_search_by_key(&offset, |&(o, _)| o) {
+            Ok(i) => offsets[i].1,
+            // not a character boundary
+            Err(i) => offsets[..i].last().map_or(0, |&(_, source)| source),
+        }
+    }
+}
+
+/// Characters of `text` in NFC with their offsets in `text`
+///
+/// The text is normalized per run from a starter that doesn't compose with the previous one, and
+/// the characters of a changed run are given the offset of the run.
+#[cfg(feature = "unicode-normalization")]
+fn nfc_chars(text: &str) -> Vec<(usize, char)> {
+    use unicode_normalization::{
+        char::{canonical_combining_class, compose},
+        is_nfc, UnicodeNormalization,
+    };
+
+    let mut out = Vec::with_capacity(text.len());
+    let mut push = |start: usize, run: &str| {
+        if is_nfc(run) {
+            out.extend(run.char_indices().map(|(i, c)| (start + i, c)));
+        } else {
+            out.extend(run.nfc().map(|c| (start, c)));
+        }
+    };
+
+    let mut start = 0;
+    let mut starter = None;
+    for (i, c) in text.char_indices() {
+        let composed = starter.and_then(|s| compose(s, c));
+        let is_starter = canonical_combining_class(c) == 0;
+        if is_starter && composed.is_none() && i > start {
+            push(start, &text[start..i]);
+            start = i;
+        }
+        starter = composed.or(if is_starter { Some(c) } else { starter });
+    }
+    push(start, &text[start..]);
+    out
+}
+
+/// Handling of invisible characters, set with [`FontStash::set_control_policy`]
+///
+/// Invisible characters are control characters (`\r`, `\n`, ..) and zero-width format
+/// characters (U+200B to U+200F, U+2028 to U+202E, U+2060 to U+2064 and U+FEFF). `\t` is left
+/// for tab stops if [`FontStash::set_tab_width`] is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ControlPolicy {
+    /// They're given to FontStash, which renders whatever glyph the font has
+    #[default]
+    PassThrough,
+    /// They're removed
+    Skip,
+    /// They're replaced with visible symbols for debugging: `\r` becomes `␍` (U+240D) and so on
+    /// (Control Pictures). Others are shown as U+FFFD
+    Replace,
+}
+
+impl ControlPolicy {
+    fn is_invisible(c: char, keeps_tab: bool) -> bool {
+        if c == '\t' {
+            return !keeps_tab;
+        }
+        c.is_control()
+            || matches!(
+                c,
+                '\u{200b}'..='\u{200f}'
+                    | '\u{2028}'..='\u{202e}'
+                    | '\u{2060}'..='\u{2064}'
+                    | '\u{feff}'
+            )
+    }
+
+    fn replacement(c: char) -> char {
+        match c as u32 {
+            n @ 0x00..=0x1f => char::from_u32(0x2400 + n).unwrap(),
+            0x7f => '\u{2421}',
+            _ => '\u{fffd}',
+        }
+    }
+}
+
+/// What to draw for characters no font has, set with [`FontStash::set_missing_glyph_policy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MissingGlyphPolicy {
+    /// The `.notdef` glyph of the font, as FontStash does (often a box, sometimes nothing)
+    #[default]
+    NotDef,
+    /// Nothing. The pen doesn't move
+    Skip,
+    /// Another character, e.g. `'?'` or U+FFFD
+    Replacement(char),
+}
+
+/// Settings applied to text iteration and measurement on the Rust side
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub(crate) struct TextOptions {
+    pub(crate) tab_width: Option<TabWidth>,
+    pub(crate) word_spacing: f32,
+    pub(crate) pixel_snap: PixelSnap,
+    pub(crate) subpixel: bool,
+    pub(crate) dpi_scale: f32,
+    pub(crate) clip_rect: Option<[f32; 4]>,
+    pub(crate) decoration: Decoration,
+    pub(crate) measure_mode: MeasureMode,
+    pub(crate) control_policy: ControlPolicy,
+    pub(crate) missing_glyph: MissingGlyphPolicy,
+    pub(crate) fixed_advance: Option<f32>,
+    pub(crate) mark_positioning: bool,
+    pub(crate) faux_bold: f32,
+    pub(crate) faux_italic: f32,
+    pub(crate) font_features: FontFeatures,
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) normalize_nfc: bool,
+    #[cfg(feature = "arabic-shaping")]
+    pub(crate) arabic_shaping: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: None,
+            word_spacing: 0.0,
+            pixel_snap: PixelSnap::Off,
+            subpixel: false,
+            dpi_scale: 1.0,
+            clip_rect: None,
+            decoration: Decoration::empty(),
+            measure_mode: MeasureMode::Rasterize,
+            control_policy: ControlPolicy::PassThrough,
+            missing_glyph: MissingGlyphPolicy::NotDef,
+            fixed_advance: None,
+            mark_positioning: false,
+            faux_bold: 0.0,
+            faux_italic: 0.0,
+            font_features: FontFeatures::new(),
+            #[cfg(feature = "unicode-normalization")]
+            normalize_nfc: true,
+            #[cfg(feature = "arabic-shaping")]
+            arabic_shaping: true,
+        }
+    }
+}
+
+impl TextOptions {
+    /// If advances of glyphs are adjusted on the Rust side. Then we can't rely on FontStash to
+    /// align or measure text.
+    pub(crate) fn adjusts_advance(&self) -> bool {
+        self.tab_width.is_some()
+            || self.word_spacing != 0.0
+            || self.pixel_snap == PixelSnap::QuadsAndAdvances
+            || self.missing_glyph != MissingGlyphPolicy::NotDef
+            || self.fixed_advance.is_some()
+            || self.mark_positioning
+            || self.faux_bold != 0.0
+    }
+}
+
+/// Rust-side text options
+///
+/// They're applied to the text iterators and the measurement functions. They're not part of the
+/// state stack.
+impl FontStash {
+    pub(crate) fn options(&self) -> TextOptions {
+        self.fons.options.get()
+    }
+
+    pub(crate) fn update_options(&self, f: impl FnOnce(&mut TextOptions)) {
+        let mut options = self.options();
+        f(&mut options);
+        // the clip rectangle doesn't affect measurement
+        let old = TextOptions {
+            clip_rect: options.clip_rect,
+            ..self.options()
+        };
+        if options != old {
+            self.invalidate_measure_cache();
+        }
+        self.fons.options.set(options);
+    }
+
+    /// Sets the width of tab stops. `\t` advances the pen to the next tab stop, measured from the
+    /// start of the text. With `None`, `\t` is handled by FontStash as a normal glyph.
+    pub fn set_tab_width(&self, tab_width: Option<TabWidth>) {
+        self.update_options(|opts| opts.tab_width = tab_width);
+    }
+
+    /// Sets extra advance after whitespace glyphs, e.g. for justified text. Unlike
+    /// [`FontStash::set_spacing`], it doesn't affect other glyphs.
+    pub fn set_word_spacing(&self, word_spacing: f32) {
+        self.update_options(|opts| opts.word_spacing = word_spacing);
+    }
+
+    /// Rounds positions of emitted quads to integer pixels so that small text looks crisp
+    pub fn set_pixel_snap(&self, pixel_snap: PixelSnap) {
+        self.update_options(|opts| opts.pixel_snap = pixel_snap);
+    }
+
+    /// Places quads at fractional pen positions for smooth motion of animated or scrolling text
+    ///
+    /// FontStash floors quad positions to integer pixels. With subpixel positioning, quads are
+    /// moved back by the fractional part of the pen position. Glyphs are still rasterized at one
+    /// phase, so the renderer should sample the atlas with linear filtering.
+    pub fn set_subpixel_positioning(&self, subpixel: bool) {
+        self.update_options(|opts| opts.subpixel = subpixel);
+    }
+
+    /// Sets the ratio of physical pixels to logical pixels, e.g. `2.0` on a Retina display
+    ///
+    /// Sizes, spacing, blur and positions are given in logical pixels. Glyphs are rasterized at
+    /// physical size, so they stay sharp, and emitted quads and measurements are divided back to
+    /// logical pixels. Pixel snapping rounds to physical pixels. The states already set are
+    /// stored in physical pixels, so set the scale before setting the size.
+    ///
+    /// Non-positive or non-finite scales are ignored.
+    pub fn set_dpi_scale(&self, scale: f32) {
+        if scale > 0.0 && scale.is_finite() {
+            self.update_options(|opts| opts.dpi_scale = scale);
+        }
+    }
+
+    pub fn dpi_scale(&self) -> f32 {
+        self.options().dpi_scale
+    }
+
+    /// Clips quads emitted by the text iterators to `rect` (`[left_x, top_y, right_x, bottom_y]`)
+    ///
+    /// Texture coordinates are cut proportionally. Glyphs outside of the rectangle are still
+    /// yielded as empty quads. Measurement is not affected.
+    pub fn set_clip_rect(&self, rect: Option<[f32; 4]>) {
+        self.update_options(|opts| opts.clip_rect = rect);
+    }
+
+    pub fn clip_rect(&self) -> Option<[f32; 4]> {
+        self.options().clip_rect
+    }
+
+    /// Makes [`FonsTextIter`](crate::FonsTextIter) yield solid quads of the lines after the
+    /// glyphs of each line
+    ///
+    /// The lines are placed with the underline and strikeout metrics of the font (or guessed from
+    /// the size if the font doesn't have them). They're mapped to the white rectangle FontStash
+    /// reserves at the left-up corner of the atlas. [`GlyphIter`](crate::GlyphIter) doesn't yield
+    /// them.
+    pub fn set_decoration(&self, decoration: Decoration) {
+        self.update_options(|opts| opts.decoration = decoration);
+    }
+
+    pub fn decoration(&self) -> Decoration {
+        self.options().decoration
+    }
+
+    /// Measures text without rasterizing glyphs with [`MeasureMode::Metrics`], so that measuring
+    /// long documents doesn't fill the atlas
+    ///
+    /// It applies to [`FontStash::text_bounds_oneline`], [`FontStash::text_size_oneline`] and the
+    /// functions built on them. Measurement with Rust-side advance adjustment (tab width, word
+    /// spacing or pixel snapping of advances) still rasterizes the glyphs.
+    pub fn set_measure_mode(&self, mode: MeasureMode) {
+        self.update_options(|opts| opts.measure_mode = mode);
+    }
+
+    pub fn measure_mode(&self) -> MeasureMode {
+        self.options().measure_mode
+    }
+
+    /// Normalizes text to NFC before iteration and measurement (on by default)
+    ///
+    /// `stb_truetype` looks up glyphs per codepoint, so decomposed sequences such as `e` +
+    /// COMBINING ACUTE ACCENT render as two glyphs. Text already in NFC is used as it is. Byte
+    /// offsets of [`GlyphQuad`](crate::GlyphQuad) are still into the given text, where a composed
+    /// character has the offset of its sequence. Turn it off if the text is shaped before it
+    /// reaches the stash.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn set_normalize_nfc(&self, normalize: bool) {
+        self.update_options(|opts| opts.normalize_nfc = normalize);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalizes_nfc(&self) -> bool {
+        self.options().normalize_nfc
+    }
+
+    /// Applies [`ControlPolicy`] to invisible characters in the quad iterators and the
+    /// measurement functions
+    ///
+    /// Byte offsets of [`GlyphQuad`](crate::GlyphQuad) are still into the given text, so skipped
+    /// characters have no glyph at their offsets.
+    pub fn set_control_policy(&self, policy: ControlPolicy) {
+        self.update_options(|opts| opts.control_policy = policy);
+    }
+
+    pub fn control_policy(&self) -> ControlPolicy {
+        self.options().control_policy
+    }
+
+    /// Sets what to draw for characters missing in the font and its fallbacks
+    ///
+    /// [`GlyphQuad::is_missing`](crate::GlyphQuad::is_missing) is still true for them, so log
+    /// unsupported characters with [`FontStash::glyph_iter`]. With policies other than
+    /// [`MissingGlyphPolicy::NotDef`], text is measured on the Rust side.
+    pub fn set_missing_glyph_policy(&self, policy: MissingGlyphPolicy) {
+        self.update_options(|opts| opts.missing_glyph = policy);
+    }
+
+    pub fn missing_glyph_policy(&self) -> MissingGlyphPolicy {
+        self.options().missing_glyph
+    }
+
+    /// Makes every glyph advance by `advance` pixels, e.g. for terminal cells with proportional
+    /// fallback fonts
+    ///
+    /// Glyphs are centered in their cells. Kerning and [`FontStash::set_spacing`] are not applied,
+    /// and wide characters (e.g. CJK) also take one cell. Tab stops still work.
+    pub fn set_fixed_advance(&self, advance: Option<f32>) {
+        self.update_options(|opts| opts.fixed_advance = advance);
+    }
+
+    pub fn fixed_advance(&self) -> Option<f32> {
+        self.options().fixed_advance
+    }
+
+    /// Places combining marks (e.g. U+0301 COMBINING ACUTE ACCENT) over the previous glyph
+    /// instead of advancing the pen for them
+    ///
+    /// Marks are centered horizontally on the base glyph and moved off it vertically if they
+    /// overlap, which is a heuristic without the anchors of the font. Kerning continues from the
+    /// base glyph. Prefer NFC (see `set_normalize_nfc`) or a shaping engine (see `shape`) where
+    /// precomposed characters or anchors are available. Text is measured on the Rust side while
+    /// it's enabled.
+    pub fn set_mark_positioning(&self, position: bool) {
+        self.update_options(|opts| opts.mark_positioning = position);
+    }
+
+    pub fn mark_positioning(&self) -> bool {
+        self.options().mark_positioning
+    }
+
+    /// Emboldens glyphs by drawing each one twice, the second time `px` pixels to the right, for
+    /// fonts without a bold weight
+    ///
+    /// [`FonsTextIter`](crate::FonsTextIter) yields the second quad right after each glyph, and
+    /// advances are widened by `px`. Glyph-level iterators such as
+    /// [`GlyphIter`](crate::GlyphIter) yield one quad per glyph. Text is measured on the Rust side
+    /// while it's enabled.
+    pub fn set_faux_bold(&self, px: f32) {
+        self.update_options(|opts| opts.faux_bold = px.max(0.0));
+    }
+
+    pub fn faux_bold(&self) -> f32 {
+        self.options().faux_bold
+    }
+
+    /// Shears glyph quads by `slant` pixels per pixel above the baseline (around `0.2`), for fonts
+    /// without an italic style. See [`Quad::sheared`](crate::Quad::sheared)
+    ///
+    /// Advances are not changed.
+    pub fn set_faux_italic(&self, slant: f32) {
+        self.update_options(|opts| opts.faux_italic = slant);
+    }
+
+    pub fn faux_italic(&self) -> f32 {
+        self.options().faux_italic
+    }
+
+    /// Sets the OpenType features the shaping backends (`shape`, `harfbuzz`) apply, e.g.
+    /// `&[("smcp", 1), ("tnum", 1)]`. See [`FontFeatures::parse`]
+    ///
+    /// Features given to the shaping call are applied after them. FontStash's own iterators map
+    /// characters to glyphs one by one, so they ignore the features.
+    pub fn set_font_features(&self, features: &[(&str, u32)]) -> Result<()> {
+        let features = FontFeatures::parse(features)?;
+        self.update_options(|opts| opts.font_features = features);
+        Ok(())
+    }
+
+    pub fn font_features(&self) -> FontFeatures {
+        self.options().font_features
+    }
+
+    /// The text normalized to NFC and shaped (if they're enabled) with [`ControlPolicy`] applied,
+    /// and the offsets of its characters in `text`. Borrowed if nothing is changed
+    pub(crate) fn prepared_text<'a>(&self, text: Cow<'a, str>) -> (Cow<'a, str>, SourceOffsets) {
+        let opts = self.options();
+        // characters of the changed text with their offsets in `text`
+        let mut chars: Option<Vec<(usize, char)>> = None;
+
+        #[cfg(feature = "unicode-normalization")]
+        if opts.normalize_nfc && !unicode_normalization::is_nfc(&text) {
+            chars = Some(self::nfc_chars(&text));
+        }
+
+        #[cfg(feature = "arabic-shaping")]
+        if opts.arabic_shaping {
+            chars = match chars {
+                None if !text.chars().any(crate::arabic::is_shaped) => None,
+                None => Some(crate::arabic::shaped_chars(&text)),
+                Some(chars) => {
+                    let (s, offsets) = SourceOffsets::collect(chars);
+                    let shaped = crate::arabic::shaped_chars(&s);
+                    Some(
+                        shaped
+                            .into_iter()
+                            .map(|(i, c)| (offsets.get(i), c))
+                            .collect(),
+                    )
+                }
+            };
+        }
+
+        let keeps_tab = opts.tab_width.is_some();
+        let is_invisible = |c: char| ControlPolicy::is_invisible(c, keeps_tab);
+        if opts.control_policy != ControlPolicy::PassThrough {
+            let has_invisible = match &chars {
+                Some(chars) => chars.iter().any(|&(_, c)| is_invisible(c)),
+                None => text.chars().any(is_invisible),
+            };
+            if has_invisible {
+                let chars = chars.get_or_insert_with(|| text.char_indices().collect());
+                match opts.control_policy {
+                    ControlPolicy::Skip => chars.retain(|&(_, c)| !is_invisible(c)),
+                    _ => {
+                        for (_, c) in chars.iter_mut().filter(|(_, c)| is_invisible(*c)) {
+                            *c = ControlPolicy::replacement(*c);
+                        }
+                    }
+                }
+            }
+        }
+
+        match chars {
+            Some(chars) => {
+                let (s, offsets) = SourceOffsets::collect(chars);
+                (Cow::Owned(s), offsets)
+            }
+            None => (text, SourceOffsets::default()),
+        }
+    }
+
+    /// `[top, thickness]` of the lines in physical pixels, y-down from the baseline
+    pub(crate) fn decoration_lines(&self, decoration: Decoration) -> [Option<[f32; 2]>; 2] {
+        if decoration.is_empty() {
+            return [None, None];
+        }
+
+        let info = self.state_info();
+        let scale = unsafe { sys::fonsGetPixelScale(self.raw()) };
+        let m = self.decoration_metrics_raw(info.font, info.size, scale);
+
+        [
+            Some(m.underline).filter(|_| decoration.contains(Decoration::UNDERLINE)),
+            Some(m.strikeout).filter(|_| decoration.contains(Decoration::STRIKETHROUGH)),
+        ]
+    }
+
+    /// Underline and strikeout lines of the font at the size (in logical pixels)
+    ///
+    /// The lines are read from the `post` and `OS/2` tables, or guessed from the size if the font
+    /// doesn't have them. [`FontStash::set_decoration`] draws the same lines.
+    pub fn decoration_metrics(&self, font: FontIx, size: f32) -> Result<DecorationMetrics> {
+        self.check_font(font)?;
+        if font.0 as usize >= self.font_count() {
+            return Err(FonsError::InvalidFontIx(font));
+        }
+        let dpi = self.dpi_scale();
+        let size = size * dpi;
+
+        let scale = self.scoped_state(|| unsafe {
+            sys::fonsSetFont(self.raw(), font.0 as c_int);
+            sys::fonsSetSize(self.raw(), size);
+            Ok(sys::fonsGetPixelScale(self.raw()))
+        })?;
+
+        let m = self.decoration_metrics_raw(font.0 as c_int, size, scale);
+        let unscale = |[top, thickness]: [f32; 2]| [top / dpi, thickness / dpi];
+        Ok(DecorationMetrics {
+            underline: unscale(m.underline),
+            strikeout: unscale(m.strikeout),
+        })
+    }
+
+    /// Decoration lines in physical pixels, given the size and the pixel scale of the font. They're
+    /// guessed if the font index is invalid
+    fn decoration_metrics_raw(&self, font: c_int, size: f32, scale: f32) -> DecorationMetrics {
+        let mut m = sys::FONSdecorationMetrics::default();
+        unsafe {
+            sys::fonsGetDecorationMetrics(self.raw(), font, &mut m);
+        }
+
+        // (position, thickness) in font units or guessed from the size in pixels
+        let line = |pos: i32, thickness: i32, guessed_pos: f32| {
+            if thickness > 0 {
+                [-pos as f32 * scale, thickness as f32 * scale]
+            } else {
+                let thickness = (size / 16.0).max(1.0);
+                [-guessed_pos * size, thickness]
+            }
+        };
+
+        DecorationMetrics {
+            underline: line(m.underlinePosition, m.underlineThickness, -0.1),
+            strikeout: line(m.strikeoutPosition, m.strikeoutThickness, 0.3),
+        }
+    }
+}
+
+/// Underline and strikeout lines of a font at some size returned by
+/// [`FontStash::decoration_metrics`]
+///
+/// Lines are `[top, thickness]`, where `top` is y-down from the baseline (positive below it).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DecorationMetrics {
+    pub underline: [f32; 2],
+    pub strikeout: [f32; 2],
+}
+
+/// State stack
+impl FontStash {
+    pub fn push_state(&mut self) {
+        unsafe {
+            sys::fonsPushState(self.raw());
+        }
+    }
+
+    pub fn pop_state(&mut self) {
+        unsafe {
+            sys::fonsPopState(self.raw());
+        }
+    }
+
+    pub fn clear_state(&mut self) {
+        unsafe {
+            sys::fonsClearState(self.raw());
+        }
+    }
+
+    /// Pushes the state or returns an error if the state stack is full
+    ///
+    /// [`FontStash::push_state`] only reports the overflow through the error callback.
+    pub fn try_push_state(&mut self) -> Result<()> {
+        if self.state_depth() >= Self::max_states() {
+            return Err(FonsError::StatesOverflow());
+        }
+        self.push_state();
+        Ok(())
+    }
+
+    /// Number of states in the stack, including the current one
+    pub fn state_depth(&self) -> usize {
+        unsafe { sys::fonsGetStateDepth(self.raw()) as usize }
+    }
+
+    /// Maximum depth of the state stack. Set `FONTSTASH_MAX_STATES` when building
+    /// `fontstash-sys` to change it
+    pub fn max_states() -> usize {
+        unsafe { sys::fonsGetMaxStates() as usize }
+    }
+
+    /// Pushes the state and returns a guard that pops it on drop
+    ///
+    /// The guard dereferences to the stash, so set states through it. Fails with
+    /// [`FonsError::StatesOverflow`] if the state stack is full (nothing is pushed or popped).
+    pub fn state_scope(&mut self) -> Result<StateGuard<'_>> {
+        self.try_push_state()?;
+        Ok(StateGuard { stash: self })
+    }
+
+    /// Runs the closure with a pushed state. The state is popped even if the closure panics
+    ///
+    /// Fails with [`FonsError::StatesOverflow`] without running the closure if the state stack is
+    /// full.
+    pub fn with_state<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> Result<T> {
+        let mut guard = self.state_scope()?;
+        Ok(f(&mut guard))
+    }
+}
+
+/// States saved with [`FontStash::snapshot`] or [`FontStash::snapshot_stack`] and re-applied
+/// with [`FontStash::restore`], e.g. across frames of immediate-mode UIs or for replaying draws
+///
+/// It holds the states of FontStash and the Rust-side text options (tab width, pixel snapping, DPI
+/// scale, clip rectangle, ...). Sizes are in logical pixels, so they're scaled with the DPI scale
+/// of the snapshot when restored.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateSnapshot {
+    /// States from the bottom of the stack. The last one is the current state
+    styles: Vec<TextStyle>,
+    is_stack: bool,
+    options: TextOptions,
+}
+
+impl StateSnapshot {
+    /// The current state
+    pub fn style(&self) -> &TextStyle {
+        // there's always the current state
+        self.styles.last().unwrap()
+    }
+
+    /// States from the bottom of the stack, ending with the current one. It's only the current
+    /// one unless the snapshot was taken with [`FontStash::snapshot_stack`]
+    ///
+    /// The Rust-side fields (faux bold and italic, font features) are not stacked, so they're the
+    /// current ones in every state.
+    pub fn stack(&self) -> &[TextStyle] {
+        &self.styles
+    }
+
+    /// If the snapshot restores the whole state stack
+    pub fn is_stack(&self) -> bool {
+        self.is_stack
+    }
+}
+
+/// Snapshots
+impl FontStash {
+    /// Saves the current state and the Rust-side text options
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            styles: alloc::vec![self.style()],
+            is_stack: false,
+            options: self.options(),
+        }
+    }
+
+    /// [`FontStash::snapshot`] with the states pushed under the current one
+    pub fn snapshot_stack(&self) -> StateSnapshot {
+        let current = self.style();
+        let dpi = self.dpi_scale();
+        let depth = self.state_depth();
+
+        let mut styles = Vec::with_capacity(depth);
+        for i in 0..depth.saturating_sub(1) {
+            let mut info: sys::FONSstateInfo = unsafe { core::mem::zeroed() };
+            if unsafe { sys::fonsGetStateInfoAt(self.raw(), i as c_int, &mut info) } == 0 {
+                break;
+            }
+            styles.push(TextStyle {
+                font: self.font_ix(info.font as u32),
+                size: info.size / dpi,
+                color: Color::from_packed(info.color),
+                blur: info.blur / dpi,
+                spacing: info.spacing / dpi,
+                align: Align::from_bits_truncate(info.align as u32),
+                ..current
+            });
+        }
+        styles.push(current);
+
+        StateSnapshot {
+            styles,
+            is_stack: true,
+            options: self.options(),
+        }
+    }
+
+    /// Applies the snapshot. The text options are replaced first
+    ///
+    /// A snapshot of [`FontStash::snapshot`] replaces the current state only. One of
+    /// [`FontStash::snapshot_stack`] pops the stack down to the bottom state and pushes the
+    /// states again, so the depth becomes the one of the snapshot. It fails with
+    /// [`FonsError::StatesOverflow`] without changing anything if the stack is deeper than
+    /// [`FontStash::max_states`].
+    pub fn restore(&mut self, snapshot: &StateSnapshot) -> Result<()> {
+        if snapshot.styles.len() > Self::max_states() {
+            return Err(FonsError::StatesOverflow());
+        }
+
+        self.update_options(|opts| *opts = snapshot.options);
+        if !snapshot.is_stack {
+            self.apply_style(snapshot.style());
+            return Ok(());
+        }
+
+        // `fonsClearState` only resets the current state
+        while self.state_depth() > 1 {
+            self.pop_state();
+        }
+        self.clear_state();
+        for (i, style) in snapshot.styles.iter().enumerate() {
+            if i != 0 {
+                self.push_state();
+            }
+            self.apply_style(style);
+        }
+        Ok(())
+    }
+}
+
+/// Pops the state on drop. Created with [`FontStash::state_scope`]
+#[derive(Debug)]
+pub struct StateGuard<'a> {
+    stash: &'a mut FontStash,
+}
+
+impl<'a> core::ops::Deref for StateGuard<'a> {
+    type Target = FontStash;
+
+    fn deref(&self) -> &Self::Target {
+        self.stash
+    }
+}
+
+impl<'a> core::ops::DerefMut for StateGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.stash
+    }
+}
+
+impl<'a> Drop for StateGuard<'a> {
+    fn drop(&mut self) {
+        self.stash.pop_state();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font;
+
+    /// `(codepoint, byte offset)` of the glyphs of `text` in the test font
+    fn glyphs(stash: &FontStash, text: &str) -> Vec<(char, usize)> {
+        stash
+            .glyph_iter(text)
+            .unwrap()
+            .map(|g| (g.codepoint, g.byte_offset))
+            .collect()
+    }
+
+    #[test]
+    fn dpi_scale() {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_dpi_scale(2.0);
+        stash.set_size(20.0);
+        assert_eq!(stash.size(), 20.0);
+
+        // rasterized at 40 pixels (16 pixels high and one pixel of padding on each side)
+        let quad = stash.text_iter_at([0.0, 0.0], "a").unwrap().next().unwrap();
+        let uv = quad.uv_rect();
+        assert_eq!((uv[2] - uv[0]) * 512.0, 18.0);
+        // and placed in logical pixels
+        assert_eq!(quad.position_rect(), [0.5, -14.5, 9.5, 0.5]);
+        assert_eq!(stash.text_size_oneline("ab")[0], 20.0);
+        assert_eq!(stash.vert_metrics().line_height, 20.0);
+
+        stash.set_dpi_scale(0.0);
+        stash.set_dpi_scale(f32::NAN);
+        assert_eq!(stash.dpi_scale(), 2.0);
+    }
+
+    #[test]
+    fn control_policy_source_offsets() {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_size(20.0);
+
+        stash.set_control_policy(ControlPolicy::Skip);
+        assert_eq!(
+            self::glyphs(&stash, "a\u{200b}b\rc"),
+            [('a', 0), ('b', 4), ('c', 6)]
+        );
+
+        stash.set_control_policy(ControlPolicy::Replace);
+        assert_eq!(
+            self::glyphs(&stash, "a\rb"),
+            [('a', 0), ('\u{240d}', 1), ('b', 2)]
+        );
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn nfc_source_offsets() {
+        let (stash, font) = test_font::stash();
+        stash.set_font(font);
+        stash.set_size(20.0);
+
+        let text = "ae\u{301}x";
+        assert_eq!(
+            self::glyphs(&stash, text),
+            [('a', 0), ('\u{e9}', 1), ('x', 4)]
+        );
+        // carets are at the boundaries of the source text
+        assert_eq!(stash.byte_index_at([0.0, 0.0], text, [12.0, 0.0]), 1);
+        assert_eq!(stash.byte_index_at([0.0, 0.0], text, [24.0, 0.0]), 4);
+        assert_eq!(stash.caret_position([0.0, 0.0], text, 4), [20.0, 0.0]);
+
+        // with the control policy applied after the normalization
+        stash.set_control_policy(ControlPolicy::Skip);
+        assert_eq!(
+            self::glyphs(&stash, "\re\u{301}\u{200b}x"),
+            [('\u{e9}', 1), ('x', 7)]
+        );
+
+        stash.set_normalize_nfc(false);
+        assert_eq!(self::glyphs(&stash, "e\u{301}").len(), 2);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn nfc_runs() {
+        assert_eq!(
+            self::nfc_chars("ae\u{301}\u{323}b"),
+            [(0, 'a'), (1, '\u{1eb9}'), (1, '\u{301}'), (6, 'b')]
+        );
+        // conjoining jamo are starters composing with the previous ones
+        assert_eq!(
+            self::nfc_chars("\u{1100}\u{1161}\u{11a8}a"),
+            [(0, '\u{ac01}'), (9, 'a')]
+        );
+        assert_eq!(self::nfc_chars("\u{c5}"), [(0, '\u{c5}')]);
+    }
+
+    #[test]
+    fn color_byte_order() {
+        let color = Color::rgba(0x12, 0x34, 0x56, 0x78);
+        assert_eq!(color.to_hex(), 0x1234_5678);
+        assert_eq!(color.packed(), 0x7856_3412);
+        assert_eq!(color.packed().to_le_bytes(), [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(<[u8; 4]>::from(color), [0x12, 0x34, 0x56, 0x78]);
+
+        assert_eq!(Color::from_hex(0x1234_5678), color);
+        assert_eq!(Color::from_packed(0x7856_3412), color);
+        assert_eq!(Color::from(0x7856_3412), color);
+        assert_eq!(u32::from(color), 0x7856_3412);
+
+        // opaque red of `glfonsRGBA(255, 0, 0, 255)`
+        assert_eq!(Color::rgb(255, 0, 0).packed(), 0xff00_00ff);
+        assert_eq!(Color::WHITE.packed(), u32::MAX);
+        assert_eq!(Color::TRANSPARENT.packed(), 0);
+    }
+
+    #[test]
+    fn color_round_trip() {
+        for x in [0, 1, 0xff, 0x8000_0001, 0x1234_5678, 0xdead_beef, u32::MAX] {
+            assert_eq!(Color::from_hex(x).to_hex(), x);
+            assert_eq!(Color::from_packed(x).packed(), x);
+            assert_eq!(u32::from(Color::from(x)), x);
+            // the two formats are each other's byte swap
+            assert_eq!(Color::from_hex(x).packed(), x.swap_bytes());
+        }
+    }
+}
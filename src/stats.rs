@@ -0,0 +1,167 @@
+/*!
+Runtime statistics returned by [`FontStash::stats`]
+
+The callbacks of `FONScontext` are interposed when the stash is created, in the same way as
+the update aggregation (see [`FontStash::set_update_aggregation`]), so that texture updates and
+expansions requested by FontStash itself are counted. Rasterized glyphs are counted by comparing
+the number of glyphs in the atlas before and after each glyph is iterated.
+*/
+
+use core::{
+    cell::Cell,
+    ffi::{c_int, c_uchar, c_void},
+    ptr::NonNull,
+};
+
+use alloc::boxed::Box;
+
+use crate::{sys, FontStash};
+
+/// Counters accumulated since the stash was created or [`FontStash::reset_stats`] was called
+///
+/// * `glyphs_rasterized`: glyphs added to the atlas while iterating text
+/// * `cache_hits`: glyphs iterated without rasterization
+/// * `atlas_resets`: calls of [`FontStash::reset_atlas`] (including the ones by
+///   [`FontStash::clear_fonts`])
+/// * `atlas_expansions`: successful [`FontStash::expand_atlas`] and `renderExpand` calls
+/// * `texture_updates`: `renderUpdate` calls, or dirty rectangles taken while aggregating updates
+/// * `bytes_uploaded`: size of the updated regions (one byte per pixel)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub glyphs_rasterized: u64,
+    pub cache_hits: u64,
+    pub atlas_resets: u64,
+    pub atlas_expansions: u64,
+    pub texture_updates: u64,
+    pub bytes_uploaded: u64,
+}
+
+/// User pointer and callbacks replaced for counting
+#[derive(Debug)]
+pub(crate) struct Counters {
+    params: sys::FONSparams,
+    stats: Cell<Stats>,
+    /// Number of glyphs in the atlas after the last iterated glyph
+    glyph_count: Cell<u32>,
+    /// If `renderExpand` succeeded since the last atlas events were fired
+    expanded: Cell<bool>,
+}
+
+impl Counters {
+    /// Replaces the callbacks of `raw` with the counting ones
+    pub unsafe fn install(raw: NonNull<sys::FONScontext>) -> Box<Self> {
+        let mut params: sys::FONSparams = core::mem::zeroed();
+        sys::fonsGetRenderCallbacks(raw.as_ptr(), &mut params);
+
+        let counters = Box::new(Self {
+            params,
+            stats: Cell::new(Stats::default()),
+            glyph_count: Cell::new(0),
+            expanded: Cell::new(false),
+        });
+
+        let mut replaced = params;
+        replaced.userPtr = &*counters as *const Self as *mut c_void;
+        replaced.renderCreate = Some(create);
+        replaced.renderResize = Some(resize);
+        replaced.renderExpand = Some(expand);
+        replaced.renderUpdate = Some(update);
+        replaced.renderDelete = Some(delete);
+        sys::fonsSetRenderCallbacks(raw.as_ptr(), &replaced);
+
+        counters
+    }
+
+    pub fn bump(&self, f: impl FnOnce(&mut Stats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+
+    pub fn take_expanded(&self) -> bool {
+        self.expanded.take()
+    }
+}
+
+unsafe fn counters<'a>(uptr: *mut c_void) -> &'a Counters {
+    &*(uptr as *const Counters)
+}
+
+unsafe extern "C" fn create(uptr: *mut c_void, width: c_int, height: c_int) -> c_int {
+    let c = counters(uptr);
+    c.params
+        .renderCreate
+        .map_or(1, |f| f(c.params.userPtr, width, height))
+}
+
+unsafe extern "C" fn resize(uptr: *mut c_void, width: c_int, height: c_int) -> c_int {
+    let c = counters(uptr);
+    c.params
+        .renderResize
+        .map_or(1, |f| f(c.params.userPtr, width, height))
+}
+
+unsafe extern "C" fn expand(uptr: *mut c_void) -> c_int {
+    let c = counters(uptr);
+    let res = c.params.renderExpand.map_or(0, |f| f(c.params.userPtr));
+    if res != 0 {
+        c.bump(|s| s.atlas_expansions += 1);
+        c.expanded.set(true);
+    }
+    res
+}
+
+unsafe extern "C" fn update(uptr: *mut c_void, rect: *mut c_int, data: *const c_uchar) -> c_int {
+    let c = counters(uptr);
+    let r = core::slice::from_raw_parts(rect, 4);
+    c.bump(|s| {
+        s.texture_updates += 1;
+        s.bytes_uploaded += self::area([r[0], r[1], r[2], r[3]]);
+    });
+    c.params
+        .renderUpdate
+        .map_or(1, |f| f(c.params.userPtr, rect, data))
+}
+
+unsafe extern "C" fn delete(uptr: *mut c_void) {
+    let c = counters(uptr);
+    if let Some(f) = c.params.renderDelete {
+        f(c.params.userPtr);
+    }
+}
+
+/// Number of pixels in `[x0, y0, x1, y1]`
+pub(crate) fn area(rect: [c_int; 4]) -> u64 {
+    let w = (rect[2] - rect[0]).max(0) as u64;
+    let h = (rect[3] - rect[1]).max(0) as u64;
+    w * h
+}
+
+/// Statistics
+impl FontStash {
+    /// Counters accumulated since the stash was created or the last [`FontStash::reset_stats`]
+    pub fn stats(&self) -> Stats {
+        self.fons.counters.stats.get()
+    }
+
+    pub fn reset_stats(&self) {
+        self.fons.counters.stats.set(Stats::default());
+    }
+
+    pub(crate) fn bump_stats(&self, f: impl FnOnce(&mut Stats)) {
+        self.fons.counters.bump(f);
+    }
+
+    /// Counts an iterated glyph as a rasterization or a cache hit
+    pub(crate) fn count_glyph(&self) {
+        let n = unsafe { sys::fonsGetGlyphRects(self.raw(), core::ptr::null_mut(), 0) } as u32;
+        let counters = &self.fons.counters;
+        // the count decreases when the atlas is reset or glyphs are evicted
+        let added = n.saturating_sub(counters.glyph_count.get());
+        counters.glyph_count.set(n);
+        counters.bump(|s| match added {
+            0 => s.cache_hits += 1,
+            n => s.glyphs_rasterized += n as u64,
+        });
+    }
+}
@@ -0,0 +1,251 @@
+/*!
+SVG export of laid-out text, e.g. for vector reports or debugging layout geometry
+
+```text
+let layout = stash.prepare(text, &style, Some(320.0))?;
+let mut svg = std::fs::File::create("layout.svg")?;
+stash.export_svg(&layout, text, &style, SvgOptions::default(), &mut svg)?;
+```
+
+Each line is written as a `<text>` run with the pen position of every character, so viewers draw
+the glyphs at the positions FontStash laid them out, given the font is installed. With
+[`SvgOptions::outlines`], glyphs are written as `<path>`s of their outlines instead (see
+[`crate::outline`]), which look the same everywhere.
+*/
+
+use core::fmt;
+use std::io::Write;
+
+use crate::{layout::TextLayout, outline::PathSegment, FontStash, Result, TextStyle};
+
+/// Options of [`FontStash::export_svg`]
+///
+/// * `outlines`: writes the glyphs as `<path>`s instead of `<text>`. Characters missing in the
+///   font of the style are skipped (fallback fonts are not considered)
+/// * `line_boxes`: draws [`crate::layout::LineBox`]es and their baselines
+/// * `glyph_boxes`: draws the rectangles of the quads
+/// * `background`: fills the bounds of the layout first
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SvgOptions {
+    pub outlines: bool,
+    pub line_boxes: bool,
+    pub glyph_boxes: bool,
+    pub background: Option<crate::Color>,
+}
+
+/// SVG
+impl FontStash {
+    /// Writes `layout` (prepared for `text` with `style`, see [`FontStash::prepare`]) as an SVG
+    /// document to `writer`
+    ///
+    /// The view box is the bounds of the layout, so the origin of the layout is at `(0, 0)` of the
+    /// user space. The font family is the one in the `name` table of the font (or the name given
+    /// to [`FontStash::add_font_mem`]). Faux bold and italic are written as `font-weight` and
+    /// `font-style`.
+    pub fn export_svg(
+        &self,
+        layout: &TextLayout,
+        text: &str,
+        style: &TextStyle,
+        opts: SvgOptions,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        let family = self
+            .font_info(style.font)
+            .map(|info| info.family.unwrap_or(info.name))
+            .unwrap_or_default();
+        let [x0, y0, _, _] = layout.bounds();
+        let [w, h] = layout.size();
+
+        writeln!(
+            writer,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
+            Num(w),
+            Num(h),
+            Num(x0),
+            Num(y0),
+            Num(w),
+            Num(h),
+        )?;
+
+        if let Some(color) = opts.background {
+            writeln!(
+                writer,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+                Num(x0),
+                Num(y0),
+                Num(w),
+                Num(h),
+                Paint("fill", color),
+            )?;
+        }
+
+        write!(
+            writer,
+            "<g font-family=\"{}\" font-size=\"{}\" {}",
+            Escape(&family),
+            Num(style.size),
+            Paint("fill", style.color),
+        )?;
+        if style.faux_bold != 0.0 {
+            write!(writer, " font-weight=\"bold\"")?;
+        }
+        if style.faux_italic != 0.0 {
+            write!(writer, " font-style=\"italic\"")?;
+        }
+        writeln!(writer, ">")?;
+
+        for (line, stops) in layout.lines().iter().zip(&layout.carets) {
+            // the last stop is the end of the line
+            let stops = &stops[..stops.len().saturating_sub(1)];
+            let chars = stops
+                .iter()
+                .filter_map(|&(i, x)| Some((text.get(i..)?.chars().next()?, x)))
+                .filter(|(c, _)| !c.is_whitespace() && !c.is_control());
+            if chars.clone().next().is_none() {
+                continue;
+            }
+
+            if opts.outlines {
+                for (c, x) in chars {
+                    let outline = self.glyph_outline_at(style.font, c, style.size)?;
+                    self::write_path(writer, &outline, [x, line.baseline], style.faux_italic)?;
+                }
+                continue;
+            }
+
+            write!(writer, "<text x=\"")?;
+            for (i, (_, x)) in chars.clone().enumerate() {
+                let sep = if i == 0 { "" } else { " " };
+                write!(writer, "{}{}", sep, Num(x))?;
+            }
+            write!(writer, "\" y=\"{}\">", Num(line.baseline))?;
+            for (c, _) in chars {
+                let mut buf = [0; 4];
+                write!(writer, "{}", Escape(c.encode_utf8(&mut buf)))?;
+            }
+            writeln!(writer, "</text>")?;
+        }
+        writeln!(writer, "</g>")?;
+
+        if opts.glyph_boxes {
+            writeln!(
+                writer,
+                "<g fill=\"none\" stroke=\"#00c0ff\" stroke-width=\"0.5\">"
+            )?;
+            for q in layout.quads().iter().filter(|q| !q.is_empty()) {
+                self::write_rect(writer, q.position_rect())?;
+            }
+            writeln!(writer, "</g>")?;
+        }
+
+        if opts.line_boxes {
+            writeln!(
+                writer,
+                "<g fill=\"none\" stroke=\"#ff4040\" stroke-width=\"0.5\">"
+            )?;
+            for line in layout.lines() {
+                self::write_rect(writer, line.rect)?;
+                let [left, _, right, _] = line.rect;
+                writeln!(
+                    writer,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+                    Num(left),
+                    Num(line.baseline),
+                    Num(right),
+                    Num(line.baseline),
+                )?;
+            }
+            writeln!(writer, "</g>")?;
+        }
+
+        writeln!(writer, "</svg>")?;
+        Ok(())
+    }
+}
+
+fn write_rect(writer: &mut impl Write, [x0, y0, x1, y1]: [f32; 4]) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+        Num(x0),
+        Num(y0),
+        Num(x1 - x0),
+        Num(y1 - y0),
+    )
+}
+
+/// `<path>` of the outline with the pen at `pen`, sheared by `slant` around the baseline
+fn write_path(
+    writer: &mut impl Write,
+    outline: &[PathSegment],
+    pen: [f32; 2],
+    slant: f32,
+) -> std::io::Result<()> {
+    if outline.is_empty() {
+        return Ok(());
+    }
+
+    let point = |[x, y]: [f32; 2]| {
+        let [x, y] = [pen[0] + x - slant * y, pen[1] + y];
+        format!("{} {}", Num(x), Num(y))
+    };
+    write!(writer, "<path d=\"")?;
+    for (i, seg) in outline.iter().enumerate() {
+        let sep = if i == 0 { "" } else { " " };
+        match *seg {
+            PathSegment::MoveTo(p) => write!(writer, "{}M{}", sep, point(p))?,
+            PathSegment::LineTo(p) => write!(writer, "{}L{}", sep, point(p))?,
+            PathSegment::QuadTo(c, p) => write!(writer, "{}Q{} {}", sep, point(c), point(p))?,
+            PathSegment::CubicTo(c0, c1, p) => {
+                write!(writer, "{}C{} {} {}", sep, point(c0), point(c1), point(p))?
+            }
+            PathSegment::Close => write!(writer, "{}Z", sep)?,
+        }
+    }
+    writeln!(writer, "\"/>")
+}
+
+/// Number rounded to 1/100 pixels
+struct Num(f32);
+
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = (self.0 * 100.0).round() / 100.0;
+        // no `-0`
+        write!(f, "{}", if v == 0.0 { 0.0 } else { v })
+    }
+}
+
+/// `fill="#rrggbb"` with the opacity if it's translucent
+struct Paint(&'static str, crate::Color);
+
+impl fmt::Display for Paint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Paint(attr, c) = *self;
+        write!(f, "{}=\"#{:02x}{:02x}{:02x}\"", attr, c.r, c.g, c.b)?;
+        if c.a != 255 {
+            write!(f, " {}-opacity=\"{}\"", attr, Num(c.a as f32 / 255.0))?;
+        }
+        Ok(())
+    }
+}
+
+/// Text escaped for XML. Characters XML can't have (control characters) are dropped
+struct Escape<'a>(&'a str);
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                c if c.is_control() => {}
+                c => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+/*!
+System fonts (`fontdb` feature)
+
+Platform fonts are located with `fontdb` and loaded into the stash. The font data is copied into
+the stash, so it doesn't have to be kept alive by the user.
+
+The system font database is scanned once per thread on the first query.
+*/
+
+use fontdb::{Database, Family, Query, Stretch, Style, Weight};
+
+use crate::{FonsError, FontIx, FontStash, Result};
+
+thread_local! {
+    static DATABASE: Database = {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        db
+    };
+}
+
+/// Style of a system font to look up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SystemFontStyle {
+    #[default]
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl SystemFontStyle {
+    fn weight(self) -> Weight {
+        match self {
+            Self::Regular | Self::Italic => Weight::NORMAL,
+            Self::Bold | Self::BoldItalic => Weight::BOLD,
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Self::Regular | Self::Bold => Style::Normal,
+            Self::Italic | Self::BoldItalic => Style::Italic,
+        }
+    }
+}
+
+/// System fonts
+impl FontStash {
+    /// Loads a platform font of the family. Fonts that are already loaded are reused
+    ///
+    /// The font is named as `family:style`, e.g. `Noto Sans:Bold`.
+    pub fn add_system_font(&self, family: &str, style: SystemFontStyle) -> Result<FontIx> {
+        self.add_system_font_impl(&[Family::Name(family)], family, style)
+    }
+
+    /// Loads the default sans-serif font of the platform, named `sans-serif:Regular`
+    pub fn add_default_ui_font(&self) -> Result<FontIx> {
+        self.add_system_font_impl(&[Family::SansSerif], "sans-serif", SystemFontStyle::Regular)
+    }
+
+    fn add_system_font_impl(
+        &self,
+        families: &[Family],
+        family: &str,
+        style: SystemFontStyle,
+    ) -> Result<FontIx> {
+        let name = format!("{}:{:?}", family, style);
+        if let Some(font) = self.font_ix_by_name(&name) {
+            return Ok(font);
+        }
+
+        let query = Query {
+            families,
+            weight: style.weight(),
+            stretch: Stretch::Normal,
+            style: style.style(),
+        };
+
+        DATABASE.with(|db| {
+            let id = db.query(&query).ok_or(FonsError::FoundNoFont())?;
+            db.with_face_data(id, |data, face_index| {
+                self.add_font_mem_copy_indexed(&name, data, face_index)
+            })
+            .unwrap_or(Err(FonsError::FoundNoFont()))
+        })
+    }
+}
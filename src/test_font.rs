@@ -0,0 +1,130 @@
+/*!
+Monospace font built in memory for the unit tests
+
+Every character from U+0020 to U+FFFF is the same rectangle advancing half an em, so widths in
+tests are `chars * size / 2`. The ascender is `0.8 em` and the descender is `-0.2 em`.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{FontIx, FontStash};
+
+const UNITS_PER_EM: u16 = 1000;
+const ADVANCE: u16 = 500;
+
+fn u16s(out: &mut Vec<u8>, xs: &[u16]) {
+    for x in xs {
+        out.extend_from_slice(&x.to_be_bytes());
+    }
+}
+
+fn u32s(out: &mut Vec<u8>, xs: &[u32]) {
+    for x in xs {
+        out.extend_from_slice(&x.to_be_bytes());
+    }
+}
+
+/// `i16` as stored in the tables
+const fn neg(x: u16) -> u16 {
+    x.wrapping_neg()
+}
+
+fn cmap() -> Vec<u8> {
+    let mut t = Vec::new();
+    // one (3, 10) subtable of format 13 mapping the range to glyph 1
+    u16s(&mut t, &[0, 1, 3, 10]);
+    u32s(&mut t, &[12]);
+    u16s(&mut t, &[13, 0]);
+    u32s(&mut t, &[16 + 12, 0, 1, 0x20, 0xffff, 1]);
+    t
+}
+
+fn glyf() -> Vec<u8> {
+    let mut t = Vec::new();
+    // clockwise rectangle of (50, 0) to (450, 700)
+    u16s(&mut t, &[1, 50, 0, 450, 700, 3, 0]);
+    t.extend_from_slice(&[1; 4]);
+    u16s(&mut t, &[50, 0, 400, 0]);
+    u16s(&mut t, &[0, 700, 0, neg(700)]);
+    t.resize(36, 0);
+    t
+}
+
+fn head() -> Vec<u8> {
+    let mut t = Vec::new();
+    u32s(&mut t, &[0x0001_0000, 0x0001_0000, 0, 0x5f0f_3cf5]);
+    u16s(&mut t, &[3, UNITS_PER_EM]);
+    t.extend_from_slice(&[0; 16]);
+    u16s(&mut t, &[50, 0, 450, 700, 0, 8, 2, 0, 0]);
+    t
+}
+
+fn hhea() -> Vec<u8> {
+    let mut t = Vec::new();
+    u32s(&mut t, &[0x0001_0000]);
+    u16s(&mut t, &[800, neg(200), 0, ADVANCE, 50, 50, 450, 1, 0, 0]);
+    u16s(&mut t, &[0, 0, 0, 0, 0, 1]);
+    t
+}
+
+fn hmtx() -> Vec<u8> {
+    let mut t = Vec::new();
+    u16s(&mut t, &[ADVANCE, 0, 50]);
+    t
+}
+
+fn loca() -> Vec<u8> {
+    let mut t = Vec::new();
+    u16s(&mut t, &[0, 0, 18]);
+    t
+}
+
+fn maxp() -> Vec<u8> {
+    let mut t = Vec::new();
+    u32s(&mut t, &[0x0001_0000]);
+    u16s(&mut t, &[2, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+    t
+}
+
+/// The sfnt data of the font
+pub(crate) fn data() -> Vec<u8> {
+    self::data_with(Vec::new())
+}
+
+/// The sfnt data of the font with `extra` tables, e.g. a stub color table
+pub(crate) fn data_with(extra: Vec<(&[u8; 4], Vec<u8>)>) -> Vec<u8> {
+    let mut tables = alloc::vec![
+        (b"cmap", self::cmap()),
+        (b"glyf", self::glyf()),
+        (b"head", self::head()),
+        (b"hhea", self::hhea()),
+        (b"hmtx", self::hmtx()),
+        (b"loca", self::loca()),
+        (b"maxp", self::maxp()),
+    ];
+    tables.extend(extra);
+    tables.sort_by_key(|(tag, _)| **tag);
+
+    let mut data = Vec::new();
+    u32s(&mut data, &[0x0001_0000]);
+    u16s(&mut data, &[tables.len() as u16, 64, 2, 48]);
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend_from_slice(&tag[..]);
+        u32s(&mut data, &[0, offset as u32, table.len() as u32]);
+        offset += (table.len() + 3) & !3;
+    }
+    for (_, table) in &tables {
+        data.extend_from_slice(table);
+        data.resize((data.len() + 3) & !3, 0);
+    }
+    data
+}
+
+/// Stash without a renderer and the font in it
+pub(crate) fn stash() -> (FontStash, FontIx) {
+    let stash = FontStash::builder(512, 512).build().unwrap();
+    let font = stash.add_font_mem_copy("mono", &self::data()).unwrap();
+    (stash, font)
+}
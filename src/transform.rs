@@ -0,0 +1,317 @@
+/*!
+2D affine transform of quadliterals, e.g. for rotated labels or text in camera space
+
+A transformed quad is not axis-aligned anymore, so it's yielded as four corners in the same order
+as [`vertex::corners`](crate::vertex::corners).
+*/
+
+use core::ops::Mul;
+
+use alloc::vec::Vec;
+
+use crate::{Align, FonsTextIter, FontStash, GlyphIter, GlyphQuad, Quad, Result};
+
+/// `(pos, uv)` of the four corners of a transformed quad in the order of
+/// [`vertex::corners`](crate::vertex::corners)
+pub type Corners = [([f32; 2], [f32; 2]); 4];
+
+/// Row-major 2x3 matrix: `[[a, b, tx], [c, d, ty]]` maps `[x, y]` to
+/// `[a * x + b * y + tx, c * x + d * y + ty]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub m: [[f32; 3]; 2],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+    };
+
+    pub fn translate(dx: f32, dy: f32) -> Self {
+        Self {
+            m: [[1.0, 0.0, dx], [0.0, 1.0, dy]],
+        }
+    }
+
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self {
+            m: [[sx, 0.0, 0.0], [0.0, sy, 0.0]],
+        }
+    }
+
+    /// Rotation about the origin. It's clockwise on screen because y goes down
+    #[cfg(feature = "std")]
+    pub fn rotate(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self::from_sin_cos(sin, cos)
+    }
+
+    /// Rotation about the origin from the sine and the cosine of the angle (works without `std`)
+    pub fn from_sin_cos(sin: f32, cos: f32) -> Self {
+        Self {
+            m: [[cos, -sin, 0.0], [sin, cos, 0.0]],
+        }
+    }
+
+    /// Transform that applies `self` and then `next`
+    pub fn then(self, next: Self) -> Self {
+        next * self
+    }
+
+    pub fn apply(&self, p: [f32; 2]) -> [f32; 2] {
+        let [r0, r1] = self.m;
+        [
+            r0[0] * p[0] + r0[1] * p[1] + r0[2],
+            r1[0] * p[0] + r1[1] * p[1] + r1[2],
+        ]
+    }
+
+    /// Corners of the quad `(pos, uv)` in the order of [`vertex::corners`](crate::vertex::corners)
+    pub fn apply_quad(&self, q: &Quad) -> Corners {
+        let mut corners = crate::vertex::corners(q);
+        for (pos, _uv) in &mut corners {
+            *pos = self.apply(*pos);
+        }
+        corners
+    }
+}
+
+/// `a * b` applies `b` first
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let [a, b] = [self.m, rhs.m];
+        let row = |r: [f32; 3]| {
+            [
+                r[0] * b[0][0] + r[1] * b[1][0],
+                r[0] * b[0][1] + r[1] * b[1][1],
+                r[0] * b[0][2] + r[1] * b[1][2] + r[2],
+            ]
+        };
+        Self {
+            m: [row(a[0]), row(a[1])],
+        }
+    }
+}
+
+impl From<[[f32; 3]; 2]> for Transform {
+    fn from(m: [[f32; 3]; 2]) -> Self {
+        Self { m }
+    }
+}
+
+/// Iterator of the corners of transformed quads
+#[derive(Debug, Clone)]
+pub struct TransformedIter<I> {
+    inner: I,
+    transform: Transform,
+}
+
+impl<I: Iterator<Item = Quad>> TransformedIter<I> {
+    pub fn new(inner: I, transform: Transform) -> Self {
+        Self { inner, transform }
+    }
+}
+
+impl<I: Iterator<Item = Quad>> Iterator for TransformedIter<I> {
+    type Item = Corners;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let q = self.inner.next()?;
+        Some(self.transform.apply_quad(&q))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Point of a glyph that [`AnimGlyph::posed`] rotates and scales about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pivot {
+    /// Pen position on the baseline
+    Origin,
+    /// Middle of the advance on the baseline, e.g. for swinging glyphs
+    #[default]
+    BaselineCenter,
+    /// Center of the quad, e.g. for spinning glyphs. The baseline center for empty quads
+    Center,
+}
+
+/// Glyph with its placement, yielded by [`FontStash::anim_glyphs_at`]
+///
+/// * `index`: number of the glyphs yielded before it, e.g. for typewriter effects
+/// * `origin`: pen position on the baseline
+/// * `advance`: horizontal distance to the next glyph
+/// * `quad`: axis-aligned quad of the glyph
+#[derive(Debug, Clone, Copy)]
+pub struct AnimGlyph {
+    pub index: usize,
+    pub codepoint: char,
+    pub byte_offset: usize,
+    pub origin: [f32; 2],
+    pub advance: f32,
+    pub quad: Quad,
+}
+
+impl AnimGlyph {
+    fn new(index: usize, glyph: GlyphQuad) -> Self {
+        Self {
+            index,
+            codepoint: glyph.codepoint,
+            byte_offset: glyph.byte_offset,
+            origin: glyph.pen,
+            advance: glyph.x_advance,
+            quad: glyph.quad,
+        }
+    }
+
+    pub fn pivot(&self, pivot: Pivot) -> [f32; 2] {
+        match pivot {
+            Pivot::Origin => self.origin,
+            Pivot::Center if !self.quad.is_empty() => {
+                let r = self.quad.position_rect();
+                [(r[0] + r[2]) / 2.0, (r[1] + r[3]) / 2.0]
+            }
+            Pivot::BaselineCenter | Pivot::Center => {
+                [self.origin[0] + self.advance / 2.0, self.origin[1]]
+            }
+        }
+    }
+
+    /// Corners of the quad with `transform` applied about the `pivot`. Offset the glyph with a
+    /// translation in the transform
+    pub fn posed(&self, pivot: Pivot, transform: impl Into<Transform>) -> Corners {
+        let [x, y] = self.pivot(pivot);
+        Transform::translate(-x, -y)
+            .then(transform.into())
+            .then(Transform::translate(x, y))
+            .apply_quad(&self.quad)
+    }
+}
+
+/// Iterator of [`AnimGlyph`]s
+pub struct AnimGlyphIter<'a> {
+    inner: GlyphIter<'a>,
+    index: usize,
+}
+
+impl<'a> Iterator for AnimGlyphIter<'a> {
+    type Item = AnimGlyph;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let glyph = self.inner.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some(AnimGlyph::new(index, glyph))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> core::iter::FusedIterator for AnimGlyphIter<'a> {}
+
+/// Transformed text
+impl FontStash {
+    /// Iterates glyphs at `pos` with their origins and advances, e.g. for wave or shake effects
+    /// that transform each glyph with [`AnimGlyph::posed`]
+    pub fn anim_glyphs_at<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+    ) -> Result<AnimGlyphIter<'a>> {
+        let pos = pos.into();
+        let inner = self.glyph_iter_at(pos, text)?;
+        Ok(AnimGlyphIter { inner, index: 0 })
+    }
+
+    /// Lays out text at `pos` as [`FontStash::text_iter_at`] and then transforms the quads
+    ///
+    /// `pos` is in the space before the transform, so rotate text about its origin with
+    /// `pos = [0.0, 0.0]` and a transform that moves it.
+    pub fn text_iter_transformed<'a>(
+        &'a self,
+        pos: impl Into<[f32; 2]>,
+        text: &'a str,
+        transform: impl Into<Transform>,
+    ) -> Result<TransformedIter<FonsTextIter<'a>>> {
+        let pos = pos.into();
+        let iter = self.text_iter_at(pos, text)?;
+        Ok(TransformedIter::new(iter, transform.into()))
+    }
+}
+
+/// Text on path
+impl FontStash {
+    /// Places each glyph of `text` along the polyline `points`, rotated to the direction of the
+    /// segment under the center of the glyph, e.g. for labels of roads on a map
+    ///
+    /// The baseline follows the path. Left-aligned text starts at the first point, centered text
+    /// is centered on the path and right-aligned text ends at the last point. Glyphs that don't
+    /// fit on the path are dropped, and so is everything if there are less than two points.
+    pub fn layout_on_path<P: Copy + Into<[f32; 2]>>(
+        &self,
+        points: &[P],
+        text: &str,
+    ) -> Result<Vec<Corners>> {
+        let points = points.iter().map(|&p| p.into()).collect::<Vec<[f32; 2]>>();
+        if points.len() < 2 {
+            return Ok(Vec::new());
+        }
+
+        // arc length at each point
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut total = 0.0;
+        lengths.push(total);
+        for w in points.windows(2) {
+            let [dx, dy] = [w[1][0] - w[0][0], w[1][1] - w[0][1]];
+            total += crate::sqrt(dx * dx + dy * dy);
+            lengths.push(total);
+        }
+
+        let align = self.align();
+        let start = if align.contains(Align::RIGHT) {
+            total
+        } else if align.contains(Align::CENTER) {
+            total / 2.0
+        } else {
+            0.0
+        };
+
+        let mut out = Vec::new();
+        for glyph in self.glyph_iter_at([start, 0.0], text)? {
+            let s = glyph.pen[0] + glyph.x_advance / 2.0;
+            if s < 0.0 || s > total {
+                continue;
+            }
+
+            let i = lengths
+                .partition_point(|&l| l <= s)
+                .clamp(1, points.len() - 1)
+                - 1;
+            let len = lengths[i + 1] - lengths[i];
+            if len <= 0.0 {
+                continue;
+            }
+
+            let [a, b] = [points[i], points[i + 1]];
+            let [cos, sin] = [(b[0] - a[0]) / len, (b[1] - a[1]) / len];
+            let t = s - lengths[i];
+            let transform = Transform::translate(-s, 0.0)
+                .then(Transform::from_sin_cos(sin, cos))
+                .then(Transform::translate(a[0] + cos * t, a[1] + sin * t));
+            out.push(transform.apply_quad(&glyph.quad));
+        }
+
+        Ok(out)
+    }
+}
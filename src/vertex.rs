@@ -0,0 +1,201 @@
+/*!
+Vertex generation from quadliterals
+
+Each [`Quad`] is turned into two triangles:
+
+```text
+0 - 1
+| / |
+3 - 2
+```
+
+Triangles are `[0, 1, 2]` and `[2, 3, 0]`. Use [`write_vertices`] for non-indexed draw calls, or
+[`write_quad_vertices`] and [`quad_indices`] for indexed draw calls. The `write_gradient_*`
+variants color the top and bottom corners differently. [`write_ndc_vertices`] outputs normalized
+device coordinates for renderers without a projection matrix.
+
+For instanced draw calls, [`FontStash::glyph_instances`] produces one [`GlyphInstance`] per glyph.
+*/
+
+use alloc::vec::Vec;
+
+use crate::{Color, FonsQuad, FontStash, Quad, Result};
+
+/// Indices of the two triangles of a quad
+pub const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 3, 0];
+
+/// Vertex layout that [`write_vertices`] can produce
+///
+/// Implement it for your own vertex type to get the layout your shader expects.
+pub trait QuadVertex {
+    fn new(pos: [f32; 2], uv: [f32; 2], color: u32) -> Self;
+}
+
+/// Default vertex layout: interleaved `pos`, `uv` and `color`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct Vertex {
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: u32,
+}
+
+impl QuadVertex for Vertex {
+    fn new(pos: [f32; 2], uv: [f32; 2], color: u32) -> Self {
+        Self { pos, uv, color }
+    }
+}
+
+/// Corners of a quad in the order of `0, 1, 2, 3`: `(pos, uv)`, with the offsets of
+/// [`Quad::sheared`] applied
+pub fn corners(q: &Quad) -> [([f32; 2], [f32; 2]); 4] {
+    let ([x0, y0, x1, y1], [s0, t0, s1, t1]) = (q.position_rect(), q.uv_rect());
+    let [top, bottom] = q.slant_offsets();
+    [
+        ([x0 + top, y0], [s0, t0]),
+        ([x1 + top, y0], [s1, t0]),
+        ([x1 + bottom, y1], [s1, t1]),
+        ([x0 + bottom, y1], [s0, t1]),
+    ]
+}
+
+/// Appends six vertices (two triangles) per quad to `out`
+pub fn write_vertices<V: QuadVertex>(
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    color: impl Into<Color>,
+    out: &mut Vec<V>,
+) {
+    let color = color.into().packed();
+    for q in quads {
+        let c = self::corners(&q.into());
+        for &i in &QUAD_INDICES {
+            let (pos, uv) = c[i as usize];
+            out.push(V::new(pos, uv, color));
+        }
+    }
+}
+
+/// Appends four vertices per quad to `out`. Draw them with [`quad_indices`]
+pub fn write_quad_vertices<V: QuadVertex>(
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    color: impl Into<Color>,
+    out: &mut Vec<V>,
+) {
+    let color = color.into().packed();
+    for q in quads {
+        for &(pos, uv) in &self::corners(&q.into()) {
+            out.push(V::new(pos, uv, color));
+        }
+    }
+}
+
+/// [`write_vertices`] in normalized device coordinates (see [`Quad::to_ndc`])
+///
+/// Flipping y turns the triangles counter-clockwise, which is the default front face of OpenGL.
+pub fn write_ndc_vertices<V: QuadVertex>(
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    viewport: [f32; 2],
+    color: impl Into<Color>,
+    out: &mut Vec<V>,
+) {
+    let quads = quads.into_iter().map(|q| q.into().to_ndc(viewport));
+    self::write_vertices(quads, color, out);
+}
+
+/// Packed colors of the corners of a quad `0, 1, 2, 3` for a vertical gradient
+fn gradient(top: Color, bottom: Color) -> [u32; 4] {
+    let [top, bottom] = [top.packed(), bottom.packed()];
+    [top, top, bottom, bottom]
+}
+
+/// [`write_vertices`] with a vertical gradient across each glyph, from `top` to `bottom`
+pub fn write_gradient_vertices<V: QuadVertex>(
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    top: impl Into<Color>,
+    bottom: impl Into<Color>,
+    out: &mut Vec<V>,
+) {
+    let colors = self::gradient(top.into(), bottom.into());
+    for q in quads {
+        let c = self::corners(&q.into());
+        for &i in &QUAD_INDICES {
+            let (pos, uv) = c[i as usize];
+            out.push(V::new(pos, uv, colors[i as usize]));
+        }
+    }
+}
+
+/// [`write_quad_vertices`] with a vertical gradient across each glyph, from `top` to `bottom`
+pub fn write_gradient_quad_vertices<V: QuadVertex>(
+    quads: impl IntoIterator<Item = impl Into<Quad>>,
+    top: impl Into<Color>,
+    bottom: impl Into<Color>,
+    out: &mut Vec<V>,
+) {
+    let colors = self::gradient(top.into(), bottom.into());
+    for q in quads {
+        for (&(pos, uv), &color) in self::corners(&q.into()).iter().zip(&colors) {
+            out.push(V::new(pos, uv, color));
+        }
+    }
+}
+
+/// Indices for `count` quads written with [`write_quad_vertices`], starting from `base_vertex`
+pub fn quad_indices(count: usize, base_vertex: u32) -> impl Iterator<Item = u32> {
+    (0..count as u32).flat_map(move |i| {
+        let base = base_vertex + i * 4;
+        QUAD_INDICES.iter().map(move |ix| base + ix)
+    })
+}
+
+/// Per-glyph data for instanced rendering
+///
+/// * `pos`: left-up corner of the quad
+/// * `uv_rect`: `[s0, t0, s1, t1]` in the font texture
+///
+/// Instances are axis-aligned, so the slant of [`Quad::sheared`] (faux italic) is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct GlyphInstance {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_rect: [f32; 4],
+}
+
+impl From<Quad> for GlyphInstance {
+    fn from(q: Quad) -> Self {
+        Self {
+            pos: q.left_top(),
+            size: q.size(),
+            uv_rect: q.uv_rect(),
+        }
+    }
+}
+
+impl From<FonsQuad> for GlyphInstance {
+    fn from(q: FonsQuad) -> Self {
+        Quad::from(q).into()
+    }
+}
+
+/// Instancing
+impl FontStash {
+    /// Clears `out` and fills it with a [`GlyphInstance`] per glyph of `text`
+    ///
+    /// Empty glyphs such as spaces are skipped.
+    pub fn glyph_instances(
+        &self,
+        pos: impl Into<[f32; 2]>,
+        text: &str,
+        out: &mut Vec<GlyphInstance>,
+    ) -> Result<()> {
+        let pos = pos.into();
+        out.clear();
+        out.extend(
+            self.text_iter_at(pos, text)?
+                .filter(|q| !q.is_empty())
+                .map(GlyphInstance::from),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+/*!
+Rasterizing glyphs on a worker thread, with the `std` feature
+
+Rasterizing a burst of new glyphs (e.g. CJK text appearing) in the middle of a frame causes a
+hitch. With [`FontStash::set_deferred_glyphs`], glyphs missing in the atlas are queued instead,
+and the text has empty placeholder quads advancing the pen as the glyphs. A [`GlyphWorker`]
+rasterizes the queued glyphs on its thread and inserts them into the atlas at the sync point:
+
+```text
+stash.set_deferred_glyphs(true);
+let mut worker = GlyphWorker::new()?;
+
+// every frame, before drawing and uploading the atlas
+worker.sync(&stash)?;
+for glyph in stash.text_iter(text)?.glyphs() {
+    // `glyph.is_deferred` until the glyph is rasterized
+}
+```
+
+The layout doesn't change when the glyphs arrive. Only glyphs of the font itself without blur are
+deferred; glyphs of the fallbacks, blurred glyphs, color glyphs and glyphs in an SDF atlas or a
+dedicated page are rasterized while drawing as usual.
+*/
+
+use core::ffi::c_int;
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    batch::PageId,
+    raster::{GlyphBitmap, Rasterizer},
+    sys, FonsError, FontStash, Result,
+};
+
+/// Deferred glyphs
+impl FontStash {
+    /// Queues the glyphs missing in the atlas instead of rasterizing them while drawing (see
+    /// [`crate::worker`]). Disabling it drops the queued glyphs, and they're rasterized while
+    /// drawing again
+    pub fn set_deferred_glyphs(&self, enabled: bool) {
+        unsafe {
+            sys::fonsSetDeferredGlyphs(self.raw(), enabled as c_int);
+        }
+        // cached quads don't have placeholders, but measured text does
+        self.invalidate_measure_cache();
+    }
+}
+
+enum Job {
+    Font(c_int, Rasterizer),
+    /// The fonts were reloaded or cleared
+    ClearFonts,
+    Glyph(sys::FONSdeferredGlyph),
+}
+
+struct Done {
+    glyph: sys::FONSdeferredGlyph,
+    /// `None` if it couldn't be rasterized on the thread
+    bitmap: Option<GlyphBitmap>,
+}
+
+/// Thread rasterizing the deferred glyphs of a stash (see [`crate::worker`])
+///
+/// The thread finishes when the worker is dropped.
+#[derive(Debug)]
+pub struct GlyphWorker {
+    jobs: Option<Sender<Job>>,
+    done: Receiver<Done>,
+    thread: Option<JoinHandle<()>>,
+    /// The stash being served
+    page: Option<PageId>,
+    /// Fonts with a rasterizer on the thread and the font revision of them
+    fonts: Vec<c_int>,
+    revision: c_int,
+    in_flight: usize,
+}
+
+impl Drop for GlyphWorker {
+    fn drop(&mut self) {
+        // closes the channel
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl GlyphWorker {
+    /// Spawns the thread. Returns [`FonsError::IoError`] if it couldn't be spawned
+    pub fn new() -> Result<Self> {
+        let (jobs, job_rx) = mpsc::channel();
+        let (done_tx, done) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("fontstash-glyphs".into())
+            .spawn(move || self::run(job_rx, done_tx))
+            .map_err(|err| FonsError::IoError(std::sync::Arc::new(err)))?;
+
+        Ok(Self {
+            jobs: Some(jobs),
+            done,
+            thread: Some(thread),
+            page: None,
+            fonts: Vec::new(),
+            revision: 0,
+            in_flight: 0,
+        })
+    }
+
+    /// Number of the glyphs being rasterized
+    pub fn pending(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Inserts the glyphs rasterized since the last sync into the atlas and sends the glyphs
+    /// queued since then to the thread. Returns the number of the inserted glyphs
+    ///
+    /// A worker serves one stash, so it returns [`FonsError::InvalidParams`] for another stash.
+    /// Returns [`FonsError::AtlasFull`] if a glyph didn't fit in the atlas even after
+    /// `renderExpand`, in which case the glyph is queued again when it's drawn.
+    pub fn sync(&mut self, stash: &FontStash) -> Result<usize> {
+        trace_span!("fontstash::sync_glyphs", pending = self.in_flight);
+        self.check_stash(stash)?;
+
+        let mut n = 0;
+        while let Ok(done) = self.done.try_recv() {
+            self.in_flight -= 1;
+            n += self::insert(stash, &done)? as usize;
+        }
+        self.send_queued(stash);
+        Ok(n)
+    }
+
+    /// [`GlyphWorker::sync`] that waits for all the queued glyphs, e.g. on a loading screen
+    pub fn finish(&mut self, stash: &FontStash) -> Result<usize> {
+        self.check_stash(stash)?;
+        self.send_queued(stash);
+
+        let mut n = 0;
+        while self.in_flight > 0 {
+            let done = match self.done.recv() {
+                Ok(done) => done,
+                // the thread is gone
+                Err(_) => break,
+            };
+            self.in_flight -= 1;
+            n += self::insert(stash, &done)? as usize;
+        }
+        Ok(n)
+    }
+
+    fn check_stash(&mut self, stash: &FontStash) -> Result<()> {
+        match self.page {
+            Some(page) if page != stash.page_id() => {
+                Err(FonsError::InvalidParams("a glyph worker serves one stash"))
+            }
+            _ => {
+                self.page = Some(stash.page_id());
+                Ok(())
+            }
+        }
+    }
+
+    fn send_queued(&mut self, stash: &FontStash) {
+        let revision = unsafe { sys::fonsGetFontRevision(stash.raw()) };
+        if revision != self.revision {
+            self.revision = revision;
+            self.fonts.clear();
+            self.send(Job::ClearFonts);
+        }
+
+        let mut glyphs = [sys::FONSdeferredGlyph::default(); 64];
+        loop {
+            let n = unsafe {
+                sys::fonsTakeDeferredGlyphs(stash.raw(), glyphs.as_mut_ptr(), glyphs.len() as c_int)
+            } as usize;
+
+            for glyph in &glyphs[..n] {
+                if !self.fonts.contains(&glyph.font) {
+                    self.fonts.push(glyph.font);
+                    // without it, the glyphs of the font come back unrasterized
+                    if let Some(rasterizer) = stash.rasterizer_at(glyph.font) {
+                        self.send(Job::Font(glyph.font, rasterizer));
+                    }
+                }
+                if self.send(Job::Glyph(*glyph)) {
+                    self.in_flight += 1;
+                } else {
+                    // rasterized now instead
+                    unsafe {
+                        sys::fonsRasterizeDeferredGlyph(stash.raw(), glyph.id);
+                    }
+                    crate::resume_callback_panic();
+                }
+            }
+
+            if n < glyphs.len() {
+                break;
+            }
+        }
+    }
+
+    /// Returns `false` if the thread is gone
+    fn send(&self, job: Job) -> bool {
+        self.jobs
+            .as_ref()
+            .is_some_and(|jobs| jobs.send(job).is_ok())
+    }
+}
+
+fn run(jobs: Receiver<Job>, done: Sender<Done>) {
+    let mut rasterizers: Vec<(c_int, Rasterizer)> = Vec::new();
+    for job in jobs {
+        match job {
+            Job::Font(font, rasterizer) => rasterizers.push((font, rasterizer)),
+            Job::ClearFonts => rasterizers.clear(),
+            Job::Glyph(glyph) => {
+                let bitmap = rasterizers
+                    .iter_mut()
+                    .find(|(font, _)| *font == glyph.font)
+                    .zip(core::char::from_u32(glyph.codepoint))
+                    .and_then(|((_, rasterizer), c)| rasterizer.rasterize(c, glyph.size));
+                if done.send(Done { glyph, bitmap }).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Inserts a glyph rasterized on the thread, or rasterizes it now if it couldn't be
+fn insert(stash: &FontStash, done: &Done) -> Result<bool> {
+    match &done.bitmap {
+        Some(bitmap) => {
+            let res = unsafe {
+                sys::fonsAddDeferredGlyph(
+                    stash.raw(),
+                    done.glyph.id,
+                    bitmap.pixels.as_ptr(),
+                    bitmap.width as c_int,
+                    bitmap.height as c_int,
+                    bitmap.width as c_int,
+                    bitmap.offset[0],
+                    bitmap.offset[1],
+                    bitmap.x_advance,
+                )
+            };
+            stash.inserted(res, (bitmap.width + 2) * (bitmap.height + 2))
+        }
+        None => {
+            let res = unsafe { sys::fonsRasterizeDeferredGlyph(stash.raw(), done.glyph.id) };
+            // the size of the glyph is not known
+            stash.inserted(res, 0)
+        }
+    }
+}